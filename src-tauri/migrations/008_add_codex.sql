@@ -0,0 +1,37 @@
+-- Codex: user-defined world-building entity types (items, factions, places, ...)
+
+-- Entity types with a JSON field schema: [{ "key", "label", "kind", "ref_type_id", "item_kind" }]
+CREATE TABLE IF NOT EXISTS codex_types (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  desc TEXT,
+  fields TEXT NOT NULL DEFAULT '[]',
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  UNIQUE(project_id, name)
+);
+CREATE INDEX IF NOT EXISTS idx_codex_types_project ON codex_types(project_id, sort_order);
+
+-- Entities of a type; field values are stored as a JSON object keyed by field key
+CREATE TABLE IF NOT EXISTS codex_entities (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL,
+  type_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  desc TEXT,
+  data TEXT NOT NULL DEFAULT '{}',
+  FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  FOREIGN KEY(type_id) REFERENCES codex_types(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_codex_entities_project ON codex_entities(project_id, type_id);
+
+-- Doc attachments, same shape as doc_characters
+CREATE TABLE IF NOT EXISTS doc_codex_entities (
+  doc_id INTEGER NOT NULL,
+  entity_id INTEGER NOT NULL,
+  PRIMARY KEY (doc_id, entity_id),
+  FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE,
+  FOREIGN KEY(entity_id) REFERENCES codex_entities(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_doc_codex_entities_entity ON doc_codex_entities(entity_id);
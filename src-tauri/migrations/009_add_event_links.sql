@@ -0,0 +1,18 @@
+-- Event participants (characters) and event locations (codex entities, e.g. of a "Location" type)
+CREATE TABLE IF NOT EXISTS event_characters (
+  event_id INTEGER NOT NULL,
+  character_id INTEGER NOT NULL,
+  PRIMARY KEY (event_id, character_id),
+  FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE,
+  FOREIGN KEY(character_id) REFERENCES characters(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_event_characters_character ON event_characters(character_id);
+
+CREATE TABLE IF NOT EXISTS event_locations (
+  event_id INTEGER NOT NULL,
+  entity_id INTEGER NOT NULL,
+  PRIMARY KEY (event_id, entity_id),
+  FOREIGN KEY(event_id) REFERENCES events(id) ON DELETE CASCADE,
+  FOREIGN KEY(entity_id) REFERENCES codex_entities(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_event_locations_entity ON event_locations(entity_id);
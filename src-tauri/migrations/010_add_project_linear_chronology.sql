@@ -0,0 +1,2 @@
+-- Mark a project as told in strict chronological order (checked by the continuity analysis)
+ALTER TABLE projects ADD COLUMN linear_chronology INTEGER NOT NULL DEFAULT 0;
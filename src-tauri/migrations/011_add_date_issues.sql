@@ -0,0 +1,10 @@
+-- Stored dates that could not be parsed as ISO 8601 when existing values were normalised
+CREATE TABLE IF NOT EXISTS date_issues (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  table_name TEXT NOT NULL,
+  column_name TEXT NOT NULL,
+  row_id INTEGER NOT NULL,
+  value TEXT NOT NULL,
+  error TEXT NOT NULL,
+  UNIQUE(table_name, column_name, row_id)
+);
@@ -0,0 +1,25 @@
+-- Per-project fictional calendars; months, weekdays and eras are JSON arrays
+CREATE TABLE IF NOT EXISTS calendars (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'dated' CHECK(kind IN ('dated', 'relative')),
+    months TEXT NOT NULL DEFAULT '[]',
+    weekdays TEXT NOT NULL DEFAULT '[]',
+    eras TEXT NOT NULL DEFAULT '[]',
+    epoch_offset INTEGER NOT NULL DEFAULT 0,
+    day_label TEXT NOT NULL DEFAULT 'Day',
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    UNIQUE(project_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendars_project ON calendars(project_id);
+
+-- Dates are kept as absolute day numbers plus the calendar they were written in (NULL = Gregorian)
+ALTER TABLE events ADD COLUMN calendar_id INTEGER REFERENCES calendars(id) ON DELETE SET NULL;
+ALTER TABLE events ADD COLUMN start_day INTEGER;
+ALTER TABLE events ADD COLUMN end_day INTEGER;
+
+ALTER TABLE timelines ADD COLUMN calendar_id INTEGER REFERENCES calendars(id) ON DELETE SET NULL;
+ALTER TABLE timelines ADD COLUMN start_day INTEGER;
+ALTER TABLE timelines ADD COLUMN end_day INTEGER;
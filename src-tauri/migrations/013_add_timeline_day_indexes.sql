@@ -0,0 +1,3 @@
+-- Range queries ("what happens between these dates") filter on the absolute day columns
+CREATE INDEX IF NOT EXISTS idx_timelines_days ON timelines(entity_type, start_day, end_day);
+CREATE INDEX IF NOT EXISTS idx_events_project_days ON events(project_id, start_day, end_day);
@@ -0,0 +1,45 @@
+-- Tie every timeline row to its owning project; rows whose entity no longer exists stay NULL (orphans)
+ALTER TABLE timelines ADD COLUMN project_id INTEGER REFERENCES projects(id) ON DELETE CASCADE;
+
+UPDATE timelines SET project_id = CASE entity_type
+    WHEN 'project' THEN (SELECT id FROM projects WHERE id = timelines.entity_id)
+    WHEN 'doc' THEN (SELECT project_id FROM docs WHERE id = timelines.entity_id)
+    WHEN 'folder' THEN (SELECT project_id FROM doc_groups WHERE id = timelines.entity_id)
+    WHEN 'event' THEN (SELECT project_id FROM events WHERE id = timelines.entity_id)
+END;
+
+CREATE INDEX IF NOT EXISTS idx_timelines_project ON timelines(project_id);
+
+-- Fill in the owner for rows inserted without one
+CREATE TRIGGER IF NOT EXISTS trg_timelines_owner AFTER INSERT ON timelines
+WHEN NEW.project_id IS NULL
+BEGIN
+    UPDATE timelines SET project_id = CASE NEW.entity_type
+        WHEN 'project' THEN (SELECT id FROM projects WHERE id = NEW.entity_id)
+        WHEN 'doc' THEN (SELECT project_id FROM docs WHERE id = NEW.entity_id)
+        WHEN 'folder' THEN (SELECT project_id FROM doc_groups WHERE id = NEW.entity_id)
+        WHEN 'event' THEN (SELECT project_id FROM events WHERE id = NEW.entity_id)
+    END
+    WHERE id = NEW.id;
+END;
+
+-- Remove a timeline together with its entity (also fires for cascaded deletes)
+CREATE TRIGGER IF NOT EXISTS trg_docs_delete_timeline AFTER DELETE ON docs
+BEGIN
+    DELETE FROM timelines WHERE entity_type = 'doc' AND entity_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_doc_groups_delete_timeline AFTER DELETE ON doc_groups
+BEGIN
+    DELETE FROM timelines WHERE entity_type = 'folder' AND entity_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_events_delete_timeline AFTER DELETE ON events
+BEGIN
+    DELETE FROM timelines WHERE entity_type = 'event' AND entity_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_projects_delete_timelines AFTER DELETE ON projects
+BEGIN
+    DELETE FROM timelines WHERE project_id = OLD.id OR (entity_type = 'project' AND entity_id = OLD.id);
+END;
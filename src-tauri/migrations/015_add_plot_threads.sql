@@ -0,0 +1,24 @@
+-- Plot threads: storylines running through a project's docs
+
+CREATE TABLE IF NOT EXISTS plot_threads (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  color TEXT,
+  desc TEXT,
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  UNIQUE(project_id, name)
+);
+CREATE INDEX IF NOT EXISTS idx_plot_threads_project ON plot_threads(project_id, sort_order);
+
+-- Which docs carry a thread, and what the doc does for it
+CREATE TABLE IF NOT EXISTS doc_plot_threads (
+  doc_id INTEGER NOT NULL,
+  thread_id INTEGER NOT NULL,
+  role TEXT NOT NULL DEFAULT 'advances' CHECK (role IN ('introduces', 'advances', 'resolves')),
+  PRIMARY KEY (doc_id, thread_id),
+  FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE,
+  FOREIGN KEY(thread_id) REFERENCES plot_threads(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_doc_plot_threads_thread ON doc_plot_threads(thread_id);
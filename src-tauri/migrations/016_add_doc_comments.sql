@@ -0,0 +1,21 @@
+-- Inline comments anchored to a character range of a doc's text, with threaded replies.
+-- Offsets count Unicode characters; `quote` is the anchored text, used to re-anchor after edits.
+-- Replies carry parent_id and no anchor of their own. `detached` marks anchors lost to an edit.
+
+CREATE TABLE IF NOT EXISTS doc_comments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  doc_id INTEGER NOT NULL,
+  parent_id INTEGER,
+  start_offset INTEGER,
+  end_offset INTEGER,
+  quote TEXT,
+  body TEXT NOT NULL,
+  author TEXT,
+  resolved INTEGER NOT NULL DEFAULT 0,
+  detached INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE,
+  FOREIGN KEY(parent_id) REFERENCES doc_comments(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_doc_comments_doc ON doc_comments(doc_id, parent_id);
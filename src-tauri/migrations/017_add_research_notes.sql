@@ -0,0 +1,40 @@
+-- Research notes on the polymorphic notes table from 001: titled, tagged, timestamped notes
+-- attached to a project, folder, doc, character or event (parent_type/parent_id), owned by a project
+
+ALTER TABLE notes ADD COLUMN project_id INTEGER REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE notes ADD COLUMN title TEXT NOT NULL DEFAULT '';
+ALTER TABLE notes ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE notes ADD COLUMN created_at TEXT;
+ALTER TABLE notes ADD COLUMN updated_at TEXT;
+
+UPDATE notes SET project_id = CASE parent_type
+    WHEN 'project' THEN (SELECT id FROM projects WHERE id = notes.parent_id)
+    WHEN 'folder' THEN (SELECT project_id FROM doc_groups WHERE id = notes.parent_id)
+    WHEN 'doc' THEN (SELECT project_id FROM docs WHERE id = notes.parent_id)
+    WHEN 'character' THEN (SELECT project_id FROM characters WHERE id = notes.parent_id)
+    WHEN 'event' THEN (SELECT project_id FROM events WHERE id = notes.parent_id)
+END;
+
+CREATE INDEX IF NOT EXISTS idx_notes_parent ON notes(parent_type, parent_id);
+CREATE INDEX IF NOT EXISTS idx_notes_project ON notes(project_id, updated_at);
+
+-- Remove notes together with what they are attached to
+CREATE TRIGGER IF NOT EXISTS trg_docs_delete_notes AFTER DELETE ON docs
+BEGIN
+    DELETE FROM notes WHERE parent_type = 'doc' AND parent_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_doc_groups_delete_notes AFTER DELETE ON doc_groups
+BEGIN
+    DELETE FROM notes WHERE parent_type = 'folder' AND parent_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_characters_delete_notes AFTER DELETE ON characters
+BEGIN
+    DELETE FROM notes WHERE parent_type = 'character' AND parent_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_events_delete_notes AFTER DELETE ON events
+BEGIN
+    DELETE FROM notes WHERE parent_type = 'event' AND parent_id = OLD.id;
+END;
@@ -0,0 +1,46 @@
+-- Attachments: binary files (images, maps, PDFs, audio) attached to a project, doc, character or
+-- location (codex entity). File contents live in a content-addressed store under the app data dir;
+-- media_blobs indexes it by SHA-256 so identical files are stored once.
+
+CREATE TABLE IF NOT EXISTS media_blobs (
+  hash TEXT PRIMARY KEY,
+  size INTEGER NOT NULL,
+  mime TEXT NOT NULL,
+  width INTEGER,
+  height INTEGER,
+  has_thumbnail INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS attachments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL,
+  parent_type TEXT NOT NULL CHECK (parent_type IN ('project', 'doc', 'character', 'location')),
+  parent_id INTEGER NOT NULL,
+  hash TEXT NOT NULL,
+  file_name TEXT NOT NULL,
+  title TEXT,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE,
+  FOREIGN KEY(hash) REFERENCES media_blobs(hash)
+);
+CREATE INDEX IF NOT EXISTS idx_attachments_parent ON attachments(parent_type, parent_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_project ON attachments(project_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(hash);
+
+-- Remove attachments together with what they are attached to; unreferenced blobs are
+-- collected by the attachments service, which also deletes their files
+CREATE TRIGGER IF NOT EXISTS trg_docs_delete_attachments AFTER DELETE ON docs
+BEGIN
+    DELETE FROM attachments WHERE parent_type = 'doc' AND parent_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_characters_delete_attachments AFTER DELETE ON characters
+BEGIN
+    DELETE FROM attachments WHERE parent_type = 'character' AND parent_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_codex_entities_delete_attachments AFTER DELETE ON codex_entities
+BEGIN
+    DELETE FROM attachments WHERE parent_type = 'location' AND parent_id = OLD.id;
+END;
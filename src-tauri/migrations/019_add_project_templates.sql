@@ -0,0 +1,8 @@
+-- Project templates saved by the user; built-in templates ship with the app and are not stored
+CREATE TABLE IF NOT EXISTS project_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    desc TEXT,
+    structure TEXT NOT NULL, -- JSON TemplateStructure
+    created_at TEXT NOT NULL
+);
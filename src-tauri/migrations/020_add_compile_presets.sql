@@ -0,0 +1,22 @@
+-- Per-doc status, label and synopsis, which compile presets filter on and can include
+CREATE TABLE IF NOT EXISTS doc_metadata (
+    doc_id INTEGER PRIMARY KEY,
+    status TEXT,
+    label TEXT,
+    synopsis TEXT,
+    FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE
+);
+
+-- Reusable compile settings per project
+CREATE TABLE IF NOT EXISTS compile_presets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    format TEXT NOT NULL, -- odt, html or pdf
+    settings TEXT NOT NULL, -- JSON CompileSettings
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE(project_id, name),
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_compile_presets_project ON compile_presets(project_id);
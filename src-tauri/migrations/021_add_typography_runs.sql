@@ -0,0 +1,22 @@
+-- Typography passes applied to doc text, with each changed doc's text before and after so a pass
+-- can be undone as long as the doc has not been edited since
+CREATE TABLE IF NOT EXISTS typography_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    doc_id INTEGER, -- set when the pass ran on a single doc
+    options TEXT NOT NULL, -- JSON TypographyOptions
+    created_at TEXT NOT NULL,
+    undone_at TEXT,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_typography_runs_project ON typography_runs(project_id);
+
+CREATE TABLE IF NOT EXISTS typography_run_docs (
+    run_id INTEGER NOT NULL,
+    doc_id INTEGER NOT NULL,
+    before_text TEXT NOT NULL,
+    after_text TEXT NOT NULL,
+    PRIMARY KEY(run_id, doc_id),
+    FOREIGN KEY(run_id) REFERENCES typography_runs(id) ON DELETE CASCADE,
+    FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+-- Screenplay mode: docs read as Fountain. A project sets the default and a doc can override it.
+CREATE TABLE IF NOT EXISTS screenplay_projects (
+    project_id INTEGER PRIMARY KEY,
+    enabled INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS screenplay_docs (
+    doc_id INTEGER PRIMARY KEY,
+    enabled INTEGER, -- NULL follows the project
+    location TEXT, -- from the doc's first scene heading
+    FOREIGN KEY(doc_id) REFERENCES docs(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- Characters linked to a screenplay doc because they speak in it (JSON array of ids), so a sync
+-- can drop the links of speakers who no longer do without touching links made by hand
+ALTER TABLE screenplay_docs ADD COLUMN speakers TEXT;
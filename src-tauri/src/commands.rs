@@ -1,11 +1,25 @@
 use crate::db::DbPool;
 use crate::models::{
     ProjectCreate, Project,
-    Character, Event,
+    Character, Event, CharacterWhereabouts, ContinuityWarning, DateIssue, MaintenanceReport,
+    DocComment, DocCommentCreate, CommentThread,
+    ResearchNote, ResearchNoteCreate, ResearchNoteUpdate,
+    Attachment, AttachmentCreate,
+    ProjectTemplate,
+    DocSplitOptions, DocMergeOptions,
+    ManuscriptPlan, ManuscriptSplitOptions, ImportReport, ImportedFile, HeadingImportOptions, CompileFormat, CompileSettings,
+    CompilePreset, CompilePresetInput, DocMetadata,
+    TypographyOptions, TypographyPreview, TypographyRun, TypographyUndo,
+    Screenplay, ScreenplayDoc, ScreenplaySync,
+    PlotThread, PlotThreadCreate, PlotThreadUpdate, DocThreadLink, ThreadMatrix, ThreadRole,
     DraftCreate, DraftUpdate, Draft,
     ProjectDraft, ProjectDraftCreate, ProjectDraftUpdate,
     FolderDraft, FolderDraftCreate, FolderDraftUpdate,
-    Timeline, TimelineCreate, TimelineUpdate
+    Timeline, TimelineCreate, TimelineUpdate,
+    CodexType, CodexTypeCreate, CodexTypeUpdate,
+    CodexEntity, CodexEntityCreate, CodexEntityUpdate,
+    Calendar, CalendarCreate, CalendarUpdate, CalendarDate,
+    TimelineNode, TimelineHit
 };
 use crate::services::projects as project_service;
 use tauri::State;
@@ -45,10 +59,55 @@ pub async fn project_update(state: State<'_, AppState>, id: i64, changes: Option
     project_service::update(pool, id, name, desc, path).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn project_set_timeline(state: State<'_, AppState>, id: i64, start: Option<String>, end: Option<String>) -> Result<Project, String> {
+    let pool = &state.pool;
+    project_service::set_timeline_range(pool, id, start, end).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn project_set_linear_chronology(state: State<'_, AppState>, id: i64, linear: bool) -> Result<Project, String> {
+    let pool = &state.pool;
+    project_service::set_linear_chronology(pool, id, linear).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn project_delete(state: State<'_, AppState>, id: i64) -> Result<bool, String> {
     let pool = &state.pool;
-    project_service::delete(pool, id).map_err(|e| e.to_string())
+    let deleted = project_service::delete(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)?;
+    Ok(deleted)
+}
+
+#[tauri::command]
+pub async fn project_duplicate(state: State<'_, AppState>, id: i64, name: String) -> Result<Project, String> {
+    let pool = &state.pool;
+    crate::services::duplicate::copy_project(pool, id, &name).map_err(|e| format!("{:#}", e))
+}
+
+// Project Template Commands
+#[tauri::command]
+pub async fn project_template_list(state: State<'_, AppState>) -> Result<Vec<ProjectTemplate>, String> {
+    let pool = &state.pool;
+    crate::services::templates::list(pool).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn project_create_from_template(state: State<'_, AppState>, payload: ProjectCreate, template: String) -> Result<Project, String> {
+    let pool = &state.pool;
+    crate::services::templates::create_project(pool, payload, &template).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn project_save_as_template(state: State<'_, AppState>, project_id: i64, name: String, desc: Option<String>, include_text: bool) -> Result<ProjectTemplate, String> {
+    let pool = &state.pool;
+    crate::services::templates::save_project(pool, project_id, &name, desc, include_text).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn project_template_delete(state: State<'_, AppState>, key: String) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::templates::delete(pool, &key).map_err(|e| format!("{:#}", e))
 }
 
 // Doc Groups Commands
@@ -76,7 +135,8 @@ pub async fn doc_group_create_after(state: State<'_, AppState>, project_id: i64,
 #[tauri::command]
 pub async fn doc_group_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
     let pool = &state.pool;
-    crate::services::doc_groups::delete_doc_group(pool, id).map_err(|e| e.to_string())
+    crate::services::doc_groups::delete_doc_group(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)
 }
 
 #[tauri::command]
@@ -91,6 +151,13 @@ pub async fn doc_group_rename(state: State<'_, AppState>, id: i64, new_name: Str
     crate::services::doc_groups::rename_doc_group(pool, id, &new_name).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn doc_group_duplicate(state: State<'_, AppState>, id: i64) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let group = crate::services::duplicate::copy_group(pool, id).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_value(group).map_err(|e| format!("{:#}", e))
+}
+
 // Docs Commands
 #[tauri::command]
 pub async fn doc_list(state: State<'_, AppState>, project_id: i64) -> Result<serde_json::Value, String> {
@@ -99,6 +166,13 @@ pub async fn doc_list(state: State<'_, AppState>, project_id: i64) -> Result<ser
     serde_json::to_value(docs).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn doc_list_story_order(state: State<'_, AppState>, project_id: i64) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let docs = crate::services::docs::list_in_story_order(pool, project_id).map_err(|e| e.to_string())?;
+    serde_json::to_value(docs).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn doc_get(state: State<'_, AppState>, id: i64) -> Result<serde_json::Value, String> {
     let pool = &state.pool;
@@ -123,7 +197,12 @@ pub async fn doc_create_after(state: State<'_, AppState>, project_id: i64, name:
 #[tauri::command]
 pub async fn doc_update_text(state: State<'_, AppState>, id: i64, text: String) -> Result<(), String> {
     let pool = &state.pool;
-    crate::services::docs::update_doc(pool, id, &text).map_err(|e| e.to_string())
+    crate::services::docs::update_doc(pool, id, &text).map_err(|e| e.to_string())?;
+    // Screenplay docs keep their location and speaker links current; new characters wait for a sync
+    if crate::services::screenplay::doc(pool, id).map_err(|e| e.to_string())?.screenplay {
+        crate::services::screenplay::sync_doc(pool, id, false).map_err(|e| e.to_string())?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -132,10 +211,23 @@ pub async fn doc_update_notes(state: State<'_, AppState>, id: i64, notes: String
     crate::services::docs::update_doc_notes(pool, id, &notes).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn doc_metadata_get(state: State<'_, AppState>, doc_id: i64) -> Result<DocMetadata, String> {
+    let pool = &state.pool;
+    crate::services::doc_metadata::get(pool, doc_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn doc_metadata_set(state: State<'_, AppState>, doc_id: i64, metadata: DocMetadata) -> Result<DocMetadata, String> {
+    let pool = &state.pool;
+    crate::services::doc_metadata::set(pool, doc_id, metadata).map_err(|e| format!("{:#}", e))
+}
+
 #[tauri::command]
 pub async fn doc_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
     let pool = &state.pool;
-    crate::services::docs::delete_doc(pool, id).map_err(|e| e.to_string())
+    crate::services::docs::delete_doc(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)
 }
 
 #[tauri::command]
@@ -156,6 +248,27 @@ pub async fn doc_rename(state: State<'_, AppState>, id: i64, new_name: String) -
     crate::services::docs::rename_doc(pool, id, &new_name).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn doc_duplicate(state: State<'_, AppState>, id: i64) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let doc = crate::services::duplicate::copy_doc(pool, id).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_value(doc).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn doc_split(state: State<'_, AppState>, doc_id: i64, offsets: Vec<usize>, options: DocSplitOptions) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let docs = crate::services::restructure::split(pool, doc_id, &offsets, options).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_value(docs).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn doc_merge(state: State<'_, AppState>, doc_ids: Vec<i64>, options: DocMergeOptions) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let doc = crate::services::restructure::merge(pool, &doc_ids, options).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_value(doc).map_err(|e| format!("{:#}", e))
+}
+
 // Legacy doc_create for backward compatibility
 #[tauri::command]
 pub async fn doc_create(state: State<'_, AppState>, project_id: i64, path: String, name: Option<String>, text: Option<String>) -> Result<serde_json::Value, String> {
@@ -187,7 +300,8 @@ pub async fn character_update(state: State<'_, AppState>, id: i64, changes: Opti
 #[tauri::command]
 pub async fn character_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
     let pool = &state.pool;
-    crate::services::characters::delete_(pool, id).map_err(|e| e.to_string())
+    crate::services::characters::delete_(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)
 }
 
 #[tauri::command]
@@ -209,9 +323,10 @@ pub async fn doc_character_detach(state: State<'_, AppState>, doc_id: i64, chara
 }
 
 #[tauri::command]
-pub async fn event_create(state: State<'_, AppState>, project_id: i64, name: String, desc: Option<String>, start_date: Option<String>, end_date: Option<String>, date: Option<String>) -> Result<Event, String> {
+#[allow(clippy::too_many_arguments)]
+pub async fn event_create(state: State<'_, AppState>, project_id: i64, name: String, desc: Option<String>, start_date: Option<String>, end_date: Option<String>, date: Option<String>, calendar_id: Option<i64>) -> Result<Event, String> {
     let pool = &state.pool;
-    crate::services::events::create(pool, project_id, &name, desc, start_date, end_date, date).map_err(|e| e.to_string())
+    crate::services::events::create(pool, project_id, &name, desc, start_date, end_date, date, calendar_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -227,7 +342,9 @@ pub async fn event_update(state: State<'_, AppState>, id: i64, changes: Option<s
     let desc = changes.as_ref().and_then(|c| c.get("desc").and_then(|v| v.as_str()).map(|s| s.to_string()));
     let start_date = changes.as_ref().and_then(|c| c.get("start_date").and_then(|v| v.as_str()).map(|s| s.to_string()));
     let end_date = changes.as_ref().and_then(|c| c.get("end_date").and_then(|v| v.as_str()).map(|s| s.to_string()));
-    crate::services::events::update(pool, id, name, desc, start_date, end_date).map_err(|e| e.to_string())
+    // calendar_id: absent keeps the calendar, null switches to Gregorian
+    let calendar_id = changes.as_ref().and_then(|c| c.get("calendar_id")).map(|v| v.as_i64());
+    crate::services::events::update(pool, id, name, desc, start_date, end_date, calendar_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -254,6 +371,60 @@ pub async fn doc_event_detach(state: State<'_, AppState>, doc_id: i64, event_id:
     crate::services::events::detach_from_doc(pool, doc_id, event_id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn event_character_list(state: State<'_, AppState>, event_id: i64) -> Result<Vec<i64>, String> {
+    let pool = &state.pool;
+    crate::services::events::list_characters(pool, event_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn event_character_attach(state: State<'_, AppState>, event_id: i64, character_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::events::attach_character(pool, event_id, character_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn event_character_detach(state: State<'_, AppState>, event_id: i64, character_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::events::detach_character(pool, event_id, character_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn character_event_list(state: State<'_, AppState>, character_id: i64) -> Result<Vec<Event>, String> {
+    let pool = &state.pool;
+    crate::services::events::list_for_character(pool, character_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn event_location_list(state: State<'_, AppState>, event_id: i64) -> Result<Vec<i64>, String> {
+    let pool = &state.pool;
+    crate::services::events::list_locations(pool, event_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn event_location_attach(state: State<'_, AppState>, event_id: i64, entity_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::events::attach_location(pool, event_id, entity_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn event_location_detach(state: State<'_, AppState>, event_id: i64, entity_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::events::detach_location(pool, event_id, entity_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn location_event_list(state: State<'_, AppState>, entity_id: i64) -> Result<Vec<Event>, String> {
+    let pool = &state.pool;
+    crate::services::events::list_for_location(pool, entity_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn character_whereabouts(state: State<'_, AppState>, project_id: i64, date: String, calendar_id: Option<i64>) -> Result<Vec<CharacterWhereabouts>, String> {
+    let pool = &state.pool;
+    crate::services::events::whereabouts(pool, project_id, &date, calendar_id).map_err(|e| e.to_string())
+}
+
 // Draft Commands
 #[tauri::command]
 pub async fn draft_create(state: State<'_, AppState>, doc_id: i64, payload: DraftCreate) -> Result<Draft, String> {
@@ -297,6 +468,49 @@ pub async fn draft_delete_all(state: State<'_, AppState>, doc_id: i64) -> Result
     crate::services::drafts::delete_all_drafts_for_doc(pool, doc_id).map_err(|e| e.to_string())
 }
 
+// Comment Commands
+#[tauri::command]
+pub async fn comment_create(state: State<'_, AppState>, doc_id: i64, payload: DocCommentCreate) -> Result<DocComment, String> {
+    let pool = &state.pool;
+    crate::services::comments::create(pool, doc_id, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_reply(state: State<'_, AppState>, comment_id: i64, body: String, author: Option<String>) -> Result<DocComment, String> {
+    let pool = &state.pool;
+    crate::services::comments::reply(pool, comment_id, &body, author).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_get(state: State<'_, AppState>, id: i64) -> Result<Option<DocComment>, String> {
+    let pool = &state.pool;
+    crate::services::comments::get(pool, id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_list(state: State<'_, AppState>, doc_id: i64, include_resolved: bool) -> Result<Vec<CommentThread>, String> {
+    let pool = &state.pool;
+    crate::services::comments::list_for_doc(pool, doc_id, include_resolved).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_update(state: State<'_, AppState>, id: i64, body: String) -> Result<DocComment, String> {
+    let pool = &state.pool;
+    crate::services::comments::update_body(pool, id, &body).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_set_resolved(state: State<'_, AppState>, id: i64, resolved: bool) -> Result<DocComment, String> {
+    let pool = &state.pool;
+    crate::services::comments::set_resolved(pool, id, resolved).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn comment_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::comments::delete(pool, id).map_err(|e| format!("{:#}", e))
+}
+
 // Project Draft Commands
 #[tauri::command]
 pub async fn project_draft_create(state: State<'_, AppState>, project_id: i64, payload: ProjectDraftCreate) -> Result<ProjectDraft, String> {
@@ -391,9 +605,27 @@ pub async fn timeline_get_by_entity(state: State<'_, AppState>, entity_type: Str
 }
 
 #[tauri::command]
-pub async fn timeline_list(state: State<'_, AppState>) -> Result<Vec<Timeline>, String> {
+pub async fn timeline_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<Timeline>, String> {
+    let pool = &state.pool;
+    crate::services::timelines::list(pool, project_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn timeline_purge_orphans(state: State<'_, AppState>) -> Result<usize, String> {
+    let pool = &state.pool;
+    crate::services::timelines::purge_orphans(pool).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn timeline_tree(state: State<'_, AppState>, project_id: i64) -> Result<TimelineNode, String> {
+    let pool = &state.pool;
+    crate::services::timeline_rollup::tree(pool, project_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn timeline_range(state: State<'_, AppState>, project_id: i64, from: Option<String>, to: Option<String>, calendar_id: Option<i64>) -> Result<Vec<TimelineHit>, String> {
     let pool = &state.pool;
-    crate::services::timelines::list(pool).map_err(|e| e.to_string())
+    crate::services::timelines::list_in_range(pool, project_id, from.as_deref(), to.as_deref(), calendar_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -414,26 +646,347 @@ pub async fn timeline_delete_by_entity(state: State<'_, AppState>, entity_type:
     crate::services::timelines::delete_by_entity(pool, &entity_type, entity_id).map_err(|e| e.to_string())
 }
 
-/// Import multiple paths (files or folders).
-/// - Files with .txt are imported as docs into the target folder (doc_group_id).
-/// - Folders always become ROOT-LEVEL doc groups (parent_id = None), regardless of the target folder.
-///   Only the folder's immediate .txt files are imported into that new group. Nested subfolders are ignored.
+// Codex Commands
+#[tauri::command]
+pub async fn codex_type_create(state: State<'_, AppState>, project_id: i64, payload: CodexTypeCreate) -> Result<CodexType, String> {
+    let pool = &state.pool;
+    crate::services::codex::create_type(pool, project_id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_type_get(state: State<'_, AppState>, id: i64) -> Result<Option<CodexType>, String> {
+    let pool = &state.pool;
+    crate::services::codex::get_type(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_type_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<CodexType>, String> {
+    let pool = &state.pool;
+    crate::services::codex::list_types(pool, project_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_type_update(state: State<'_, AppState>, id: i64, payload: CodexTypeUpdate) -> Result<CodexType, String> {
+    let pool = &state.pool;
+    crate::services::codex::update_type(pool, id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_type_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::codex::delete_type(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)
+}
+
+#[tauri::command]
+pub async fn codex_entity_create(state: State<'_, AppState>, project_id: i64, payload: CodexEntityCreate) -> Result<CodexEntity, String> {
+    let pool = &state.pool;
+    crate::services::codex::create_entity(pool, project_id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_entity_get(state: State<'_, AppState>, id: i64) -> Result<Option<CodexEntity>, String> {
+    let pool = &state.pool;
+    crate::services::codex::get_entity(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_entity_list(state: State<'_, AppState>, project_id: i64, type_id: Option<i64>) -> Result<Vec<CodexEntity>, String> {
+    let pool = &state.pool;
+    crate::services::codex::list_entities(pool, project_id, type_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_entity_update(state: State<'_, AppState>, id: i64, payload: CodexEntityUpdate) -> Result<CodexEntity, String> {
+    let pool = &state.pool;
+    crate::services::codex::update_entity(pool, id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn codex_entity_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::codex::delete_entity(pool, id).map_err(|e| e.to_string())?;
+    collect_media(pool)
+}
+
+#[tauri::command]
+pub async fn doc_codex_list(state: State<'_, AppState>, doc_id: i64) -> Result<Vec<i64>, String> {
+    let pool = &state.pool;
+    crate::services::codex::list_for_doc(pool, doc_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn doc_codex_attach(state: State<'_, AppState>, doc_id: i64, entity_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::codex::attach_to_doc(pool, doc_id, entity_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn doc_codex_detach(state: State<'_, AppState>, doc_id: i64, entity_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::codex::detach_from_doc(pool, doc_id, entity_id).map_err(|e| e.to_string())
+}
+
+// Research Note Commands
 #[tauri::command]
-pub async fn import_txt_files(state: State<'_, AppState>, project_id: i64, doc_group_id: i64, files: Vec<String>) -> Result<usize, String> {
+pub async fn research_note_create(state: State<'_, AppState>, payload: ResearchNoteCreate) -> Result<ResearchNote, String> {
     let pool = &state.pool;
-    let mut imported = 0usize;
+    crate::services::research_notes::create(pool, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_get(state: State<'_, AppState>, id: i64) -> Result<Option<ResearchNote>, String> {
+    let pool = &state.pool;
+    crate::services::research_notes::get(pool, id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_list_for(state: State<'_, AppState>, parent_type: String, parent_id: i64) -> Result<Vec<ResearchNote>, String> {
+    let pool = &state.pool;
+    crate::services::research_notes::list_for(pool, &parent_type, parent_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_search(state: State<'_, AppState>, project_id: i64, query: Option<String>, tag: Option<String>) -> Result<Vec<ResearchNote>, String> {
+    let pool = &state.pool;
+    crate::services::research_notes::search(pool, project_id, query.as_deref(), tag.as_deref()).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_tags(state: State<'_, AppState>, project_id: i64) -> Result<Vec<String>, String> {
+    let pool = &state.pool;
+    crate::services::research_notes::list_tags(pool, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_update(state: State<'_, AppState>, id: i64, payload: ResearchNoteUpdate) -> Result<ResearchNote, String> {
+    let pool = &state.pool;
+    crate::services::research_notes::update(pool, id, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn research_note_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::research_notes::delete(pool, id).map_err(|e| format!("{:#}", e))
+}
+
+// Attachment Commands
+
+/// Remove media files left unused once something that held attachments was deleted
+fn collect_media(pool: &DbPool) -> Result<(), String> {
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::collect_garbage(pool, &root).map_err(|e| format!("{:#}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn attachment_add(state: State<'_, AppState>, payload: AttachmentCreate) -> Result<Attachment, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::add_file(pool, &root, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_get(state: State<'_, AppState>, id: i64) -> Result<Option<Attachment>, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::get(pool, &root, id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_list_for(state: State<'_, AppState>, parent_type: String, parent_id: i64) -> Result<Vec<Attachment>, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::list_for(pool, &root, &parent_type, parent_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<Attachment>, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::list(pool, &root, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_rename(state: State<'_, AppState>, id: i64, title: Option<String>) -> Result<Attachment, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::rename(pool, &root, id, title).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::delete(pool, &root, id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn attachment_collect_garbage(state: State<'_, AppState>) -> Result<usize, String> {
+    let pool = &state.pool;
+    let root = crate::services::attachments::store_dir().map_err(|e| format!("{:#}", e))?;
+    crate::services::attachments::collect_garbage(pool, &root).map_err(|e| format!("{:#}", e))
+}
+
+// Plot Thread Commands
+#[tauri::command]
+pub async fn plot_thread_create(state: State<'_, AppState>, project_id: i64, payload: PlotThreadCreate) -> Result<PlotThread, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::create(pool, project_id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn plot_thread_get(state: State<'_, AppState>, id: i64) -> Result<Option<PlotThread>, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::get(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn plot_thread_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<PlotThread>, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::list(pool, project_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn plot_thread_update(state: State<'_, AppState>, id: i64, payload: PlotThreadUpdate) -> Result<PlotThread, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::update(pool, id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn plot_thread_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::delete(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn doc_thread_list(state: State<'_, AppState>, doc_id: i64) -> Result<Vec<DocThreadLink>, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::list_for_doc(pool, doc_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn doc_thread_link(state: State<'_, AppState>, doc_id: i64, thread_id: i64, role: ThreadRole) -> Result<DocThreadLink, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::link_doc(pool, doc_id, thread_id, role).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn doc_thread_unlink(state: State<'_, AppState>, doc_id: i64, thread_id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::unlink_doc(pool, doc_id, thread_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn plot_thread_matrix(state: State<'_, AppState>, project_id: i64) -> Result<ThreadMatrix, String> {
+    let pool = &state.pool;
+    crate::services::plot_threads::matrix(pool, project_id).map_err(|e| e.to_string())
+}
+
+// Continuity Commands
+#[tauri::command]
+pub async fn continuity_check(state: State<'_, AppState>, project_id: i64) -> Result<Vec<ContinuityWarning>, String> {
+    let pool = &state.pool;
+    crate::services::continuity::check(pool, project_id).map_err(|e| e.to_string())
+}
+
+// Calendar Commands
+#[tauri::command]
+pub async fn calendar_create(state: State<'_, AppState>, project_id: i64, payload: CalendarCreate) -> Result<Calendar, String> {
+    let pool = &state.pool;
+    crate::services::calendars::create(pool, project_id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_get(state: State<'_, AppState>, id: i64) -> Result<Option<Calendar>, String> {
+    let pool = &state.pool;
+    crate::services::calendars::get(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<Calendar>, String> {
+    let pool = &state.pool;
+    crate::services::calendars::list(pool, project_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_update(state: State<'_, AppState>, id: i64, payload: CalendarUpdate) -> Result<Calendar, String> {
+    let pool = &state.pool;
+    crate::services::calendars::update(pool, id, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::calendars::delete(pool, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_render(state: State<'_, AppState>, calendar_id: Option<i64>, day: i64) -> Result<CalendarDate, String> {
+    let pool = &state.pool;
+    crate::services::calendars::render_day(pool, calendar_id, day).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calendar_parse(state: State<'_, AppState>, calendar_id: Option<i64>, text: String) -> Result<(i64, i64), String> {
+    let pool = &state.pool;
+    crate::services::calendars::parse_date(pool, calendar_id, &text).map_err(|e| e.to_string())
+}
+
+// Date Normalisation Commands
+#[tauri::command]
+pub async fn date_normalize(state: State<'_, AppState>) -> Result<Vec<DateIssue>, String> {
+    let pool = &state.pool;
+    crate::services::date_normalization::normalize_all(pool).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn date_issue_list(state: State<'_, AppState>) -> Result<Vec<DateIssue>, String> {
+    let pool = &state.pool;
+    crate::services::date_normalization::list_issues(pool).map_err(|e| e.to_string())
+}
+
+// Maintenance Commands
+#[tauri::command]
+pub async fn maintenance_run(state: State<'_, AppState>, repair: bool, optimize: bool) -> Result<MaintenanceReport, String> {
+    let pool = &state.pool;
+    crate::services::maintenance::run(pool, repair, optimize).map_err(|e| e.to_string())
+}
+
+/// Import multiple paths (files or folders).
+/// - Files with .txt, .docx or .odt are imported as docs into the target folder (doc_group_id).
+/// - Folders always become ROOT-LEVEL doc groups (parent_id = None), regardless of the target folder.
+///   Only the folder's immediate .txt/.docx/.odt files are imported into that new group. Nested subfolders are ignored.
+/// - Each file's encoding is detected and its line endings normalised; files that cannot be read
+///   are skipped and listed in the report with a warning instead of aborting the import.
+/// - Word and OpenDocument files keep bold/italics as markdown markers and their comments as doc
+///   notes; `headings` says whether Heading 1/2 paragraphs start groups or docs (by default they
+///   stay in the text).
+#[tauri::command]
+pub async fn import_txt_files(state: State<'_, AppState>, project_id: i64, doc_group_id: i64, files: Vec<String>, headings: Option<HeadingImportOptions>) -> Result<ImportReport, String> {
+    let pool = &state.pool;
+    let mut report = ImportReport::default();
+    let headings = headings.unwrap_or_default();
+    let is_importable = crate::services::manuscript::is_importable;
+    let import_file = |path: &Path, group_id: i64, report: &mut ImportReport| -> Result<(), String> {
+        let entry = match crate::services::manuscript::plan_file(path, &headings) {
+            Ok((plan, encoding)) => {
+                report.imported += crate::services::manuscript::apply(pool, project_id, Some(group_id), &plan)
+                    .map_err(|e| e.to_string())?;
+                ImportedFile { path: path.display().to_string(), encoding: Some(encoding.to_string()), warning: None }
+            }
+            Err(e) => ImportedFile { path: path.display().to_string(), encoding: None, warning: Some(format!("{:#}", e)) },
+        };
+        report.files.push(entry);
+        Ok(())
+    };
 
     for p in files {
         let path = Path::new(&p);
         if path.is_file() {
-            // import a single file if .txt
-            if path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("txt")).unwrap_or(false) {
-                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
-                let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", p, e))?;
-                let doc = crate::services::docs::create_doc(pool, project_id, name, Some(doc_group_id))
-                    .map_err(|e| e.to_string())?;
-                crate::services::docs::update_doc(pool, doc.id, &content).map_err(|e| e.to_string())?;
-                imported += 1;
+            // import a single file if .txt, .docx or .odt
+            if is_importable(path) {
+                import_file(path, doc_group_id, &mut report)?;
             }
             continue;
         }
@@ -444,32 +997,45 @@ pub async fn import_txt_files(state: State<'_, AppState>, project_id: i64, doc_g
             let group = crate::services::doc_groups::create_doc_group(pool, project_id, &group_name, None)
                 .map_err(|e| e.to_string())?;
 
-            // Import only immediate .txt files (ignore subdirectories)
-            let entries = std::fs::read_dir(&path).map_err(|e| format!("Failed to read dir {}: {}", p, e))?;
-            for entry in entries {
-                let entry = entry.map_err(|e| e.to_string())?;
+            // Import only immediate .txt/.docx/.odt files (ignore subdirectories)
+            let entries = match std::fs::read_dir(path) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    report.files.push(ImportedFile { path: p.clone(), encoding: None, warning: Some(format!("Failed to read dir: {}", e)) });
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
                 let entry_path = entry.path();
-                if entry_path.is_file() {
-                    if entry_path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("txt")).unwrap_or(false) {
-                        let name = entry_path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
-                        let content = std::fs::read_to_string(&entry_path).map_err(|e| format!("Failed to read {}: {}", entry_path.display(), e))?;
-                        let doc = crate::services::docs::create_doc(pool, project_id, name, Some(group.id))
-                            .map_err(|e| e.to_string())?;
-                        crate::services::docs::update_doc(pool, doc.id, &content).map_err(|e| e.to_string())?;
-                        imported += 1;
-                    }
+                if entry_path.is_file() && is_importable(&entry_path) {
+                    import_file(&entry_path, group.id, &mut report)?;
                 }
             }
         }
     }
 
-    Ok(imported)
+    Ok(report)
+}
+
+/// Import one large manuscript file, split into chapter groups and scene docs.
+/// With `dry_run` nothing is written and the returned plan is a preview of the tree.
+#[tauri::command]
+pub async fn import_manuscript(state: State<'_, AppState>, project_id: i64, doc_group_id: Option<i64>, file: String, options: ManuscriptSplitOptions, dry_run: bool) -> Result<ManuscriptPlan, String> {
+    let pool = &state.pool;
+    let path = Path::new(&file);
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
+    let content = crate::services::text_decode::read_text_file(path).map_err(|e| format!("{:#}", e))?;
+    let plan = crate::services::manuscript::plan(&content.text, name, &options).map_err(|e| format!("{:#}", e))?;
+    if !dry_run {
+        crate::services::manuscript::apply(pool, project_id, doc_group_id, &plan).map_err(|e| format!("{:#}", e))?;
+    }
+    Ok(plan)
 }
 
 /// Import an entire project from a folder path.
 /// - Creates a new project named after the folder (path basename)
-/// - For each immediate subfolder: creates a root-level doc group and imports its immediate .txt files
-/// - For immediate .txt files in the root: creates a doc group named "UNSORTED" (created last) and imports them there
+/// - For each immediate subfolder: creates a root-level doc group and imports its immediate .txt/.docx/.odt files
+/// - For immediate .txt/.docx/.odt files in the root: creates a doc group named "UNSORTED" (created last) and imports them there
 #[tauri::command]
 pub async fn import_project(state: State<'_, AppState>, folder_path: String) -> Result<serde_json::Value, String> {
     let pool = &state.pool;
@@ -500,6 +1066,36 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
             doc_timelines: std::collections::HashMap<i64, Option<crate::models::Timeline>>,
             #[serde(default)]
             drafts_by_doc: std::collections::HashMap<i64, Vec<crate::models::Draft>>,
+            #[serde(default)]
+            comments_by_doc: std::collections::HashMap<i64, Vec<crate::models::CommentThread>>,
+            #[serde(default)]
+            codex_types: Vec<crate::models::CodexType>,
+            #[serde(default)]
+            codex_entities: Vec<crate::models::CodexEntity>,
+            #[serde(default)]
+            doc_codex_entities: std::collections::HashMap<i64, Vec<i64>>,
+            #[serde(default)]
+            event_characters: std::collections::HashMap<i64, Vec<i64>>,
+            #[serde(default)]
+            event_locations: std::collections::HashMap<i64, Vec<i64>>,
+            #[serde(default)]
+            calendars: Vec<crate::models::Calendar>,
+            #[serde(default)]
+            plot_threads: Vec<crate::models::PlotThread>,
+            #[serde(default)]
+            doc_plot_threads: std::collections::HashMap<i64, Vec<crate::models::DocThreadLink>>,
+            #[serde(default)]
+            research_notes: Vec<crate::models::ResearchNote>,
+            #[serde(default)]
+            attachments: Vec<crate::models::Attachment>,
+            #[serde(default)]
+            doc_metadata: std::collections::HashMap<i64, DocMetadata>,
+            #[serde(default)]
+            compile_presets: Vec<CompilePreset>,
+            #[serde(default)]
+            screenplay_project: bool,
+            #[serde(default)]
+            screenplay_docs: std::collections::HashMap<i64, ScreenplayDoc>,
         }
         let parsed: ImportFile = match serde_json::from_str(&content) {
             Ok(v) => v,
@@ -518,9 +1114,16 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
         // Create project (prefer metadata project name)
         let project_name = parsed.project.name.clone();
         let payload = crate::models::ProjectCreate { name: project_name, desc: parsed.project.desc.clone(), path: Some(folder_path.clone()) };
-        let new_project = crate::services::projects::create(pool, payload).map_err(|e| e.to_string())?;
+        let mut new_project = crate::services::projects::create(pool, payload).map_err(|e| e.to_string())?;
+        if parsed.project.linear_chronology {
+            new_project = crate::services::projects::set_linear_chronology(pool, new_project.id, true).map_err(|e| e.to_string())?;
+        }
 
         use std::collections::HashMap;
+        // Calendars first: events and timelines refer to them
+        let calendar_id_map = crate::services::calendars::import_snapshot(pool, new_project.id, &parsed.calendars).map_err(|e| e.to_string())?;
+        let map_calendar = |old: Option<i64>| old.and_then(|id| calendar_id_map.get(&id).copied());
+
         // Create groups in parent-first order using original ids for mapping
         let mut groups_by_parent: HashMap<Option<i64>, Vec<&crate::models::DocGroup>> = HashMap::new();
         for g in &parsed.groups {
@@ -574,6 +1177,13 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
             }
         }
 
+        // Comment threads per doc, anchored to the text imported above
+        for (old_doc_id, threads) in parsed.comments_by_doc.iter() {
+            if let Some(&new_doc_id) = doc_id_map.get(old_doc_id) {
+                crate::services::comments::import_threads(pool, new_doc_id, threads).map_err(|e| e.to_string())?;
+            }
+        }
+
         // Characters
         let mut char_id_map: HashMap<i64, i64> = HashMap::new();
         for c in &parsed.characters {
@@ -583,7 +1193,7 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
         // Events
         let mut event_id_map: HashMap<i64, i64> = HashMap::new();
         for e in &parsed.events {
-            let created = crate::services::events::create(pool, new_project.id, &e.name, e.desc.clone(), e.start_date.clone(), e.end_date.clone(), e.date.clone()).map_err(|e| e.to_string())?;
+            let created = crate::services::events::create(pool, new_project.id, &e.name, e.desc.clone(), e.start_date.clone(), e.end_date.clone(), e.date.clone(), map_calendar(e.calendar_id)).map_err(|e| e.to_string())?;
             event_id_map.insert(e.id, created.id);
         }
 
@@ -607,14 +1217,105 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
             }
         }
 
+        // Codex
+        let codex_id_map = crate::services::codex::import_snapshot(pool, new_project.id, &parsed.codex_types, &parsed.codex_entities).map_err(|e| e.to_string())?;
+        // Events and attachments only link to location entities; older exports may hold others
+        let location_types: std::collections::HashSet<i64> = parsed.codex_types.iter()
+            .filter(|t| t.name.eq_ignore_ascii_case("location"))
+            .map(|t| t.id)
+            .collect();
+        let old_locations: std::collections::HashSet<i64> = parsed.codex_entities.iter()
+            .filter(|e| location_types.contains(&e.type_id))
+            .map(|e| e.id)
+            .collect();
+        for (old_doc_id, old_entities) in parsed.doc_codex_entities.iter() {
+            if let Some(&new_doc_id) = doc_id_map.get(old_doc_id) {
+                for old_en in old_entities {
+                    if let Some(&new_en_id) = codex_id_map.get(old_en) {
+                        crate::services::codex::attach_to_doc(pool, new_doc_id, new_en_id).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+        // Plot threads
+        let thread_id_map = crate::services::plot_threads::import_snapshot(pool, new_project.id, &parsed.plot_threads).map_err(|e| e.to_string())?;
+        for (old_doc_id, links) in parsed.doc_plot_threads.iter() {
+            if let Some(&new_doc_id) = doc_id_map.get(old_doc_id) {
+                for link in links {
+                    if let Some(&new_thread_id) = thread_id_map.get(&link.thread_id) {
+                        crate::services::plot_threads::link_doc(pool, new_doc_id, new_thread_id, link.role).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+        for (old_ev_id, old_chars) in parsed.event_characters.iter() {
+            if let Some(&new_ev_id) = event_id_map.get(old_ev_id) {
+                for old_ch in old_chars {
+                    if let Some(&new_ch_id) = char_id_map.get(old_ch) {
+                        crate::services::events::attach_character(pool, new_ev_id, new_ch_id).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+        for (old_ev_id, old_event_locations) in parsed.event_locations.iter() {
+            if let Some(&new_ev_id) = event_id_map.get(old_ev_id) {
+                for old_loc in old_event_locations.iter().filter(|id| old_locations.contains(id)) {
+                    if let Some(&new_loc_id) = codex_id_map.get(old_loc) {
+                        crate::services::events::attach_location(pool, new_ev_id, new_loc_id).map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+
+        // Research notes, re-attached to the imported project, folders, docs, characters and events
+        crate::services::research_notes::import_snapshot(pool, new_project.id, &parsed.research_notes, |parent_type, old_id| {
+            match parent_type {
+                "project" => Some(new_project.id),
+                "folder" => group_id_map.get(&old_id).copied(),
+                "doc" => doc_id_map.get(&old_id).copied(),
+                "character" => char_id_map.get(&old_id).copied(),
+                "event" => event_id_map.get(&old_id).copied(),
+                _ => None,
+            }
+        }).map_err(|e| e.to_string())?;
+
+        // Attachments, read back from the export's media folder into the local store
+        if !parsed.attachments.is_empty() {
+            let root = crate::services::attachments::store_dir().map_err(|e| e.to_string())?;
+            crate::services::attachments::import_media(pool, &root, &base.join("media"), &parsed.attachments, |parent_type, old_id| {
+                match parent_type {
+                    "project" => Some(new_project.id),
+                    "doc" => doc_id_map.get(&old_id).copied(),
+                    "character" => char_id_map.get(&old_id).copied(),
+                    "location" if old_locations.contains(&old_id) => codex_id_map.get(&old_id).copied(),
+                    _ => None,
+                }
+            }).map_err(|e| e.to_string())?;
+        }
+
+        // Doc status, label and synopsis, and compile presets with their folders and docs remapped
+        for (old_doc_id, meta) in parsed.doc_metadata.iter() {
+            if let Some(&new_doc_id) = doc_id_map.get(old_doc_id) {
+                crate::services::doc_metadata::set(pool, new_doc_id, meta.clone()).map_err(|e| e.to_string())?;
+            }
+        }
+        for preset in parsed.compile_presets.iter() {
+            let settings = crate::services::compile_presets::remap_settings(preset.settings.clone(), &group_id_map, &doc_id_map);
+            let input = CompilePresetInput { name: preset.name.clone(), format: preset.format, settings };
+            crate::services::compile_presets::create(pool, new_project.id, input).map_err(|e| e.to_string())?;
+        }
+
+        // Screenplay mode of the project and of docs that set their own, with scene locations
+        crate::services::screenplay::import_snapshot(pool, new_project.id, parsed.screenplay_project, &parsed.screenplay_docs, &doc_id_map).map_err(|e| e.to_string())?;
+
         // Timelines
         if let Some(tl) = parsed.project_timeline.clone() {
-            let _ = crate::services::timelines::create(pool, crate::models::TimelineCreate { entity_type: "project".into(), entity_id: new_project.id, start_date: tl.start_date, end_date: tl.end_date }).map_err(|e| e.to_string())?;
+            let _ = crate::services::timelines::create(pool, crate::models::TimelineCreate { entity_type: "project".into(), entity_id: new_project.id, start_date: tl.start_date, end_date: tl.end_date, calendar_id: map_calendar(tl.calendar_id) }).map_err(|e| e.to_string())?;
         }
         for (old_doc_id, maybe_tl) in parsed.doc_timelines.iter() {
             if let Some(&new_doc_id) = doc_id_map.get(old_doc_id) {
                 if let Some(tl) = maybe_tl {
-                    let _ = crate::services::timelines::create(pool, crate::models::TimelineCreate { entity_type: "doc".into(), entity_id: new_doc_id, start_date: tl.start_date.clone(), end_date: tl.end_date.clone() }).map_err(|e| e.to_string())?;
+                    let _ = crate::services::timelines::create(pool, crate::models::TimelineCreate { entity_type: "doc".into(), entity_id: new_doc_id, start_date: tl.start_date.clone(), end_date: tl.end_date.clone(), calendar_id: map_calendar(tl.calendar_id) }).map_err(|e| e.to_string())?;
                 }
             }
         }
@@ -626,14 +1327,42 @@ pub async fn import_project(state: State<'_, AppState>, folder_path: String) ->
         return legacy_import_folder(pool, base, &folder_path);
 }
 
-// Helper to perform legacy folder import
+/// Import a Scrivener project (.scriv bundle folder) as a new project.
+/// - The binder becomes nested doc groups and docs; RTF content is converted to text
+/// - Synopsis, notes, label, status and keywords go into each doc's notes
+/// - Research items become project notes; the Trash is skipped
+#[tauri::command]
+pub async fn import_scrivener(state: State<'_, AppState>, bundle_path: String) -> Result<serde_json::Value, String> {
+    let pool = &state.pool;
+    let bundle = Path::new(&bundle_path);
+    if !bundle.is_dir() {
+        return Err("Selected path is not a Scrivener project (.scriv) folder".to_string());
+    }
+    let project = crate::services::scrivener::import(pool, bundle).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_value(project).map_err(|e| format!("{:#}", e))
+}
+
+// Helper to perform legacy folder import; the project comes back with a `skipped` list of files
+// that could not be read
 fn legacy_import_folder(pool: &crate::db::DbPool, base: &Path, folder_path: &str) -> Result<serde_json::Value, String> {
         // Project name from folder basename
         let project_name = base.file_name().and_then(|s| s.to_str()).unwrap_or("Imported Project").to_string();
         let payload = crate::models::ProjectCreate { name: project_name.clone(), desc: None, path: Some(folder_path.to_string()) };
         let project = crate::services::projects::create(pool, payload).map_err(|e| e.to_string())?;
 
-        // Read entries and partition into subdirs and root .txt files
+        // Files that cannot be read are skipped and listed with a warning instead of aborting the import
+        let mut skipped: Vec<ImportedFile> = Vec::new();
+        let import_file = |path: &Path, group_id: i64, skipped: &mut Vec<ImportedFile>| -> Result<(), String> {
+                match crate::services::manuscript::plan_file(path, &HeadingImportOptions::default()) {
+                        Ok((plan, _)) => {
+                                crate::services::manuscript::apply(pool, project.id, Some(group_id), &plan).map_err(|e| e.to_string())?;
+                        }
+                        Err(e) => skipped.push(ImportedFile { path: path.display().to_string(), encoding: None, warning: Some(format!("{:#}", e)) }),
+                }
+                Ok(())
+        };
+
+        // Read entries and partition into subdirs and root .txt/.docx/.odt files
         let mut subdirs: Vec<(String, std::path::PathBuf)> = Vec::new();
         let mut root_txt_files: Vec<std::path::PathBuf> = Vec::new();
         for entry in fs::read_dir(base).map_err(|e| format!("Failed to read dir {}: {}", base.display(), e))? {
@@ -643,7 +1372,7 @@ fn legacy_import_folder(pool: &crate::db::DbPool, base: &Path, folder_path: &str
                         let name = p.file_name().and_then(|s| s.to_str()).unwrap_or("Folder").to_string();
                         subdirs.push((name, p));
                 } else if p.is_file() {
-                        if p.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("txt")).unwrap_or(false) {
+                        if crate::services::manuscript::is_importable(&p) {
                                 root_txt_files.push(p);
                         }
                 }
@@ -656,11 +1385,8 @@ fn legacy_import_folder(pool: &crate::db::DbPool, base: &Path, folder_path: &str
                         let entry = entry.map_err(|e| e.to_string())?;
                         let file_path = entry.path();
                         if file_path.is_file() {
-                                if file_path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("txt")).unwrap_or(false) {
-                                        let name = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
-                                        let text = fs::read_to_string(&file_path).map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
-                                        let doc = crate::services::docs::create_doc(pool, project.id, name, Some(group.id)).map_err(|e| e.to_string())?;
-                                        crate::services::docs::update_doc(pool, doc.id, &text).map_err(|e| e.to_string())?;
+                                if crate::services::manuscript::is_importable(&file_path) {
+                                        import_file(&file_path, group.id, &mut skipped)?;
                                 }
                         }
                 }
@@ -668,17 +1394,19 @@ fn legacy_import_folder(pool: &crate::db::DbPool, base: &Path, folder_path: &str
         if !root_txt_files.is_empty() {
                 let unsorted = crate::services::doc_groups::create_doc_group(pool, project.id, "UNSORTED", None).map_err(|e| e.to_string())?;
                 for file_path in root_txt_files.iter() {
-                        let name = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
-                        let text = fs::read_to_string(&file_path).map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
-                        let doc = crate::services::docs::create_doc(pool, project.id, name, Some(unsorted.id)).map_err(|e| e.to_string())?;
-                        crate::services::docs::update_doc(pool, doc.id, &text).map_err(|e| e.to_string())?;
+                        import_file(file_path, unsorted.id, &mut skipped)?;
                 }
         }
-        serde_json::to_value(project).map_err(|e| e.to_string())
+        let mut value = serde_json::to_value(project).map_err(|e| e.to_string())?;
+        value["skipped"] = serde_json::to_value(skipped).map_err(|e| e.to_string())?;
+        Ok(value)
 }
 
+/// Export a project as folders of text files plus a metadata.json that can be imported again.
+/// With `preset_id`, the preset decides which docs get text files, how files and folders are named
+/// and how the text is converted; metadata.json always covers the whole project.
 #[tauri::command]
-pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_path: String) -> Result<(), String> {
+pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_path: String, preset_id: Option<i64>) -> Result<(), String> {
     // use std::path::{PathBuf};
     let pool = &state.pool;
 
@@ -696,21 +1424,19 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
     let groups = crate::services::doc_groups::list_doc_groups(pool, project_id).map_err(|e| e.to_string())?;
     let docs = crate::services::docs::list_docs(pool, project_id).map_err(|e| e.to_string())?;
 
-    // Build maps
     use std::collections::HashMap;
-    let mut children: HashMap<Option<i64>, Vec<&crate::models::DocGroup>> = HashMap::new();
-    for g in &groups {
-        children.entry(g.parent_id).or_default().push(g);
-    }
-    // Sort siblings by sort_order
-    for v in children.values_mut() {
-        v.sort_by_key(|g| g.sort_order.unwrap_or(0));
-    }
-    let mut docs_by_group: HashMap<i64, Vec<&crate::models::Doc>> = HashMap::new();
-    for d in &docs {
-        if let Some(gid) = d.doc_group_id { docs_by_group.entry(gid).or_default().push(d); }
-    }
-    for v in docs_by_group.values_mut() { v.sort_by_key(|d| d.sort_order.unwrap_or(0)); }
+    let mut tree = crate::services::project_tree::load(pool, project_id).map_err(|e| e.to_string())?;
+    let settings = match preset_id {
+        Some(id) => {
+            let preset = crate::services::compile_presets::get(pool, id).map_err(|e| e.to_string())?
+                .filter(|p| p.project_id == project_id)
+                .ok_or_else(|| "Compile preset not found".to_string())?;
+            let doc_meta = crate::services::doc_metadata::list_for_project(pool, project_id).map_err(|e| e.to_string())?;
+            tree = crate::services::compile::select(tree, &preset.settings, &doc_meta, &Default::default());
+            preset.settings
+        }
+        None => CompileSettings::default(),
+    };
 
     // Helper: sanitize names
     fn sanitize(s: &str) -> String {
@@ -720,45 +1446,64 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
         out.chars().map(|c| if bad.contains(&c) { '_' } else { c }).collect()
     }
 
+    // Name from the settings' template (default "{index} {name}"): {index} is the position in the
+    // parent ("2" for a group, "2.3" for a doc), {n} the position among siblings
+    fn entry_name(settings: &CompileSettings, index: &str, n: usize, name: &str) -> String {
+        let template = settings.file_names.as_deref().filter(|t| !t.trim().is_empty()).unwrap_or("{index} {name}");
+        let name = template.replace("{index}", index).replace("{n}", &n.to_string()).replace("{name}", name);
+        sanitize(&name)
+    }
+
+    // Write docs as text files in `dir`, each followed by its drafts; `group_index` prefixes the
+    // doc index inside a group, root-level docs are indexed by position alone
+    fn export_docs(
+        pool: &crate::db::DbPool,
+        settings: &CompileSettings,
+        dir: &Path,
+        docs: &[crate::models::Doc],
+        group_index: Option<usize>,
+    ) -> Result<(), String> {
+        for (i, d) in docs.iter().enumerate() {
+            let doc_index = match group_index {
+                Some(gi) => format!("{}.{}", gi, i + 1),
+                None => (i + 1).to_string(),
+            };
+            let doc_name = sanitize(d.name.as_deref().unwrap_or("Untitled"));
+            let stem = entry_name(settings, &doc_index, i + 1, &doc_name);
+            let file_path = dir.join(format!("{}.txt", stem));
+            let content = crate::services::compile::convert_text(d.text.as_deref().unwrap_or(""), settings);
+            fs::write(&file_path, content).map_err(|e| format!("Write doc failed: {}", e))?;
+
+            // Drafts as separate files: doc file name + draft_index
+            let drafts = crate::services::drafts::list_drafts(pool, d.id).map_err(|e| e.to_string())?;
+            for (k, draft) in drafts.iter().enumerate() {
+                let draft_file = format!("{} draft-{}.txt", stem, k + 1);
+                let draft_path = dir.join(draft_file);
+                let content = crate::services::compile::convert_text(&draft.content, settings);
+                fs::write(&draft_path, content).map_err(|e| format!("Write draft failed: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
     // Export recursively
     fn export_group_recursive(
         pool: &crate::db::DbPool,
+        settings: &CompileSettings,
         base_dir: &Path,
-        group: &crate::models::DocGroup,
+        node: &crate::services::project_tree::TreeGroup,
         group_index: usize,
-        children: &HashMap<Option<i64>, Vec<&crate::models::DocGroup>>,
-        docs_by_group: &HashMap<i64, Vec<&crate::models::Doc>>,
     ) -> Result<(), String> {
-        // Directory name: "{index} {name}"
-        let dir_name = format!("{} {}", group_index, sanitize(&group.name));
+        let dir_name = entry_name(settings, &group_index.to_string(), group_index, &sanitize(&node.group.name));
         let group_dir = base_dir.join(dir_name);
         fs::create_dir_all(&group_dir).map_err(|e| format!("Failed to create group dir: {}", e))?;
 
         // Docs in this group
-        if let Some(dd) = docs_by_group.get(&group.id) {
-            for (i, d) in dd.iter().enumerate() {
-                let doc_index = format!("{}.{}", group_index, i + 1);
-                let doc_name = sanitize(d.name.as_deref().unwrap_or("Untitled"));
-                let file_name = format!("{} {}.txt", doc_index, doc_name);
-                let file_path = group_dir.join(file_name);
-                let content = d.text.clone().unwrap_or_default();
-                fs::write(&file_path, content).map_err(|e| format!("Write doc failed: {}", e))?;
-
-                // Drafts as separate files: doc_index + doc_file + draft_index
-                let drafts = crate::services::drafts::list_drafts(pool, d.id).map_err(|e| e.to_string())?;
-                for (k, draft) in drafts.iter().enumerate() {
-                    let draft_file = format!("{} {} draft-{}.txt", doc_index, doc_name, k + 1);
-                    let draft_path = group_dir.join(draft_file);
-                    fs::write(&draft_path, &draft.content).map_err(|e| format!("Write draft failed: {}", e))?;
-                }
-            }
-        }
+        export_docs(pool, settings, &group_dir, &node.docs, Some(group_index))?;
 
         // Recurse into child groups under this group
-        if let Some(childs) = children.get(&Some(group.id)) {
-            for (idx, child) in childs.iter().enumerate() {
-                export_group_recursive(pool, &group_dir, child, idx + 1, children, docs_by_group)?;
-            }
+        for (idx, child) in node.children.iter().enumerate() {
+            export_group_recursive(pool, settings, &group_dir, child, idx + 1)?;
         }
         Ok(())
     }
@@ -776,11 +1521,10 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
     };
     fs::create_dir_all(&export_root).map_err(|e| format!("Failed to create export folder: {}", e))?;
 
-    // Export root-level groups into export_root
-    if let Some(root_groups) = children.get(&None) {
-        for (gi, g) in root_groups.iter().enumerate() {
-            export_group_recursive(pool, &export_root, g, gi + 1, &children, &docs_by_group)?;
-        }
+    // Export root-level docs, then root-level groups, into export_root
+    export_docs(pool, &settings, &export_root, &tree.docs, None)?;
+    for (gi, g) in tree.groups.iter().enumerate() {
+        export_group_recursive(pool, &settings, &export_root, g, gi + 1)?;
     }
 
     // Build metadata
@@ -789,12 +1533,38 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
     // doc -> character ids
     let mut doc_characters: HashMap<i64, Vec<i64>> = HashMap::new();
     let mut doc_events: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut doc_codex_entities: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut doc_plot_threads: HashMap<i64, Vec<crate::models::DocThreadLink>> = HashMap::new();
     for d in &docs {
         let ch = crate::services::characters::list_for_doc(pool, d.id).map_err(|e| e.to_string())?;
         doc_characters.insert(d.id, ch);
         let ev = crate::services::events::list_for_doc(pool, d.id).map_err(|e| e.to_string())?;
         doc_events.insert(d.id, ev);
+        let cx = crate::services::codex::list_for_doc(pool, d.id).map_err(|e| e.to_string())?;
+        if !cx.is_empty() { doc_codex_entities.insert(d.id, cx); }
+        let th = crate::services::plot_threads::list_for_doc(pool, d.id).map_err(|e| e.to_string())?;
+        if !th.is_empty() { doc_plot_threads.insert(d.id, th); }
     }
+    // event -> participant character ids / location entity ids
+    let mut event_characters: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut event_locations: HashMap<i64, Vec<i64>> = HashMap::new();
+    for e in &events {
+        let ch = crate::services::events::list_characters(pool, e.id).map_err(|e| e.to_string())?;
+        if !ch.is_empty() { event_characters.insert(e.id, ch); }
+        let loc = crate::services::events::list_locations(pool, e.id).map_err(|e| e.to_string())?;
+        if !loc.is_empty() { event_locations.insert(e.id, loc); }
+    }
+    let calendars = crate::services::calendars::list(pool, project_id).map_err(|e| e.to_string())?;
+    let codex_types = crate::services::codex::list_types(pool, project_id).map_err(|e| e.to_string())?;
+    let codex_entities = crate::services::codex::list_entities(pool, project_id, None).map_err(|e| e.to_string())?;
+    let plot_threads = crate::services::plot_threads::list(pool, project_id).map_err(|e| e.to_string())?;
+    let research_notes = crate::services::research_notes::list(pool, project_id).map_err(|e| e.to_string())?;
+    let doc_metadata = crate::services::doc_metadata::list_for_project(pool, project_id).map_err(|e| e.to_string())?;
+    let compile_presets = crate::services::compile_presets::list(pool, project_id).map_err(|e| e.to_string())?;
+    let screenplay_project = crate::services::screenplay::project_enabled(pool, project_id).map_err(|e| e.to_string())?;
+    let screenplay_docs = crate::services::screenplay::list_for_project(pool, project_id).map_err(|e| e.to_string())?;
+    let media_root = crate::services::attachments::store_dir().map_err(|e| e.to_string())?;
+    let attachments = crate::services::attachments::export_media(pool, &media_root, project_id, &export_root.join("media")).map_err(|e| e.to_string())?;
     let project_timeline = crate::services::timelines::get_by_entity(pool, "project", project_id).map_err(|e| e.to_string())?;
     let mut doc_timelines: HashMap<i64, Option<crate::models::Timeline>> = HashMap::new();
     for d in &docs {
@@ -808,6 +1578,11 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
         let ds = crate::services::drafts::list_drafts(pool, d.id).map_err(|e| e.to_string())?;
         if !ds.is_empty() { drafts_by_doc.insert(d.id, ds); }
     }
+    let mut comments_by_doc: HashMap<i64, Vec<crate::models::CommentThread>> = HashMap::new();
+    for d in &docs {
+        let threads = crate::services::comments::list_for_doc(pool, d.id, true).map_err(|e| e.to_string())?;
+        if !threads.is_empty() { comments_by_doc.insert(d.id, threads); }
+    }
 
     let meta = serde_json::json!({
         "meta": {
@@ -825,6 +1600,21 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
         "project_timeline": project_timeline,
         "doc_timelines": doc_timelines,
         "drafts_by_doc": drafts_by_doc,
+        "comments_by_doc": comments_by_doc,
+        "codex_types": codex_types,
+        "codex_entities": codex_entities,
+        "doc_codex_entities": doc_codex_entities,
+        "event_characters": event_characters,
+        "event_locations": event_locations,
+        "calendars": calendars,
+        "plot_threads": plot_threads,
+        "doc_plot_threads": doc_plot_threads,
+        "research_notes": research_notes,
+        "attachments": attachments,
+        "doc_metadata": doc_metadata,
+        "compile_presets": compile_presets,
+        "screenplay_project": screenplay_project,
+        "screenplay_docs": screenplay_docs,
     });
 
     let meta_json = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
@@ -832,4 +1622,130 @@ pub async fn export_project(state: State<'_, AppState>, project_id: i64, dest_pa
     fs::write(&meta_path, meta_json).map_err(|e| format!("Write metadata failed: {}", e))?;
 
     Ok(())
+}
+
+/// Compile a project's manuscript into a single file (`dest_path`) in the given format.
+/// Groups become headings by tree depth and the docs within them are separated by scene breaks.
+/// `settings` are those of a compile preset, given ad hoc: what to include, heading templates, text
+/// conversions, and layout such as the HTML theme, PDF page size and title page.
+#[tauri::command]
+pub async fn compile_project(state: State<'_, AppState>, project_id: i64, format: CompileFormat, dest_path: String, settings: Option<CompileSettings>) -> Result<(), String> {
+    let pool = &state.pool;
+    let settings = settings.unwrap_or_default();
+    crate::services::compile::compile(pool, project_id, format, &settings, Path::new(&dest_path)).map_err(|e| format!("{:#}", e))
+}
+
+/// Compile a project with one of its saved presets, in the preset's format
+#[tauri::command]
+pub async fn compile_with_preset(state: State<'_, AppState>, preset_id: i64, dest_path: String) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::compile::compile_preset(pool, preset_id, Path::new(&dest_path)).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn compile_preset_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<CompilePreset>, String> {
+    let pool = &state.pool;
+    crate::services::compile_presets::list(pool, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn compile_preset_create(state: State<'_, AppState>, project_id: i64, payload: CompilePresetInput) -> Result<CompilePreset, String> {
+    let pool = &state.pool;
+    crate::services::compile_presets::create(pool, project_id, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn compile_preset_update(state: State<'_, AppState>, id: i64, payload: CompilePresetInput) -> Result<CompilePreset, String> {
+    let pool = &state.pool;
+    crate::services::compile_presets::update(pool, id, payload).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn compile_preset_delete(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::compile_presets::delete(pool, id).map_err(|e| format!("{:#}", e))
+}
+
+/// What a typography pass would change in one doc, or the whole project when doc_id is None
+#[tauri::command]
+pub async fn typography_preview(state: State<'_, AppState>, project_id: i64, doc_id: Option<i64>, options: TypographyOptions) -> Result<Vec<TypographyPreview>, String> {
+    let pool = &state.pool;
+    crate::services::typography::preview(pool, project_id, doc_id, &options).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn typography_apply(state: State<'_, AppState>, project_id: i64, doc_id: Option<i64>, options: TypographyOptions) -> Result<TypographyRun, String> {
+    let pool = &state.pool;
+    crate::services::typography::apply(pool, project_id, doc_id, &options).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn typography_run_list(state: State<'_, AppState>, project_id: i64) -> Result<Vec<TypographyRun>, String> {
+    let pool = &state.pool;
+    crate::services::typography::list_runs(pool, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn typography_undo(state: State<'_, AppState>, run_id: i64) -> Result<TypographyUndo, String> {
+    let pool = &state.pool;
+    crate::services::typography::undo(pool, run_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn screenplay_project_get(state: State<'_, AppState>, project_id: i64) -> Result<bool, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::project_enabled(pool, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn screenplay_project_set(state: State<'_, AppState>, project_id: i64, enabled: bool) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::screenplay::set_project(pool, project_id, enabled).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn screenplay_doc_get(state: State<'_, AppState>, doc_id: i64) -> Result<ScreenplayDoc, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::doc(pool, doc_id).map_err(|e| format!("{:#}", e))
+}
+
+/// Set a doc's screenplay mode (None follows the project) and sync it
+#[tauri::command]
+pub async fn screenplay_doc_set(state: State<'_, AppState>, doc_id: i64, mode: Option<bool>) -> Result<ScreenplayDoc, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::set_doc(pool, doc_id, mode).map_err(|e| format!("{:#}", e))?;
+    crate::services::screenplay::sync_doc(pool, doc_id, false).map_err(|e| format!("{:#}", e))?;
+    crate::services::screenplay::doc(pool, doc_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn screenplay_parse(state: State<'_, AppState>, doc_id: i64) -> Result<Screenplay, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::parse_doc(pool, doc_id).map_err(|e| format!("{:#}", e))
+}
+
+/// Refresh locations and speaker links of every screenplay doc, adding characters for new speakers
+#[tauri::command]
+pub async fn screenplay_sync(state: State<'_, AppState>, project_id: i64) -> Result<ScreenplaySync, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::sync_project(pool, project_id).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn import_fountain(state: State<'_, AppState>, project_id: i64, doc_group_id: Option<i64>, file: String) -> Result<ScreenplaySync, String> {
+    let pool = &state.pool;
+    crate::services::screenplay::import(pool, project_id, doc_group_id, Path::new(&file)).map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn export_fountain(state: State<'_, AppState>, project_id: i64, dest_path: String) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::screenplay::export(pool, project_id, Path::new(&dest_path)).map_err(|e| format!("{:#}", e))
+}
+
+/// Compile the project's screenplay docs to an industry-format PDF
+#[tauri::command]
+pub async fn compile_screenplay(state: State<'_, AppState>, project_id: i64, dest_path: String) -> Result<(), String> {
+    let pool = &state.pool;
+    crate::services::screenplay::compile(pool, project_id, Path::new(&dest_path)).map_err(|e| format!("{:#}", e))
 }
\ No newline at end of file
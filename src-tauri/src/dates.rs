@@ -0,0 +1,298 @@
+use chrono::{Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DateError {
+    #[error("invalid date '{0}': expected ISO 8601 (YYYY, YYYY-MM, YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS][Z|±HH:MM])")]
+    Malformed(String),
+    #[error("invalid date range: end {end} is before start {start}")]
+    InvertedRange { start: String, end: String },
+}
+
+/// An ISO 8601 date with optional precision: a year, a month, a day or a full datetime.
+/// Datetimes keep the offset they were written with; ordering compares the UTC instant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialDate {
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+    pub time: Option<NaiveTime>,
+    pub offset: Option<FixedOffset>,
+}
+
+fn digits(s: &str, min: usize, max: usize) -> Option<u32> {
+    if s.len() < min || s.len() > max || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    if s == "Z" || s == "z" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match s.chars().next()? { '+' => 1, '-' => -1, _ => return None };
+    let rest = &s[1..];
+    let (h, m) = match rest.len() {
+        2 => (rest, "00"),
+        4 => (&rest[..2], &rest[2..]),
+        5 if &rest[2..3] == ":" => (&rest[..2], &rest[3..]),
+        _ => return None,
+    };
+    let (h, m) = (digits(h, 2, 2)?, digits(m, 2, 2)?);
+    if h > 23 || m > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60) as i32)
+}
+
+fn parse_time(s: &str) -> Option<(NaiveTime, Option<FixedOffset>)> {
+    let split = s.find(['Z', 'z', '+', '-']).unwrap_or(s.len());
+    let (clock, offset) = s.split_at(split);
+    let offset = if offset.is_empty() { None } else { Some(parse_offset(offset)?) };
+    let time = NaiveTime::parse_from_str(clock, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(clock, "%H:%M"))
+        .ok()?;
+    Some((time, offset))
+}
+
+impl PartialDate {
+    /// Parse an ISO 8601 date of year, month or day precision, or a datetime
+    pub fn parse(input: &str) -> Result<PartialDate, DateError> {
+        let malformed = || DateError::Malformed(input.to_string());
+        let s = input.trim();
+        let (date_part, time_part) = match s.find(['T', 't', ' ']) {
+            Some(i) => (&s[..i], Some(s[i + 1..].trim())),
+            None => (s, None),
+        };
+
+        let (sign, unsigned) = match date_part.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, date_part.strip_prefix('+').unwrap_or(date_part)),
+        };
+        let mut parts = unsigned.split('-');
+        let year = parts.next().and_then(|y| digits(y, 4, 6)).ok_or_else(malformed)? as i32 * sign;
+        let month = match parts.next() {
+            Some(m) => Some(digits(m, 2, 2).filter(|m| (1..=12).contains(m)).ok_or_else(malformed)?),
+            None => None,
+        };
+        let day = match parts.next() {
+            Some(d) => Some(digits(d, 2, 2).ok_or_else(malformed)?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(malformed());
+        }
+        if let (Some(m), Some(d)) = (month, day) {
+            NaiveDate::from_ymd_opt(year, m, d).ok_or_else(malformed)?;
+        }
+
+        let (time, offset) = match time_part {
+            Some(t) if day.is_some() => {
+                let (time, offset) = parse_time(t).ok_or_else(malformed)?;
+                (Some(time), offset)
+            }
+            Some(_) => return Err(malformed()),
+            None => (None, None),
+        };
+
+        // Years beyond chrono's range (about ±262143) would have no instants to compare by
+        let date = PartialDate { year, month, day, time, offset };
+        if date.first_instant().is_none() || date.last_instant().is_none() {
+            return Err(malformed());
+        }
+        Ok(date)
+    }
+
+    /// Earliest instant covered (start of the year, month, day, or the datetime itself);
+    /// None only for dates built outside `parse` that chrono cannot represent
+    pub fn first_instant(&self) -> Option<NaiveDateTime> {
+        let date = NaiveDate::from_ymd_opt(self.year, self.month.unwrap_or(1), self.day.unwrap_or(1))?;
+        match self.time {
+            Some(t) => {
+                let local = date.and_time(t);
+                match self.offset {
+                    Some(o) => local.checked_sub_signed(Duration::seconds(o.local_minus_utc() as i64)),
+                    None => Some(local),
+                }
+            }
+            None => date.and_hms_opt(0, 0, 0),
+        }
+    }
+
+    /// Latest instant covered (end of the year, month, day, or the datetime itself)
+    pub fn last_instant(&self) -> Option<NaiveDateTime> {
+        if self.time.is_some() {
+            return self.first_instant();
+        }
+        let end_exclusive = match (self.month, self.day) {
+            (Some(m), Some(d)) => NaiveDate::from_ymd_opt(self.year, m, d).and_then(|d| d.succ_opt()),
+            (Some(12), None) => NaiveDate::from_ymd_opt(self.year.checked_add(1)?, 1, 1),
+            (Some(m), None) => NaiveDate::from_ymd_opt(self.year, m + 1, 1),
+            (None, _) => NaiveDate::from_ymd_opt(self.year.checked_add(1)?, 1, 1),
+        }?;
+        end_exclusive.and_hms_opt(0, 0, 0)?.checked_sub_signed(Duration::seconds(1))
+    }
+
+    /// Absolute day numbers of the first and last day covered
+    pub fn day_range(&self) -> Option<(i64, i64)> {
+        Some((day_number(self.first_instant()?.date()), day_number(self.last_instant()?.date())))
+    }
+
+    fn precision_rank(&self) -> u8 {
+        match (self.month, self.day, self.time) {
+            (None, _, _) => 0,
+            (Some(_), None, _) => 1,
+            (Some(_), Some(_), None) => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl Ord for PartialDate {
+    /// Chronological by earliest instant; a coarser date sorts before a finer one starting at the same instant.
+    /// The same instant written with different offsets is ordered by offset, so `Equal` agrees with `==`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.first_instant()
+            .cmp(&other.first_instant())
+            .then(self.precision_rank().cmp(&other.precision_rank()))
+            .then_with(|| self.offset.map(|o| o.local_minus_utc()).cmp(&other.offset.map(|o| o.local_minus_utc())))
+            .then_with(|| (self.year, self.month, self.day, self.time).cmp(&(other.year, other.month, other.day, other.time)))
+    }
+}
+
+impl PartialOrd for PartialDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PartialDate {
+    /// Canonical ISO 8601 form, e.g. `2025`, `2025-03`, `2025-03-01`, `2025-03-01T09:30:00+02:00`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.year < 0 {
+            write!(f, "-{:04}", -self.year)?;
+        } else {
+            write!(f, "{:04}", self.year)?;
+        }
+        if let Some(m) = self.month { write!(f, "-{:02}", m)?; }
+        if let Some(d) = self.day { write!(f, "-{:02}", d)?; }
+        if let Some(t) = self.time {
+            write!(f, "T{}", t.format("%H:%M:%S%.f"))?;
+            match self.offset {
+                Some(o) if o.local_minus_utc() == 0 => write!(f, "Z")?,
+                Some(o) => write!(f, "{}", o)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Absolute day number shared by all calendars: Gregorian 0001-01-01 is day 1
+pub fn day_number(date: NaiveDate) -> i64 {
+    date.num_days_from_ce() as i64
+}
+
+/// Gregorian date of an absolute day number
+pub fn from_day_number(day: i64) -> Option<NaiveDate> {
+    i32::try_from(day).ok().and_then(NaiveDate::from_num_days_from_ce_opt)
+}
+
+/// Seconds on the absolute day scale, so datetimes compare with whole calendar days
+pub fn epoch_seconds(instant: NaiveDateTime) -> i64 {
+    day_number(instant.date()) * SECONDS_PER_DAY + instant.num_seconds_from_midnight() as i64
+}
+
+/// Parse an optional date, treating blank strings as absent
+pub fn parse_opt(value: Option<&str>) -> Result<Option<PartialDate>, DateError> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(v) => PartialDate::parse(v).map(Some),
+    }
+}
+
+/// Validate and rewrite an optional date into canonical form
+pub fn normalize(value: Option<String>) -> Result<Option<String>, DateError> {
+    Ok(parse_opt(value.as_deref())?.map(|d| d.to_string()))
+}
+
+/// Reject ranges that end before they start; a missing bound is always valid
+pub fn validate_range(start: Option<&str>, end: Option<&str>) -> Result<(), DateError> {
+    if let (Some(s), Some(e)) = (parse_opt(start)?, parse_opt(end)?) {
+        if e.last_instant() < s.first_instant() {
+            return Err(DateError::InvertedRange { start: s.to_string(), end: e.to_string() });
+        }
+    }
+    Ok(())
+}
+
+/// Normalise both bounds of a range and check their order
+pub fn normalize_range(start: Option<String>, end: Option<String>) -> Result<(Option<String>, Option<String>), DateError> {
+    let start = normalize(start)?;
+    let end = normalize(end)?;
+    validate_range(start.as_deref(), end.as_deref())?;
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_precision_into_canonical_form() {
+        assert_eq!(PartialDate::parse("2025").unwrap().to_string(), "2025");
+        assert_eq!(PartialDate::parse("2025-03").unwrap().to_string(), "2025-03");
+        assert_eq!(PartialDate::parse(" 2025-03-01 ").unwrap().to_string(), "2025-03-01");
+        assert_eq!(PartialDate::parse("2025-03-01T09:30").unwrap().to_string(), "2025-03-01T09:30:00");
+        assert_eq!(PartialDate::parse("2025-03-01 09:30:15Z").unwrap().to_string(), "2025-03-01T09:30:15Z");
+        assert_eq!(PartialDate::parse("2025-03-01T09:30:00+0200").unwrap().to_string(), "2025-03-01T09:30:00+02:00");
+        assert_eq!(PartialDate::parse("-0044-03-15").unwrap().to_string(), "-0044-03-15");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for bad in ["", "25", "2025-13", "2025-02-30", "2025-3-1", "March 2025", "2025-03T10:00", "2025-03-01T25:00", "2025-03-01-02", "300000", "300000-01", "262143-12-31"] {
+            assert!(matches!(PartialDate::parse(bad), Err(DateError::Malformed(_))), "accepted {:?}", bad);
+        }
+    }
+
+    #[test]
+    fn partial_dates_cover_their_whole_period() {
+        let month = PartialDate::parse("2024-02").unwrap();
+        assert_eq!(month.first_instant().unwrap().to_string(), "2024-02-01 00:00:00");
+        assert_eq!(month.last_instant().unwrap().to_string(), "2024-02-29 23:59:59");
+        let year = PartialDate::parse("2025").unwrap();
+        assert_eq!(year.last_instant().unwrap().to_string(), "2025-12-31 23:59:59");
+    }
+
+    #[test]
+    fn ordering_and_ranges() {
+        let mut dates: Vec<PartialDate> = ["2025-03-01", "-0100", "2025", "2024-12-31T23:00:00-02:00", "2025-01-01T00:30:00Z"]
+            .iter()
+            .map(|s| PartialDate::parse(s).unwrap())
+            .collect();
+        dates.sort();
+        let sorted: Vec<String> = dates.iter().map(|d| d.to_string()).collect();
+        assert_eq!(sorted, vec!["-0100", "2025", "2025-01-01T00:30:00Z", "2024-12-31T23:00:00-02:00", "2025-03-01"]);
+
+        // One instant in two offsets: ordered, and unequal both ways
+        let utc = PartialDate::parse("2025-01-01T00:30:00Z").unwrap();
+        let local = PartialDate::parse("2025-01-01T02:30:00+02:00").unwrap();
+        assert_ne!(utc, local);
+        assert_ne!(utc.cmp(&local), Ordering::Equal);
+        assert_eq!(utc.cmp(&local), local.cmp(&utc).reverse());
+        assert_eq!(utc.cmp(&utc.clone()), Ordering::Equal);
+
+        let march = PartialDate::parse("2025-03").unwrap().day_range().unwrap();
+        assert_eq!(march.1 - march.0, 30);
+        assert_eq!(from_day_number(march.0).unwrap().to_string(), "2025-03-01");
+
+        assert!(validate_range(Some("2025-03"), Some("2025-03-15")).is_ok());
+        assert!(validate_range(Some("2025-03-15"), None).is_ok());
+        assert!(matches!(validate_range(Some("2025-03-15"), Some("2025-02")), Err(DateError::InvertedRange { .. })));
+    }
+}
@@ -2,15 +2,22 @@ use anyhow::Context;
 use r2d2_sqlite::SqliteConnectionManager;
 use r2d2::{Pool, PooledConnection};
 use std::fs;
+use std::path::PathBuf;
 use dirs::data_local_dir;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 pub type DbConn = PooledConnection<SqliteConnectionManager>;
 
-pub fn init_pool() -> anyhow::Result<DbPool> {
+/// The app's data directory (database, media store), created on first use
+pub fn app_data_dir() -> anyhow::Result<PathBuf> {
     let mut dir = data_local_dir().ok_or_else(|| anyhow::anyhow!("failed to get app local data dir"))?;
     dir.push("cora");
     fs::create_dir_all(&dir).context("creating app data dir")?;
+    Ok(dir)
+}
+
+pub fn init_pool() -> anyhow::Result<DbPool> {
+    let mut dir = app_data_dir()?;
     dir.push("app.db");
 
     let manager = SqliteConnectionManager::file(&dir);
@@ -71,6 +78,186 @@ pub fn init_pool() -> anyhow::Result<DbPool> {
         conn.execute_batch(include_str!("../migrations/007_add_project_folder_drafts.sql")).context("running migrations 007")?;
     }
 
+    // Conditionally run 008: codex types, entities and doc attachments
+    let codex_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='codex_types'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if codex_missing {
+        conn.execute_batch(include_str!("../migrations/008_add_codex.sql")).context("running migrations 008")?;
+    }
+
+    // Conditionally run 009: event participants and locations
+    let event_links_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='event_characters'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if event_links_missing {
+        conn.execute_batch(include_str!("../migrations/009_add_event_links.sql")).context("running migrations 009")?;
+    }
+
+    // Conditionally run 010: projects.linear_chronology
+    let mut stmt = conn.prepare("PRAGMA table_info(projects)")?;
+    let has_linear = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == "linear_chronology");
+    if !has_linear {
+        conn.execute_batch(include_str!("../migrations/010_add_project_linear_chronology.sql")).context("running migrations 010")?;
+    }
+
+    // Conditionally run 011: normalise stored dates once, recording values that cannot be parsed
+    let date_issues_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='date_issues'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if date_issues_missing {
+        conn.execute_batch(include_str!("../migrations/011_add_date_issues.sql")).context("running migrations 011")?;
+    }
+
+    // Conditionally run 012: calendars, plus absolute day columns filled in by the normalisation pass below
+    let calendars_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='calendars'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if calendars_missing {
+        conn.execute_batch(include_str!("../migrations/012_add_calendars.sql")).context("running migrations 012")?;
+    }
+
+    // Conditionally run 013: indexes on the absolute day columns
+    let day_indexes_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='index' AND name='idx_timelines_days'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if day_indexes_missing {
+        conn.execute_batch(include_str!("../migrations/013_add_timeline_day_indexes.sql")).context("running migrations 013")?;
+    }
+
+    // Conditionally run 014: timelines.project_id plus delete triggers
+    let mut stmt = conn.prepare("PRAGMA table_info(timelines)")?;
+    let has_timeline_project = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == "project_id");
+    if !has_timeline_project {
+        conn.execute_batch(include_str!("../migrations/014_scope_timelines_to_projects.sql")).context("running migrations 014")?;
+    }
+
+    // Conditionally run 015: plot threads and their doc links
+    let plot_threads_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='plot_threads'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if plot_threads_missing {
+        conn.execute_batch(include_str!("../migrations/015_add_plot_threads.sql")).context("running migrations 015")?;
+    }
+
+    // Conditionally run 016: range-anchored doc comments
+    let doc_comments_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='doc_comments'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if doc_comments_missing {
+        conn.execute_batch(include_str!("../migrations/016_add_doc_comments.sql")).context("running migrations 016")?;
+    }
+
+    // Conditionally run 017: research notes columns on the notes table
+    let mut stmt = conn.prepare("PRAGMA table_info(notes)")?;
+    let has_note_titles = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == "title");
+    if !has_note_titles {
+        conn.execute_batch(include_str!("../migrations/017_add_research_notes.sql")).context("running migrations 017")?;
+    }
+
+    // Conditionally run 018: attachments and the content-addressed media store index
+    let attachments_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='attachments'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if attachments_missing {
+        conn.execute_batch(include_str!("../migrations/018_add_attachments.sql")).context("running migrations 018")?;
+    }
+
+    // Conditionally run 019: user-saved project templates
+    let templates_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='project_templates'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if templates_missing {
+        conn.execute_batch(include_str!("../migrations/019_add_project_templates.sql")).context("running migrations 019")?;
+    }
+
+    // Conditionally run 020: doc metadata and compile presets
+    let presets_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='compile_presets'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if presets_missing {
+        conn.execute_batch(include_str!("../migrations/020_add_compile_presets.sql")).context("running migrations 020")?;
+    }
+
+    // Conditionally run 021: undoable typography passes
+    let typography_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='typography_runs'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if typography_missing {
+        conn.execute_batch(include_str!("../migrations/021_add_typography_runs.sql")).context("running migrations 021")?;
+    }
+
+    // Conditionally run 022: screenplay mode and scene locations
+    let screenplay_missing: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='screenplay_docs'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).unwrap_or(0) == 0;
+
+    if screenplay_missing {
+        conn.execute_batch(include_str!("../migrations/022_add_screenplay.sql")).context("running migrations 022")?;
+    }
+
+    // Conditionally run 023: speaker links made by screenplay sync
+    let mut stmt = conn.prepare("PRAGMA table_info(screenplay_docs)")?;
+    let has_speakers = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == "speakers");
+    if !has_speakers {
+        conn.execute_batch(include_str!("../migrations/023_add_screenplay_speakers.sql")).context("running migrations 023")?;
+    }
+
+    if date_issues_missing || calendars_missing {
+        crate::services::date_normalization::normalize_stored(&conn).context("normalising stored dates")?;
+    }
+
     Ok(pool)
 }
 
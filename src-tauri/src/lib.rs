@@ -1,4 +1,5 @@
 mod db;
+mod dates;
 mod models;
 mod services {
     pub mod projects;
@@ -10,6 +11,39 @@ mod services {
     pub mod project_drafts;
     pub mod folder_drafts;
     pub mod timelines;
+    pub mod codex;
+    pub mod continuity;
+    pub mod date_normalization;
+    pub mod calendars;
+    pub mod timeline_rollup;
+    pub mod maintenance;
+    pub mod plot_threads;
+    pub mod comments;
+    pub mod research_notes;
+    pub mod attachments;
+    pub mod templates;
+    pub mod duplicate;
+    pub mod restructure;
+    pub mod manuscript;
+    pub mod text_decode;
+    pub mod rich_text;
+    pub mod docx;
+    pub mod rtf;
+    pub mod scrivener;
+    pub mod project_tree;
+    pub mod compile;
+    pub mod odt;
+    pub mod html;
+    pub mod pdf;
+    pub mod manuscript_pdf;
+    pub mod typography;
+    pub mod doc_metadata;
+    pub mod compile_presets;
+    pub mod fountain;
+    pub mod screenplay;
+    pub mod screenplay_pdf;
+    #[cfg(test)]
+    pub mod test_support;
 }
 mod commands;
 
@@ -40,24 +74,38 @@ pub fn run() {
             commands::project_get,
             commands::project_list,
             commands::project_update,
+            commands::project_set_timeline,
+            commands::project_set_linear_chronology,
             commands::project_delete,
+            commands::project_duplicate,
+            commands::project_template_list,
+            commands::project_create_from_template,
+            commands::project_save_as_template,
+            commands::project_template_delete,
             commands::doc_create,
             commands::doc_list,
+            commands::doc_list_story_order,
             commands::doc_get,
             commands::doc_create_new,
             commands::doc_create_after,
             commands::doc_update_text,
             commands::doc_update_notes,
+            commands::doc_metadata_get,
+            commands::doc_metadata_set,
             commands::doc_delete,
             commands::doc_reorder,
             commands::doc_move_to_group,
             commands::doc_rename,
+            commands::doc_duplicate,
+            commands::doc_split,
+            commands::doc_merge,
             commands::doc_group_list,
             commands::doc_group_create,
             commands::doc_group_create_after,
             commands::doc_group_delete,
             commands::doc_group_reorder,
             commands::doc_group_rename,
+            commands::doc_group_duplicate,
             commands::character_create,
             commands::character_list,
             commands::character_update,
@@ -72,6 +120,15 @@ pub fn run() {
             commands::doc_event_list,
             commands::doc_event_attach,
             commands::doc_event_detach,
+            commands::event_character_list,
+            commands::event_character_attach,
+            commands::event_character_detach,
+            commands::character_event_list,
+            commands::event_location_list,
+            commands::event_location_attach,
+            commands::event_location_detach,
+            commands::location_event_list,
+            commands::character_whereabouts,
             commands::draft_create,
             commands::draft_get,
             commands::draft_list,
@@ -79,6 +136,13 @@ pub fn run() {
             commands::draft_delete,
             commands::draft_restore,
             commands::draft_delete_all,
+            commands::comment_create,
+            commands::comment_reply,
+            commands::comment_get,
+            commands::comment_list,
+            commands::comment_update,
+            commands::comment_set_resolved,
+            commands::comment_delete,
             commands::project_draft_create,
             commands::project_draft_get,
             commands::project_draft_list,
@@ -95,12 +159,83 @@ pub fn run() {
             commands::timeline_get,
             commands::timeline_get_by_entity,
             commands::timeline_list,
+            commands::timeline_purge_orphans,
+            commands::maintenance_run,
+            commands::timeline_tree,
+            commands::timeline_range,
             commands::timeline_update,
             commands::timeline_delete,
             commands::timeline_delete_by_entity,
+            commands::codex_type_create,
+            commands::codex_type_get,
+            commands::codex_type_list,
+            commands::codex_type_update,
+            commands::codex_type_delete,
+            commands::codex_entity_create,
+            commands::codex_entity_get,
+            commands::codex_entity_list,
+            commands::codex_entity_update,
+            commands::codex_entity_delete,
+            commands::doc_codex_list,
+            commands::doc_codex_attach,
+            commands::doc_codex_detach,
+            commands::research_note_create,
+            commands::research_note_get,
+            commands::research_note_list_for,
+            commands::research_note_search,
+            commands::research_note_tags,
+            commands::research_note_update,
+            commands::research_note_delete,
+            commands::attachment_add,
+            commands::attachment_get,
+            commands::attachment_list_for,
+            commands::attachment_list,
+            commands::attachment_rename,
+            commands::attachment_delete,
+            commands::attachment_collect_garbage,
+            commands::plot_thread_create,
+            commands::plot_thread_get,
+            commands::plot_thread_list,
+            commands::plot_thread_update,
+            commands::plot_thread_delete,
+            commands::doc_thread_list,
+            commands::doc_thread_link,
+            commands::doc_thread_unlink,
+            commands::plot_thread_matrix,
+            commands::continuity_check,
+            commands::calendar_create,
+            commands::calendar_get,
+            commands::calendar_list,
+            commands::calendar_update,
+            commands::calendar_delete,
+            commands::calendar_render,
+            commands::calendar_parse,
+            commands::date_normalize,
+            commands::date_issue_list,
             commands::import_txt_files,
+            commands::import_manuscript,
             commands::import_project,
+            commands::import_scrivener,
             commands::export_project,
+            commands::compile_project,
+            commands::compile_with_preset,
+            commands::compile_preset_list,
+            commands::compile_preset_create,
+            commands::compile_preset_update,
+            commands::compile_preset_delete,
+            commands::typography_preview,
+            commands::typography_apply,
+            commands::typography_run_list,
+            commands::typography_undo,
+            commands::screenplay_project_get,
+            commands::screenplay_project_set,
+            commands::screenplay_doc_get,
+            commands::screenplay_doc_set,
+            commands::screenplay_parse,
+            commands::screenplay_sync,
+            commands::import_fountain,
+            commands::export_fountain,
+            commands::compile_screenplay,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -8,6 +8,9 @@ pub struct Project {
     pub path: Option<String>,
     pub timeline_start: Option<String>,
     pub timeline_end: Option<String>,
+    // Manuscript order must follow story chronology (see continuity checks)
+    #[serde(default)]
+    pub linear_chronology: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,7 +42,7 @@ pub struct Doc {
     pub sort_order: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Character {
     pub id: i64,
     pub project_id: i64,
@@ -58,6 +61,22 @@ pub struct Event {
     // New fields for range
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    // Calendar the dates are written in (None = Gregorian ISO 8601) and the absolute days they cover
+    #[serde(default)]
+    pub calendar_id: Option<i64>,
+    #[serde(default)]
+    pub start_day: Option<i64>,
+    #[serde(default)]
+    pub end_day: Option<i64>,
+}
+
+// Where a character is on a given date: an event covering that date and its locations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterWhereabouts {
+    pub character_id: i64,
+    pub event_id: i64,
+    pub event_name: String,
+    pub location_ids: Vec<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -133,8 +152,16 @@ pub struct Timeline {
     pub id: i64,
     pub entity_type: String, // 'project', 'doc', 'folder', 'event'
     pub entity_id: i64,
+    #[serde(default)]
+    pub project_id: Option<i64>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    #[serde(default)]
+    pub calendar_id: Option<i64>,
+    #[serde(default)]
+    pub start_day: Option<i64>,
+    #[serde(default)]
+    pub end_day: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,10 +170,838 @@ pub struct TimelineCreate {
     pub entity_id: i64,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    #[serde(default)]
+    pub calendar_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimelineUpdate {
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    // Absent keeps the calendar, null switches to Gregorian; without new dates the stored days are converted
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub calendar_id: Option<Option<i64>>,
+}
+
+// Distinguish an absent field (None) from an explicit null (Some(None))
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// Codex (user-defined world-building entity types)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodexFieldKind {
+    Text,
+    Number,
+    Date,
+    Reference,
+    List,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexField {
+    pub key: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub kind: CodexFieldKind,
+    // For references (or lists of references): restrict targets to this codex type
+    #[serde(default)]
+    pub ref_type_id: Option<i64>,
+    // For lists: kind of each item (defaults to text, lists cannot nest)
+    #[serde(default)]
+    pub item_kind: Option<CodexFieldKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexType {
+    pub id: i64,
+    pub project_id: i64,
+    pub name: String,
+    pub desc: Option<String>,
+    pub fields: Vec<CodexField>,
+    pub sort_order: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexTypeCreate {
+    pub name: String,
+    pub desc: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CodexField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexTypeUpdate {
+    pub name: Option<String>,
+    pub desc: Option<String>,
+    pub fields: Option<Vec<CodexField>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexEntity {
+    pub id: i64,
+    pub project_id: i64,
+    pub type_id: i64,
+    pub name: String,
+    pub desc: Option<String>,
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexEntityCreate {
+    pub type_id: i64,
+    pub name: String,
+    pub desc: Option<String>,
+    #[serde(default)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodexEntityUpdate {
+    pub name: Option<String>,
+    pub desc: Option<String>,
+    pub data: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+// Continuity analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityRef {
+    pub entity_type: String, // 'project', 'doc', 'event', 'character'
+    pub entity_id: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContinuityIssue {
+    CharacterDoubleBooked,
+    DocOutsideProjectRange,
+    EventEndsBeforeStart,
+    DocOutOfChronology,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContinuityWarning {
+    pub kind: ContinuityIssue,
+    pub message: String,
+    pub entities: Vec<EntityRef>,
+}
+
+// A stored date value that could not be parsed during normalisation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DateIssue {
+    pub table_name: String,
+    pub column_name: String,
+    pub row_id: i64,
+    pub value: String,
+    pub error: String,
+}
+
+// Plot threads (storylines) and the role a doc plays for each
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlotThread {
+    pub id: i64,
+    pub project_id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    pub desc: Option<String>,
+    pub sort_order: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlotThreadCreate {
+    pub name: String,
+    pub color: Option<String>,
+    pub desc: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlotThreadUpdate {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub desc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadRole {
+    Introduces,
+    #[default]
+    Advances,
+    Resolves,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocThreadLink {
+    pub doc_id: i64,
+    pub thread_id: i64,
+    pub role: ThreadRole,
+}
+
+// Scene matrix: docs in manuscript order against threads, one cell per thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMatrixRow {
+    pub doc_id: i64,
+    pub doc_name: Option<String>,
+    pub cells: Vec<Option<ThreadRole>>,
+}
+
+// Per-thread summary over the matrix rows; indexes are row positions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadCoverage {
+    pub thread_id: i64,
+    pub appearances: usize,
+    pub first_row: Option<usize>,
+    pub last_row: Option<usize>,
+    pub longest_gap: usize,
+    pub resolved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMatrix {
+    pub threads: Vec<PlotThread>,
+    pub rows: Vec<ThreadMatrixRow>,
+    pub coverage: Vec<ThreadCoverage>,
+}
+
+// Inline comments: anchored to a character range of a doc's text; replies have a parent and no anchor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocComment {
+    pub id: i64,
+    pub doc_id: i64,
+    pub parent_id: Option<i64>,
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    pub quote: Option<String>,
+    pub body: String,
+    pub author: Option<String>,
+    pub resolved: bool,
+    pub detached: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocCommentCreate {
+    pub start_offset: i64,
+    pub end_offset: i64,
+    pub body: String,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentThread {
+    pub comment: DocComment,
+    pub replies: Vec<DocComment>,
+}
+
+// Research notes: titled, tagged notes attached to a project, folder, doc, character or event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchNote {
+    pub id: i64,
+    pub project_id: i64,
+    pub parent_type: String, // 'project', 'folder', 'doc', 'character', 'event'
+    pub parent_id: i64,
+    pub title: String,
+    pub text: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchNoteCreate {
+    pub parent_type: String,
+    pub parent_id: i64,
+    pub title: String,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchNoteUpdate {
+    pub title: Option<String>,
+    pub text: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+// Attachments: files in the content-addressed media store, attached to a project, doc, character or location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub project_id: i64,
+    pub parent_type: String, // 'project', 'doc', 'character', 'location'
+    pub parent_id: i64,
+    pub hash: String,
+    pub file_name: String,
+    pub title: Option<String>,
+    pub mime: String,
+    pub size: i64,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub created_at: String,
+    // Absolute paths into the media store, filled in when listing
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub thumbnail_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentCreate {
+    pub parent_type: String,
+    pub parent_id: i64,
+    pub source_path: String,
+    pub title: Option<String>,
+}
+
+// Splitting and merging docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitShare {
+    // Only the first part keeps it
+    First,
+    // Every part gets a copy
+    #[default]
+    All,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocSplitOptions {
+    // Names for the new parts (the first part keeps the doc's name); missing names are numbered
+    #[serde(default)]
+    pub names: Vec<String>,
+    // Character, event, codex and plot thread links
+    #[serde(default)]
+    pub links: SplitShare,
+    // Timeline dates
+    #[serde(default)]
+    pub timeline: SplitShare,
+    // Doc notes and research notes attached to the doc
+    #[serde(default = "split_share_first")]
+    pub notes: SplitShare,
+}
+
+fn split_share_first() -> SplitShare {
+    SplitShare::First
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeTimeline {
+    // Keep the first doc's dates
+    #[default]
+    First,
+    // Cover the earliest start to the latest end of all merged docs
+    Span,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocMergeOptions {
+    // Text placed between merged docs; None means a blank line
+    #[serde(default)]
+    pub separator: Option<String>,
+    #[serde(default)]
+    pub timeline: MergeTimeline,
+}
+
+// Outcome of a text file import: one entry per file, imported or skipped with a warning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedFile {
+    pub path: String,
+    pub encoding: Option<String>,
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub files: Vec<ImportedFile>,
+}
+
+// Importing one large manuscript file as chapters (groups) and scenes (docs)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManuscriptSplitOptions {
+    // Regex for a chapter heading line, e.g. `^Chapter \d+` or `^# `
+    #[serde(default)]
+    pub chapter_pattern: Option<String>,
+    // Regex for a scene break line, e.g. `^\*\*\*$` or `^#$`
+    #[serde(default)]
+    pub scene_pattern: Option<String>,
+    // A run of at least this many blank lines also breaks a scene
+    #[serde(default)]
+    pub blank_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedDoc {
+    pub name: String,
+    pub text: String,
+    pub words: usize,
+    // Doc notes to set, e.g. comments carried over from a Word file
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedGroup {
+    pub name: String,
+    pub docs: Vec<PlannedDoc>,
+}
+
+// The tree an import will create: loose docs first (text before the first chapter), then groups
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManuscriptPlan {
+    pub docs: Vec<PlannedDoc>,
+    pub groups: Vec<PlannedGroup>,
+}
+
+// What a Heading 1 or Heading 2 paragraph in an imported Word or OpenDocument file becomes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportHeading {
+    // Kept in the doc text as a markdown heading
+    #[default]
+    Text,
+    // Starts a new group named after the heading
+    Group,
+    // Starts a new doc named after the heading
+    Doc,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeadingImportOptions {
+    #[serde(default)]
+    pub heading1: ImportHeading,
+    #[serde(default)]
+    pub heading2: ImportHeading,
+}
+
+// Single-file output formats a project can be compiled to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompileFormat {
+    Odt,
+    Html,
+    Pdf,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+    A5,
+}
+
+// Layout options for compiled HTML and PDF output
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompileOptions {
+    // HTML theme: classic (default), modern or manuscript
+    #[serde(default)]
+    pub theme: Option<String>,
+    // Extra CSS appended after the theme
+    #[serde(default)]
+    pub css: Option<String>,
+    #[serde(default)]
+    pub page_size: PageSize,
+    // Title page before the text; on unless set to false
+    #[serde(default)]
+    pub title_page: Option<bool>,
+}
+
+// Typography conventions of a language
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypographyLocale {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+// Which typography rules a normalisation pass applies
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TypographyOptions {
+    #[serde(default)]
+    pub locale: TypographyLocale,
+    // Straight quotes become the locale's quotation marks
+    #[serde(default)]
+    pub smart_quotes: bool,
+    // "---" and "--" become dashes ("em_dashes" in older presets)
+    #[serde(default, alias = "em_dashes")]
+    pub dashes: bool,
+    // "..." becomes an ellipsis
+    #[serde(default)]
+    pub ellipses: bool,
+    // Runs of spaces inside a line shrink to one
+    #[serde(default)]
+    pub double_spaces: bool,
+    // No-break spaces where the locale keeps words together, e.g. "Dr. Watson", "z. B.", "Quoi ?"
+    #[serde(default)]
+    pub nbsp: bool,
+}
+
+// One changed spot in a typography preview, with a little surrounding text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypographyEdit {
+    pub before: String,
+    pub after: String,
+}
+
+// What a typography pass would change in one doc
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypographyPreview {
+    pub doc_id: i64,
+    pub doc_name: Option<String>,
+    pub changes: usize,
+    // The first changes, for display
+    pub edits: Vec<TypographyEdit>,
+}
+
+// A typography pass applied to docs.text, kept so it can be undone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypographyRun {
+    pub id: i64,
+    pub project_id: i64,
+    // The single doc the pass ran on; None for the whole project
+    pub doc_id: Option<i64>,
+    pub options: TypographyOptions,
+    pub docs_changed: usize,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+// Result of undoing a pass: docs edited since the pass are left alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TypographyUndo {
+    pub restored: Vec<i64>,
+    pub skipped: Vec<i64>,
+}
+
+// One element of a screenplay written in Fountain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScreenplayElement {
+    // "INT. KITCHEN - NIGHT", with an optional scene number from "#12A#"
+    SceneHeading { text: String, number: Option<String> },
+    Action { text: String },
+    // Speaker cue; extension is e.g. "V.O." and dual marks dual dialogue ("^")
+    Character { name: String, extension: Option<String>, dual: bool },
+    Parenthetical { text: String },
+    Dialogue { text: String },
+    Transition { text: String },
+    Centered { text: String },
+    Lyrics { text: String },
+    // "#" outline sections and "=" synopses; neither is printed
+    Section { depth: usize, text: String },
+    Synopsis { text: String },
+    PageBreak,
+}
+
+// A parsed Fountain text: title page fields in order, then the script
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Screenplay {
+    pub title_page: Vec<(String, String)>,
+    pub elements: Vec<ScreenplayElement>,
+}
+
+// Screenplay mode of a doc and the location taken from its first scene heading
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenplayDoc {
+    pub doc_id: i64,
+    // Whether the doc is read as Fountain, after applying the project default
+    pub screenplay: bool,
+    // The doc's own setting; None follows the project
+    pub mode: Option<bool>,
+    pub location: Option<String>,
+}
+
+// Outcome of syncing screenplay docs with locations and characters
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScreenplaySync {
+    pub docs: usize,
+    // Speakers that matched no character and were added
+    pub characters_created: Vec<Character>,
+    // Speaker-to-doc links made, new characters included
+    pub characters_linked: usize,
+}
+
+// Status, label and synopsis of a doc, used to select and annotate docs when compiling
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocMetadata {
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub synopsis: Option<String>,
+}
+
+// What a compile includes and how it is laid out. Status and label filters compare case-insensitively.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompileSettings {
+    #[serde(flatten)]
+    pub options: CompileOptions,
+    // Groups left out together with everything inside them
+    #[serde(default)]
+    pub exclude_groups: Vec<i64>,
+    #[serde(default)]
+    pub exclude_docs: Vec<i64>,
+    // When not empty, only docs with one of these statuses (labels) are included
+    #[serde(default)]
+    pub include_statuses: Vec<String>,
+    #[serde(default)]
+    pub exclude_statuses: Vec<String>,
+    #[serde(default)]
+    pub include_labels: Vec<String>,
+    #[serde(default)]
+    pub exclude_labels: Vec<String>,
+    // Group heading per tree depth, top level first, e.g. "Chapter {n}: {name}"; {n} counts the
+    // included groups of that depth through the whole book and {roman} is the same in roman numerals
+    #[serde(default)]
+    pub heading_templates: Vec<String>,
+    // Scene break text; each format has its own when unset
+    #[serde(default)]
+    pub scene_separator: Option<String>,
+    // Typography applied to the compiled text only; the docs keep theirs
+    #[serde(flatten)]
+    pub typography: TypographyOptions,
+    #[serde(default)]
+    pub include_synopses: bool,
+    #[serde(default)]
+    pub include_notes: bool,
+    // Docs set before and after the manuscript, each under its own name as a heading
+    #[serde(default)]
+    pub front_matter: Vec<i64>,
+    #[serde(default)]
+    pub back_matter: Vec<i64>,
+    // Folder and file names on export, with {index}, {n} and {name}; "{index} {name}" when unset
+    #[serde(default)]
+    pub file_names: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompilePreset {
+    pub id: i64,
+    pub project_id: i64,
+    pub name: String,
+    pub format: CompileFormat,
+    pub settings: CompileSettings,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompilePresetInput {
+    pub name: String,
+    pub format: CompileFormat,
+    #[serde(default)]
+    pub settings: CompileSettings,
+}
+
+// Project templates: a folder/doc outline plus starter characters, events, codex types and plot threads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateDoc {
+    pub name: String,
+    // Writing prompt, placed in the new doc's notes
+    #[serde(default)]
+    pub prompt: Option<String>,
+    // Manuscript text, only kept by templates saved with their text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateGroup {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<TemplateDoc>,
+    #[serde(default)]
+    pub groups: Vec<TemplateGroup>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateEntry {
+    pub name: String,
+    #[serde(default)]
+    pub desc: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateStructure {
+    #[serde(default)]
+    pub groups: Vec<TemplateGroup>,
+    // Docs at the project root, after the groups
+    #[serde(default)]
+    pub docs: Vec<TemplateDoc>,
+    #[serde(default)]
+    pub characters: Vec<TemplateEntry>,
+    #[serde(default)]
+    pub events: Vec<TemplateEntry>,
+    // Reference fields point at other types by their position in this list
+    #[serde(default)]
+    pub codex_types: Vec<CodexTypeCreate>,
+    #[serde(default)]
+    pub plot_threads: Vec<PlotThreadCreate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    pub key: String, // built-in name such as 'three_act', or 'saved:<id>'
+    pub name: String,
+    pub desc: Option<String>,
+    pub builtin: bool,
+    pub structure: TemplateStructure,
+}
+
+// Database maintenance: problems found by a maintenance run and whether they were repaired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceCheck {
+    Integrity,
+    ForeignKey,
+    MissingParent,
+    MissingProject,
+    ForeignGroup,
+    OrphanTimeline,
+    SortOrder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceIssue {
+    pub check: MaintenanceCheck,
+    pub table_name: String,
+    pub row_id: Option<i64>,
+    pub message: String,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub issues: Vec<MaintenanceIssue>,
+    pub optimized: bool,
+}
+
+// Fictional calendars: month names and lengths, week days, eras, or a relative "Day N" count
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarKind {
+    #[default]
+    Dated,
+    Relative,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarMonth {
+    pub name: String,
+    pub days: i64,
+}
+
+// An era starts at an absolute calendar year; years within it count from 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEra {
+    pub name: String,
+    pub start_year: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calendar {
+    pub id: i64,
+    pub project_id: i64,
+    pub name: String,
+    pub kind: CalendarKind,
+    pub months: Vec<CalendarMonth>,
+    pub weekdays: Vec<String>,
+    pub eras: Vec<CalendarEra>,
+    // Absolute day number of the first day of year 1 (or of "Day 1"); Gregorian 0001-01-01 is day 1
+    pub epoch_offset: i64,
+    pub day_label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarCreate {
+    pub name: String,
+    #[serde(default)]
+    pub kind: CalendarKind,
+    #[serde(default)]
+    pub months: Vec<CalendarMonth>,
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    #[serde(default)]
+    pub eras: Vec<CalendarEra>,
+    #[serde(default)]
+    pub epoch_offset: i64,
+    pub day_label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarUpdate {
+    pub name: Option<String>,
+    pub months: Option<Vec<CalendarMonth>>,
+    pub weekdays: Option<Vec<String>>,
+    pub eras: Option<Vec<CalendarEra>>,
+    pub epoch_offset: Option<i64>,
+    pub day_label: Option<String>,
+}
+
+// An absolute day rendered through a calendar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarDate {
+    pub day: i64,
+    pub text: String,
+    pub year: Option<i64>,
+    pub era: Option<String>,
+    pub year_of_era: Option<i64>,
+    pub month: Option<String>,
+    pub day_of_month: Option<i64>,
+    pub weekday: Option<String>,
+}
+
+// Timeline roll-up: a dated range in absolute days plus the text it was written as
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineSpan {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub start_day: i64,
+    pub end_day: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineSource {
+    Explicit,
+    Derived,
+    Undated,
+}
+
+// One node of the project timeline hierarchy: project → folders → docs → events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineNode {
+    pub entity_type: String, // 'project', 'folder', 'doc', 'event'
+    pub entity_id: i64,
+    pub name: String,
+    pub explicit: Option<TimelineSpan>,
+    pub derived: Option<TimelineSpan>,
+    pub source: TimelineSource,
+    pub children: Vec<TimelineNode>,
+}
+
+// A doc or event whose dates overlap a queried range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineHit {
+    pub entity_type: String, // 'doc' or 'event'
+    pub entity_id: i64,
+    pub name: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub calendar_id: Option<i64>,
+    pub start_day: i64,
+    pub end_day: i64,
 }
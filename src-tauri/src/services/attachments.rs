@@ -0,0 +1,479 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{Attachment, AttachmentCreate};
+use anyhow::Context;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// How old an unreferenced store file must be before garbage collection removes it
+const ORPHAN_GRACE: Duration = Duration::from_secs(10 * 60);
+
+const ATTACHMENT_COLUMNS: &str = "a.id, a.project_id, a.parent_type, a.parent_id, a.hash, a.file_name, a.title, \
+     b.mime, b.size, b.width, b.height, a.created_at, b.has_thumbnail";
+
+/// Root of the media store: `<app data dir>/media`
+pub fn store_dir() -> anyhow::Result<PathBuf> {
+    let dir = crate::db::app_data_dir()?.join("media");
+    fs::create_dir_all(&dir).context("creating media store")?;
+    Ok(dir)
+}
+
+/// Blobs are sharded by the first two hex digits of their hash
+fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(&hash[..2]).join(hash)
+}
+
+fn thumbnail_path(root: &Path, hash: &str) -> PathBuf {
+    root.join("thumbs").join(format!("{}.png", hash))
+}
+
+fn attachment_from_row(root: &Path, row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    let hash: String = row.get(4)?;
+    let has_thumbnail: bool = row.get(12)?;
+    Ok(Attachment {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        parent_type: row.get(2)?,
+        parent_id: row.get(3)?,
+        file_name: row.get(5)?,
+        title: row.get(6)?,
+        mime: row.get(7)?,
+        size: row.get(8)?,
+        width: row.get(9)?,
+        height: row.get(10)?,
+        created_at: row.get(11)?,
+        path: blob_path(root, &hash).to_string_lossy().into_owned(),
+        thumbnail_path: has_thumbnail.then(|| thumbnail_path(root, &hash).to_string_lossy().into_owned()),
+        hash,
+    })
+}
+
+fn query(conn: &Connection, root: &Path, filter: &str, params: impl rusqlite::Params) -> anyhow::Result<Vec<Attachment>> {
+    let sql = format!(
+        "SELECT {} FROM attachments a JOIN media_blobs b ON b.hash = a.hash WHERE {} ORDER BY a.created_at, a.id",
+        ATTACHMENT_COLUMNS, filter
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(params, |row| attachment_from_row(root, row))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Project owning the attachment's parent, or None if the parent does not exist
+fn owner_project(conn: &Connection, parent_type: &str, parent_id: i64) -> anyhow::Result<Option<i64>> {
+    let sql = match parent_type {
+        "project" => "SELECT id FROM projects WHERE id = ?1",
+        "doc" => "SELECT project_id FROM docs WHERE id = ?1",
+        "character" => "SELECT project_id FROM characters WHERE id = ?1",
+        "location" => {
+            return match crate::services::codex::location_project(conn, parent_id)? {
+                Some((_, false)) => anyhow::bail!("codex entity {} is not a location", parent_id),
+                found => Ok(found.map(|(project_id, _)| project_id)),
+            };
+        }
+        other => anyhow::bail!("files cannot be attached to '{}'", other),
+    };
+    Ok(conn.query_row(sql, rusqlite::params![parent_id], |row| row.get::<_, Option<i64>>(0)).optional()?.flatten())
+}
+
+/// MIME type from the file's magic bytes, falling back to its extension for text formats
+fn detect_mime(bytes: &[u8], file_name: &str) -> String {
+    if let Some(kind) = infer::get(bytes) {
+        return kind.mime_type().to_string();
+    }
+    let ext = Path::new(file_name).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "txt" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "html" | "htm" => "text/html",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// Write `bytes` to `path` unless it already exists; written via a temp file so a crash never leaves half a blob
+fn write_once(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    let dir = path.parent().ok_or_else(|| anyhow::anyhow!("invalid media path"))?;
+    fs::create_dir_all(dir).context("creating media directory")?;
+    let tmp = path.with_extension("part");
+    fs::write(&tmp, bytes).context("writing media file")?;
+    fs::rename(&tmp, path).context("moving media file into place")?;
+    Ok(())
+}
+
+/// Store a blob (once per hash) and return its hash; images get their size recorded and a PNG thumbnail.
+/// The row goes in before the files so a failed write rolls back with the caller's transaction;
+/// files left behind by a transaction that fails later are swept by `collect_garbage`.
+fn store_blob(conn: &Connection, root: &Path, bytes: &[u8], file_name: &str) -> anyhow::Result<String> {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+    let known: bool = conn.query_row(
+        "SELECT COUNT(*) FROM media_blobs WHERE hash = ?1",
+        rusqlite::params![hash],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+    if known {
+        write_once(&blob_path(root, &hash), bytes)?;
+        return Ok(hash);
+    }
+
+    let mime = detect_mime(bytes, file_name);
+    let mut dimensions = None;
+    let mut thumbnail = None;
+    if mime.starts_with("image/") {
+        // Formats the image crate cannot decode are stored without a thumbnail
+        if let Ok(img) = image::load_from_memory(bytes) {
+            dimensions = Some((img.width() as i64, img.height() as i64));
+            let mut png = Vec::new();
+            img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .context("encoding thumbnail")?;
+            thumbnail = Some(png);
+        }
+    }
+    conn.execute(
+        "INSERT INTO media_blobs (hash, size, mime, width, height, has_thumbnail, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![hash, bytes.len() as i64, mime, dimensions.map(|d| d.0), dimensions.map(|d| d.1), thumbnail.is_some(), Utc::now().to_rfc3339()],
+    ).context("recording media blob")?;
+    write_once(&blob_path(root, &hash), bytes)?;
+    if let Some(png) = thumbnail {
+        write_once(&thumbnail_path(root, &hash), &png)?;
+    }
+    Ok(hash)
+}
+
+/// Attach file contents to a project, doc, character or location
+pub fn add_bytes(
+    pool: &DbPool,
+    root: &Path,
+    parent_type: &str,
+    parent_id: i64,
+    file_name: &str,
+    title: Option<String>,
+    bytes: &[u8],
+) -> anyhow::Result<Attachment> {
+    let conn = get_conn(pool)?;
+    let project_id = owner_project(&conn, parent_type, parent_id)?
+        .ok_or_else(|| anyhow::anyhow!("{} {} not found", parent_type, parent_id))?;
+    let tx = conn.unchecked_transaction()?;
+    let hash = store_blob(&tx, root, bytes, file_name)?;
+    tx.execute(
+        "INSERT INTO attachments (project_id, parent_type, parent_id, hash, file_name, title, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![project_id, parent_type, parent_id, hash, file_name, title, Utc::now().to_rfc3339()],
+    ).context("inserting attachment")?;
+    let id = tx.last_insert_rowid();
+    tx.commit()?;
+    query(&conn, root, "a.id = ?1", rusqlite::params![id])?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("attachment not found after creation"))
+}
+
+/// Attach a file from disk; the store keeps its own copy
+pub fn add_file(pool: &DbPool, root: &Path, payload: AttachmentCreate) -> anyhow::Result<Attachment> {
+    let source = Path::new(&payload.source_path);
+    let bytes = fs::read(source).with_context(|| format!("reading {}", payload.source_path))?;
+    let file_name = source.file_name().and_then(|n| n.to_str()).unwrap_or("attachment");
+    add_bytes(pool, root, &payload.parent_type, payload.parent_id, file_name, payload.title, &bytes)
+}
+
+pub fn get(pool: &DbPool, root: &Path, id: i64) -> anyhow::Result<Option<Attachment>> {
+    let conn = get_conn(pool)?;
+    Ok(query(&conn, root, "a.id = ?1", rusqlite::params![id])?.pop())
+}
+
+/// Attachments of one project, doc, character or location, oldest first
+pub fn list_for(pool: &DbPool, root: &Path, parent_type: &str, parent_id: i64) -> anyhow::Result<Vec<Attachment>> {
+    let conn = get_conn(pool)?;
+    query(&conn, root, "a.parent_type = ?1 AND a.parent_id = ?2", rusqlite::params![parent_type, parent_id])
+}
+
+/// Every attachment in a project, wherever it is attached
+pub fn list(pool: &DbPool, root: &Path, project_id: i64) -> anyhow::Result<Vec<Attachment>> {
+    let conn = get_conn(pool)?;
+    query(&conn, root, "a.project_id = ?1", rusqlite::params![project_id])
+}
+
+pub fn rename(pool: &DbPool, root: &Path, id: i64, title: Option<String>) -> anyhow::Result<Attachment> {
+    let conn = get_conn(pool)?;
+    conn.execute("UPDATE attachments SET title = ?1 WHERE id = ?2", rusqlite::params![title, id])?;
+    query(&conn, root, "a.id = ?1", rusqlite::params![id])?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("attachment not found"))
+}
+
+/// Remove an attachment; its file goes too once nothing else refers to it
+pub fn delete(pool: &DbPool, root: &Path, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM attachments WHERE id = ?1", rusqlite::params![id])?;
+    drop(conn);
+    collect_garbage(pool, root)?;
+    Ok(())
+}
+
+/// Delete blobs no attachment refers to any more (e.g. after their doc or project was deleted),
+/// then any file in the store without a blob row. Returns the number of blobs removed.
+pub fn collect_garbage(pool: &DbPool, root: &Path) -> anyhow::Result<usize> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare("SELECT hash FROM media_blobs WHERE hash NOT IN (SELECT hash FROM attachments)")?;
+    let unused = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    for hash in &unused {
+        conn.execute("DELETE FROM media_blobs WHERE hash = ?1", rusqlite::params![hash])?;
+        for path in [blob_path(root, hash), thumbnail_path(root, hash)] {
+            if path.exists() {
+                fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
+    }
+    sweep_orphans(&conn, root)?;
+    Ok(unused.len())
+}
+
+/// Remove store files that no media_blobs row accounts for. Recent files are left alone:
+/// they may belong to a transaction that has not committed yet.
+fn sweep_orphans(conn: &Connection, root: &Path) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare("SELECT hash FROM media_blobs")?;
+    let known = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<HashSet<_>, _>>()?;
+    let Ok(dirs) = fs::read_dir(root) else { return Ok(()) };
+    for dir in dirs {
+        let dir = dir?.path();
+        let Some(dir_name) = dir.file_name().and_then(|n| n.to_str()).map(str::to_string) else { continue };
+        let is_thumbs = dir_name == "thumbs";
+        if !dir.is_dir() || !(is_thumbs || dir_name.len() == 2) {
+            continue;
+        }
+        for file in fs::read_dir(&dir)? {
+            let path = file?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let hash = if is_thumbs { name.strip_suffix(".png").unwrap_or(name) } else { name };
+            if known.contains(hash) || path.extension().is_some_and(|e| e == "part") {
+                continue;
+            }
+            let age = fs::metadata(&path)?.modified()?.elapsed().unwrap_or_default();
+            if age >= ORPHAN_GRACE {
+                fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Export / import
+
+/// Copy a project's blobs into `dest` as `<hash>` files, for a self-contained export
+pub fn export_media(pool: &DbPool, root: &Path, project_id: i64, dest: &Path) -> anyhow::Result<Vec<Attachment>> {
+    let attachments = list(pool, root, project_id)?;
+    if attachments.is_empty() {
+        return Ok(attachments);
+    }
+    fs::create_dir_all(dest).context("creating media export folder")?;
+    for a in &attachments {
+        let target = dest.join(&a.hash);
+        if !target.exists() {
+            fs::copy(blob_path(root, &a.hash), &target).with_context(|| format!("exporting {}", a.file_name))?;
+        }
+    }
+    Ok(attachments)
+}
+
+/// Re-attach exported files from `source` (the folder written by `export_media`);
+/// `map_parent` translates an old (parent_type, parent_id) to the new parent id.
+/// Attachments whose parent or file did not come across, or whose file does not match its
+/// hash, are skipped.
+pub fn import_media(
+    pool: &DbPool,
+    root: &Path,
+    source: &Path,
+    attachments: &[Attachment],
+    map_parent: impl Fn(&str, i64) -> Option<i64>,
+) -> anyhow::Result<()> {
+    for a in attachments {
+        let Some(parent_id) = map_parent(&a.parent_type, a.parent_id) else { continue };
+        // The hash names the file, so anything but a SHA-256 digest could point outside `source`
+        if a.hash.len() != 64 || !a.hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            continue;
+        }
+        let Ok(bytes) = fs::read(source.join(&a.hash)) else { continue };
+        if format!("{:x}", Sha256::digest(&bytes)) != a.hash {
+            continue;
+        }
+        add_bytes(pool, root, &a.parent_type, parent_id, &a.file_name, a.title.clone(), &bytes)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::time::SystemTime;
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+        let mut out = Vec::new();
+        img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png).unwrap();
+        out
+    }
+
+    /// Project 1 with character 1, doc 1, a "Location" entity 1 and an "Item" entity 2,
+    /// plus an empty media store directory
+    fn setup(name: &str) -> (DbPool, PathBuf) {
+        let manager = SqliteConnectionManager::file(format!("file:memattachments_{}?mode=memory&cache=shared", name));
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/008_add_codex.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/018_add_attachments.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO projects (id, name) VALUES (1, 'P');
+             INSERT INTO characters (id, project_id, name) VALUES (1, 1, 'Mara');
+             INSERT INTO docs (id, project_id, path, name) VALUES (1, 1, '', 'Harbour');
+             INSERT INTO codex_types (id, project_id, name) VALUES (1, 1, 'Location'), (2, 1, 'Item');
+             INSERT INTO codex_entities (id, project_id, type_id, name) VALUES (1, 1, 1, 'Lighthouse'), (2, 1, 2, 'Compass');",
+        ).unwrap();
+        let root = std::env::temp_dir().join(format!("cora-media-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        (pool, root)
+    }
+
+    /// Pretend a file was written long enough ago for garbage collection to consider it
+    fn age(path: &Path) {
+        let old = SystemTime::now() - ORPHAN_GRACE * 2;
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+    }
+
+    #[test]
+    fn images_get_dimensions_and_thumbnails() {
+        let (pool, root) = setup("images");
+        let map = add_bytes(&pool, &root, "project", 1, "map.png", Some("Harbour map".into()), &png_bytes(600, 300)).unwrap();
+        assert_eq!(map.mime, "image/png");
+        assert_eq!((map.width, map.height), (Some(600), Some(300)));
+        let thumb = image::open(map.thumbnail_path.as_ref().unwrap()).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (256, 128));
+
+        let notes = add_bytes(&pool, &root, "character", 1, "voice.md", None, b"# Accent\nsoft vowels").unwrap();
+        assert_eq!(notes.mime, "text/markdown");
+        assert!(notes.thumbnail_path.is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn identical_files_are_stored_once() {
+        let (pool, root) = setup("dedup");
+        let map = png_bytes(40, 20);
+        let first = add_bytes(&pool, &root, "project", 1, "map.png", None, &map).unwrap();
+        let again = add_bytes(&pool, &root, "doc", 1, "copy.png", None, &map).unwrap();
+        assert_eq!(again.hash, first.hash);
+        assert_eq!(again.path, first.path);
+        assert_eq!(list(&pool, &root, 1).unwrap().len(), 2);
+
+        let conn = pool.get().unwrap();
+        let blobs: i64 = conn.query_row("SELECT COUNT(*) FROM media_blobs", [], |row| row.get(0)).unwrap();
+        assert_eq!(blobs, 1);
+        let shard = blob_path(&root, &first.hash).parent().unwrap().to_path_buf();
+        assert_eq!(fs::read_dir(shard).unwrap().count(), 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn parents_must_exist_and_locations_must_be_locations() {
+        let (pool, root) = setup("parents");
+        assert!(add_bytes(&pool, &root, "character", 9, "x.txt", None, b"x").is_err());
+        assert!(add_bytes(&pool, &root, "event", 1, "x.txt", None, b"x").is_err());
+        let err = add_bytes(&pool, &root, "location", 2, "x.txt", None, b"x").unwrap_err();
+        assert!(err.to_string().contains("not a location"));
+        assert!(add_bytes(&pool, &root, "location", 1, "x.txt", None, b"x").is_ok());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn shared_blobs_outlive_all_but_their_last_attachment() {
+        let (pool, root) = setup("shared");
+        let map = png_bytes(40, 20);
+        let first = add_bytes(&pool, &root, "project", 1, "map.png", None, &map).unwrap();
+        let again = add_bytes(&pool, &root, "doc", 1, "copy.png", None, &map).unwrap();
+        let notes = add_bytes(&pool, &root, "character", 1, "voice.md", None, b"soft vowels").unwrap();
+
+        delete(&pool, &root, first.id).unwrap();
+        assert!(Path::new(&again.path).exists());
+        assert!(Path::new(again.thumbnail_path.as_ref().unwrap()).exists());
+
+        // Deleting the owner removes its attachments; collection then removes the files
+        pool.get().unwrap().execute("DELETE FROM docs WHERE id = 1", []).unwrap();
+        assert_eq!(collect_garbage(&pool, &root).unwrap(), 1);
+        assert!(!Path::new(&again.path).exists());
+        assert!(!Path::new(again.thumbnail_path.as_ref().unwrap()).exists());
+        assert!(Path::new(&notes.path).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn garbage_collection_sweeps_files_without_rows() {
+        let (pool, root) = setup("orphans");
+        let kept = add_bytes(&pool, &root, "project", 1, "kept.txt", None, b"kept").unwrap();
+
+        // A failed transaction after the files were written leaves them behind
+        let hash = format!("{:x}", Sha256::digest(b"rolled back"));
+        let orphan = blob_path(&root, &hash);
+        let orphan_thumb = thumbnail_path(&root, &hash);
+        write_once(&orphan, b"rolled back").unwrap();
+        write_once(&orphan_thumb, b"png").unwrap();
+        let fresh = blob_path(&root, &format!("{:x}", Sha256::digest(b"in flight")));
+        write_once(&fresh, b"in flight").unwrap();
+        age(&orphan);
+        age(&orphan_thumb);
+        age(Path::new(&kept.path));
+
+        assert_eq!(collect_garbage(&pool, &root).unwrap(), 0);
+        assert!(!orphan.exists());
+        assert!(!orphan_thumb.exists());
+        assert!(fresh.exists(), "recent files may belong to an uncommitted transaction");
+        assert!(Path::new(&kept.path).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn failed_attach_records_no_blob() {
+        let (pool, root) = setup("rollback");
+        pool.get().unwrap().execute_batch(
+            "CREATE TRIGGER fail_attach BEFORE INSERT ON attachments BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+        ).unwrap();
+        assert!(add_bytes(&pool, &root, "project", 1, "map.png", None, &png_bytes(40, 20)).is_err());
+        let blobs: i64 = pool.get().unwrap().query_row("SELECT COUNT(*) FROM media_blobs", [], |row| row.get(0)).unwrap();
+        assert_eq!(blobs, 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn import_takes_only_files_matching_their_hash() {
+        let (pool, root) = setup("import");
+        let template = add_bytes(&pool, &root, "character", 1, "voice.md", None, b"soft vowels").unwrap();
+        let source = root.join("export");
+        fs::create_dir_all(&source).unwrap();
+        let valid = format!("{:x}", Sha256::digest(b"tide table"));
+        let tampered = format!("{:x}", Sha256::digest(b"original"));
+        fs::write(source.join(&valid), b"tide table").unwrap();
+        fs::write(source.join(&tampered), b"altered").unwrap();
+        fs::write(root.join("outside"), b"secret").unwrap();
+        let exported = |hash: &str| Attachment { hash: hash.to_string(), file_name: "tides.txt".into(), ..template.clone() };
+
+        import_media(&pool, &root, &source, &[exported(&valid), exported(&tampered), exported("../outside")], |_, id| Some(id)).unwrap();
+        let imported: Vec<_> = list(&pool, &root, 1).unwrap().into_iter().filter(|a| a.file_name == "tides.txt").collect();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].hash, valid);
+        assert!(!blob_path(&root, &tampered).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,655 @@
+use crate::dates::{self, SECONDS_PER_DAY};
+use crate::db::{DbPool, get_conn};
+use crate::models::{Calendar, CalendarCreate, CalendarDate, CalendarEra, CalendarKind, CalendarMonth, CalendarUpdate};
+use anyhow::Context;
+use chrono::Datelike;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashSet;
+
+/// Upper bound on the days in a calendar year, keeping day arithmetic far from overflow
+const MAX_YEAR_DAYS: i64 = 100_000;
+
+const CALENDAR_COLUMNS: &str = "id, project_id, name, kind, months, weekdays, eras, epoch_offset, day_label";
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<T> {
+    let raw: String = row.get(idx)?;
+    serde_json::from_str(&raw)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn calendar_from_row(row: &rusqlite::Row) -> rusqlite::Result<Calendar> {
+    let kind: String = row.get(3)?;
+    Ok(Calendar {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        kind: if kind == "relative" { CalendarKind::Relative } else { CalendarKind::Dated },
+        months: json_column(row, 4)?,
+        weekdays: json_column(row, 5)?,
+        eras: json_column(row, 6)?,
+        epoch_offset: row.get(7)?,
+        day_label: row.get(8)?,
+    })
+}
+
+fn kind_str(kind: CalendarKind) -> &'static str {
+    match kind {
+        CalendarKind::Dated => "dated",
+        CalendarKind::Relative => "relative",
+    }
+}
+
+pub fn load(conn: &Connection, id: i64) -> anyhow::Result<Option<Calendar>> {
+    let sql = format!("SELECT {} FROM calendars WHERE id = ?1", CALENDAR_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], calendar_from_row).optional()?)
+}
+
+/// Load an optional calendar; None means Gregorian
+fn load_opt(conn: &Connection, id: Option<i64>) -> anyhow::Result<Option<Calendar>> {
+    match id {
+        Some(id) => Ok(Some(load(conn, id)?.ok_or_else(|| anyhow::anyhow!("calendar {} not found", id))?)),
+        None => Ok(None),
+    }
+}
+
+/// Load a calendar for a record in `project_id`, refusing one from another project
+fn load_for_project(conn: &Connection, project_id: i64, id: Option<i64>) -> anyhow::Result<Option<Calendar>> {
+    let calendar = load_opt(conn, id)?;
+    if let Some(c) = &calendar {
+        if c.project_id != project_id {
+            anyhow::bail!("calendar {} belongs to another project", c.id);
+        }
+    }
+    Ok(calendar)
+}
+
+fn check_names<'a>(what: &str, names: impl Iterator<Item = &'a str>) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("{} name cannot be empty", what);
+        }
+        if !seen.insert(name.to_lowercase()) {
+            anyhow::bail!("duplicate {} name '{}'", what, name);
+        }
+    }
+    Ok(())
+}
+
+/// Check a calendar definition and put eras in chronological order
+fn validate(calendar: &mut Calendar) -> anyhow::Result<()> {
+    if calendar.name.trim().is_empty() {
+        anyhow::bail!("name cannot be empty");
+    }
+    match calendar.kind {
+        CalendarKind::Dated => {
+            if calendar.months.is_empty() {
+                anyhow::bail!("a dated calendar needs at least one month");
+            }
+            if let Some(m) = calendar.months.iter().find(|m| m.days < 1) {
+                anyhow::bail!("month '{}' must have at least one day", m.name);
+            }
+            if let Some(m) = calendar.months.iter().find(|m| m.days > MAX_YEAR_DAYS) {
+                anyhow::bail!("month '{}' cannot have more than {} days", m.name, MAX_YEAR_DAYS);
+            }
+            if year_length(calendar)? > MAX_YEAR_DAYS {
+                anyhow::bail!("a year cannot have more than {} days", MAX_YEAR_DAYS);
+            }
+            check_names("month", calendar.months.iter().map(|m| m.name.as_str()))?;
+        }
+        CalendarKind::Relative => {
+            if calendar.day_label.trim().is_empty() {
+                anyhow::bail!("day label cannot be empty");
+            }
+        }
+    }
+    check_names("weekday", calendar.weekdays.iter().map(|w| w.as_str()))?;
+    check_names("era", calendar.eras.iter().map(|e| e.name.as_str()))?;
+    calendar.eras.sort_by_key(|e| e.start_year);
+    Ok(())
+}
+
+/// Sum of month lengths; stored calendars predating the size check may overflow it
+fn sum_days(calendar: &Calendar, months: &[CalendarMonth]) -> anyhow::Result<i64> {
+    months
+        .iter()
+        .try_fold(0i64, |total, m| total.checked_add(m.days))
+        .ok_or_else(|| anyhow::anyhow!("calendar '{}' has too many days in a year", calendar.name))
+}
+
+fn year_length(calendar: &Calendar) -> anyhow::Result<i64> {
+    sum_days(calendar, &calendar.months)
+}
+
+/// Days of the year before the given month (0-based)
+fn days_before_month(calendar: &Calendar, month: usize) -> anyhow::Result<i64> {
+    sum_days(calendar, &calendar.months[..month])
+}
+
+fn weekday(calendar: &Calendar, day: i64) -> Option<String> {
+    if calendar.weekdays.is_empty() {
+        return None;
+    }
+    let idx = day.checked_sub(calendar.epoch_offset)?.rem_euclid(calendar.weekdays.len() as i64) as usize;
+    Some(calendar.weekdays[idx].clone())
+}
+
+/// Render an absolute day through a calendar; None renders Gregorian ISO 8601
+pub fn render(calendar: Option<&Calendar>, day: i64) -> anyhow::Result<CalendarDate> {
+    let Some(calendar) = calendar else {
+        let date = dates::from_day_number(day).ok_or_else(|| anyhow::anyhow!("day {} is out of range", day))?;
+        let iso = dates::PartialDate { year: date.year(), month: Some(date.month()), day: Some(date.day()), time: None, offset: None };
+        return Ok(CalendarDate {
+            day,
+            text: iso.to_string(),
+            year: Some(date.year() as i64),
+            era: None,
+            year_of_era: None,
+            month: Some(date.format("%B").to_string()),
+            day_of_month: Some(date.day() as i64),
+            weekday: Some(date.format("%A").to_string()),
+        });
+    };
+
+    let out_of_range = || anyhow::anyhow!("day {} is out of range in calendar '{}'", day, calendar.name);
+    let internal = day.checked_sub(calendar.epoch_offset).ok_or_else(out_of_range)?;
+    if calendar.kind == CalendarKind::Relative {
+        return Ok(CalendarDate {
+            day,
+            text: format!("{} {}", calendar.day_label, internal.checked_add(1).ok_or_else(out_of_range)?),
+            year: None,
+            era: None,
+            year_of_era: None,
+            month: None,
+            day_of_month: None,
+            weekday: weekday(calendar, day),
+        });
+    }
+
+    let length = year_length(calendar)?;
+    let year = internal.div_euclid(length).checked_add(1).ok_or_else(out_of_range)?;
+    let mut remaining = internal.rem_euclid(length);
+    let mut month = 0;
+    while remaining >= calendar.months[month].days {
+        remaining -= calendar.months[month].days;
+        month += 1;
+    }
+    let day_of_month = remaining + 1;
+    let month_name = calendar.months[month].name.clone();
+    let era = calendar.eras.iter().rev().find(|e| e.start_year <= year);
+    let year_of_era = era
+        .map(|e| year.checked_sub(e.start_year).and_then(|y| y.checked_add(1)).ok_or_else(out_of_range))
+        .transpose()?;
+    let text = match (era, year_of_era) {
+        (Some(e), Some(y)) => format!("{} {} {} {}", day_of_month, month_name, y, e.name),
+        _ => format!("{} {} {}", day_of_month, month_name, year),
+    };
+    Ok(CalendarDate {
+        day,
+        text,
+        year: Some(year),
+        era: era.map(|e| e.name.clone()),
+        year_of_era,
+        month: Some(month_name),
+        day_of_month: Some(day_of_month),
+        weekday: weekday(calendar, day),
+    })
+}
+
+/// Strip a case-insensitive prefix followed by whitespace or a comma
+fn strip_word_prefix<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let head = text.get(..word.len())?;
+    let rest = &text[word.len()..];
+    if head.eq_ignore_ascii_case(word) && (rest.is_empty() || rest.starts_with([' ', ','])) {
+        Some(rest.trim_start_matches([' ', ',']))
+    } else {
+        None
+    }
+}
+
+/// Strip a case-insensitive suffix preceded by whitespace
+fn strip_word_suffix<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let split = text.len().checked_sub(word.len())?;
+    let (rest, tail) = (text.get(..split)?, text.get(split..)?);
+    if tail.eq_ignore_ascii_case(word) && rest.ends_with(' ') {
+        Some(rest.trim_end())
+    } else {
+        None
+    }
+}
+
+/// Parse a date written in a calendar into the absolute days it covers.
+/// Dated calendars accept "[Weekday,] [day] Month year [Era]" or just "year [Era]";
+/// relative calendars accept "Day N". None parses Gregorian ISO 8601.
+pub fn parse(calendar: Option<&Calendar>, text: &str) -> anyhow::Result<(i64, i64)> {
+    let Some(calendar) = calendar else {
+        let date = dates::PartialDate::parse(text)?;
+        return date.day_range().ok_or_else(|| anyhow::anyhow!("'{}' is out of range", text));
+    };
+    let malformed = || anyhow::anyhow!("'{}' is not a date in calendar '{}'", text, calendar.name);
+    let mut s = text.trim();
+
+    if calendar.kind == CalendarKind::Relative {
+        let number = strip_word_prefix(s, calendar.day_label.trim()).unwrap_or(s);
+        let n: i64 = number.trim().parse().map_err(|_| malformed())?;
+        let day = calendar.epoch_offset.checked_add(n).and_then(|d| d.checked_sub(1)).ok_or_else(malformed)?;
+        return Ok((day, day));
+    }
+
+    let mut named_weekday = None;
+    for w in &calendar.weekdays {
+        if let Some(rest) = strip_word_prefix(s, w.trim()) {
+            named_weekday = Some(w.clone());
+            s = rest;
+            break;
+        }
+    }
+    let mut eras: Vec<&CalendarEra> = calendar.eras.iter().collect();
+    eras.sort_by_key(|e| std::cmp::Reverse(e.name.len()));
+    let mut era = None;
+    for e in eras {
+        if let Some(rest) = strip_word_suffix(s, e.name.trim()) {
+            era = Some(e);
+            s = rest;
+            break;
+        }
+    }
+
+    let tokens: Vec<&str> = s.split([' ', ',']).filter(|t| !t.is_empty()).collect();
+    let (year_token, rest) = tokens.split_last().ok_or_else(malformed)?;
+    let written_year: i64 = year_token.parse().map_err(|_| malformed())?;
+    let year = match era {
+        Some(e) => e.start_year.checked_add(written_year).and_then(|y| y.checked_sub(1)).ok_or_else(malformed)?,
+        None => written_year,
+    };
+    let length = year_length(calendar)?;
+    // Years are typed by the user; overflowing ones are not dates rather than a panic
+    let year_start = year
+        .checked_sub(1)
+        .and_then(|y| y.checked_mul(length))
+        .and_then(|d| d.checked_add(calendar.epoch_offset))
+        .ok_or_else(malformed)?;
+    if rest.is_empty() {
+        let year_end = year_start.checked_add(length - 1).ok_or_else(malformed)?;
+        return Ok((year_start, year_end));
+    }
+
+    let (day_of_month, month_tokens) = match rest.split_first() {
+        Some((first, month)) if !month.is_empty() => match first.parse::<i64>() {
+            Ok(d) => (Some(d), month),
+            Err(_) => (None, rest),
+        },
+        _ => (None, rest),
+    };
+    let month_text = month_tokens.join(" ");
+    let month = calendar.months.iter().position(|m| m.name.trim().eq_ignore_ascii_case(&month_text))
+        .or_else(|| month_text.parse::<usize>().ok().filter(|n| (1..=calendar.months.len()).contains(n)).map(|n| n - 1))
+        .ok_or_else(|| anyhow::anyhow!("unknown month '{}' in calendar '{}'", month_text, calendar.name))?;
+    let month_start = year_start.checked_add(days_before_month(calendar, month)?).ok_or_else(malformed)?;
+    let month_days = calendar.months[month].days;
+
+    let Some(d) = day_of_month else {
+        let month_end = month_start.checked_add(month_days - 1).ok_or_else(malformed)?;
+        return Ok((month_start, month_end));
+    };
+    if !(1..=month_days).contains(&d) {
+        anyhow::bail!("{} has only {} days", calendar.months[month].name, month_days);
+    }
+    let day = month_start.checked_add(d - 1).ok_or_else(malformed)?;
+    if let Some(w) = named_weekday {
+        if weekday(calendar, day).as_deref() != Some(w.as_str()) {
+            anyhow::bail!("'{}' does not fall on a {}", text, w);
+        }
+    }
+    Ok((day, day))
+}
+
+/// A date range resolved through a calendar: display text plus the absolute days covered
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRange {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub start_day: Option<i64>,
+    pub end_day: Option<i64>,
+}
+
+/// Validate a range written in a calendar (None = Gregorian ISO 8601).
+/// Gregorian text keeps its precision; calendar dates are re-rendered from the days they cover,
+/// with the end only shown when the range spans more than one day.
+pub fn resolve_range(conn: &Connection, project_id: i64, calendar_id: Option<i64>, start: Option<String>, end: Option<String>) -> anyhow::Result<ResolvedRange> {
+    let calendar = load_for_project(conn, project_id, calendar_id)?;
+    let Some(calendar) = calendar else {
+        let (start_date, end_date) = dates::normalize_range(start, end)?;
+        let (start_day, end_day) = match gregorian_days(start_date.as_deref(), end_date.as_deref()) {
+            Some((s, e)) => (Some(s), Some(e)),
+            None => (None, None),
+        };
+        return Ok(ResolvedRange { start_date, end_date, start_day, end_day });
+    };
+
+    let parse_bound = |value: Option<String>| -> anyhow::Result<Option<(i64, i64)>> {
+        match value.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(v) => parse(Some(&calendar), v).map(Some),
+        }
+    };
+    let (first, last) = match (parse_bound(start)?, parse_bound(end)?) {
+        (Some(s), Some(e)) => {
+            if e.1 < s.0 {
+                anyhow::bail!("invalid date range: end is before start");
+            }
+            (s.0, e.1)
+        }
+        (Some(b), None) | (None, Some(b)) => b,
+        (None, None) => return Ok(ResolvedRange { start_date: None, end_date: None, start_day: None, end_day: None }),
+    };
+    render_days(Some(&calendar), first, last)
+}
+
+/// Render a stored day range; the end is only shown when it differs from the start
+fn render_days(calendar: Option<&Calendar>, first: i64, last: i64) -> anyhow::Result<ResolvedRange> {
+    let start_date = render(calendar, first)?.text;
+    let end_date = if last != first { Some(render(calendar, last)?.text) } else { None };
+    Ok(ResolvedRange { start_date: Some(start_date), end_date, start_day: Some(first), end_day: Some(last) })
+}
+
+/// Convert stored days into another calendar
+pub fn convert_range(conn: &Connection, project_id: i64, calendar_id: Option<i64>, start_day: Option<i64>, end_day: Option<i64>) -> anyhow::Result<ResolvedRange> {
+    let calendar = load_for_project(conn, project_id, calendar_id)?;
+    match (start_day, end_day) {
+        (Some(s), Some(e)) => render_days(calendar.as_ref(), s, e),
+        (Some(d), None) | (None, Some(d)) => render_days(calendar.as_ref(), d, d),
+        (None, None) => Ok(ResolvedRange { start_date: None, end_date: None, start_day: None, end_day: None }),
+    }
+}
+
+/// First and last day covered by Gregorian text; a single bound stands for both
+pub fn gregorian_days(start: Option<&str>, end: Option<&str>) -> Option<(i64, i64)> {
+    let start = dates::parse_opt(start).ok().flatten().and_then(|d| d.day_range());
+    let end = dates::parse_opt(end).ok().flatten().and_then(|d| d.day_range());
+    match (start, end) {
+        (Some(s), Some(e)) => Some((s.0, e.1)),
+        (Some(b), None) | (None, Some(b)) => Some(b),
+        (None, None) => None,
+    }
+}
+
+/// Absolute (first, last) second covered by a stored range, comparable across calendars.
+/// Calendar dates cover whole days; Gregorian text keeps its time of day.
+pub fn stored_span(calendar_id: Option<i64>, start_day: Option<i64>, end_day: Option<i64>, start: Option<&str>, end: Option<&str>) -> Option<(i64, i64)> {
+    if calendar_id.is_some() {
+        let (first, last) = match (start_day, end_day) {
+            (Some(s), Some(e)) => (s, e),
+            (Some(d), None) | (None, Some(d)) => (d, d),
+            (None, None) => return None,
+        };
+        return Some((first * SECONDS_PER_DAY, (last + 1) * SECONDS_PER_DAY - 1));
+    }
+    let start = dates::parse_opt(start).ok().flatten();
+    let end = dates::parse_opt(end).ok().flatten();
+    let (first, last) = match (start, end) {
+        (Some(s), Some(e)) => (s.first_instant()?, e.last_instant()?),
+        (Some(d), None) | (None, Some(d)) => (d.first_instant()?, d.last_instant()?),
+        (None, None) => return None,
+    };
+    Some((dates::epoch_seconds(first), dates::epoch_seconds(last)))
+}
+
+/// Seconds covered by a single date written in a calendar, for queries like "who was where on …"
+pub fn query_span(pool: &DbPool, calendar_id: Option<i64>, text: &str) -> anyhow::Result<(i64, i64)> {
+    let conn = get_conn(pool)?;
+    let calendar = load_opt(&conn, calendar_id)?;
+    if calendar.is_none() {
+        let date = dates::PartialDate::parse(text)?;
+        let (first, last) = date.first_instant().zip(date.last_instant()).ok_or_else(|| anyhow::anyhow!("'{}' is out of range", text))?;
+        return Ok((dates::epoch_seconds(first), dates::epoch_seconds(last)));
+    }
+    let (first, last) = parse(calendar.as_ref(), text)?;
+    Ok((first * SECONDS_PER_DAY, (last + 1) * SECONDS_PER_DAY - 1))
+}
+
+/// Recompute the Gregorian day columns of events and timelines from their text
+pub fn refresh_gregorian_days(conn: &Connection) -> anyhow::Result<()> {
+    for (table, start_expr) in [("events", "COALESCE(start_date, date)"), ("timelines", "start_date")] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, {}, end_date FROM {} WHERE calendar_id IS NULL", start_expr, table
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut update = conn.prepare(&format!("UPDATE {} SET start_day = ?1, end_day = ?2 WHERE id = ?3", table))?;
+        for (id, start, end) in rows {
+            let days = gregorian_days(start.as_deref(), end.as_deref());
+            update.execute(rusqlite::params![days.map(|d| d.0), days.map(|d| d.1), id])?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-render the text of every event and timeline written in a calendar from its stored days
+fn rerender(conn: &Connection, calendar: &Calendar) -> anyhow::Result<()> {
+    for table in ["events", "timelines"] {
+        let mut stmt = conn.prepare(&format!("SELECT id, start_day, end_day FROM {} WHERE calendar_id = ?1", table))?;
+        let rows = stmt.query_map(rusqlite::params![calendar.id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Option<i64>>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut update = conn.prepare(&format!("UPDATE {} SET start_date = ?1, end_date = ?2 WHERE id = ?3", table))?;
+        for (id, start_day, end_day) in rows {
+            let r = match (start_day, end_day) {
+                (Some(s), Some(e)) => render_days(Some(calendar), s, e)?,
+                (Some(d), None) | (None, Some(d)) => render_days(Some(calendar), d, d)?,
+                (None, None) => continue,
+            };
+            update.execute(rusqlite::params![r.start_date, r.end_date, id])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn create(pool: &DbPool, project_id: i64, payload: CalendarCreate) -> anyhow::Result<Calendar> {
+    let mut calendar = Calendar {
+        id: 0,
+        project_id,
+        name: payload.name,
+        kind: payload.kind,
+        months: payload.months,
+        weekdays: payload.weekdays,
+        eras: payload.eras,
+        epoch_offset: payload.epoch_offset,
+        day_label: payload.day_label.unwrap_or_else(|| "Day".to_string()),
+    };
+    validate(&mut calendar)?;
+
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "INSERT INTO calendars (project_id, name, kind, months, weekdays, eras, epoch_offset, day_label) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            project_id,
+            calendar.name.trim(),
+            kind_str(calendar.kind),
+            serde_json::to_string(&calendar.months)?,
+            serde_json::to_string(&calendar.weekdays)?,
+            serde_json::to_string(&calendar.eras)?,
+            calendar.epoch_offset,
+            calendar.day_label,
+        ],
+    ).context("inserting calendar")?;
+    let id = conn.last_insert_rowid();
+    load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("calendar not found after creation"))
+}
+
+pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Calendar>> {
+    let conn = get_conn(pool)?;
+    load(&conn, id)
+}
+
+/// List a project's calendars by name
+pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Calendar>> {
+    let conn = get_conn(pool)?;
+    let sql = format!("SELECT {} FROM calendars WHERE project_id = ?1 ORDER BY name COLLATE NOCASE, id", CALENDAR_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![project_id], calendar_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Update a calendar; stored dates keep their absolute days and are re-rendered through the new definition
+pub fn update(pool: &DbPool, id: i64, payload: CalendarUpdate) -> anyhow::Result<Calendar> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let mut calendar = load(&tx, id)?.ok_or_else(|| anyhow::anyhow!("calendar not found"))?;
+    if let Some(name) = payload.name { calendar.name = name.trim().to_string(); }
+    if let Some(months) = payload.months { calendar.months = months; }
+    if let Some(weekdays) = payload.weekdays { calendar.weekdays = weekdays; }
+    if let Some(eras) = payload.eras { calendar.eras = eras; }
+    if let Some(offset) = payload.epoch_offset { calendar.epoch_offset = offset; }
+    if let Some(label) = payload.day_label { calendar.day_label = label; }
+    validate(&mut calendar)?;
+
+    tx.execute(
+        "UPDATE calendars SET name = ?1, months = ?2, weekdays = ?3, eras = ?4, epoch_offset = ?5, day_label = ?6 WHERE id = ?7",
+        rusqlite::params![
+            calendar.name,
+            serde_json::to_string(&calendar.months)?,
+            serde_json::to_string(&calendar.weekdays)?,
+            serde_json::to_string(&calendar.eras)?,
+            calendar.epoch_offset,
+            calendar.day_label,
+            id,
+        ],
+    ).context("updating calendar")?;
+    rerender(&tx, &calendar)?;
+    tx.commit()?;
+    Ok(calendar)
+}
+
+/// Delete a calendar that no event or timeline is written in
+pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    let in_use: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM events WHERE calendar_id = ?1) + (SELECT COUNT(*) FROM timelines WHERE calendar_id = ?1)",
+        rusqlite::params![id],
+        |row| row.get(0),
+    )?;
+    if in_use > 0 {
+        anyhow::bail!("calendar is used by {} dated item(s); convert them first", in_use);
+    }
+    let rows = conn.execute("DELETE FROM calendars WHERE id = ?1", rusqlite::params![id])?;
+    if rows == 0 {
+        anyhow::bail!("calendar not found");
+    }
+    Ok(())
+}
+
+/// Render an absolute day through a calendar (None = Gregorian)
+pub fn render_day(pool: &DbPool, calendar_id: Option<i64>, day: i64) -> anyhow::Result<CalendarDate> {
+    let conn = get_conn(pool)?;
+    render(load_opt(&conn, calendar_id)?.as_ref(), day)
+}
+
+/// Parse a date written in a calendar into the first and last absolute day it covers
+pub fn parse_date(pool: &DbPool, calendar_id: Option<i64>, text: &str) -> anyhow::Result<(i64, i64)> {
+    let conn = get_conn(pool)?;
+    parse(load_opt(&conn, calendar_id)?.as_ref(), text)
+}
+
+/// Recreate a project's calendars from an export, returning old → new ids
+pub fn import_snapshot(pool: &DbPool, project_id: i64, calendars: &[Calendar]) -> anyhow::Result<std::collections::HashMap<i64, i64>> {
+    let mut ids = std::collections::HashMap::new();
+    for c in calendars {
+        let created = create(pool, project_id, CalendarCreate {
+            name: c.name.clone(),
+            kind: c.kind,
+            months: c.months.clone(),
+            weekdays: c.weekdays.clone(),
+            eras: c.eras.clone(),
+            epoch_offset: c.epoch_offset,
+            day_label: Some(c.day_label.clone()),
+        })?;
+        ids.insert(c.id, created.id);
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shire() -> Calendar {
+        let month = |name: &str, days: i64| CalendarMonth { name: name.to_string(), days };
+        Calendar {
+            id: 1,
+            project_id: 1,
+            name: "Shire Reckoning".to_string(),
+            kind: CalendarKind::Dated,
+            months: vec![month("Afteryule", 30), month("Solmath", 30), month("Midyear", 5), month("Foreyule", 35)],
+            weekdays: vec!["Sterday".into(), "Sunday".into(), "Monday".into(), "Trewsday".into(), "Hevensday".into(), "Mersday".into(), "Highday".into()],
+            eras: vec![CalendarEra { name: "Fourth Age".into(), start_year: 3022 }],
+            epoch_offset: 1000,
+            day_label: "Day".to_string(),
+        }
+    }
+
+    #[test]
+    fn dated_calendar_round_trips() {
+        let cal = shire();
+        let (day, same) = parse(Some(&cal), "3 Solmath 12").unwrap();
+        assert_eq!(day, same);
+        assert_eq!(day, 1000 + 11 * 100 + 30 + 2);
+        let rendered = render(Some(&cal), day).unwrap();
+        assert_eq!(rendered.text, "3 Solmath 12");
+        assert_eq!(rendered.weekday.as_deref(), Some("Mersday"));
+        assert_eq!(parse(Some(&cal), "Mersday, 3 Solmath 12").unwrap().0, day);
+        assert!(parse(Some(&cal), "Sunday, 3 Solmath 12").is_err());
+
+        let era_day = parse(Some(&cal), "1 Afteryule 2 Fourth Age").unwrap().0;
+        assert_eq!(render(Some(&cal), era_day).unwrap().text, "1 Afteryule 2 Fourth Age");
+        assert_eq!(render(Some(&cal), era_day).unwrap().year, Some(3023));
+
+        let (first, last) = parse(Some(&cal), "midyear 12").unwrap();
+        assert_eq!(last - first, 4);
+        let (first, last) = parse(Some(&cal), "12").unwrap();
+        assert_eq!(last - first, 99);
+        assert!(parse(Some(&cal), "31 Solmath 12").is_err());
+        assert!(parse(Some(&cal), "3 Rethe 12").is_err());
+        assert!(parse(Some(&cal), "9223372036854775807").is_err());
+        assert!(parse(Some(&cal), "1 Solmath 9223372036854775807 Fourth Age").is_err());
+
+        // Days before year 1 still render and parse
+        let before = render(Some(&cal), 950).unwrap();
+        assert_eq!(before.text, "21 Solmath 0");
+        assert_eq!(parse(Some(&cal), &before.text).unwrap().0, 950);
+    }
+
+    #[test]
+    fn relative_and_gregorian() {
+        let mut cal = shire();
+        cal.kind = CalendarKind::Relative;
+        cal.epoch_offset = 0;
+        assert_eq!(parse(Some(&cal), "Day 14").unwrap(), (13, 13));
+        assert_eq!(parse(Some(&cal), "day -2").unwrap(), (-3, -3));
+        assert_eq!(render(Some(&cal), 13).unwrap().text, "Day 14");
+        assert!(parse(Some(&cal), "Day -9223372036854775808").is_err());
+        assert!(render(Some(&cal), i64::MAX).is_err());
+
+        let (first, last) = parse(None, "2025-03").unwrap();
+        assert_eq!(render(None, first).unwrap().text, "2025-03-01");
+        assert_eq!(render(None, last).unwrap().weekday.as_deref(), Some("Monday"));
+    }
+
+    #[test]
+    fn oversized_years_are_rejected() {
+        let mut cal = shire();
+        cal.months[0].days = MAX_YEAR_DAYS + 1;
+        assert!(validate(&mut cal).unwrap_err().to_string().contains("Afteryule"));
+        let mut cal = shire();
+        cal.months[0].days = MAX_YEAR_DAYS;
+        assert!(validate(&mut cal).unwrap_err().to_string().contains("a year"));
+        assert!(validate(&mut shire()).is_ok());
+
+        // Calendars stored before the check error out rather than overflow
+        let mut cal = shire();
+        cal.months[0].days = i64::MAX;
+        assert!(render(Some(&cal), 5000).is_err());
+        assert!(parse(Some(&cal), "1 Foreyule 2").is_err());
+    }
+}
@@ -0,0 +1,585 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{
+    CodexEntity, CodexEntityCreate, CodexEntityUpdate,
+    CodexField, CodexFieldKind, CodexType, CodexTypeCreate, CodexTypeUpdate,
+};
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<T> {
+    let raw: String = row.get(idx)?;
+    serde_json::from_str(&raw)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn type_from_row(row: &rusqlite::Row) -> rusqlite::Result<CodexType> {
+    Ok(CodexType {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        desc: row.get(3)?,
+        fields: json_column(row, 4)?,
+        sort_order: row.get(5)?,
+    })
+}
+
+fn entity_from_row(row: &rusqlite::Row) -> rusqlite::Result<CodexEntity> {
+    Ok(CodexEntity {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        type_id: row.get(2)?,
+        name: row.get(3)?,
+        desc: row.get(4)?,
+        data: json_column(row, 5)?,
+    })
+}
+
+const TYPE_COLUMNS: &str = "id, project_id, name, desc, fields, sort_order";
+const ENTITY_COLUMNS: &str = "id, project_id, type_id, name, desc, data";
+
+fn load_type(conn: &Connection, id: i64) -> anyhow::Result<Option<CodexType>> {
+    let sql = format!("SELECT {} FROM codex_types WHERE id = ?1", TYPE_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], type_from_row).optional()?)
+}
+
+fn load_entity(conn: &Connection, id: i64) -> anyhow::Result<Option<CodexEntity>> {
+    let sql = format!("SELECT {} FROM codex_entities WHERE id = ?1", ENTITY_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], entity_from_row).optional()?)
+}
+
+/// Project of a codex entity and whether it is a location (its type is named "Location"),
+/// or None if the entity does not exist
+pub fn location_project(conn: &Connection, entity_id: i64) -> anyhow::Result<Option<(i64, bool)>> {
+    Ok(conn.query_row(
+        "SELECT e.project_id, t.name = 'Location' COLLATE NOCASE
+         FROM codex_entities e JOIN codex_types t ON t.id = e.type_id WHERE e.id = ?1",
+        rusqlite::params![entity_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?)
+}
+
+/// Check a field schema: unique non-empty keys, no nested lists, references only to types of the same project
+fn validate_fields(conn: &Connection, project_id: i64, fields: &[CodexField]) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for f in fields {
+        let key = f.key.trim();
+        if key.is_empty() {
+            anyhow::bail!("field key cannot be empty");
+        }
+        if !seen.insert(key) {
+            anyhow::bail!("duplicate field key '{}'", key);
+        }
+        if f.kind == CodexFieldKind::List && f.item_kind == Some(CodexFieldKind::List) {
+            anyhow::bail!("field '{}': lists cannot contain lists", key);
+        }
+        if let Some(ref_type_id) = f.ref_type_id {
+            let refers = f.kind == CodexFieldKind::Reference
+                || (f.kind == CodexFieldKind::List && f.item_kind == Some(CodexFieldKind::Reference));
+            if !refers {
+                anyhow::bail!("field '{}': ref_type_id is only allowed on reference fields", key);
+            }
+            let owner: Option<i64> = conn.query_row(
+                "SELECT project_id FROM codex_types WHERE id = ?1",
+                rusqlite::params![ref_type_id],
+                |row| row.get(0),
+            ).optional()?;
+            if owner != Some(project_id) {
+                anyhow::bail!("field '{}': referenced codex type {} not found in project", key, ref_type_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_value(conn: &Connection, project_id: i64, field: &CodexField, kind: CodexFieldKind, value: &Value) -> anyhow::Result<()> {
+    let key = &field.key;
+    match kind {
+        CodexFieldKind::Text => {
+            if !value.is_string() { anyhow::bail!("field '{}' must be text", key); }
+        }
+        CodexFieldKind::Number => {
+            if !value.is_number() { anyhow::bail!("field '{}' must be a number", key); }
+        }
+        CodexFieldKind::Date => {
+            let text = value.as_str().ok_or_else(|| anyhow::anyhow!("field '{}' must be a date", key))?;
+            crate::dates::PartialDate::parse(text).with_context(|| format!("field '{}'", key))?;
+        }
+        CodexFieldKind::Reference => {
+            let target = value.as_i64().ok_or_else(|| anyhow::anyhow!("field '{}' must be a codex entity id", key))?;
+            let found: Option<(i64, i64)> = conn.query_row(
+                "SELECT project_id, type_id FROM codex_entities WHERE id = ?1",
+                rusqlite::params![target],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional()?;
+            match found {
+                Some((pid, type_id)) if pid == project_id => {
+                    if field.ref_type_id.map(|t| t != type_id).unwrap_or(false) {
+                        anyhow::bail!("field '{}': entity {} has the wrong type", key, target);
+                    }
+                }
+                _ => anyhow::bail!("field '{}': codex entity {} not found in project", key, target),
+            }
+        }
+        CodexFieldKind::List => {
+            let items = value.as_array().ok_or_else(|| anyhow::anyhow!("field '{}' must be a list", key))?;
+            let item_kind = field.item_kind.unwrap_or(CodexFieldKind::Text);
+            for item in items {
+                validate_value(conn, project_id, field, item_kind, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check entity data against its type schema; null clears a field, unknown keys are rejected
+fn validate_data(conn: &Connection, codex_type: &CodexType, data: &Map<String, Value>) -> anyhow::Result<()> {
+    for (key, value) in data {
+        let field = codex_type.fields.iter().find(|f| &f.key == key)
+            .ok_or_else(|| anyhow::anyhow!("unknown field '{}' for type '{}'", key, codex_type.name))?;
+        if value.is_null() {
+            continue;
+        }
+        validate_value(conn, codex_type.project_id, field, field.kind, value)?;
+    }
+    Ok(())
+}
+
+// Types
+
+pub fn create_type(pool: &DbPool, project_id: i64, payload: CodexTypeCreate) -> anyhow::Result<CodexType> {
+    let mut conn = get_conn(pool)?;
+
+    if payload.name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+
+    let tx = conn.transaction()?;
+    validate_fields(&tx, project_id, &payload.fields)?;
+    let next_order: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM codex_types WHERE project_id = ?1",
+        rusqlite::params![project_id],
+        |row| row.get(0),
+    )?;
+    tx.execute(
+        "INSERT INTO codex_types (project_id, name, desc, fields, sort_order) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![project_id, payload.name, payload.desc, serde_json::to_string(&payload.fields)?, next_order],
+    ).context("inserting codex type")?;
+
+    let id = tx.last_insert_rowid();
+    let created = load_type(&tx, id)?.ok_or_else(|| anyhow::anyhow!("codex type not found after creation"))?;
+    tx.commit()?;
+    Ok(created)
+}
+
+pub fn get_type(pool: &DbPool, id: i64) -> anyhow::Result<Option<CodexType>> {
+    let conn = get_conn(pool)?;
+    load_type(&conn, id)
+}
+
+/// List all codex types for a project
+pub fn list_types(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<CodexType>> {
+    let conn = get_conn(pool)?;
+    let sql = format!("SELECT {} FROM codex_types WHERE project_id = ?1 ORDER BY sort_order, id", TYPE_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![project_id], type_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Update a codex type. Entity values of removed fields are dropped; values of fields whose kind
+/// or reference target changed are checked against the new definition, refusing the update if any no longer fit.
+pub fn update_type(pool: &DbPool, id: i64, payload: CodexTypeUpdate) -> anyhow::Result<CodexType> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let current = load_type(&tx, id)?.ok_or_else(|| anyhow::anyhow!("codex type not found"))?;
+
+    let new_name = payload.name.unwrap_or(current.name);
+    if new_name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+    let new_desc = payload.desc.or(current.desc);
+    let new_fields = payload.fields.unwrap_or_else(|| current.fields.clone());
+    validate_fields(&tx, current.project_id, &new_fields)?;
+
+    let definition = |f: &CodexField| (f.kind, f.item_kind, f.ref_type_id);
+    let changed: Vec<&CodexField> = new_fields
+        .iter()
+        .filter(|f| !current.fields.iter().any(|old| old.key == f.key && definition(old) == definition(f)))
+        .collect();
+    let sql = format!("SELECT {} FROM codex_entities WHERE type_id = ?1", ENTITY_COLUMNS);
+    let entities = tx.prepare(&sql)?
+        .query_map(rusqlite::params![id], entity_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    for entity in entities {
+        let mut data = entity.data.clone();
+        data.retain(|key, _| new_fields.iter().any(|f| &f.key == key));
+        for field in &changed {
+            if let Some(value) = data.get(&field.key).filter(|v| !v.is_null()) {
+                validate_value(&tx, current.project_id, field, field.kind, value)
+                    .with_context(|| format!("entity '{}'", entity.name))?;
+            }
+        }
+        if data != entity.data {
+            tx.execute(
+                "UPDATE codex_entities SET data = ?1 WHERE id = ?2",
+                rusqlite::params![serde_json::to_string(&data)?, entity.id],
+            ).context("migrating codex entity data")?;
+        }
+    }
+
+    tx.execute(
+        "UPDATE codex_types SET name = ?1, desc = ?2, fields = ?3 WHERE id = ?4",
+        rusqlite::params![new_name, new_desc, serde_json::to_string(&new_fields)?, id],
+    ).context("updating codex type")?;
+
+    let updated = load_type(&tx, id)?.ok_or_else(|| anyhow::anyhow!("codex type not found after update"))?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+/// Delete a codex type together with its entities
+pub fn delete_type(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM codex_types WHERE id = ?1", rusqlite::params![id])?;
+    // Cascades remove entities and their doc attachments due to FK
+    Ok(())
+}
+
+// Entities
+
+pub fn create_entity(pool: &DbPool, project_id: i64, payload: CodexEntityCreate) -> anyhow::Result<CodexEntity> {
+    let mut conn = get_conn(pool)?;
+
+    if payload.name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+
+    let tx = conn.transaction()?;
+    let codex_type = load_type(&tx, payload.type_id)?
+        .filter(|t| t.project_id == project_id)
+        .ok_or_else(|| anyhow::anyhow!("codex type not found in project"))?;
+    validate_data(&tx, &codex_type, &payload.data)?;
+
+    tx.execute(
+        "INSERT INTO codex_entities (project_id, type_id, name, desc, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![project_id, payload.type_id, payload.name, payload.desc, serde_json::to_string(&payload.data)?],
+    ).context("inserting codex entity")?;
+
+    let id = tx.last_insert_rowid();
+    let created = load_entity(&tx, id)?.ok_or_else(|| anyhow::anyhow!("codex entity not found after creation"))?;
+    tx.commit()?;
+    Ok(created)
+}
+
+pub fn get_entity(pool: &DbPool, id: i64) -> anyhow::Result<Option<CodexEntity>> {
+    let conn = get_conn(pool)?;
+    load_entity(&conn, id)
+}
+
+/// List codex entities for a project, optionally restricted to one type
+pub fn list_entities(pool: &DbPool, project_id: i64, type_id: Option<i64>) -> anyhow::Result<Vec<CodexEntity>> {
+    let conn = get_conn(pool)?;
+    let sql = format!(
+        "SELECT {} FROM codex_entities WHERE project_id = ?1 AND (?2 IS NULL OR type_id = ?2) ORDER BY name COLLATE NOCASE",
+        ENTITY_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![project_id, type_id], entity_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Update a codex entity; `data` replaces the stored values as a whole
+pub fn update_entity(pool: &DbPool, id: i64, payload: CodexEntityUpdate) -> anyhow::Result<CodexEntity> {
+    let conn = get_conn(pool)?;
+    let current = load_entity(&conn, id)?.ok_or_else(|| anyhow::anyhow!("codex entity not found"))?;
+
+    let new_name = payload.name.unwrap_or(current.name);
+    if new_name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+    let new_desc = payload.desc.or(current.desc);
+    let new_data = match payload.data {
+        Some(data) => {
+            let codex_type = load_type(&conn, current.type_id)?.ok_or_else(|| anyhow::anyhow!("codex type not found"))?;
+            validate_data(&conn, &codex_type, &data)?;
+            data
+        }
+        None => current.data,
+    };
+
+    conn.execute(
+        "UPDATE codex_entities SET name = ?1, desc = ?2, data = ?3 WHERE id = ?4",
+        rusqlite::params![new_name, new_desc, serde_json::to_string(&new_data)?, id],
+    ).context("updating codex entity")?;
+
+    load_entity(&conn, id)?.ok_or_else(|| anyhow::anyhow!("codex entity not found after update"))
+}
+
+pub fn delete_entity(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM codex_entities WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+// Doc attachments
+
+/// List codex entity ids attached to a doc
+pub fn list_for_doc(pool: &DbPool, doc_id: i64) -> anyhow::Result<Vec<i64>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare("SELECT entity_id FROM doc_codex_entities WHERE doc_id = ?1 ORDER BY entity_id")?;
+    let ids = stmt.query_map(rusqlite::params![doc_id], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// Attach a codex entity to a doc (idempotent)
+pub fn attach_to_doc(pool: &DbPool, doc_id: i64, entity_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO doc_codex_entities (doc_id, entity_id) VALUES (?1, ?2)",
+        rusqlite::params![doc_id, entity_id],
+    )?;
+    Ok(())
+}
+
+/// Detach a codex entity from a doc (idempotent)
+pub fn detach_from_doc(pool: &DbPool, doc_id: i64, entity_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "DELETE FROM doc_codex_entities WHERE doc_id = ?1 AND entity_id = ?2",
+        rusqlite::params![doc_id, entity_id],
+    )?;
+    Ok(())
+}
+
+// Import
+
+fn remap_reference(value: &Value, entity_map: &HashMap<i64, i64>) -> Value {
+    value.as_i64()
+        .and_then(|old| entity_map.get(&old))
+        .map(|new| Value::from(*new))
+        .unwrap_or(Value::Null)
+}
+
+/// Rewrite reference values in entity data to new entity ids; dangling references become null
+pub fn remap_data(fields: &[CodexField], data: &Map<String, Value>, entity_map: &HashMap<i64, i64>) -> Map<String, Value> {
+    let mut out = data.clone();
+    for f in fields {
+        let Some(value) = out.get_mut(&f.key) else { continue };
+        match (f.kind, f.item_kind) {
+            (CodexFieldKind::Reference, _) => *value = remap_reference(value, entity_map),
+            (CodexFieldKind::List, Some(CodexFieldKind::Reference)) => {
+                if let Some(items) = value.as_array() {
+                    let remapped = items.iter()
+                        .map(|v| remap_reference(v, entity_map))
+                        .filter(|v| !v.is_null())
+                        .collect();
+                    *value = Value::Array(remapped);
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Recreate exported codex types and entities in a project, remapping type and entity references.
+/// Returns the old -> new entity id map so callers can restore doc attachments.
+pub fn import_snapshot(pool: &DbPool, project_id: i64, types: &[CodexType], entities: &[CodexEntity]) -> anyhow::Result<HashMap<i64, i64>> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+
+    let mut type_map: HashMap<i64, i64> = HashMap::new();
+    for t in types {
+        tx.execute(
+            "INSERT INTO codex_types (project_id, name, desc, fields, sort_order) VALUES (?1, ?2, ?3, '[]', ?4)",
+            rusqlite::params![project_id, t.name, t.desc, t.sort_order],
+        ).context("importing codex type")?;
+        type_map.insert(t.id, tx.last_insert_rowid());
+    }
+    // Fields are written once every type exists so ref_type_id can be remapped
+    let mut fields_by_new_type: HashMap<i64, Vec<CodexField>> = HashMap::new();
+    for t in types {
+        let fields: Vec<CodexField> = t.fields.iter().cloned().map(|mut f| {
+            f.ref_type_id = f.ref_type_id.and_then(|old| type_map.get(&old).copied());
+            f
+        }).collect();
+        let new_id = type_map[&t.id];
+        tx.execute(
+            "UPDATE codex_types SET fields = ?1 WHERE id = ?2",
+            rusqlite::params![serde_json::to_string(&fields)?, new_id],
+        )?;
+        fields_by_new_type.insert(new_id, fields);
+    }
+
+    let mut entity_map: HashMap<i64, i64> = HashMap::new();
+    for e in entities {
+        let Some(&new_type_id) = type_map.get(&e.type_id) else { continue };
+        tx.execute(
+            "INSERT INTO codex_entities (project_id, type_id, name, desc) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![project_id, new_type_id, e.name, e.desc],
+        ).context("importing codex entity")?;
+        entity_map.insert(e.id, tx.last_insert_rowid());
+    }
+    for e in entities {
+        let (Some(&new_id), Some(&new_type_id)) = (entity_map.get(&e.id), type_map.get(&e.type_id)) else { continue };
+        let data = remap_data(&fields_by_new_type[&new_type_id], &e.data, &entity_map);
+        tx.execute(
+            "UPDATE codex_entities SET data = ?1 WHERE id = ?2",
+            rusqlite::params![serde_json::to_string(&data)?, new_id],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(entity_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn make_pool() -> DbPool {
+        let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let db_name = format!("file:memcodex{}?mode=memory&cache=shared", id);
+        let manager = SqliteConnectionManager::file(&db_name);
+        Pool::new(manager).unwrap()
+    }
+
+    fn init_schema(conn: &rusqlite::Connection) -> i64 {
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/008_add_codex.sql")).unwrap();
+        conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn field(key: &str, kind: CodexFieldKind) -> CodexField {
+        CodexField { key: key.into(), label: None, kind, ref_type_id: None, item_kind: None }
+    }
+
+    #[test]
+    fn codex_type_and_entity_roundtrip() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        let project_id = init_schema(&conn);
+
+        let faction = create_type(&pool, project_id, CodexTypeCreate {
+            name: "Faction".into(),
+            desc: None,
+            fields: vec![field("motto", CodexFieldKind::Text), field("members", CodexFieldKind::Number)],
+        }).unwrap();
+        let mut ship_fields = vec![field("crew", CodexFieldKind::List)];
+        let mut owner = field("owner", CodexFieldKind::Reference);
+        owner.ref_type_id = Some(faction.id);
+        ship_fields.push(owner);
+        let ship = create_type(&pool, project_id, CodexTypeCreate { name: "Ship".into(), desc: None, fields: ship_fields }).unwrap();
+
+        let mut data = Map::new();
+        data.insert("motto".into(), Value::from("Ever onward"));
+        data.insert("members".into(), Value::from(120));
+        let guild = create_entity(&pool, project_id, CodexEntityCreate { type_id: faction.id, name: "Guild".into(), desc: None, data }).unwrap();
+
+        let mut data = Map::new();
+        data.insert("owner".into(), Value::from(guild.id));
+        data.insert("crew".into(), serde_json::json!(["Mara", "Tol"]));
+        let vessel = create_entity(&pool, project_id, CodexEntityCreate { type_id: ship.id, name: "Kestrel".into(), desc: None, data }).unwrap();
+
+        let got = get_entity(&pool, vessel.id).unwrap().unwrap();
+        assert_eq!(got.data.get("owner"), Some(&Value::from(guild.id)));
+        assert_eq!(list_entities(&pool, project_id, Some(ship.id)).unwrap().len(), 1);
+        assert_eq!(list_entities(&pool, project_id, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn codex_entity_data_is_validated() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        let project_id = init_schema(&conn);
+
+        let item = create_type(&pool, project_id, CodexTypeCreate {
+            name: "Item".into(),
+            desc: None,
+            fields: vec![field("weight", CodexFieldKind::Number), field("origin", CodexFieldKind::Reference)],
+        }).unwrap();
+
+        let mut data = Map::new();
+        data.insert("weight".into(), Value::from("heavy"));
+        assert!(create_entity(&pool, project_id, CodexEntityCreate { type_id: item.id, name: "Sword".into(), desc: None, data }).is_err());
+
+        let mut data = Map::new();
+        data.insert("origin".into(), Value::from(9999));
+        assert!(create_entity(&pool, project_id, CodexEntityCreate { type_id: item.id, name: "Sword".into(), desc: None, data }).is_err());
+
+        let mut data = Map::new();
+        data.insert("colour".into(), Value::from("red"));
+        assert!(create_entity(&pool, project_id, CodexEntityCreate { type_id: item.id, name: "Sword".into(), desc: None, data }).is_err());
+
+        let dup = vec![field("a", CodexFieldKind::Text), field("a", CodexFieldKind::Number)];
+        assert!(update_type(&pool, item.id, CodexTypeUpdate { name: None, desc: None, fields: Some(dup) }).is_err());
+    }
+
+    #[test]
+    fn codex_type_changes_carry_entity_data_along() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        let project_id = init_schema(&conn);
+
+        let item = create_type(&pool, project_id, CodexTypeCreate {
+            name: "Item".into(),
+            desc: None,
+            fields: vec![field("weight", CodexFieldKind::Text), field("colour", CodexFieldKind::Text)],
+        }).unwrap();
+        let mut data = Map::new();
+        data.insert("weight".into(), Value::from("heavy"));
+        data.insert("colour".into(), Value::from("red"));
+        let sword = create_entity(&pool, project_id, CodexEntityCreate { type_id: item.id, name: "Sword".into(), desc: None, data }).unwrap();
+
+        // A kind the stored value does not fit is refused, leaving type and entity as they were
+        let to_number = vec![field("weight", CodexFieldKind::Number), field("colour", CodexFieldKind::Text)];
+        let err = update_type(&pool, item.id, CodexTypeUpdate { name: None, desc: None, fields: Some(to_number) }).unwrap_err();
+        assert!(format!("{:#}", err).contains("Sword"));
+        assert_eq!(get_type(&pool, item.id).unwrap().unwrap().fields[0].kind, CodexFieldKind::Text);
+
+        // Removing a field drops its values, so the entity stays editable
+        update_type(&pool, item.id, CodexTypeUpdate { name: None, desc: None, fields: Some(vec![field("weight", CodexFieldKind::Text)]) }).unwrap();
+        let stored = get_entity(&pool, sword.id).unwrap().unwrap();
+        assert!(!stored.data.contains_key("colour"));
+        let renamed = update_entity(&pool, sword.id, CodexEntityUpdate { name: Some("Blade".into()), desc: None, data: Some(stored.data) }).unwrap();
+        assert_eq!(renamed.data.get("weight"), Some(&Value::from("heavy")));
+
+        // Once the old value is cleared, the kind can change
+        let mut data = Map::new();
+        data.insert("weight".into(), Value::Null);
+        update_entity(&pool, sword.id, CodexEntityUpdate { name: None, desc: None, data: Some(data) }).unwrap();
+        update_type(&pool, item.id, CodexTypeUpdate { name: None, desc: None, fields: Some(vec![field("weight", CodexFieldKind::Number)]) }).unwrap();
+        let mut data = Map::new();
+        data.insert("weight".into(), Value::from(3));
+        let weighed = update_entity(&pool, sword.id, CodexEntityUpdate { name: None, desc: None, data: Some(data) }).unwrap();
+        assert_eq!(weighed.data.get("weight"), Some(&Value::from(3)));
+    }
+
+    #[test]
+    fn codex_doc_attachments() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        let project_id = init_schema(&conn);
+        conn.execute("INSERT INTO docs (project_id, path) VALUES (?1, '')", rusqlite::params![project_id]).unwrap();
+        let doc_id = conn.last_insert_rowid();
+
+        let spell = create_type(&pool, project_id, CodexTypeCreate { name: "Spell".into(), desc: None, fields: vec![] }).unwrap();
+        let fireball = create_entity(&pool, project_id, CodexEntityCreate { type_id: spell.id, name: "Fireball".into(), desc: None, data: Map::new() }).unwrap();
+
+        attach_to_doc(&pool, doc_id, fireball.id).unwrap();
+        attach_to_doc(&pool, doc_id, fireball.id).unwrap();
+        assert_eq!(list_for_doc(&pool, doc_id).unwrap(), vec![fireball.id]);
+
+        delete_type(&pool, spell.id).unwrap();
+        assert!(list_for_doc(&pool, doc_id).unwrap().is_empty());
+    }
+}
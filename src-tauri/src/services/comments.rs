@@ -0,0 +1,407 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{CommentThread, DocComment, DocCommentCreate};
+use anyhow::Context;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use similar::{DiffTag, TextDiff};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+const COMMENT_COLUMNS: &str =
+    "id, doc_id, parent_id, start_offset, end_offset, quote, body, author, resolved, detached, created_at, updated_at";
+
+fn comment_from_row(row: &rusqlite::Row) -> rusqlite::Result<DocComment> {
+    Ok(DocComment {
+        id: row.get(0)?,
+        doc_id: row.get(1)?,
+        parent_id: row.get(2)?,
+        start_offset: row.get(3)?,
+        end_offset: row.get(4)?,
+        quote: row.get(5)?,
+        body: row.get(6)?,
+        author: row.get(7)?,
+        resolved: row.get(8)?,
+        detached: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
+
+fn load(conn: &Connection, id: i64) -> anyhow::Result<Option<DocComment>> {
+    let sql = format!("SELECT {} FROM doc_comments WHERE id = ?1", COMMENT_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], comment_from_row).optional()?)
+}
+
+/// Characters `start..end` of `text` (offsets count chars, not bytes)
+fn char_slice(text: &str, start: usize, end: usize) -> String {
+    text.chars().skip(start).take(end.saturating_sub(start)).collect()
+}
+
+/// Start a comment thread on a range of the doc's current text
+pub fn create(pool: &DbPool, doc_id: i64, payload: DocCommentCreate) -> anyhow::Result<DocComment> {
+    let conn = get_conn(pool)?;
+    if payload.body.trim().is_empty() {
+        return Err(anyhow::anyhow!("comment cannot be empty"));
+    }
+    let text: Option<String> = conn.query_row(
+        "SELECT text FROM docs WHERE id = ?1",
+        rusqlite::params![doc_id],
+        |row| row.get(0),
+    ).optional()?.ok_or_else(|| anyhow::anyhow!("doc not found"))?;
+    let text = text.unwrap_or_default();
+    let len = text.chars().count() as i64;
+    if payload.start_offset < 0 || payload.end_offset < payload.start_offset || payload.end_offset > len {
+        anyhow::bail!("invalid range {}..{} for a text of {} characters", payload.start_offset, payload.end_offset, len);
+    }
+    let quote = char_slice(&text, payload.start_offset as usize, payload.end_offset as usize);
+
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO doc_comments (doc_id, start_offset, end_offset, quote, body, author, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+        rusqlite::params![doc_id, payload.start_offset, payload.end_offset, quote, payload.body, payload.author, now],
+    ).context("inserting comment")?;
+    load(&conn, conn.last_insert_rowid())?.ok_or_else(|| anyhow::anyhow!("comment not found after creation"))
+}
+
+/// Reply to a thread; replying to a reply attaches to the same thread
+pub fn reply(pool: &DbPool, comment_id: i64, body: &str, author: Option<String>) -> anyhow::Result<DocComment> {
+    let conn = get_conn(pool)?;
+    if body.trim().is_empty() {
+        return Err(anyhow::anyhow!("comment cannot be empty"));
+    }
+    let target = load(&conn, comment_id)?.ok_or_else(|| anyhow::anyhow!("comment not found"))?;
+    let root_id = target.parent_id.unwrap_or(target.id);
+
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO doc_comments (doc_id, parent_id, body, author, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+        rusqlite::params![target.doc_id, root_id, body, author, now],
+    ).context("inserting reply")?;
+    load(&conn, conn.last_insert_rowid())?.ok_or_else(|| anyhow::anyhow!("reply not found after creation"))
+}
+
+pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<DocComment>> {
+    let conn = get_conn(pool)?;
+    load(&conn, id)
+}
+
+/// Comment threads of a doc in text order, replies oldest first; resolved threads only when asked for
+pub fn list_for_doc(pool: &DbPool, doc_id: i64, include_resolved: bool) -> anyhow::Result<Vec<CommentThread>> {
+    let conn = get_conn(pool)?;
+    let sql = format!(
+        "SELECT {} FROM doc_comments WHERE doc_id = ?1 ORDER BY parent_id IS NOT NULL, start_offset, id",
+        COMMENT_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let comments = stmt.query_map(rusqlite::params![doc_id], comment_from_row)?.collect::<Result<Vec<_>, _>>()?;
+
+    let mut threads: Vec<CommentThread> = Vec::new();
+    let mut index: HashMap<i64, usize> = HashMap::new();
+    for c in comments {
+        match c.parent_id {
+            None => {
+                index.insert(c.id, threads.len());
+                threads.push(CommentThread { comment: c, replies: Vec::new() });
+            }
+            Some(parent) => {
+                if let Some(&i) = index.get(&parent) {
+                    threads[i].replies.push(c);
+                }
+            }
+        }
+    }
+    threads.retain(|t| include_resolved || !t.comment.resolved);
+    Ok(threads)
+}
+
+pub fn update_body(pool: &DbPool, id: i64, body: &str) -> anyhow::Result<DocComment> {
+    let conn = get_conn(pool)?;
+    if body.trim().is_empty() {
+        return Err(anyhow::anyhow!("comment cannot be empty"));
+    }
+    let changed = conn.execute(
+        "UPDATE doc_comments SET body = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![body, Utc::now().to_rfc3339(), id],
+    )?;
+    if changed == 0 {
+        anyhow::bail!("comment not found");
+    }
+    load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("comment not found after update"))
+}
+
+/// Resolve or reopen the thread a comment belongs to
+pub fn set_resolved(pool: &DbPool, id: i64, resolved: bool) -> anyhow::Result<DocComment> {
+    let conn = get_conn(pool)?;
+    let target = load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("comment not found"))?;
+    let root_id = target.parent_id.unwrap_or(target.id);
+    conn.execute(
+        "UPDATE doc_comments SET resolved = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![resolved, Utc::now().to_rfc3339(), root_id],
+    )?;
+    load(&conn, root_id)?.ok_or_else(|| anyhow::anyhow!("comment not found after update"))
+}
+
+/// Delete a comment; deleting the first comment of a thread removes its replies too
+pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM doc_comments WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+// Anchor remapping
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Anchor {
+    start: usize,
+    end: usize,
+    quote: String,
+    detached: bool,
+}
+
+/// Old-to-new position mapping from a character diff
+struct PositionMap {
+    ops: Vec<(DiffTag, Range<usize>, Range<usize>)>,
+    old_len: usize,
+    new_len: usize,
+}
+
+impl PositionMap {
+    fn new(old: &str, new: &str) -> PositionMap {
+        let diff = TextDiff::configure().timeout(Duration::from_secs(1)).diff_chars(old, new);
+        PositionMap {
+            ops: diff.ops().iter().map(|op| op.as_tag_tuple()).collect(),
+            old_len: old.chars().count(),
+            new_len: new.chars().count(),
+        }
+    }
+
+    fn op_containing(&self, old_char: usize) -> Option<&(DiffTag, Range<usize>, Range<usize>)> {
+        self.ops.iter().find(|(_, old, _)| old.contains(&old_char))
+    }
+
+    /// A range start sticks to the character after it: text inserted right before it stays outside,
+    /// and if that character was deleted the start moves past the replacement
+    fn map_start(&self, pos: usize) -> usize {
+        if pos >= self.old_len {
+            return self.new_len;
+        }
+        match self.op_containing(pos) {
+            Some((DiffTag::Equal, old, new)) => new.start + (pos - old.start),
+            Some((_, _, new)) => new.end,
+            None => self.new_len,
+        }
+    }
+
+    /// A range end sticks to the character before it, mirroring `map_start`
+    fn map_end(&self, pos: usize) -> usize {
+        if pos == 0 {
+            return 0;
+        }
+        match self.op_containing(pos - 1) {
+            Some((DiffTag::Equal, old, new)) => new.start + (pos - 1 - old.start) + 1,
+            Some((_, _, new)) => new.start,
+            None => self.new_len,
+        }
+    }
+}
+
+/// Char offset of the occurrence of `quote` in `text` closest to `near`
+fn find_quote(text: &str, quote: &str, near: usize) -> Option<usize> {
+    if quote.is_empty() {
+        return None;
+    }
+    text.match_indices(quote)
+        .map(|(byte, _)| text[..byte].chars().count())
+        .min_by_key(|&pos| pos.abs_diff(near))
+}
+
+fn remap(map: &PositionMap, new_text: &str, start: usize, end: usize, quote: &str) -> Anchor {
+    let new_start = map.map_start(start);
+    let new_end = map.map_end(end).max(new_start);
+    let current = char_slice(new_text, new_start, new_end);
+    if current == quote {
+        return Anchor { start: new_start, end: new_end, quote: current, detached: false };
+    }
+    if new_end > new_start {
+        // The anchored text was edited in place: keep pointing at what is there now
+        return Anchor { start: new_start, end: new_end, quote: current, detached: false };
+    }
+    // The anchored text was deleted or replaced wholesale; look for it elsewhere (e.g. moved)
+    match find_quote(new_text, quote, new_start) {
+        Some(pos) => Anchor { start: pos, end: pos + quote.chars().count(), quote: quote.to_string(), detached: false },
+        None => Anchor { start: new_start, end: new_start, quote: quote.to_string(), detached: true },
+    }
+}
+
+/// Move the anchors of a doc's comment threads from `old_text` to `new_text`.
+/// Called by every writer of `docs.text`, inside the same transaction.
+pub fn remap_anchors(conn: &Connection, doc_id: i64, old_text: &str, new_text: &str) -> anyhow::Result<()> {
+    if old_text == new_text {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, start_offset, end_offset, COALESCE(quote, ''), detached FROM doc_comments
+         WHERE doc_id = ?1 AND parent_id IS NULL AND start_offset IS NOT NULL",
+    )?;
+    let anchors = stmt
+        .query_map(rusqlite::params![doc_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, String>(3)?, row.get::<_, bool>(4)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if anchors.is_empty() {
+        return Ok(());
+    }
+
+    let map = PositionMap::new(old_text, new_text);
+    for (id, start, end, quote, detached) in anchors {
+        let anchor = if detached {
+            // Lost earlier: only a reappearing quote brings it back
+            match find_quote(new_text, &quote, start.max(0) as usize) {
+                Some(pos) => Anchor { start: pos, end: pos + quote.chars().count(), quote, detached: false },
+                None => continue,
+            }
+        } else {
+            remap(&map, new_text, start.max(0) as usize, end.max(0) as usize, &quote)
+        };
+        conn.execute(
+            "UPDATE doc_comments SET start_offset = ?1, end_offset = ?2, quote = ?3, detached = ?4 WHERE id = ?5",
+            rusqlite::params![anchor.start as i64, anchor.end as i64, anchor.quote, anchor.detached, id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Hand a split doc's threads to the parts: `parts` are the (start, end, doc_id) char ranges of
+/// `text` in order. A thread goes to the part its anchor starts in and is clipped to that part;
+/// replies follow their thread.
+pub fn split_anchors(conn: &Connection, doc_id: i64, text: &str, parts: &[(usize, usize, i64)]) -> anyhow::Result<()> {
+    let Some(last) = parts.last() else { return Ok(()) };
+    let mut stmt = conn.prepare(
+        "SELECT id, start_offset, end_offset FROM doc_comments
+         WHERE doc_id = ?1 AND parent_id IS NULL AND start_offset IS NOT NULL",
+    )?;
+    let anchors = stmt
+        .query_map(rusqlite::params![doc_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (id, start, end) in anchors {
+        let (start, end) = (start.max(0) as usize, end.max(0) as usize);
+        let &(part_start, part_end, part_doc) = parts.iter()
+            .find(|(s, e, _)| start >= *s && start < *e)
+            .unwrap_or(last);
+        let start = start.clamp(part_start, part_end);
+        let end = end.clamp(start, part_end);
+        conn.execute(
+            "UPDATE doc_comments SET doc_id = ?1, start_offset = ?2, end_offset = ?3, quote = ?4 WHERE id = ?5",
+            rusqlite::params![part_doc, (start - part_start) as i64, (end - part_start) as i64, char_slice(text, start, end), id],
+        )?;
+        conn.execute("UPDATE doc_comments SET doc_id = ?1 WHERE parent_id = ?2", rusqlite::params![part_doc, id])?;
+    }
+    Ok(())
+}
+
+/// Move every comment of `from` onto `to`, whose text contains `from`'s text at char `offset`
+pub fn move_anchors(conn: &Connection, from: i64, to: i64, offset: usize) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE doc_comments SET doc_id = ?2, start_offset = start_offset + ?3, end_offset = end_offset + ?3 WHERE doc_id = ?1",
+        rusqlite::params![from, to, offset as i64],
+    )?;
+    Ok(())
+}
+
+// Import
+
+/// Recreate exported threads on a doc, keeping their anchors and state
+pub fn import_threads(pool: &DbPool, doc_id: i64, threads: &[CommentThread]) -> anyhow::Result<()> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    for t in threads {
+        let c = &t.comment;
+        tx.execute(
+            "INSERT INTO doc_comments (doc_id, start_offset, end_offset, quote, body, author, resolved, detached, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![doc_id, c.start_offset, c.end_offset, c.quote, c.body, c.author, c.resolved, c.detached, c.created_at, c.updated_at],
+        ).context("importing comment")?;
+        let root_id = tx.last_insert_rowid();
+        for r in &t.replies {
+            tx.execute(
+                "INSERT INTO doc_comments (doc_id, parent_id, body, author, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![doc_id, root_id, r.body, r.author, r.created_at, r.updated_at],
+            ).context("importing reply")?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    fn anchor(old: &str, new: &str, quote: &str) -> Anchor {
+        let start = old[..old.find(quote).unwrap()].chars().count();
+        let end = start + quote.chars().count();
+        remap(&PositionMap::new(old, new), new, start, end, quote)
+    }
+
+    #[test]
+    fn anchors_follow_edits_around_and_inside_them() {
+        let old = "The storm broke at dawn. Mara ran for the boats.";
+        // Insertions before the range shift it; insertions at its edges stay outside
+        let moved = anchor(old, "Without warning, the storm broke at dawn. Mara ran for the boats.", "storm broke");
+        assert_eq!((moved.quote.as_str(), moved.detached), ("storm broke", false));
+        let edges = anchor(old, "The great storm broke loose at dawn. Mara ran for the boats.", "storm broke");
+        assert_eq!(edges.quote, "storm broke");
+
+        // Edited inside: the anchor keeps covering the changed words
+        let edited = anchor(old, "The storm finally broke at dawn. Mara ran for the boats.", "storm broke");
+        assert_eq!(edited.quote, "storm finally broke");
+
+        // Sentences swapped: the anchor travels with its sentence
+        let swapped = anchor(old, "Mara ran for the boats. The storm broke at dawn.", "Mara ran");
+        assert_eq!((swapped.start, swapped.quote.as_str()), (0, "Mara ran"));
+
+        // Gone entirely
+        let lost = anchor(old, "The storm broke at dawn.", "ran for the boats");
+        assert!(lost.detached);
+        assert_eq!(lost.start, lost.end);
+
+        // Offsets count characters, not bytes
+        let unicode = anchor("Café. Éclair au chocolat.", "Ein Café. Éclair au chocolat.", "Éclair");
+        assert_eq!((unicode.start, unicode.end), (10, 16));
+    }
+
+    #[test]
+    fn threads_replies_and_doc_updates() {
+        let manager = SqliteConnectionManager::file("file:memcomments?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/016_add_doc_comments.sql")).unwrap();
+        conn.execute("INSERT INTO projects (id, name) VALUES (1, 'P')", []).unwrap();
+        conn.execute("INSERT INTO docs (id, project_id, path, name, text) VALUES (1, 1, '', 'D', 'She left. He stayed.')", []).unwrap();
+        drop(conn);
+
+        assert!(create(&pool, 1, DocCommentCreate { start_offset: 5, end_offset: 99, body: "x".into(), author: None }).is_err());
+        let c = create(&pool, 1, DocCommentCreate { start_offset: 10, end_offset: 20, body: "Too abrupt?".into(), author: Some("Editor".into()) }).unwrap();
+        assert_eq!(c.quote.as_deref(), Some("He stayed."));
+        let r = reply(&pool, c.id, "Intended.", None).unwrap();
+        reply(&pool, r.id, "Fair.", Some("Editor".into())).unwrap();
+
+        crate::services::docs::update_doc(&pool, 1, "She left at once. He stayed.").unwrap();
+        let threads = list_for_doc(&pool, 1, false).unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].replies.len(), 2);
+        assert_eq!((threads[0].comment.start_offset, threads[0].comment.end_offset), (Some(18), Some(28)));
+
+        set_resolved(&pool, r.id, true).unwrap();
+        assert!(list_for_doc(&pool, 1, false).unwrap().is_empty());
+        assert_eq!(list_for_doc(&pool, 1, true).unwrap().len(), 1);
+
+        delete(&pool, c.id).unwrap();
+        assert!(get(&pool, r.id).unwrap().is_none());
+    }
+}
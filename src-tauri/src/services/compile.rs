@@ -0,0 +1,374 @@
+use crate::db::DbPool;
+use crate::models::{CompileFormat, CompileSettings, Doc, DocMetadata};
+use crate::services::project_tree::{ProjectTree, TreeGroup};
+use crate::services::rich_text::{self, Span};
+use crate::services::typography;
+use anyhow::Context;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Deepest heading level the output formats style
+pub const MAX_HEADING: u8 = 6;
+
+/// One block of the compiled manuscript
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    // Group headings carry an anchor id, unique in the manuscript, for links and the contents
+    Heading { level: u8, text: String, anchor: Option<String> },
+    Paragraph(Vec<Span>),
+    SceneBreak,
+}
+
+/// The manuscript laid out for output, independent of the file format
+#[derive(Debug, Default)]
+pub struct Manuscript {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub blocks: Vec<Block>,
+    // Text of a scene break; None leaves it to the format
+    pub scene_separator: Option<String>,
+}
+
+/// A scene break line in doc text: `***`, `* * *` or a lone `#`
+fn is_scene_break(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact == "#" || (compact.len() >= 3 && compact.chars().all(|c| c == '*'))
+}
+
+/// Blocks of one doc text: each non-empty line is a paragraph, `#` lines are headings below `depth`
+fn text_blocks(text: &str, depth: u8, out: &mut Vec<Block>) {
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+        if is_scene_break(line) {
+            out.push(Block::SceneBreak);
+            continue;
+        }
+        let hashes = line.chars().take_while(|&c| c == '#').count();
+        if hashes > 0 && line[hashes..].starts_with(' ') {
+            let level = (depth as usize + hashes).min(MAX_HEADING as usize) as u8;
+            out.push(Block::Heading { level, text: line[hashes..].trim().to_string(), anchor: None });
+            continue;
+        }
+        out.push(Block::Paragraph(rich_text::parse_markdown(line)));
+    }
+}
+
+/// Anchor id from a heading: lowercase ASCII words joined by dashes, made unique with a suffix
+fn anchor_for(text: &str, used: &mut HashSet<String>) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = if slug.is_empty() { "section".to_string() } else { slug };
+    let mut anchor = base.clone();
+    let mut n = 2;
+    while !used.insert(anchor.clone()) {
+        anchor = format!("{}-{}", base, n);
+        n += 1;
+    }
+    anchor
+}
+
+/// Roman numeral of a positive number
+fn roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out
+}
+
+fn matches_any(value: Option<&str>, list: &[String]) -> bool {
+    value.is_some_and(|v| list.iter().any(|item| item.trim().eq_ignore_ascii_case(v.trim())))
+}
+
+/// Whether the settings' doc filters let a doc through
+fn keeps_doc(doc: &Doc, settings: &CompileSettings, meta: &HashMap<i64, DocMetadata>) -> bool {
+    if settings.exclude_docs.contains(&doc.id) {
+        return false;
+    }
+    let m = meta.get(&doc.id);
+    let status = m.and_then(|m| m.status.as_deref());
+    let label = m.and_then(|m| m.label.as_deref());
+    (settings.include_statuses.is_empty() || matches_any(status, &settings.include_statuses))
+        && !matches_any(status, &settings.exclude_statuses)
+        && (settings.include_labels.is_empty() || matches_any(label, &settings.include_labels))
+        && !matches_any(label, &settings.exclude_labels)
+}
+
+/// The part of a project tree a compile includes: excluded groups go with everything inside them,
+/// docs go by id, status and label, `skip` docs are left out too, and groups left with nothing
+/// to show are dropped
+pub fn select(tree: ProjectTree, settings: &CompileSettings, meta: &HashMap<i64, DocMetadata>, skip: &HashSet<i64>) -> ProjectTree {
+    let keep = |doc: &Doc| !skip.contains(&doc.id) && keeps_doc(doc, settings, meta);
+    fn prune(groups: Vec<TreeGroup>, settings: &CompileSettings, keep: &dyn Fn(&Doc) -> bool) -> Vec<TreeGroup> {
+        groups.into_iter().filter(|g| !settings.exclude_groups.contains(&g.group.id)).filter_map(|g| {
+            let docs: Vec<Doc> = g.docs.into_iter().filter(|d| keep(d)).collect();
+            let children = prune(g.children, settings, keep);
+            (!docs.is_empty() || !children.is_empty()).then_some(TreeGroup { group: g.group, docs, children })
+        }).collect()
+    }
+    ProjectTree {
+        docs: tree.docs.into_iter().filter(|d| keep(d)).collect(),
+        groups: prune(tree.groups, settings, &keep),
+    }
+}
+
+/// Text with the settings' typography applied
+pub fn convert_text(text: &str, settings: &CompileSettings) -> String {
+    typography::transform(text, &settings.typography)
+}
+
+/// Collects blocks while walking the tree, numbering group headings per depth
+struct Builder<'a> {
+    settings: &'a CompileSettings,
+    meta: &'a HashMap<i64, DocMetadata>,
+    used: HashSet<String>,
+    counts: Vec<usize>,
+    blocks: Vec<Block>,
+}
+
+impl Builder<'_> {
+    fn convert_spans(&self, spans: Vec<Span>) -> Vec<Span> {
+        let mut prev = None;
+        spans.into_iter().map(|mut span| {
+            span.text = typography::transform_after(&span.text, prev, &self.settings.typography);
+            prev = span.text.chars().last().or(prev);
+            span
+        }).collect()
+    }
+
+    fn convert(&self, blocks: Vec<Block>) -> Vec<Block> {
+        blocks.into_iter().map(|block| match block {
+            Block::Heading { level, text, anchor } => Block::Heading { level, text: convert_text(&text, self.settings), anchor },
+            Block::Paragraph(spans) => Block::Paragraph(self.convert_spans(spans)),
+            Block::SceneBreak => Block::SceneBreak,
+        }).collect()
+    }
+
+    fn italic(text: &str) -> Block {
+        Block::Paragraph(vec![Span { text: text.trim().to_string(), bold: false, italic: true }])
+    }
+
+    /// A doc's text, with its synopsis before and its notes after when the settings ask for them
+    fn doc(&mut self, doc: &Doc, depth: u8) {
+        let mut blocks = Vec::new();
+        if self.settings.include_synopses {
+            if let Some(synopsis) = self.meta.get(&doc.id).and_then(|m| m.synopsis.as_deref()) {
+                blocks.extend(synopsis.lines().filter(|l| !l.trim().is_empty()).map(Self::italic));
+            }
+        }
+        text_blocks(doc.text.as_deref().unwrap_or(""), depth, &mut blocks);
+        if self.settings.include_notes {
+            let notes = doc.notes.as_deref().unwrap_or("");
+            blocks.extend(notes.lines().filter(|l| !l.trim().is_empty()).map(Self::italic));
+        }
+        let blocks = self.convert(blocks);
+        self.blocks.extend(blocks);
+    }
+
+    /// Docs of one level, separated by scene breaks
+    fn docs(&mut self, docs: &[Doc], depth: u8) {
+        for (i, doc) in docs.iter().enumerate() {
+            if i > 0 && !matches!(self.blocks.last(), Some(Block::SceneBreak) | Some(Block::Heading { .. })) {
+                self.blocks.push(Block::SceneBreak);
+            }
+            self.doc(doc, depth);
+        }
+    }
+
+    /// An anchored heading; group headings follow the template for their depth
+    fn heading(&mut self, depth: u8, name: &str, template: Option<&str>) {
+        let text = match template.filter(|t| !t.trim().is_empty()) {
+            Some(template) => {
+                let index = depth as usize - 1;
+                if self.counts.len() <= index {
+                    self.counts.resize(index + 1, 0);
+                }
+                self.counts[index] += 1;
+                let n = self.counts[index];
+                template.replace("{n}", &n.to_string()).replace("{roman}", &roman(n)).replace("{name}", name)
+            }
+            None => name.to_string(),
+        };
+        let text = convert_text(&text, self.settings);
+        let anchor = anchor_for(&text, &mut self.used);
+        self.blocks.push(Block::Heading { level: depth.min(MAX_HEADING), text, anchor: Some(anchor) });
+    }
+
+    fn group(&mut self, node: &TreeGroup, depth: u8) {
+        let template = self.settings.heading_templates.get(depth as usize - 1).map(String::as_str).unwrap_or("{name}");
+        self.heading(depth, &node.group.name, Some(template));
+        self.docs(&node.docs, depth);
+        for child in &node.children {
+            self.group(child, depth + 1);
+        }
+    }
+
+    /// Front or back matter: each doc under its own name as a top-level heading
+    fn matter(&mut self, docs: &[Doc]) {
+        for doc in docs {
+            self.heading(1, doc.name.as_deref().unwrap_or("Untitled"), None);
+            self.doc(doc, 1);
+        }
+    }
+}
+
+/// Lay out a project for compiling: front matter, root docs, then every group as a heading at its
+/// tree depth followed by its docs (separated by scene breaks) and its subgroups, then back matter.
+/// The settings choose what is included and how headings and text are rendered.
+pub fn build(pool: &DbPool, project_id: i64, settings: &CompileSettings) -> anyhow::Result<Manuscript> {
+    let project = crate::services::projects::get(pool, project_id)?
+        .ok_or_else(|| anyhow::anyhow!("project {} not found", project_id))?;
+    let meta = crate::services::doc_metadata::list_for_project(pool, project_id)?;
+    let matter_docs = |ids: &[i64]| -> anyhow::Result<Vec<Doc>> {
+        ids.iter().map(|&id| {
+            crate::services::docs::get_doc(pool, id)?
+                .filter(|d| d.project_id == project_id)
+                .ok_or_else(|| anyhow::anyhow!("front or back matter doc {} is not in this project", id))
+        }).collect()
+    };
+    let front = matter_docs(&settings.front_matter)?;
+    let back = matter_docs(&settings.back_matter)?;
+    let skip: HashSet<i64> = front.iter().chain(&back).map(|d| d.id).collect();
+    let tree = select(crate::services::project_tree::load(pool, project_id)?, settings, &meta, &skip);
+
+    let mut builder = Builder { settings, meta: &meta, used: HashSet::new(), counts: Vec::new(), blocks: Vec::new() };
+    builder.matter(&front);
+    builder.docs(&tree.docs, 0);
+    for group in &tree.groups {
+        builder.group(group, 1);
+    }
+    builder.matter(&back);
+
+    let subtitle = project.desc.filter(|d| !d.trim().is_empty());
+    let scene_separator = settings.scene_separator.clone().filter(|s| !s.trim().is_empty());
+    Ok(Manuscript { title: project.name, subtitle, blocks: builder.blocks, scene_separator })
+}
+
+/// Compile a project into a single file at `dest`
+pub fn compile(pool: &DbPool, project_id: i64, format: CompileFormat, settings: &CompileSettings, dest: &Path) -> anyhow::Result<()> {
+    let manuscript = build(pool, project_id, settings)?;
+    let options = &settings.options;
+    let bytes = match format {
+        CompileFormat::Odt => crate::services::odt::write(&manuscript)?,
+        CompileFormat::Html => crate::services::html::write(&manuscript, options)?.into_bytes(),
+        CompileFormat::Pdf => crate::services::manuscript_pdf::write(&manuscript, options),
+    };
+    std::fs::write(dest, bytes).with_context(|| format!("writing {}", dest.display()))
+}
+
+/// Compile a project with one of its saved presets
+pub fn compile_preset(pool: &DbPool, preset_id: i64, dest: &Path) -> anyhow::Result<()> {
+    let preset = crate::services::compile_presets::get(pool, preset_id)?
+        .ok_or_else(|| anyhow::anyhow!("compile preset {} not found", preset_id))?;
+    compile(pool, preset.project_id, preset.format, &preset.settings, dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CompilePresetInput, ProjectCreate, TypographyOptions};
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    fn paragraph(text: &str) -> Block {
+        Block::Paragraph(vec![Span { text: text.into(), bold: false, italic: false }])
+    }
+
+    #[test]
+    fn presets_select_number_and_convert() {
+        let pool: DbPool = Pool::new(SqliteConnectionManager::file("file:memcompile?mode=memory&cache=shared")).unwrap();
+        let conn = pool.get().unwrap();
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/016_add_doc_comments.sql"),
+            include_str!("../../migrations/020_add_compile_presets.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        drop(conn);
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        let doc = |name: &str, group: Option<i64>, text: &str| {
+            let d = crate::services::docs::create_doc(&pool, project.id, name, group).unwrap();
+            crate::services::docs::update_doc(&pool, d.id, text).unwrap();
+            d.id
+        };
+        let dedication = doc("Dedication", None, "For M.");
+        let one = crate::services::doc_groups::create_doc_group(&pool, project.id, "Landfall", None).unwrap();
+        let s1 = doc("s1", Some(one.id), "\"Go,\" she said -- and didn't.");
+        let s2 = doc("s2", Some(one.id), "Cut scene.");
+        let two = crate::services::doc_groups::create_doc_group(&pool, project.id, "Storm", None).unwrap();
+        // No status: passes exclude filters but not include filters
+        doc("s3", Some(two.id), "Only a draft.");
+        let three = crate::services::doc_groups::create_doc_group(&pool, project.id, "Calm", None).unwrap();
+        doc("s4", Some(three.id), "Quiet 'sea'.");
+        let meta = |status: &str, synopsis: Option<&str>| DocMetadata { status: Some(status.into()), label: None, synopsis: synopsis.map(String::from) };
+        crate::services::doc_metadata::set(&pool, s1, meta("Final", Some("Mara leaves."))).unwrap();
+        crate::services::doc_metadata::set(&pool, s2, meta("final", None)).unwrap();
+
+        let settings = CompileSettings {
+            exclude_docs: vec![s2],
+            exclude_statuses: vec!["Draft".into()],
+            heading_templates: vec!["Chapter {n}: {name}".into()],
+            typography: TypographyOptions { smart_quotes: true, dashes: true, ..Default::default() },
+            include_synopses: true,
+            front_matter: vec![dedication],
+            scene_separator: Some("~".into()),
+            ..Default::default()
+        };
+        let preset = crate::services::compile_presets::create(&pool, project.id, CompilePresetInput {
+            name: "Print".into(),
+            format: CompileFormat::Html,
+            settings: settings.clone(),
+        }).unwrap();
+        assert_eq!(crate::services::compile_presets::list(&pool, project.id).unwrap()[0].settings.heading_templates, settings.heading_templates);
+
+        let manuscript = build(&pool, project.id, &preset.settings).unwrap();
+        let heading = |text: &str, anchor: &str| Block::Heading { level: 1, text: text.into(), anchor: Some(anchor.into()) };
+        let italic = Block::Paragraph(vec![Span { text: "Mara leaves.".into(), bold: false, italic: true }]);
+        assert_eq!(manuscript.blocks, vec![
+            heading("Dedication", "dedication"),
+            paragraph("For M."),
+            heading("Chapter 1: Landfall", "chapter-1-landfall"),
+            italic,
+            paragraph("\u{201C}Go,\u{201D} she said \u{2014} and didn\u{2019}t."),
+            heading("Chapter 2: Storm", "chapter-2-storm"),
+            paragraph("Only a draft."),
+            heading("Chapter 3: Calm", "chapter-3-calm"),
+            paragraph("Quiet \u{2018}sea\u{2019}."),
+        ]);
+        assert_eq!(manuscript.scene_separator.as_deref(), Some("~"));
+
+        // Only final docs: the groups left empty disappear and numbering closes up
+        let finals = CompileSettings { include_statuses: vec!["FINAL".into()], heading_templates: vec!["Part {roman}".into()], ..Default::default() };
+        let manuscript = build(&pool, project.id, &finals).unwrap();
+        assert_eq!(manuscript.blocks, vec![
+            heading("Part I", "part-i"),
+            paragraph("\"Go,\" she said -- and didn't."),
+            Block::SceneBreak,
+            paragraph("Cut scene."),
+        ]);
+
+        let dest = std::env::temp_dir().join(format!("crate-compile-{}.html", std::process::id()));
+        compile_preset(&pool, preset.id, &dest).unwrap();
+        let html = std::fs::read_to_string(&dest).unwrap();
+        assert!(html.contains("<a href=\"#chapter-2-storm\">Chapter 2: Storm</a>"));
+        std::fs::remove_file(&dest).ok();
+    }
+}
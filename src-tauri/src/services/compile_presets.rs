@@ -0,0 +1,185 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{CompileFormat, CompilePreset, CompilePresetInput, CompileSettings};
+use anyhow::Context;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+
+const PRESET_COLUMNS: &str = "id, project_id, name, format, settings, created_at, updated_at";
+
+fn preset_from_row(row: &rusqlite::Row) -> rusqlite::Result<CompilePreset> {
+    let format: String = row.get(3)?;
+    let settings: String = row.get(4)?;
+    let invalid = |col: usize, e: serde_json::Error| rusqlite::Error::FromSqlConversionFailure(col, rusqlite::types::Type::Text, Box::new(e));
+    Ok(CompilePreset {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        format: serde_json::from_value(serde_json::Value::String(format)).map_err(|e| invalid(3, e))?,
+        settings: serde_json::from_str(&settings).map_err(|e| invalid(4, e))?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn format_str(format: CompileFormat) -> &'static str {
+    match format {
+        CompileFormat::Odt => "odt",
+        CompileFormat::Html => "html",
+        CompileFormat::Pdf => "pdf",
+    }
+}
+
+fn load(conn: &Connection, id: i64) -> anyhow::Result<Option<CompilePreset>> {
+    let sql = format!("SELECT {} FROM compile_presets WHERE id = ?1", PRESET_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], preset_from_row).optional()?)
+}
+
+pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<CompilePreset>> {
+    let conn = get_conn(pool)?;
+    let sql = format!("SELECT {} FROM compile_presets WHERE project_id = ?1 ORDER BY name COLLATE NOCASE", PRESET_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let presets = stmt.query_map(rusqlite::params![project_id], preset_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(presets)
+}
+
+pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<CompilePreset>> {
+    let conn = get_conn(pool)?;
+    load(&conn, id)
+}
+
+pub fn create(pool: &DbPool, project_id: i64, payload: CompilePresetInput) -> anyhow::Result<CompilePreset> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        anyhow::bail!("preset name cannot be empty");
+    }
+    let conn = get_conn(pool)?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO compile_presets (project_id, name, format, settings, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+        rusqlite::params![project_id, name, format_str(payload.format), serde_json::to_string(&payload.settings)?, now],
+    ).with_context(|| format!("creating compile preset '{}'", name))?;
+    load(&conn, conn.last_insert_rowid())?.ok_or_else(|| anyhow::anyhow!("compile preset not found after creation"))
+}
+
+/// Replace a preset's name, format and settings
+pub fn update(pool: &DbPool, id: i64, payload: CompilePresetInput) -> anyhow::Result<CompilePreset> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        anyhow::bail!("preset name cannot be empty");
+    }
+    let conn = get_conn(pool)?;
+    let changed = conn.execute(
+        "UPDATE compile_presets SET name = ?1, format = ?2, settings = ?3, updated_at = ?4 WHERE id = ?5",
+        rusqlite::params![name, format_str(payload.format), serde_json::to_string(&payload.settings)?, Utc::now().to_rfc3339(), id],
+    ).with_context(|| format!("updating compile preset '{}'", name))?;
+    if changed == 0 {
+        anyhow::bail!("compile preset {} not found", id);
+    }
+    load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("compile preset {} not found", id))
+}
+
+pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM compile_presets WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+/// Point a preset's group and doc ids at copied or imported ones, dropping ids with no counterpart
+pub fn remap_settings(settings: CompileSettings, groups: &HashMap<i64, i64>, docs: &HashMap<i64, i64>) -> CompileSettings {
+    let map = |ids: Vec<i64>, map: &HashMap<i64, i64>| ids.into_iter().filter_map(|id| map.get(&id).copied()).collect();
+    CompileSettings {
+        exclude_groups: map(settings.exclude_groups, groups),
+        exclude_docs: map(settings.exclude_docs, docs),
+        front_matter: map(settings.front_matter, docs),
+        back_matter: map(settings.back_matter, docs),
+        ..settings
+    }
+}
+
+/// Rewrite the settings of freshly copied presets (new id -> settings) through `remap_settings`
+pub fn remap_copied(conn: &Connection, presets: &HashMap<i64, i64>, groups: &HashMap<i64, i64>, docs: &HashMap<i64, i64>) -> anyhow::Result<()> {
+    for &id in presets.values() {
+        let Some(preset) = load(conn, id)? else { continue };
+        let settings = remap_settings(preset.settings, groups, docs);
+        conn.execute("UPDATE compile_presets SET settings = ?1 WHERE id = ?2", rusqlite::params![serde_json::to_string(&settings)?, id])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectCreate;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn make_pool() -> DbPool {
+        let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let db_name = format!("file:mempresets{}?mode=memory&cache=shared", id);
+        Pool::new(SqliteConnectionManager::file(&db_name)).unwrap()
+    }
+
+    fn init_schema(conn: &Connection) {
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/020_add_compile_presets.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+    }
+
+    fn settings() -> CompileSettings {
+        CompileSettings {
+            exclude_groups: vec![1, 2],
+            exclude_docs: vec![10, 99],
+            front_matter: vec![11],
+            back_matter: vec![12, 98],
+            include_statuses: vec!["Final".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_remap_settings_drops_ids_without_counterpart() {
+        let groups = HashMap::from([(1, 101)]);
+        let docs = HashMap::from([(10, 110), (11, 111), (12, 112)]);
+        let remapped = remap_settings(settings(), &groups, &docs);
+        assert_eq!(remapped.exclude_groups, vec![101]);
+        assert_eq!(remapped.exclude_docs, vec![110]);
+        assert_eq!(remapped.front_matter, vec![111]);
+        assert_eq!(remapped.back_matter, vec![112]);
+        // Everything that is not an id is kept
+        assert_eq!(remapped.include_statuses, vec!["Final".to_string()]);
+
+        let emptied = remap_settings(settings(), &HashMap::new(), &HashMap::new());
+        assert!(emptied.exclude_groups.is_empty() && emptied.exclude_docs.is_empty());
+        assert!(emptied.front_matter.is_empty() && emptied.back_matter.is_empty());
+    }
+
+    #[test]
+    fn test_remap_copied_rewrites_only_the_copied_presets() {
+        let pool = make_pool();
+        init_schema(&pool.get().unwrap());
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        let input = |name: &str| CompilePresetInput { name: name.into(), format: CompileFormat::Pdf, settings: settings() };
+        let original = create(&pool, project.id, input("Original")).unwrap();
+        let copy = create(&pool, project.id, input("Copy")).unwrap();
+
+        let presets = HashMap::from([(original.id, copy.id)]);
+        let groups = HashMap::from([(2, 202)]);
+        let docs = HashMap::from([(99, 199), (12, 112)]);
+        remap_copied(&pool.get().unwrap(), &presets, &groups, &docs).unwrap();
+
+        let copied = get(&pool, copy.id).unwrap().unwrap().settings;
+        assert_eq!(copied.exclude_groups, vec![202]);
+        assert_eq!(copied.exclude_docs, vec![199]);
+        assert!(copied.front_matter.is_empty());
+        assert_eq!(copied.back_matter, vec![112]);
+        assert_eq!(get(&pool, original.id).unwrap().unwrap().settings.exclude_docs, vec![10, 99]);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{ContinuityIssue, ContinuityWarning, EntityRef};
+use crate::services::calendars::stored_span;
+use std::collections::HashMap;
+
+fn entity(entity_type: &str, entity_id: i64) -> EntityRef {
+    EntityRef { entity_type: entity_type.to_string(), entity_id }
+}
+
+/// A dated entity: its dates as written, plus the absolute seconds they cover
+struct Dated {
+    label: String,
+    span: (i64, i64),
+}
+
+fn label(start: Option<&str>, end: Option<&str>) -> String {
+    match (start.filter(|s| !s.trim().is_empty()), end.filter(|s| !s.trim().is_empty())) {
+        (Some(s), Some(e)) if s != e => format!("{} – {}", s, e),
+        (Some(d), _) | (None, Some(d)) => d.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+fn overlaps(a: (i64, i64), b: (i64, i64)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1
+}
+
+/// Run every continuity check for a project and return the warnings found.
+/// Dates in any calendar are compared on the shared absolute day scale;
+/// partial dates cover their whole period, so "2025-03" overlaps "2025-03-09".
+pub fn check(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<ContinuityWarning>> {
+    let project = crate::services::projects::get(pool, project_id)?
+        .ok_or_else(|| anyhow::anyhow!("project not found"))?;
+    let mut warnings = Vec::new();
+
+    // Events whose end precedes their start
+    let events = crate::services::events::list(pool, project_id)?;
+    let mut event_spans: HashMap<i64, (String, Dated)> = HashMap::new();
+    for e in &events {
+        let name = e.name.clone();
+        let start_text = e.start_date.as_deref().or(e.date.as_deref());
+        let end_text = e.end_date.as_deref();
+        let start = stored_span(e.calendar_id, e.start_day, None, start_text, None);
+        let end = stored_span(e.calendar_id, None, e.end_day, None, end_text);
+        if let (Some(start), Some(end)) = (start, end) {
+            if end.1 < start.0 {
+                warnings.push(ContinuityWarning {
+                    kind: ContinuityIssue::EventEndsBeforeStart,
+                    message: format!("\"{}\" ends ({}) before it starts ({})", name, end_text.unwrap_or(""), start_text.unwrap_or("")),
+                    entities: vec![entity("event", e.id)],
+                });
+                continue;
+            }
+        }
+        if let Some(span) = crate::services::events::span_of(e) {
+            event_spans.insert(e.id, (name, Dated { label: label(start_text, end_text), span }));
+        }
+    }
+
+    let conn = get_conn(pool)?;
+
+    // Characters taking part in overlapping events
+    let mut stmt = conn.prepare(
+        "SELECT c.id, COALESCE(c.name, ''), ec.event_id
+         FROM event_characters ec JOIN characters c ON c.id = ec.character_id
+         WHERE c.project_id = ?1
+         ORDER BY c.id, ec.event_id"
+    )?;
+    let rows = stmt.query_map(rusqlite::params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut by_character: Vec<(i64, String, Vec<i64>)> = Vec::new();
+    for (character_id, character_name, event_id) in rows {
+        if !event_spans.contains_key(&event_id) { continue; }
+        match by_character.last_mut() {
+            Some(last) if last.0 == character_id => last.2.push(event_id),
+            _ => by_character.push((character_id, character_name, vec![event_id])),
+        }
+    }
+    for (character_id, character_name, event_ids) in &by_character {
+        for (i, a) in event_ids.iter().enumerate() {
+            for b in &event_ids[i + 1..] {
+                let (a_name, a_dated) = &event_spans[a];
+                let (b_name, b_dated) = &event_spans[b];
+                if overlaps(a_dated.span, b_dated.span) {
+                    warnings.push(ContinuityWarning {
+                        kind: ContinuityIssue::CharacterDoubleBooked,
+                        message: format!(
+                            "{} is in \"{}\" ({}) and \"{}\" ({}) at the same time",
+                            character_name, a_name, a_dated.label, b_name, b_dated.label
+                        ),
+                        entities: vec![entity("character", *character_id), entity("event", *a), entity("event", *b)],
+                    });
+                }
+            }
+        }
+    }
+
+    // Doc timelines, walked in manuscript order
+    let mut stmt = conn.prepare(
+        "SELECT t.entity_id, t.start_date, t.end_date, t.calendar_id, t.start_day, t.end_day
+         FROM timelines t JOIN docs d ON d.id = t.entity_id
+         WHERE t.entity_type = 'doc' AND d.project_id = ?1"
+    )?;
+    let doc_spans: HashMap<i64, Dated> = stmt
+        .query_map(rusqlite::params![project_id], |row| {
+            let start: Option<String> = row.get(1)?;
+            let end: Option<String> = row.get(2)?;
+            let span = stored_span(row.get(3)?, row.get(4)?, row.get(5)?, start.as_deref(), end.as_deref());
+            Ok((row.get::<_, i64>(0)?, span.map(|span| Dated { label: label(start.as_deref(), end.as_deref()), span })))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|(id, dated)| dated.map(|d| (id, d)))
+        .collect();
+    let docs = crate::services::docs::list_in_manuscript_order(pool, project_id)?;
+
+    // Project range: explicit project columns, otherwise the project's timeline row
+    let (project_start, project_end, project_range) = if project.timeline_start.is_some() || project.timeline_end.is_some() {
+        let (start, end) = (project.timeline_start.as_deref(), project.timeline_end.as_deref());
+        (
+            stored_span(None, None, None, start, None).map(|s| s.0),
+            stored_span(None, None, None, None, end).map(|s| s.1),
+            format!("{} – {}", start.unwrap_or("…"), end.unwrap_or("…")),
+        )
+    } else {
+        match crate::services::timelines::get_by_entity(pool, "project", project_id)? {
+            Some(t) => (
+                stored_span(t.calendar_id, t.start_day, None, t.start_date.as_deref(), None).map(|s| s.0),
+                stored_span(t.calendar_id, None, t.end_day, None, t.end_date.as_deref()).map(|s| s.1),
+                format!("{} – {}", t.start_date.as_deref().unwrap_or("…"), t.end_date.as_deref().unwrap_or("…")),
+            ),
+            None => (None, None, String::new()),
+        }
+    };
+
+    let mut latest: Option<(i64, String, i64, String)> = None; // (doc id, name, start, label)
+    for d in &docs {
+        let Some(dated) = doc_spans.get(&d.id) else { continue };
+        let name = d.name.clone().unwrap_or_else(|| "Untitled".to_string());
+
+        let starts_early = project_start.map(|ps| dated.span.0 < ps).unwrap_or(false);
+        let ends_late = project_end.map(|pe| dated.span.1 > pe).unwrap_or(false);
+        if starts_early || ends_late {
+            warnings.push(ContinuityWarning {
+                kind: ContinuityIssue::DocOutsideProjectRange,
+                message: format!("\"{}\" ({}) falls outside the project timeline ({})", name, dated.label, project_range),
+                entities: vec![entity("doc", d.id), entity("project", project_id)],
+            });
+        }
+
+        if project.linear_chronology {
+            match &latest {
+                Some((prev_id, prev_name, prev_start, prev_label)) if dated.span.0 < *prev_start => {
+                    warnings.push(ContinuityWarning {
+                        kind: ContinuityIssue::DocOutOfChronology,
+                        message: format!(
+                            "\"{}\" ({}) starts before \"{}\" ({}) which comes earlier in the manuscript",
+                            name, dated.label, prev_name, prev_label
+                        ),
+                        entities: vec![entity("doc", d.id), entity("doc", *prev_id)],
+                    });
+                }
+                _ => latest = Some((d.id, name.clone(), dated.span.0, dated.label.clone())),
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    fn make_pool() -> DbPool {
+        let manager = SqliteConnectionManager::file("file:memcontinuity?mode=memory&cache=shared");
+        Pool::new(manager).unwrap()
+    }
+
+    #[test]
+    fn continuity_reports_each_issue() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/008_add_codex.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/009_add_event_links.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/010_add_project_linear_chronology.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+
+        conn.execute(
+            "INSERT INTO projects (name, timeline_start, timeline_end, linear_chronology) VALUES ('P', '2025-01-01', '2025-12-31', 1)",
+            [],
+        ).unwrap();
+        let project_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO characters (project_id, name) VALUES (?1, 'Mara')", rusqlite::params![project_id]).unwrap();
+        let mara = conn.last_insert_rowid();
+
+        let insert_event = |name: &str, start: &str, end: &str| -> i64 {
+            conn.execute(
+                "INSERT INTO events (project_id, name, start_date, end_date) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![project_id, name, start, end],
+            ).unwrap();
+            conn.last_insert_rowid()
+        };
+        let siege = insert_event("Siege", "2025-03-01", "2025-03-10");
+        let wedding = insert_event("Wedding", "2025-03-09", "2025-03-09");
+        insert_event("Backwards", "2025-05-10", "2025-05-01");
+        for ev in [siege, wedding] {
+            conn.execute("INSERT INTO event_characters (event_id, character_id) VALUES (?1, ?2)", rusqlite::params![ev, mara]).unwrap();
+        }
+
+        let insert_doc = |name: &str, order: i64, start: &str, end: &str| -> i64 {
+            conn.execute(
+                "INSERT INTO docs (project_id, path, name, sort_order) VALUES (?1, '', ?2, ?3)",
+                rusqlite::params![project_id, name, order],
+            ).unwrap();
+            let id = conn.last_insert_rowid();
+            conn.execute(
+                "INSERT INTO timelines (entity_type, entity_id, start_date, end_date) VALUES ('doc', ?1, ?2, ?3)",
+                rusqlite::params![id, start, end],
+            ).unwrap();
+            id
+        };
+        insert_doc("One", 0, "2025-02-01", "2025-02-02");
+        insert_doc("Two", 1, "2025-01-15", "2025-01-16");
+        insert_doc("Three", 2, "2025-11-30", "2026-01-05");
+
+        let warnings = check(&pool, project_id).unwrap();
+        let kinds: Vec<ContinuityIssue> = warnings.iter().map(|w| w.kind).collect();
+        assert!(kinds.contains(&ContinuityIssue::EventEndsBeforeStart));
+        assert!(kinds.contains(&ContinuityIssue::CharacterDoubleBooked));
+        assert!(kinds.contains(&ContinuityIssue::DocOutsideProjectRange));
+        assert!(kinds.contains(&ContinuityIssue::DocOutOfChronology));
+        assert_eq!(warnings.len(), 4);
+
+        let double = warnings.iter().find(|w| w.kind == ContinuityIssue::CharacterDoubleBooked).unwrap();
+        assert_eq!(double.entities.len(), 3);
+        assert_eq!(double.entities[0].entity_id, mara);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::dates::PartialDate;
+use crate::db::{DbPool, get_conn};
+use crate::models::DateIssue;
+use rusqlite::Connection;
+
+/// Every stored free-form date column, and whether its table can hold dates in a fictional calendar
+const DATE_COLUMNS: &[(&str, &str, bool)] = &[
+    ("projects", "timeline_start", false),
+    ("projects", "timeline_end", false),
+    ("events", "date", true),
+    ("events", "start_date", true),
+    ("events", "end_date", true),
+    ("timelines", "start_date", true),
+    ("timelines", "end_date", true),
+];
+
+/// Rewrite parseable dates into canonical ISO form and record the rest in `date_issues`.
+/// Only Gregorian rows are checked; the absolute day columns are recomputed afterwards.
+/// Unparseable values are left untouched so nothing is lost; the issue list is rebuilt on each run.
+pub fn normalize_stored(conn: &Connection) -> anyhow::Result<Vec<DateIssue>> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM date_issues", [])?;
+
+    let mut issues = Vec::new();
+    for (table, column, calendared) in DATE_COLUMNS {
+        let gregorian_only = if *calendared { " AND calendar_id IS NULL" } else { "" };
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id, {col} FROM {table} WHERE {col} IS NOT NULL{filter}", col = column, table = table, filter = gregorian_only
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (row_id, value) in rows {
+            let normalized = if value.trim().is_empty() {
+                Ok(None)
+            } else {
+                PartialDate::parse(&value).map(|d| Some(d.to_string()))
+            };
+            match normalized {
+                Ok(n) if n.as_deref() != Some(value.as_str()) => {
+                    tx.execute(
+                        &format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column),
+                        rusqlite::params![n, row_id],
+                    )?;
+                }
+                Ok(_) => {}
+                Err(e) => issues.push(DateIssue {
+                    table_name: table.to_string(),
+                    column_name: column.to_string(),
+                    row_id,
+                    value,
+                    error: e.to_string(),
+                }),
+            }
+        }
+    }
+
+    crate::services::calendars::refresh_gregorian_days(&tx)?;
+
+    for i in &issues {
+        tx.execute(
+            "INSERT INTO date_issues (table_name, column_name, row_id, value, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![i.table_name, i.column_name, i.row_id, i.value, i.error],
+        )?;
+    }
+    tx.commit()?;
+    Ok(issues)
+}
+
+/// Re-run normalisation, e.g. after the user fixed some of the reported values
+pub fn normalize_all(pool: &DbPool) -> anyhow::Result<Vec<DateIssue>> {
+    let conn = get_conn(pool)?;
+    normalize_stored(&conn)
+}
+
+/// Dates that could not be parsed on the last normalisation run
+pub fn list_issues(pool: &DbPool) -> anyhow::Result<Vec<DateIssue>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT table_name, column_name, row_id, value, error FROM date_issues ORDER BY table_name, row_id, column_name"
+    )?;
+    let items = stmt.query_map([], |row| {
+        Ok(DateIssue {
+            table_name: row.get(0)?,
+            column_name: row.get(1)?,
+            row_id: row.get(2)?,
+            value: row.get(3)?,
+            error: row.get(4)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    #[test]
+    fn normalizes_values_and_reports_failures() {
+        let manager = SqliteConnectionManager::file("file:memdatenorm?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/011_add_date_issues.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name, timeline_start) VALUES ('P', '2025-1-1')", []).unwrap();
+        let project_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO events (project_id, name, start_date, end_date) VALUES (?1, 'E', ' 2025-03-01 ', '2025-03-02 10:00')",
+            rusqlite::params![project_id],
+        ).unwrap();
+        let event_id = conn.last_insert_rowid();
+
+        let issues = normalize_all(&pool).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].table_name, "projects");
+        assert_eq!(issues[0].value, "2025-1-1");
+
+        let (start, end): (String, String) = conn.query_row(
+            "SELECT start_date, end_date FROM events WHERE id = ?1", rusqlite::params![event_id], |r| Ok((r.get(0)?, r.get(1)?)),
+        ).unwrap();
+        assert_eq!(start, "2025-03-01");
+        assert_eq!(end, "2025-03-02T10:00:00");
+        let days: i64 = conn.query_row(
+            "SELECT end_day - start_day FROM events WHERE id = ?1", rusqlite::params![event_id], |r| r.get(0),
+        ).unwrap();
+        assert_eq!(days, 1);
+        assert_eq!(list_issues(&pool).unwrap().len(), 1);
+    }
+}
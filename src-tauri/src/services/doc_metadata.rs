@@ -0,0 +1,94 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::DocMetadata;
+use anyhow::Context;
+use rusqlite::OptionalExtension;
+use std::collections::HashMap;
+
+/// Blank values are stored as NULL
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Metadata of a doc; a doc that never had any gets empty metadata
+pub fn get(pool: &DbPool, doc_id: i64) -> anyhow::Result<DocMetadata> {
+    let conn = get_conn(pool)?;
+    let meta = conn.query_row(
+        "SELECT status, label, synopsis FROM doc_metadata WHERE doc_id = ?1",
+        rusqlite::params![doc_id],
+        |row| Ok(DocMetadata { status: row.get(0)?, label: row.get(1)?, synopsis: row.get(2)? }),
+    ).optional()?;
+    Ok(meta.unwrap_or_default())
+}
+
+/// Replace the metadata of a doc
+pub fn set(pool: &DbPool, doc_id: i64, meta: DocMetadata) -> anyhow::Result<DocMetadata> {
+    let conn = get_conn(pool)?;
+    let meta = DocMetadata { status: clean(meta.status), label: clean(meta.label), synopsis: clean(meta.synopsis) };
+    conn.execute(
+        "INSERT INTO doc_metadata (doc_id, status, label, synopsis) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(doc_id) DO UPDATE SET status = excluded.status, label = excluded.label, synopsis = excluded.synopsis",
+        rusqlite::params![doc_id, meta.status, meta.label, meta.synopsis],
+    ).context("saving doc metadata")?;
+    Ok(meta)
+}
+
+/// Metadata of every doc in a project that has some, by doc id
+pub fn list_for_project(pool: &DbPool, project_id: i64) -> anyhow::Result<HashMap<i64, DocMetadata>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT m.doc_id, m.status, m.label, m.synopsis FROM doc_metadata m
+         JOIN docs d ON d.id = m.doc_id WHERE d.project_id = ?1",
+    )?;
+    let rows = stmt.query_map(rusqlite::params![project_id], |row| {
+        Ok((row.get::<_, i64>(0)?, DocMetadata { status: row.get(1)?, label: row.get(2)?, synopsis: row.get(3)? }))
+    })?;
+    Ok(rows.collect::<Result<HashMap<_, _>, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectCreate;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn make_pool() -> DbPool {
+        let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let db_name = format!("file:memdocmeta{}?mode=memory&cache=shared", id);
+        Pool::new(SqliteConnectionManager::file(&db_name)).unwrap()
+    }
+
+    fn init_schema(conn: &rusqlite::Connection) {
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/020_add_compile_presets.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_blank_values_are_stored_as_null() {
+        let pool = make_pool();
+        init_schema(&pool.get().unwrap());
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        let doc = crate::services::docs::create_doc(&pool, project.id, "Arrival", None).unwrap();
+
+        let saved = set(&pool, doc.id, DocMetadata { status: Some("  Final ".into()), label: Some("   ".into()), synopsis: Some(String::new()) }).unwrap();
+        assert_eq!(saved, DocMetadata { status: Some("Final".into()), label: None, synopsis: None });
+        let nulls: i64 = pool.get().unwrap().query_row(
+            "SELECT COUNT(*) FROM doc_metadata WHERE doc_id = ?1 AND label IS NULL AND synopsis IS NULL",
+            rusqlite::params![doc.id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(nulls, 1);
+        assert_eq!(get(&pool, doc.id).unwrap(), saved);
+        assert_eq!(list_for_project(&pool, project.id).unwrap().get(&doc.id), Some(&saved));
+    }
+}
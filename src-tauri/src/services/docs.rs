@@ -1,7 +1,8 @@
 use crate::db::{DbPool, get_conn};
-use crate::models::Doc;
+use crate::models::{Doc, DocGroup};
 use rusqlite::OptionalExtension;
 use anyhow::Context;
+use std::collections::HashMap;
 
 /// List all docs for a project, ordered by doc_group_id and sort_order
 pub fn list_docs(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Doc>> {
@@ -30,6 +31,69 @@ pub fn list_docs(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Doc>> {
     Ok(docs)
 }
 
+/// List all docs for a project in manuscript order: root-level docs first, then every group
+/// depth-first (a group's own docs before its subgroups), siblings by sort_order
+pub fn list_in_manuscript_order(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Doc>> {
+    let groups = crate::services::doc_groups::list_doc_groups(pool, project_id)?;
+    let docs = list_docs(pool, project_id)?;
+
+    let mut children: HashMap<Option<i64>, Vec<&DocGroup>> = HashMap::new();
+    for g in &groups {
+        children.entry(g.parent_id).or_default().push(g);
+    }
+    for v in children.values_mut() {
+        v.sort_by_key(|g| g.sort_order.unwrap_or(0));
+    }
+    let mut docs_by_group: HashMap<Option<i64>, Vec<Doc>> = HashMap::new();
+    for d in docs {
+        docs_by_group.entry(d.doc_group_id).or_default().push(d);
+    }
+    for v in docs_by_group.values_mut() {
+        v.sort_by_key(|d| d.sort_order.unwrap_or(0));
+    }
+
+    fn walk(
+        group_id: Option<i64>,
+        children: &HashMap<Option<i64>, Vec<&DocGroup>>,
+        docs_by_group: &mut HashMap<Option<i64>, Vec<Doc>>,
+        out: &mut Vec<Doc>,
+    ) {
+        if let Some(dd) = docs_by_group.remove(&group_id) {
+            out.extend(dd);
+        }
+        if let Some(childs) = children.get(&group_id) {
+            for child in childs {
+                walk(Some(child.id), children, docs_by_group, out);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(None, &children, &mut docs_by_group, &mut out);
+    Ok(out)
+}
+
+/// List docs in story-chronological order: by the start of their effective timeline
+/// (explicit, or derived from attached events), ties and undated docs in manuscript order
+pub fn list_in_story_order(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Doc>> {
+    let tree = crate::services::timeline_rollup::tree(pool, project_id)?;
+    let mut starts: HashMap<i64, i64> = HashMap::new();
+    let mut stack = vec![&tree];
+    while let Some(node) = stack.pop() {
+        if node.entity_type == "doc" {
+            if let Some(span) = crate::services::timeline_rollup::effective(node) {
+                starts.insert(node.entity_id, span.start_day);
+            }
+        }
+        stack.extend(node.children.iter());
+    }
+
+    let mut docs = list_in_manuscript_order(pool, project_id)?;
+    // Stable sort keeps manuscript order among equal starts; undated docs go last
+    docs.sort_by_key(|d| starts.get(&d.id).map_or((1, 0), |s| (0, *s)));
+    Ok(docs)
+}
+
 /// Create a new doc with auto-calculated sort_order
 pub fn create_doc(pool: &DbPool, project_id: i64, name: &str, doc_group_id: Option<i64>) -> anyhow::Result<Doc> {
     let conn = get_conn(pool)?;
@@ -127,10 +191,19 @@ pub fn get_doc(pool: &DbPool, id: i64) -> anyhow::Result<Option<Doc>> {
 /// Update doc text content
 pub fn update_doc(pool: &DbPool, id: i64, text: &str) -> anyhow::Result<()> {
     let conn = get_conn(pool)?;
-    conn.execute(
+    let tx = conn.unchecked_transaction()?;
+    let old_text: Option<String> = tx.query_row(
+        "SELECT text FROM docs WHERE id = ?1",
+        rusqlite::params![id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    tx.execute(
         "UPDATE docs SET text = ?1 WHERE id = ?2",
         rusqlite::params![text, id],
     ).context("updating doc text")?;
+    // Keep inline comments pointing at the same words
+    crate::services::comments::remap_anchors(&tx, id, old_text.as_deref().unwrap_or(""), text)?;
+    tx.commit()?;
     Ok(())
 }
 
@@ -449,6 +522,7 @@ mod tests {
         
         // Initialize schema
         conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/016_add_doc_comments.sql")).unwrap();
 
         conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
         let project_id = conn.last_insert_rowid();
@@ -462,4 +536,34 @@ mod tests {
         assert_eq!(updated.notes, Some("Preserved notes".to_string()));
         assert_eq!(updated.text, Some("Updated text".to_string()));
     }
+
+    #[test]
+    fn docs_in_story_order() {
+        let pool = make_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
+        let project_id = conn.last_insert_rowid();
+        let flashback = create_doc(&pool, project_id, "Flashback", None).unwrap();
+        let undated = create_doc(&pool, project_id, "Interlude", None).unwrap();
+        let present = create_doc(&pool, project_id, "Present", None).unwrap();
+        let childhood = create_doc(&pool, project_id, "Childhood", None).unwrap();
+
+        for (doc_id, date) in [(flashback.id, "2020-05-01"), (present.id, "2025-01-01")] {
+            crate::services::timelines::create(&pool, crate::models::TimelineCreate {
+                entity_type: "doc".into(), entity_id: doc_id, start_date: Some(date.into()), end_date: None, calendar_id: None,
+            }).unwrap();
+        }
+        // Placed by its attached event
+        let birth = crate::services::events::create(&pool, project_id, "Birth", None, Some("1999".into()), None, None, None).unwrap();
+        crate::services::events::attach_to_doc(&pool, childhood.id, birth.id).unwrap();
+
+        let order: Vec<i64> = list_in_story_order(&pool, project_id).unwrap().iter().map(|d| d.id).collect();
+        assert_eq!(order, vec![childhood.id, flashback.id, present.id, undated.id]);
+    }
 }
@@ -0,0 +1,269 @@
+use crate::models::{HeadingImportOptions, ManuscriptPlan};
+use crate::services::rich_text::{self, Comment, Paragraph};
+use anyhow::Context;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// `<w:b/>` is on; `w:val` of 0/false/off switches it off
+fn toggle(e: &BytesStart) -> bool {
+    !matches!(attr(e, b"val").as_deref(), Some("0" | "false" | "off" | "none"))
+}
+
+/// Heading level of a paragraph style id such as `Heading1` or `heading 2`
+fn heading_level(style: &str) -> u8 {
+    let style = style.to_ascii_lowercase().replace(' ', "");
+    match style.as_str() {
+        "title" => 1,
+        _ => style.strip_prefix("heading").and_then(|n| n.parse::<u8>().ok()).unwrap_or(0),
+    }
+}
+
+fn read_part(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> anyhow::Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut xml = String::new();
+    file.read_to_string(&mut xml).with_context(|| format!("reading {}", name))?;
+    Ok(Some(xml))
+}
+
+/// Paragraphs of word/document.xml, recording which paragraph each comment id is anchored to
+/// and the text it covers
+fn parse_document(xml: &str, comments: &mut HashMap<String, Comment>) -> anyhow::Result<Vec<Paragraph>> {
+    let mut reader = Reader::from_str(xml);
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut current: Option<Paragraph> = None;
+    let (mut in_run, mut in_text, mut bold, mut italic) = (false, false, false, false);
+    let mut open_comments: Vec<String> = Vec::new();
+    loop {
+        let event = reader.read_event().context("parsing word/document.xml")?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    if let Some(p) = current.take() {
+                        paragraphs.push(p);
+                    }
+                    if empty {
+                        paragraphs.push(Paragraph::default());
+                    } else {
+                        current = Some(Paragraph::default());
+                    }
+                }
+                b"pStyle" => {
+                    if let (Some(p), Some(style)) = (current.as_mut(), attr(&e, b"val")) {
+                        p.level = heading_level(&style);
+                    }
+                }
+                b"outlineLvl" => {
+                    if let (Some(p), Some(level)) = (current.as_mut(), attr(&e, b"val").and_then(|v| v.parse::<u8>().ok())) {
+                        if p.level == 0 && level < 9 {
+                            p.level = level + 1;
+                        }
+                    }
+                }
+                b"r" if !empty => {
+                    in_run = true;
+                    bold = false;
+                    italic = false;
+                }
+                b"b" if in_run => bold = toggle(&e),
+                b"i" if in_run => italic = toggle(&e),
+                b"t" if !empty => in_text = true,
+                b"tab" | b"br" | b"cr" if in_run => {
+                    let ch = if e.local_name().as_ref() == b"tab" { "\t" } else { "\n" };
+                    if let Some(p) = current.as_mut() {
+                        p.push(ch, bold, italic);
+                    }
+                }
+                b"commentRangeStart" | b"commentReference" => {
+                    if let Some(id) = attr(&e, b"id") {
+                        let entry = comments.entry(id.clone()).or_default();
+                        if entry.paragraph.is_none() {
+                            entry.paragraph = Some(paragraphs.len());
+                        }
+                        if e.local_name().as_ref() == b"commentRangeStart" {
+                            open_comments.push(id);
+                        }
+                    }
+                }
+                b"commentRangeEnd" => {
+                    if let Some(id) = attr(&e, b"id") {
+                        open_comments.retain(|open| *open != id);
+                    }
+                }
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    if let Some(p) = current.take() {
+                        paragraphs.push(p);
+                    }
+                }
+                b"r" => in_run = false,
+                b"t" => in_text = false,
+                _ => {}
+            },
+            Event::Text(t) if in_text => {
+                let text = t.unescape().context("parsing word/document.xml")?;
+                for id in &open_comments {
+                    if let Some(c) = comments.get_mut(id) {
+                        c.anchor.push_str(&text);
+                    }
+                }
+                if let Some(p) = current.as_mut() {
+                    p.push(&text, bold, italic);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if let Some(p) = current {
+        paragraphs.push(p);
+    }
+    Ok(paragraphs)
+}
+
+/// Author and plain text of each comment in word/comments.xml, by comment id
+fn parse_comments(xml: &str, comments: &mut HashMap<String, Comment>) -> anyhow::Result<()> {
+    let mut reader = Reader::from_str(xml);
+    let mut current: Option<String> = None;
+    let mut in_text = false;
+    loop {
+        match reader.read_event().context("parsing word/comments.xml")? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"comment" => {
+                    let id = attr(&e, b"id").unwrap_or_default();
+                    let entry = comments.entry(id.clone()).or_default();
+                    entry.author = attr(&e, b"author").unwrap_or_default();
+                    current = Some(id);
+                }
+                b"p" => {
+                    if let Some(c) = current.as_ref().and_then(|id| comments.get_mut(id)) {
+                        if !c.text.is_empty() {
+                            c.text.push('\n');
+                        }
+                    }
+                }
+                b"t" => in_text = true,
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"comment" => current = None,
+                b"t" => in_text = false,
+                _ => {}
+            },
+            Event::Text(t) if in_text => {
+                let text = t.unescape().context("parsing word/comments.xml")?;
+                if let Some(c) = current.as_ref().and_then(|id| comments.get_mut(id)) {
+                    c.text.push_str(&text);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Work out the docs and groups a Word file becomes (see `rich_text::plan`)
+pub fn plan(bytes: &[u8], name: &str, options: &HeadingImportOptions) -> anyhow::Result<ManuscriptPlan> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("not a Word (.docx) file")?;
+    let document = read_part(&mut archive, "word/document.xml")?.context("word/document.xml missing: not a Word (.docx) file")?;
+    let mut comments: HashMap<String, Comment> = HashMap::new();
+    let paragraphs = parse_document(&document, &mut comments)?;
+    if let Some(xml) = read_part(&mut archive, "word/comments.xml")? {
+        parse_comments(&xml, &mut comments)?;
+    }
+    let mut comments: Vec<(String, Comment)> = comments.into_iter().collect();
+    comments.sort_by_key(|(id, _)| id.parse::<i64>().unwrap_or(i64::MAX));
+    Ok(rich_text::plan(&paragraphs, comments.into_iter().map(|(_, c)| c).collect(), name, options))
+}
+
+/// Read and plan a .docx file, docs named after the file where no heading names them
+pub fn read_docx_file(path: &Path, options: &HeadingImportOptions) -> anyhow::Result<ManuscriptPlan> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
+    plan(&bytes, name, options).with_context(|| format!("importing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ImportHeading;
+    use std::io::Write;
+
+    fn docx(document: &str, comments: &str) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, xml) in [("word/document.xml", document), ("word/comments.xml", comments)] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    const DOCUMENT: &str = r#"<w:document xmlns:w="w"><w:body>
+<w:p><w:r><w:t>Foreword.</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Part One</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Arrival</w:t></w:r></w:p>
+<w:p><w:r><w:t xml:space="preserve">Mara </w:t></w:r><w:r><w:rPr><w:i/></w:rPr><w:t xml:space="preserve">ran </w:t></w:r><w:commentRangeStart w:id="0"/><w:r><w:rPr><w:b/></w:rPr><w:t>fast</w:t></w:r><w:commentRangeEnd w:id="0"/><w:r><w:rPr><w:b w:val="0"/></w:rPr><w:t xml:space="preserve"> &amp; far.</w:t></w:r><w:r><w:commentReference w:id="0"/></w:r></w:p>
+<w:p/>
+<w:p><w:r><w:t>She hid.</w:t></w:r></w:p>
+</w:body></w:document>"#;
+
+    const COMMENTS: &str = r#"<w:comments xmlns:w="w"><w:comment w:id="0" w:author="Ed"><w:p><w:r><w:t>Too quick?</w:t></w:r></w:p></w:comment></w:comments>"#;
+
+    fn split_headings() -> HeadingImportOptions {
+        HeadingImportOptions { heading1: ImportHeading::Group, heading2: ImportHeading::Doc }
+    }
+
+    #[test]
+    fn headings_become_groups_and_docs() {
+        let plan = plan(&docx(DOCUMENT, COMMENTS), "Harbour", &split_headings()).unwrap();
+        assert_eq!(plan.docs.len(), 1);
+        assert_eq!((plan.docs[0].name.as_str(), plan.docs[0].text.as_str()), ("Harbour", "Foreword."));
+        assert_eq!(plan.groups.len(), 1);
+        assert_eq!(plan.groups[0].name, "Part One");
+        assert_eq!(plan.groups[0].docs[0].name, "Arrival");
+    }
+
+    #[test]
+    fn emphasis_becomes_markers_and_empty_paragraphs_go() {
+        let plan = plan(&docx(DOCUMENT, COMMENTS), "Harbour", &split_headings()).unwrap();
+        assert_eq!(plan.groups[0].docs[0].text, "Mara *ran* **fast** & far.\n\nShe hid.");
+    }
+
+    #[test]
+    fn comments_become_notes_of_their_doc() {
+        let plan = plan(&docx(DOCUMENT, COMMENTS), "Harbour", &split_headings()).unwrap();
+        assert_eq!(plan.groups[0].docs[0].notes.as_deref(), Some("> fast\nEd: Too quick?"));
+    }
+
+    #[test]
+    fn headings_stay_in_one_doc_by_default() {
+        let kept = plan(&docx(DOCUMENT, COMMENTS), "Harbour", &HeadingImportOptions::default()).unwrap();
+        assert!(kept.groups.is_empty());
+        assert_eq!(kept.docs.len(), 1);
+        assert!(kept.docs[0].text.starts_with("Foreword.\n\n# Part One\n\n## Arrival\n\nMara"));
+        assert!(kept.docs[0].notes.is_some());
+    }
+
+    #[test]
+    fn files_that_are_not_docx_are_rejected() {
+        assert!(plan(b"plain text", "x", &split_headings()).is_err());
+    }
+}
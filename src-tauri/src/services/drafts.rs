@@ -102,18 +102,13 @@ pub fn delete_draft(pool: &DbPool, id: i64) -> anyhow::Result<()> {
 }
 
 pub fn restore_draft_to_doc(pool: &DbPool, draft_id: i64) -> anyhow::Result<()> {
-    let conn = pool.get()?;
-
     // Get the draft
     let draft = get_draft(pool, draft_id)?
         .context("draft not found")?;
 
-    // Update the document's text with draft content
-    conn.execute(
-        "UPDATE docs SET text = ?1 WHERE id = ?2",
-        rusqlite::params![draft.content, draft.doc_id],
-    )
-    .context("restoring draft to document")?;
+    // Update the document's text with draft content (remaps inline comments like any other edit)
+    crate::services::docs::update_doc(pool, draft.doc_id, &draft.content)
+        .context("restoring draft to document")?;
 
     Ok(())
 }
@@ -152,6 +147,8 @@ mod tests {
             .expect("Failed to execute migration 003");
         conn.execute_batch(include_str!("../../migrations/004_add_doc_drafts.sql"))
             .expect("Failed to execute migration 004");
+        conn.execute_batch(include_str!("../../migrations/016_add_doc_comments.sql"))
+            .expect("Failed to execute migration 016");
         conn.execute_batch("PRAGMA foreign_keys = ON;")
             .expect("Failed to enable foreign keys");
     }
@@ -0,0 +1,456 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{CodexField, Doc, DocGroup, Project};
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension};
+use rusqlite::types::Value;
+use std::collections::HashMap;
+
+/// Old id -> new id for everything copied by one operation. Tables copied within their own
+/// project leave the maps for project-level rows (characters, events, ...) empty, so references
+/// to those rows are kept as they are.
+#[derive(Default)]
+struct IdMaps {
+    projects: HashMap<i64, i64>,
+    groups: HashMap<i64, i64>,
+    docs: HashMap<i64, i64>,
+    characters: HashMap<i64, i64>,
+    events: HashMap<i64, i64>,
+    calendars: HashMap<i64, i64>,
+    codex_types: HashMap<i64, i64>,
+    codex_entities: HashMap<i64, i64>,
+    threads: HashMap<i64, i64>,
+}
+
+/// How a column of a copied row is rewritten
+pub enum Remap<'m> {
+    Set(Value),
+    /// Look the old id up; ids missing from the map are kept
+    Map(&'m HashMap<i64, i64>),
+}
+
+/// JSON array of the map's old ids, for `IN (SELECT value FROM json_each(?))` filters
+fn old_ids(map: &HashMap<i64, i64>) -> String {
+    serde_json::to_string(&map.keys().collect::<Vec<_>>()).unwrap_or_else(|_| "[]".into())
+}
+
+fn columns(conn: &Connection, table: &str) -> anyhow::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let cols = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(cols)
+}
+
+/// Copy the rows of `table` matching `filter` (a WHERE clause over `param`), rewriting columns as
+/// given in `remaps`. Every other column is copied verbatim, so columns added by later migrations
+/// come along without changes here. Returns old id -> new id (empty for link tables without ids).
+pub fn copy_rows(conn: &Connection, table: &str, filter: &str, param: &dyn rusqlite::ToSql, remaps: &[(&str, Remap)]) -> anyhow::Result<HashMap<i64, i64>> {
+    let all = columns(conn, table)?;
+    let has_id = all.iter().any(|c| c == "id");
+    let cols: Vec<String> = all.into_iter().filter(|c| c != "id").collect();
+    let select = format!(
+        "SELECT {}{} FROM {} WHERE {} ORDER BY rowid",
+        if has_id { "id, " } else { "0, " },
+        cols.join(", "), table, filter
+    );
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        cols.join(", "),
+        (1..=cols.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+    );
+
+    let rows: Vec<(i64, Vec<Value>)> = {
+        let mut stmt = conn.prepare(&select)?;
+        let rows = stmt.query_map([param], |row| {
+            let values = (1..=cols.len()).map(|i| row.get::<_, Value>(i)).collect::<Result<Vec<_>, _>>()?;
+            Ok((row.get::<_, i64>(0)?, values))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut id_map = HashMap::new();
+    let mut stmt = conn.prepare(&insert)?;
+    for (old_id, mut values) in rows {
+        for (column, remap) in remaps {
+            let Some(i) = cols.iter().position(|c| c == column) else { continue };
+            match remap {
+                Remap::Set(v) => values[i] = v.clone(),
+                Remap::Map(map) => {
+                    if let Value::Integer(old) = values[i] {
+                        if let Some(&new) = map.get(&old) {
+                            values[i] = Value::Integer(new);
+                        }
+                    }
+                }
+            }
+        }
+        stmt.execute(rusqlite::params_from_iter(values)).with_context(|| format!("copying {} row", table))?;
+        if has_id {
+            id_map.insert(old_id, conn.last_insert_rowid());
+        }
+    }
+    Ok(id_map)
+}
+
+/// Point a self-reference (e.g. a group's parent) of freshly copied rows at the copies
+fn relink(conn: &Connection, table: &str, column: &str, map: &HashMap<i64, i64>) -> anyhow::Result<()> {
+    let sql = format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column);
+    let lookup = format!("SELECT {} FROM {} WHERE id = ?1", column, table);
+    for &new in map.values() {
+        let old: Option<i64> = conn.query_row(&lookup, [new], |row| row.get(0))?;
+        if let Some(&target) = old.and_then(|o| map.get(&o)) {
+            conn.execute(&sql, rusqlite::params![target, new])?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy rows of a polymorphic table (timelines, notes, attachments) owned by copied entities
+fn copy_owned(conn: &Connection, maps: &IdMaps, table: &str, type_col: &str, id_col: &str, owner: &str, owners: &HashMap<i64, i64>) -> anyhow::Result<()> {
+    if owners.is_empty() {
+        return Ok(());
+    }
+    let filter = format!("{} = '{}' AND {} IN (SELECT value FROM json_each(?1))", type_col, owner, id_col);
+    let mut remaps = vec![(id_col, Remap::Map(owners)), ("project_id", Remap::Map(&maps.projects))];
+    if table == "timelines" {
+        remaps.push(("calendar_id", Remap::Map(&maps.calendars)));
+    }
+    copy_rows(conn, table, &filter, &old_ids(owners), &remaps)?;
+    Ok(())
+}
+
+/// Copy everything hanging off the docs in `maps.docs`: drafts, comments, links, metadata, screenplay
+/// mode, timeline, notes, attachments
+fn copy_doc_dependents(conn: &Connection, maps: &IdMaps) -> anyhow::Result<()> {
+    let docs = old_ids(&maps.docs);
+    let in_docs = "doc_id IN (SELECT value FROM json_each(?1))";
+    copy_rows(conn, "drafts", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs))])?;
+    let comments = copy_rows(conn, "doc_comments", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs))])?;
+    relink(conn, "doc_comments", "parent_id", &comments)?;
+    copy_rows(conn, "doc_characters", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs)), ("character_id", Remap::Map(&maps.characters))])?;
+    copy_rows(conn, "doc_events", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs)), ("event_id", Remap::Map(&maps.events))])?;
+    copy_rows(conn, "doc_codex_entities", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs)), ("entity_id", Remap::Map(&maps.codex_entities))])?;
+    copy_rows(conn, "doc_plot_threads", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs)), ("thread_id", Remap::Map(&maps.threads))])?;
+    copy_rows(conn, "doc_metadata", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs))])?;
+    copy_rows(conn, "screenplay_docs", in_docs, &docs, &[("doc_id", Remap::Map(&maps.docs))])?;
+    copy_owned(conn, maps, "timelines", "entity_type", "entity_id", "doc", &maps.docs)?;
+    copy_owned(conn, maps, "notes", "parent_type", "parent_id", "doc", &maps.docs)?;
+    copy_owned(conn, maps, "attachments", "parent_type", "parent_id", "doc", &maps.docs)?;
+    Ok(())
+}
+
+/// Copy the groups in `ids` (a JSON array) with their folder drafts, timelines and notes, then their docs
+fn copy_groups(conn: &Connection, maps: &mut IdMaps, ids: &str) -> anyhow::Result<()> {
+    let groups = copy_rows(conn, "doc_groups", "id IN (SELECT value FROM json_each(?1))", &ids, &[("project_id", Remap::Map(&maps.projects))])?;
+    relink(conn, "doc_groups", "parent_id", &groups)?;
+    maps.groups.extend(groups);
+
+    let in_groups = "doc_group_id IN (SELECT value FROM json_each(?1))";
+    copy_rows(conn, "folder_drafts", in_groups, &old_ids(&maps.groups), &[("doc_group_id", Remap::Map(&maps.groups))])?;
+    copy_owned(conn, maps, "timelines", "entity_type", "entity_id", "folder", &maps.groups)?;
+    copy_owned(conn, maps, "notes", "parent_type", "parent_id", "folder", &maps.groups)?;
+
+    let docs = copy_rows(conn, "docs", in_groups, &old_ids(&maps.groups), &[
+        ("project_id", Remap::Map(&maps.projects)),
+        ("doc_group_id", Remap::Map(&maps.groups)),
+    ])?;
+    maps.docs.extend(docs);
+    Ok(())
+}
+
+/// Append " (copy)" to a name
+fn copy_name(name: Option<String>) -> String {
+    format!("{} (copy)", name.unwrap_or_default()).trim_start().to_string()
+}
+
+/// Duplicate a doc with its text, notes, drafts, comments, character/event/codex/thread links,
+/// status/label/synopsis, screenplay mode and location, timeline, research notes and attachments. The copy is placed right after the original.
+pub fn copy_doc(pool: &DbPool, doc_id: i64) -> anyhow::Result<Doc> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let (project_id, group_id, sort_order, name): (i64, Option<i64>, i64, Option<String>) = tx.query_row(
+        "SELECT project_id, doc_group_id, sort_order, name FROM docs WHERE id = ?1",
+        [doc_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).map_err(|_| anyhow::anyhow!("doc not found"))?;
+    tx.execute(
+        "UPDATE docs SET sort_order = sort_order + 1 WHERE project_id = ?1 AND doc_group_id IS ?2 AND sort_order > ?3",
+        rusqlite::params![project_id, group_id, sort_order],
+    )?;
+
+    let maps = IdMaps {
+        docs: copy_rows(&tx, "docs", "id = ?1", &doc_id, &[
+            ("name", Remap::Set(Value::Text(copy_name(name)))),
+            ("sort_order", Remap::Set(Value::Integer(sort_order + 1))),
+        ])?,
+        ..Default::default()
+    };
+    copy_doc_dependents(&tx, &maps)?;
+    let new_id = maps.docs[&doc_id];
+    tx.commit()?;
+    drop(conn);
+    crate::services::docs::get_doc(pool, new_id)?.ok_or_else(|| anyhow::anyhow!("doc not found after copy"))
+}
+
+/// Duplicate a group with all its subgroups and docs (see `copy_doc` for what a doc copy carries).
+/// The copy is placed right after the original among its siblings.
+pub fn copy_group(pool: &DbPool, group_id: i64) -> anyhow::Result<DocGroup> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let (project_id, parent_id, sort_order, name): (i64, Option<i64>, i64, Option<String>) = tx.query_row(
+        "SELECT project_id, parent_id, sort_order, name FROM doc_groups WHERE id = ?1",
+        [group_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).map_err(|_| anyhow::anyhow!("group not found"))?;
+    tx.execute(
+        "UPDATE doc_groups SET sort_order = sort_order + 1 WHERE project_id = ?1 AND parent_id IS ?2 AND sort_order > ?3",
+        rusqlite::params![project_id, parent_id, sort_order],
+    )?;
+
+    let subtree: Vec<i64> = {
+        let mut stmt = tx.prepare(
+            "WITH RECURSIVE sub(id) AS (SELECT ?1 UNION ALL SELECT g.id FROM doc_groups g JOIN sub ON g.parent_id = sub.id)
+             SELECT id FROM sub",
+        )?;
+        let ids = stmt.query_map([group_id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        ids
+    };
+    let mut maps = IdMaps::default();
+    copy_groups(&tx, &mut maps, &serde_json::to_string(&subtree)?)?;
+    copy_doc_dependents(&tx, &maps)?;
+
+    let new_id = maps.groups[&group_id];
+    let name = copy_name(name);
+    tx.execute(
+        "UPDATE doc_groups SET name = ?1, sort_order = ?2 WHERE id = ?3",
+        rusqlite::params![name, sort_order + 1, new_id],
+    )?;
+    tx.commit()?;
+    Ok(DocGroup { id: new_id, project_id, name, parent_id, sort_order: Some(sort_order + 1) })
+}
+
+/// Duplicate a whole project under a new name, e.g. to try an alternative ending. Every row is
+/// copied and every id remapped, including codex references inside type fields and entity data
+/// and the folders and docs that compile presets name.
+/// The copy has no export folder of its own.
+pub fn copy_project(pool: &DbPool, project_id: i64, name: &str) -> anyhow::Result<Project> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("name cannot be empty");
+    }
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let mut maps = IdMaps {
+        projects: copy_rows(&tx, "projects", "id = ?1", &project_id, &[
+            ("name", Remap::Set(Value::Text(name.to_string()))),
+            ("path", Remap::Set(Value::Null)),
+        ])?,
+        ..Default::default()
+    };
+    let new_project = *maps.projects.get(&project_id).ok_or_else(|| anyhow::anyhow!("project not found"))?;
+
+    let in_project = "project_id = ?1";
+    maps.calendars = copy_rows(&tx, "calendars", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    maps.characters = copy_rows(&tx, "characters", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    maps.events = copy_rows(&tx, "events", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects)), ("calendar_id", Remap::Map(&maps.calendars))])?;
+    maps.threads = copy_rows(&tx, "plot_threads", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    maps.codex_types = copy_rows(&tx, "codex_types", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    maps.codex_entities = copy_rows(&tx, "codex_entities", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects)), ("type_id", Remap::Map(&maps.codex_types))])?;
+    remap_codex(&tx, &maps)?;
+
+    let in_events = "event_id IN (SELECT value FROM json_each(?1))";
+    copy_rows(&tx, "event_characters", in_events, &old_ids(&maps.events), &[("event_id", Remap::Map(&maps.events)), ("character_id", Remap::Map(&maps.characters))])?;
+    copy_rows(&tx, "event_locations", in_events, &old_ids(&maps.events), &[("event_id", Remap::Map(&maps.events)), ("entity_id", Remap::Map(&maps.codex_entities))])?;
+    copy_rows(&tx, "project_drafts", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    copy_rows(&tx, "screenplay_projects", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+
+    let groups: Vec<i64> = {
+        let mut stmt = tx.prepare("SELECT id FROM doc_groups WHERE project_id = ?1")?;
+        let ids = stmt.query_map([project_id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        ids
+    };
+    copy_groups(&tx, &mut maps, &serde_json::to_string(&groups)?)?;
+    // Docs outside any group
+    let root_docs = copy_rows(&tx, "docs", "project_id = ?1 AND doc_group_id IS NULL", &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    maps.docs.extend(root_docs);
+    copy_doc_dependents(&tx, &maps)?;
+    let presets = copy_rows(&tx, "compile_presets", in_project, &project_id, &[("project_id", Remap::Map(&maps.projects))])?;
+    crate::services::compile_presets::remap_copied(&tx, &presets, &maps.groups, &maps.docs)?;
+
+    for (table, type_col, id_col) in [("timelines", "entity_type", "entity_id"), ("notes", "parent_type", "parent_id"), ("attachments", "parent_type", "parent_id")] {
+        copy_owned(&tx, &maps, table, type_col, id_col, "project", &maps.projects)?;
+    }
+    copy_owned(&tx, &maps, "timelines", "entity_type", "entity_id", "event", &maps.events)?;
+    copy_owned(&tx, &maps, "notes", "parent_type", "parent_id", "character", &maps.characters)?;
+    copy_owned(&tx, &maps, "notes", "parent_type", "parent_id", "event", &maps.events)?;
+    copy_owned(&tx, &maps, "attachments", "parent_type", "parent_id", "character", &maps.characters)?;
+    copy_owned(&tx, &maps, "attachments", "parent_type", "parent_id", "location", &maps.codex_entities)?;
+    remap_speakers(&tx, &maps)?;
+
+    tx.commit()?;
+    drop(conn);
+    crate::services::projects::get(pool, new_project)?.ok_or_else(|| anyhow::anyhow!("project not found after copy"))
+}
+
+/// Point the speaker lists of copied screenplay docs at the copied characters
+fn remap_speakers(conn: &Connection, maps: &IdMaps) -> anyhow::Result<()> {
+    for &new in maps.docs.values() {
+        let json: Option<String> = conn.query_row("SELECT speakers FROM screenplay_docs WHERE doc_id = ?1", [new], |row| row.get(0)).optional()?.flatten();
+        let Some(ids) = json.and_then(|j| serde_json::from_str::<Vec<i64>>(&j).ok()) else { continue };
+        let ids: Vec<i64> = ids.into_iter().map(|id| maps.characters.get(&id).copied().unwrap_or(id)).collect();
+        conn.execute("UPDATE screenplay_docs SET speakers = ?1 WHERE doc_id = ?2", rusqlite::params![serde_json::to_string(&ids)?, new])?;
+    }
+    Ok(())
+}
+
+/// Point copied codex types' reference fields and copied entities' reference values at the copies
+fn remap_codex(conn: &Connection, maps: &IdMaps) -> anyhow::Result<()> {
+    let mut fields_by_type: HashMap<i64, Vec<CodexField>> = HashMap::new();
+    for &new in maps.codex_types.values() {
+        let json: String = conn.query_row("SELECT fields FROM codex_types WHERE id = ?1", [new], |row| row.get(0))?;
+        let fields: Vec<CodexField> = serde_json::from_str::<Vec<CodexField>>(&json)?
+            .into_iter()
+            .map(|f| CodexField { ref_type_id: f.ref_type_id.and_then(|old| maps.codex_types.get(&old).copied()), ..f })
+            .collect();
+        conn.execute("UPDATE codex_types SET fields = ?1 WHERE id = ?2", rusqlite::params![serde_json::to_string(&fields)?, new])?;
+        fields_by_type.insert(new, fields);
+    }
+    for &new in maps.codex_entities.values() {
+        let (type_id, json): (i64, String) = conn.query_row("SELECT type_id, data FROM codex_entities WHERE id = ?1", [new], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let Some(fields) = fields_by_type.get(&type_id) else { continue };
+        let data = crate::services::codex::remap_data(fields, &serde_json::from_str(&json)?, &maps.codex_entities);
+        conn.execute("UPDATE codex_entities SET data = ?1 WHERE id = ?2", rusqlite::params![serde_json::to_string(&data)?, new])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support;
+
+    /// Project 1: Part One > Chapter 1 holding Arrival and Storm, Part Two, root-level Ending, and
+    /// a row in every table that points at a doc, group or other project data
+    fn setup() -> DbPool {
+        let pool = test_support::pool("duplicate");
+        pool.get().unwrap().execute_batch(
+            r#"INSERT INTO projects (id, name, path) VALUES (1, 'Harbour', '/books/harbour');
+             INSERT INTO doc_groups (id, project_id, name, parent_id, sort_order) VALUES (1, 1, 'Part One', NULL, 0), (2, 1, 'Chapter 1', 1, 0), (3, 1, 'Part Two', NULL, 1);
+             INSERT INTO docs (id, project_id, path, name, text, notes, doc_group_id, sort_order) VALUES
+               (1, 1, '', 'Arrival', 'Mara ran.', 'tighten', 2, 0),
+               (2, 1, '', 'Storm', 'Rain.', NULL, 2, 1),
+               (3, 1, '', 'Ending', 'Fin.', NULL, NULL, 0);
+             INSERT INTO characters (id, project_id, name) VALUES (1, 1, 'Mara');
+             INSERT INTO events (id, project_id, name) VALUES (1, 1, 'Landfall');
+             INSERT INTO codex_types (id, project_id, name, fields) VALUES (1, 1, 'Place', '[{"key":"near","kind":"reference","ref_type_id":1}]');
+             INSERT INTO codex_entities (id, project_id, type_id, name, data) VALUES (1, 1, 1, 'Quay', '{}'), (2, 1, 1, 'Market', '{"near":1}');
+             INSERT INTO plot_threads (id, project_id, name) VALUES (1, 1, 'Smuggling');
+             INSERT INTO doc_characters VALUES (1, 1);
+             INSERT INTO doc_events VALUES (1, 1);
+             INSERT INTO doc_codex_entities VALUES (1, 2);
+             INSERT INTO doc_plot_threads VALUES (1, 1, 'introduces');
+             INSERT INTO event_characters VALUES (1, 1);
+             INSERT INTO doc_metadata (doc_id, status, synopsis) VALUES (1, 'Final', 'Mara lands.');
+             INSERT INTO screenplay_projects (project_id, enabled) VALUES (1, 1);
+             INSERT INTO screenplay_docs (doc_id, enabled, location, speakers) VALUES (1, 0, 'QUAY', '[1]');
+             INSERT INTO compile_presets (project_id, name, format, settings, created_at, updated_at) VALUES
+               (1, 'Book', 'pdf', '{"exclude_groups":[3],"exclude_docs":[2],"front_matter":[3]}', 't', 't');
+             INSERT INTO drafts (doc_id, name, content, created_at, updated_at) VALUES (1, 'v1', 'Mara walked.', 't', 't');
+             INSERT INTO doc_comments (id, doc_id, parent_id, start_offset, end_offset, quote, body, created_at, updated_at) VALUES
+               (1, 1, NULL, 0, 4, 'Mara', 'Who?', 't', 't'), (2, 1, 1, NULL, NULL, NULL, 'The lead', 't', 't');
+             INSERT INTO timelines (entity_type, entity_id, start_date) VALUES ('doc', 1, '1850-03-01'), ('folder', 2, '1850-03');
+             INSERT INTO notes (project_id, parent_type, parent_id, title) VALUES (1, 'doc', 1, 'Tides'), (1, 'character', 1, 'Accent');"#,
+        ).unwrap();
+        pool
+    }
+
+    #[test]
+    fn doc_copy_lands_after_the_original_with_its_data() {
+        let pool = setup();
+        let doc = copy_doc(&pool, 1).unwrap();
+        assert_eq!((doc.name.as_deref(), doc.sort_order, doc.text.as_deref()), (Some("Arrival (copy)"), Some(1), Some("Mara ran.")));
+        assert_eq!(crate::services::docs::get_doc(&pool, 2).unwrap().unwrap().sort_order, Some(2));
+        assert_eq!(crate::services::characters::list_for_doc(&pool, doc.id).unwrap(), vec![1]);
+        assert_eq!(crate::services::drafts::list_drafts(&pool, doc.id).unwrap().len(), 1);
+        assert_eq!(crate::services::doc_metadata::get(&pool, doc.id).unwrap().synopsis.as_deref(), Some("Mara lands."));
+        let script = crate::services::screenplay::doc(&pool, doc.id).unwrap();
+        assert_eq!((script.mode, script.location.as_deref()), (Some(false), Some("QUAY")));
+        let threads = crate::services::comments::list_for_doc(&pool, doc.id, true).unwrap();
+        assert_eq!(threads[0].replies.len(), 1);
+        assert!(crate::services::timelines::get_by_entity(&pool, "doc", doc.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn group_copy_carries_nested_groups_and_their_docs() {
+        let pool = setup();
+        let part = copy_group(&pool, 1).unwrap();
+        assert_eq!((part.name.as_str(), part.sort_order), ("Part One (copy)", Some(1)));
+        let groups = crate::services::doc_groups::list_doc_groups(&pool, 1).unwrap();
+        let chapter = groups.iter().find(|g| g.parent_id == Some(part.id)).unwrap();
+        assert_eq!(chapter.name, "Chapter 1");
+        assert_eq!(crate::services::docs::list_docs(&pool, 1).unwrap().iter().filter(|d| d.doc_group_id == Some(chapter.id)).count(), 2);
+        assert!(crate::services::timelines::get_by_entity(&pool, "folder", chapter.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn project_copy_links_only_its_own_rows() {
+        let pool = setup();
+        let fork = copy_project(&pool, 1, "Harbour (alt ending)").unwrap();
+        assert_eq!(fork.path, None);
+        assert_eq!(crate::services::docs::list_docs(&pool, fork.id).unwrap().len(), 3);
+        let leaks: i64 = pool.get().unwrap().query_row(
+            "SELECT (SELECT COUNT(*) FROM docs d JOIN doc_groups g ON g.id = d.doc_group_id WHERE d.project_id = ?1 AND g.project_id <> ?1)
+                  + (SELECT COUNT(*) FROM doc_characters dc JOIN docs d ON d.id = dc.doc_id JOIN characters c ON c.id = dc.character_id WHERE d.project_id = ?1 AND c.project_id <> ?1)
+                  + (SELECT COUNT(*) FROM doc_codex_entities dx JOIN docs d ON d.id = dx.doc_id JOIN codex_entities e ON e.id = dx.entity_id WHERE d.project_id = ?1 AND e.project_id <> ?1)
+                  + (SELECT COUNT(*) FROM doc_plot_threads dt JOIN docs d ON d.id = dt.doc_id JOIN plot_threads t ON t.id = dt.thread_id WHERE d.project_id = ?1 AND t.project_id <> ?1)
+                  + (SELECT COUNT(*) FROM event_characters ec JOIN events e ON e.id = ec.event_id JOIN characters c ON c.id = ec.character_id WHERE e.project_id = ?1 AND c.project_id <> ?1)",
+            [fork.id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(leaks, 0);
+        assert_eq!(crate::services::research_notes::list(&pool, fork.id).unwrap().len(), 2);
+        assert_eq!(crate::services::timelines::list(&pool, fork.id).unwrap().len(), 2);
+        let metadata = |project| crate::services::doc_metadata::list_for_project(&pool, project).unwrap().len();
+        assert_eq!(metadata(fork.id), metadata(1));
+    }
+
+    #[test]
+    fn project_copy_remaps_codex_references() {
+        let pool = setup();
+        let fork = copy_project(&pool, 1, "Harbour (alt ending)").unwrap();
+        let entities = crate::services::codex::list_entities(&pool, fork.id, None).unwrap();
+        let quay = entities.iter().find(|e| e.name == "Quay").unwrap();
+        let market = entities.iter().find(|e| e.name == "Market").unwrap();
+        assert_eq!(market.data["near"], quay.id);
+        assert_eq!(crate::services::codex::list_types(&pool, fork.id).unwrap()[0].fields[0].ref_type_id, Some(quay.type_id));
+    }
+
+    #[test]
+    fn project_copy_remaps_compile_preset_ids() {
+        let pool = setup();
+        let fork = copy_project(&pool, 1, "Harbour (alt ending)").unwrap();
+        let preset = &crate::services::compile_presets::list(&pool, fork.id).unwrap()[0];
+        let fork_docs = crate::services::docs::list_docs(&pool, fork.id).unwrap();
+        let fork_groups = crate::services::doc_groups::list_doc_groups(&pool, fork.id).unwrap();
+        let named = |name: &str| fork_docs.iter().find(|d| d.name.as_deref() == Some(name) && d.doc_group_id.is_none()).unwrap().id;
+        assert_eq!(preset.settings.front_matter, vec![named("Ending")]);
+        assert!(fork_groups.iter().any(|g| preset.settings.exclude_groups == vec![g.id] && g.name == "Part Two"));
+        assert!(fork_docs.iter().any(|d| preset.settings.exclude_docs == vec![d.id] && d.name.as_deref() == Some("Storm")));
+    }
+
+    #[test]
+    fn project_copy_remaps_screenplay_speakers() {
+        let pool = setup();
+        let fork = copy_project(&pool, 1, "Harbour (alt ending)").unwrap();
+        assert!(crate::services::screenplay::project_enabled(&pool, fork.id).unwrap());
+        let scripts = |project| crate::services::screenplay::list_for_project(&pool, project).unwrap().len();
+        assert_eq!(scripts(fork.id), scripts(1));
+        let conn = pool.get().unwrap();
+        let fork_speakers: String = conn.query_row(
+            "SELECT s.speakers FROM screenplay_docs s JOIN docs d ON d.id = s.doc_id WHERE d.project_id = ?1 LIMIT 1",
+            [fork.id],
+            |row| row.get(0),
+        ).unwrap();
+        let fork_mara: i64 = conn.query_row("SELECT id FROM characters WHERE project_id = ?1", [fork.id], |row| row.get(0)).unwrap();
+        assert_eq!(fork_speakers, format!("[{}]", fork_mara));
+    }
+}
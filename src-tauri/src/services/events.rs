@@ -1,33 +1,33 @@
 use crate::db::{DbPool, get_conn};
-use crate::models::Event;
+use crate::models::{CharacterWhereabouts, Event};
+use crate::services::calendars;
 use anyhow::Context;
 use rusqlite::OptionalExtension;
+use std::cmp::Ordering;
 
-pub fn create(pool: &DbPool, project_id: i64, name: &str, desc: Option<String>, start_date: Option<String>, end_date: Option<String>, date: Option<String>) -> anyhow::Result<Event> {
+const EVENT_COLUMNS: &str = "id, project_id, name, desc, date, start_date, end_date, calendar_id, start_day, end_day";
+
+#[allow(clippy::too_many_arguments)]
+pub fn create(pool: &DbPool, project_id: i64, name: &str, desc: Option<String>, start_date: Option<String>, end_date: Option<String>, date: Option<String>, calendar_id: Option<i64>) -> anyhow::Result<Event> {
     let mut conn = get_conn(pool)?;
 
     if name.trim().is_empty() {
         return Err(anyhow::anyhow!("name cannot be empty"));
     }
+    // The legacy single date only exists for Gregorian events; it stands in for a missing start
+    let date = if calendar_id.is_some() { None } else { crate::dates::normalize(date)? };
+    let range = calendars::resolve_range(&conn, project_id, calendar_id, start_date, end_date)?;
+    let (start_day, end_day) = event_days(&range, date.as_deref());
 
     let tx = conn.transaction()?;
     tx.execute(
-        "INSERT INTO events (project_id, name, desc, date, start_date, end_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        rusqlite::params![project_id, name, desc, date, start_date, end_date],
+        "INSERT INTO events (project_id, name, desc, date, start_date, end_date, calendar_id, start_day, end_day) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![project_id, name, desc, date, range.start_date, range.end_date, calendar_id, start_day, end_day],
     ).context("inserting event")?;
 
     let id = tx.last_insert_rowid();
-    let event = tx.query_row("SELECT id, project_id, name, desc, date, start_date, end_date FROM events WHERE id = ?1", rusqlite::params![id], |row| {
-        Ok(Event {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            desc: row.get(3)?,
-            date: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-        })
-    })?;
+    let sql = format!("SELECT {} FROM events WHERE id = ?1", EVENT_COLUMNS);
+    let event = tx.query_row(&sql, rusqlite::params![id], event_from_row)?;
 
     tx.commit()?;
     Ok(event)
@@ -35,61 +35,82 @@ pub fn create(pool: &DbPool, project_id: i64, name: &str, desc: Option<String>,
 
 pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Event>> {
     let conn = get_conn(pool)?;
-    let res = conn.query_row::<Event, _, _>("SELECT id, project_id, name, desc, date, start_date, end_date FROM events WHERE id = ?1", rusqlite::params![id], |row| {
-        Ok(Event {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            desc: row.get(3)?,
-            date: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-        })
-    }).optional()?;
+    let sql = format!("SELECT {} FROM events WHERE id = ?1", EVENT_COLUMNS);
+    let res = conn.query_row(&sql, rusqlite::params![id], event_from_row).optional()?;
     Ok(res)
 }
 
 pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Event>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare("SELECT id, project_id, name, desc, date, start_date, end_date FROM events WHERE project_id = ?1 ORDER BY id ASC")?;
-    let rows = stmt.query_map(rusqlite::params![project_id], |row| {
-        Ok(Event {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            desc: row.get(3)?,
-            date: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-        })
-    })?;
+    let sql = format!("SELECT {} FROM events WHERE project_id = ?1 ORDER BY id ASC", EVENT_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params![project_id], event_from_row)?;
     let mut items = Vec::new();
     for r in rows { items.push(r?); }
+    items.sort_by(|a, b| compare_chronologically(a, b).then(a.id.cmp(&b.id)));
     Ok(items)
 }
 
-pub fn update(pool: &DbPool, id: i64, name: Option<String>, desc: Option<String>, start_date: Option<String>, end_date: Option<String>) -> anyhow::Result<Event> {
-    let conn = get_conn(pool)?;
-    // Build dynamic SQL
-    let mut sets: Vec<&str> = Vec::new();
-    if name.is_some() { sets.push("name = COALESCE(?1, name)"); } else { sets.push("name = name"); }
-    if desc.is_some() { sets.push("desc = COALESCE(?2, desc)"); } else { sets.push("desc = desc"); }
-    if start_date.is_some() { sets.push("start_date = COALESCE(?3, start_date)"); } else { sets.push("start_date = start_date"); }
-    if end_date.is_some() { sets.push("end_date = COALESCE(?4, end_date)"); } else { sets.push("end_date = end_date"); }
-    let sql = format!("UPDATE events SET {} WHERE id = ?5", sets.join(", "));
-    conn.execute(&sql, rusqlite::params![name, desc, start_date, end_date, id])?;
-
-    let ev = conn.query_row("SELECT id, project_id, name, desc, date, start_date, end_date FROM events WHERE id = ?1", rusqlite::params![id], |row| {
-        Ok(Event {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            desc: row.get(3)?,
-            date: row.get(4)?,
-            start_date: row.get(5)?,
-            end_date: row.get(6)?,
-        })
-    })?;
+/// Day columns of an event; a Gregorian event without a start is placed by its legacy date
+fn event_days(range: &calendars::ResolvedRange, date: Option<&str>) -> (Option<i64>, Option<i64>) {
+    match (&range.start_date, calendars::gregorian_days(date, None)) {
+        (None, Some((first, last))) => (Some(first), Some(range.end_day.unwrap_or(last))),
+        _ => (range.start_day, range.end_day),
+    }
+}
+
+/// Absolute seconds an event covers, whatever calendar it is written in.
+/// The legacy single date stands in for a missing start.
+pub fn span_of(event: &Event) -> Option<(i64, i64)> {
+    calendars::stored_span(
+        event.calendar_id,
+        event.start_day,
+        event.end_day,
+        event.start_date.as_deref().or(event.date.as_deref()),
+        event.end_date.as_deref(),
+    )
+}
+
+/// Order by start; undated events go last
+fn compare_chronologically(a: &Event, b: &Event) -> Ordering {
+    match (span_of(a), span_of(b)) {
+        (Some(x), Some(y)) => x.0.cmp(&y.0),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Update an event. A provided blank date clears the field; an absent one keeps the stored value.
+/// `calendar_id` of `Some(None)` switches to Gregorian; switching calendars without new dates
+/// converts the stored days into the new calendar.
+pub fn update(pool: &DbPool, id: i64, name: Option<String>, desc: Option<String>, start_date: Option<String>, end_date: Option<String>, calendar_id: Option<Option<i64>>) -> anyhow::Result<Event> {
+    let conn = get_conn(pool)?;
+    let current = get(pool, id)?.ok_or_else(|| anyhow::anyhow!("event not found"))?;
+
+    let calendar_id = calendar_id.unwrap_or(current.calendar_id);
+    let range = if calendar_id != current.calendar_id && start_date.is_none() && end_date.is_none() {
+        calendars::convert_range(&conn, current.project_id, calendar_id, current.start_day, current.end_day)?
+    } else {
+        calendars::resolve_range(
+            &conn,
+            current.project_id,
+            calendar_id,
+            start_date.or(current.start_date),
+            end_date.or(current.end_date),
+        )?
+    };
+
+    let date = if calendar_id.is_some() { None } else { current.date };
+    let (start_day, end_day) = event_days(&range, date.as_deref());
+
+    conn.execute(
+        "UPDATE events SET name = COALESCE(?1, name), desc = COALESCE(?2, desc), date = ?3, start_date = ?4, end_date = ?5, calendar_id = ?6, start_day = ?7, end_day = ?8 WHERE id = ?9",
+        rusqlite::params![name, desc, date, range.start_date, range.end_date, calendar_id, start_day, end_day, id],
+    ).context("updating event")?;
+
+    let sql = format!("SELECT {} FROM events WHERE id = ?1", EVENT_COLUMNS);
+    let ev = conn.query_row(&sql, rusqlite::params![id], event_from_row)?;
     Ok(ev)
 }
 
@@ -120,6 +141,162 @@ pub fn detach_from_doc(pool: &DbPool, doc_id: i64, event_id: i64) -> anyhow::Res
     Ok(())
 }
 
+fn event_from_row(row: &rusqlite::Row) -> rusqlite::Result<Event> {
+    Ok(Event {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        desc: row.get(3)?,
+        date: row.get(4)?,
+        start_date: row.get(5)?,
+        end_date: row.get(6)?,
+        calendar_id: row.get(7)?,
+        start_day: row.get(8)?,
+        end_day: row.get(9)?,
+    })
+}
+
+/// List character ids participating in an event
+pub fn list_characters(pool: &DbPool, event_id: i64) -> anyhow::Result<Vec<i64>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare("SELECT character_id FROM event_characters WHERE event_id = ?1 ORDER BY character_id")?;
+    let ids = stmt.query_map(rusqlite::params![event_id], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// Add a character as participant of an event (idempotent)
+pub fn attach_character(pool: &DbPool, event_id: i64, character_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    let same_project: Option<bool> = conn.query_row(
+        "SELECT e.project_id = c.project_id FROM events e, characters c WHERE e.id = ?1 AND c.id = ?2",
+        rusqlite::params![event_id, character_id],
+        |row| row.get(0),
+    ).optional()?;
+    match same_project {
+        None => anyhow::bail!("event or character not found"),
+        Some(false) => anyhow::bail!("event and character belong to different projects"),
+        Some(true) => {}
+    }
+    conn.execute("INSERT OR IGNORE INTO event_characters (event_id, character_id) VALUES (?1, ?2)", rusqlite::params![event_id, character_id])?;
+    Ok(())
+}
+
+/// Remove a character from an event (idempotent)
+pub fn detach_character(pool: &DbPool, event_id: i64, character_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM event_characters WHERE event_id = ?1 AND character_id = ?2", rusqlite::params![event_id, character_id])?;
+    Ok(())
+}
+
+/// List the events a character took part in, earliest first
+pub fn list_for_character(pool: &DbPool, character_id: i64) -> anyhow::Result<Vec<Event>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.project_id, e.name, e.desc, e.date, e.start_date, e.end_date, e.calendar_id, e.start_day, e.end_day
+         FROM events e JOIN event_characters ec ON ec.event_id = e.id
+         WHERE ec.character_id = ?1
+         ORDER BY e.id"
+    )?;
+    let mut items = stmt.query_map(rusqlite::params![character_id], event_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    items.sort_by(compare_chronologically);
+    Ok(items)
+}
+
+/// List codex entity ids where an event takes place
+pub fn list_locations(pool: &DbPool, event_id: i64) -> anyhow::Result<Vec<i64>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare("SELECT entity_id FROM event_locations WHERE event_id = ?1 ORDER BY entity_id")?;
+    let ids = stmt.query_map(rusqlite::params![event_id], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// Set a location codex entity as a location of an event (idempotent)
+pub fn attach_location(pool: &DbPool, event_id: i64, entity_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    let event_project: Option<i64> = conn.query_row(
+        "SELECT project_id FROM events WHERE id = ?1",
+        rusqlite::params![event_id],
+        |row| row.get(0),
+    ).optional()?;
+    let location = crate::services::codex::location_project(&conn, entity_id)?;
+    match (event_project, location) {
+        (None, _) | (_, None) => anyhow::bail!("event or location not found"),
+        (_, Some((_, false))) => anyhow::bail!("codex entity {} is not a location", entity_id),
+        (Some(event_project), Some((entity_project, true))) if event_project != entity_project => {
+            anyhow::bail!("event and location belong to different projects")
+        }
+        _ => {}
+    }
+    conn.execute("INSERT OR IGNORE INTO event_locations (event_id, entity_id) VALUES (?1, ?2)", rusqlite::params![event_id, entity_id])?;
+    Ok(())
+}
+
+/// Remove a location from an event (idempotent)
+pub fn detach_location(pool: &DbPool, event_id: i64, entity_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM event_locations WHERE event_id = ?1 AND entity_id = ?2", rusqlite::params![event_id, entity_id])?;
+    Ok(())
+}
+
+/// List the events that happened at a location, earliest first
+pub fn list_for_location(pool: &DbPool, entity_id: i64) -> anyhow::Result<Vec<Event>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.project_id, e.name, e.desc, e.date, e.start_date, e.end_date, e.calendar_id, e.start_day, e.end_day
+         FROM events e JOIN event_locations el ON el.event_id = e.id
+         WHERE el.entity_id = ?1
+         ORDER BY e.id"
+    )?;
+    let mut items = stmt.query_map(rusqlite::params![entity_id], event_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    items.sort_by(compare_chronologically);
+    Ok(items)
+}
+
+/// Where was everyone on a given date: every participant of every event whose range covers `date`.
+/// The date is read in the given calendar (None = Gregorian); partial dates cover their whole period
+/// and an event without an end date covers only its start.
+pub fn whereabouts(pool: &DbPool, project_id: i64, date: &str, calendar_id: Option<i64>) -> anyhow::Result<Vec<CharacterWhereabouts>> {
+    let (first, last) = calendars::query_span(pool, calendar_id, date)?;
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT ec.character_id, e.id, e.project_id, e.name, e.desc, e.date, e.start_date, e.end_date, e.calendar_id, e.start_day, e.end_day
+         FROM events e JOIN event_characters ec ON ec.event_id = e.id
+         WHERE e.project_id = ?1"
+    )?;
+    let rows = stmt.query_map(rusqlite::params![project_id], |row| {
+        let event = Event {
+            id: row.get(1)?,
+            project_id: row.get(2)?,
+            name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            desc: row.get(4)?,
+            date: row.get(5)?,
+            start_date: row.get(6)?,
+            end_date: row.get(7)?,
+            calendar_id: row.get(8)?,
+            start_day: row.get(9)?,
+            end_day: row.get(10)?,
+        };
+        Ok((row.get::<_, i64>(0)?, event))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    let mut covering = Vec::new();
+    for (character_id, event) in rows {
+        let Some(span) = span_of(&event) else { continue };
+        if span.0 <= last && span.1 >= first {
+            covering.push((character_id, span.0, event.id, event.name));
+        }
+    }
+    covering.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut loc_stmt = conn.prepare("SELECT entity_id FROM event_locations WHERE event_id = ?1 ORDER BY entity_id")?;
+    let mut out = Vec::new();
+    for (character_id, _, event_id, event_name) in covering {
+        let location_ids = loc_stmt.query_map(rusqlite::params![event_id], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+        out.push(CharacterWhereabouts { character_id, event_id, event_name, location_ids });
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,12 +313,142 @@ mod tests {
         let pool = make_pool();
         let conn = pool.get().unwrap();
         conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
 
         conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
         let project_id = conn.last_insert_rowid();
 
-        let event = create(&pool, project_id, "Battle", Some("Big battle".into()), Some("2025-01-01".into()), Some("2025-01-02".into()), None).unwrap();
+        let event = create(&pool, project_id, "Battle", Some("Big battle".into()), Some("2025-01-01".into()), Some("2025-01-02".into()), None, None).unwrap();
         let got = get(&pool, event.id).unwrap().unwrap();
         assert_eq!(got.project_id, project_id);
     }
+
+    #[test]
+    fn event_dates_are_validated_and_sorted() {
+        let manager = SqliteConnectionManager::file("file:memeventdates?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
+        let project_id = conn.last_insert_rowid();
+
+        assert!(create(&pool, project_id, "Bad", None, Some("next spring".into()), None, None, None).is_err());
+        assert!(create(&pool, project_id, "Inverted", None, Some("2025-03-10".into()), Some("2025-03-01".into()), None, None).is_err());
+
+        let late = create(&pool, project_id, "Late", None, Some("2025-06-01".into()), None, None, None).unwrap();
+        let undated = create(&pool, project_id, "Undated", None, None, None, None, None).unwrap();
+        let early = create(&pool, project_id, "Early", None, Some("2025-03".into()), Some("2025-03-15T12:00".into()), None, None).unwrap();
+        let legacy = create(&pool, project_id, "Legacy", None, None, None, Some("2024".into()), None).unwrap();
+        assert_eq!(early.end_date.as_deref(), Some("2025-03-15T12:00:00"));
+
+        let voyage_calendar = calendars::create(&pool, project_id, crate::models::CalendarCreate {
+            name: "Voyage".into(),
+            kind: crate::models::CalendarKind::Relative,
+            months: vec![],
+            weekdays: vec![],
+            eras: vec![],
+            epoch_offset: crate::dates::day_number(chrono::NaiveDate::from_ymd_opt(2025, 5, 1).unwrap()),
+            day_label: None,
+        }).unwrap();
+        let voyage = create(&pool, project_id, "Voyage", None, Some("Day 3".into()), None, None, Some(voyage_calendar.id)).unwrap();
+        assert_eq!(voyage.start_date.as_deref(), Some("Day 3"));
+
+        let order: Vec<i64> = list(&pool, project_id).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(order, vec![legacy.id, early.id, voyage.id, late.id, undated.id]);
+
+        // Converting to Gregorian keeps the day
+        let converted = update(&pool, voyage.id, None, None, None, None, Some(None)).unwrap();
+        assert_eq!(converted.start_date.as_deref(), Some("2025-05-03"));
+        assert_eq!(converted.start_day, voyage.start_day);
+
+        assert!(update(&pool, late.id, None, None, None, Some("2025-05-01".into()), None).is_err());
+        let cleared = update(&pool, early.id, None, None, None, Some("".into()), None).unwrap();
+        assert_eq!(cleared.end_date, None);
+    }
+
+    #[test]
+    fn event_participants_and_locations() {
+        let manager = SqliteConnectionManager::file("file:memeventlinks?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/008_add_codex.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/009_add_event_links.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name) VALUES (?1)", rusqlite::params!["P"]).unwrap();
+        let project_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO characters (project_id, name) VALUES (?1, 'Mara')", rusqlite::params![project_id]).unwrap();
+        let mara = conn.last_insert_rowid();
+        conn.execute("INSERT INTO codex_types (project_id, name) VALUES (?1, 'Location')", rusqlite::params![project_id]).unwrap();
+        let location_type = conn.last_insert_rowid();
+        conn.execute("INSERT INTO codex_entities (project_id, type_id, name) VALUES (?1, ?2, 'Castle')", rusqlite::params![project_id, location_type]).unwrap();
+        let castle = conn.last_insert_rowid();
+
+        let siege = create(&pool, project_id, "Siege", None, Some("2025-03-01".into()), Some("2025-03-10".into()), None, None).unwrap();
+        let feast = create(&pool, project_id, "Feast", None, Some("2025-04-01".into()), None, None, None).unwrap();
+        attach_character(&pool, siege.id, mara).unwrap();
+        attach_character(&pool, feast.id, mara).unwrap();
+        attach_location(&pool, siege.id, castle).unwrap();
+
+        assert_eq!(list_characters(&pool, siege.id).unwrap(), vec![mara]);
+        let mara_events: Vec<i64> = list_for_character(&pool, mara).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(mara_events, vec![siege.id, feast.id]);
+        assert_eq!(list_for_location(&pool, castle).unwrap().len(), 1);
+
+        let on_day = whereabouts(&pool, project_id, "2025-03-05", None).unwrap();
+        assert_eq!(on_day.len(), 1);
+        assert_eq!(on_day[0].event_id, siege.id);
+        assert_eq!(on_day[0].location_ids, vec![castle]);
+        assert!(whereabouts(&pool, project_id, "2025-03-20", None).unwrap().is_empty());
+
+        detach_character(&pool, siege.id, mara).unwrap();
+        assert!(list_characters(&pool, siege.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn links_stay_within_the_project_and_locations_must_be_locations() {
+        let manager = SqliteConnectionManager::file("file:memeventlinkchecks?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/008_add_codex.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/009_add_event_links.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO projects (id, name) VALUES (1, 'P'), (2, 'Other');
+             INSERT INTO characters (id, project_id, name) VALUES (1, 1, 'Mara'), (2, 2, 'Stranger');
+             INSERT INTO codex_types (id, project_id, name) VALUES (1, 1, 'Location'), (2, 1, 'Item'), (3, 2, 'location');
+             INSERT INTO codex_entities (id, project_id, type_id, name) VALUES (1, 1, 1, 'Castle'), (2, 1, 2, 'Sword'), (3, 2, 3, 'Elsewhere');",
+        ).unwrap();
+
+        let siege = create(&pool, 1, "Siege", None, Some("2025-03-01".into()), None, None, None).unwrap();
+        let err = attach_character(&pool, siege.id, 2).unwrap_err();
+        assert!(err.to_string().contains("different projects"));
+        assert!(attach_character(&pool, siege.id, 9).is_err());
+        assert!(attach_character(&pool, 99, 1).is_err());
+        assert!(list_characters(&pool, siege.id).unwrap().is_empty());
+
+        let err = attach_location(&pool, siege.id, 2).unwrap_err();
+        assert!(err.to_string().contains("not a location"));
+        let err = attach_location(&pool, siege.id, 3).unwrap_err();
+        assert!(err.to_string().contains("different projects"));
+        assert!(attach_location(&pool, siege.id, 9).is_err());
+        assert!(list_locations(&pool, siege.id).unwrap().is_empty());
+
+        attach_character(&pool, siege.id, 1).unwrap();
+        attach_location(&pool, siege.id, 1).unwrap();
+        assert_eq!(list_characters(&pool, siege.id).unwrap(), vec![1]);
+        assert_eq!(list_locations(&pool, siege.id).unwrap(), vec![1]);
+    }
 }
@@ -0,0 +1,330 @@
+use crate::models::{ManuscriptPlan, PlannedDoc, PlannedGroup, Screenplay, ScreenplayElement};
+use crate::services::text_decode::normalize_newlines;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Keys that start a title page; anything else on the first line is script
+const TITLE_KEYS: &[&str] = &[
+    "title", "credit", "author", "authors", "source", "draft date", "date", "contact", "copyright", "notes", "revision",
+];
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("built-in fountain pattern"))
+}
+
+/// Blank out boneyard (`/* */`) and notes (`[[ ]]`), keeping their line breaks so lines still
+/// match the source. An unclosed comment runs to the end.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let next = [("/*", "*/"), ("[[", "]]")]
+            .into_iter()
+            .filter_map(|(open, close)| rest.find(open).map(|i| (i, open, close)))
+            .min_by_key(|(i, _, _)| *i);
+        let Some((start, open, close)) = next else {
+            out.push_str(rest);
+            return out;
+        };
+        out.push_str(&rest[..start]);
+        let after = &rest[start + open.len()..];
+        let (inner, tail) = match after.find(close) {
+            Some(end) => (&after[..end], &after[end + close.len()..]),
+            None => (after, ""),
+        };
+        out.extend(inner.chars().filter(|&c| c == '\n'));
+        rest = tail;
+    }
+}
+
+/// Text without emphasis markers (`*`, `**`, `_`); backslash-escaped markers stay as literals
+pub fn plain(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            '*' | '_' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Title page fields and the line the script starts on
+fn title_page(lines: &[&str]) -> (Vec<(String, String)>, usize) {
+    static KEY: OnceLock<Regex> = OnceLock::new();
+    let key = regex(&KEY, r"^([A-Za-z][A-Za-z ]*):[ \t]*(.*)$");
+    let is_field = |line: &str| key.captures(line).is_some_and(|c| TITLE_KEYS.contains(&c[1].trim().to_lowercase().as_str()));
+    let mut fields: Vec<(String, String)> = Vec::new();
+    if !lines.first().is_some_and(|l| is_field(l)) {
+        return (fields, 0);
+    }
+    let mut i = 0;
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        let line = lines[i];
+        match key.captures(line).filter(|_| !line.starts_with([' ', '\t'])) {
+            Some(caps) => fields.push((caps[1].trim().to_string(), caps[2].trim().to_string())),
+            None => {
+                if let Some((_, value)) = fields.last_mut() {
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(line.trim());
+                }
+            }
+        }
+        i += 1;
+    }
+    (fields, i)
+}
+
+/// Heading text and scene number of a scene heading line: INT, EXT, EST, INT./EXT or I/E, or
+/// forced with a leading `.`
+fn scene_heading(line: &str) -> Option<(String, Option<String>)> {
+    static SCENE: OnceLock<Regex> = OnceLock::new();
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let text = match line.strip_prefix('.') {
+        Some(rest) if !rest.starts_with('.') => rest,
+        _ if regex(&SCENE, r"(?i)^(int\.?/ext|int|ext|est|i/e)[. ]").is_match(line) => line,
+        _ => return None,
+    };
+    Some(match regex(&NUMBER, r"^(.*?)\s*#([\w.\-]+)#$").captures(text) {
+        Some(caps) => (caps[1].trim().to_string(), Some(caps[2].to_string())),
+        None => (text.trim().to_string(), None),
+    })
+}
+
+/// Name, extension and dual flag of a character cue: an upper-case line, or any line forced with `@`
+fn character(line: &str) -> Option<(String, Option<String>, bool)> {
+    let (line, forced) = match line.strip_prefix('@') {
+        Some(rest) => (rest, true),
+        None => (line, false),
+    };
+    let (line, dual) = match line.trim_end().strip_suffix('^') {
+        Some(rest) => (rest.trim_end(), true),
+        None => (line.trim_end(), false),
+    };
+    let (name, extension) = match line.find('(') {
+        Some(i) => (line[..i].trim(), Some(line[i..].trim().trim_start_matches('(').trim_end_matches(')').trim())),
+        None => (line.trim(), None),
+    };
+    let upper = name.chars().any(char::is_alphabetic) && !name.chars().any(char::is_lowercase);
+    if name.is_empty() || !(forced || upper) {
+        return None;
+    }
+    Some((name.to_string(), extension.filter(|e| !e.is_empty()).map(String::from), dual))
+}
+
+fn is_transition(line: &str) -> bool {
+    line.ends_with("TO:") && !line.chars().any(char::is_lowercase)
+}
+
+// Elements with the source line each starts on
+type Placed = Vec<(usize, ScreenplayElement)>;
+
+fn flush_action(action: &mut Vec<&str>, start: usize, elements: &mut Placed) {
+    if !action.is_empty() {
+        elements.push((start, ScreenplayElement::Action { text: action.join("\n") }));
+        action.clear();
+    }
+}
+
+/// Title page and elements, each with the line it starts on
+fn read(text: &str) -> (Vec<(String, String)>, Placed) {
+    let text = strip_comments(&normalize_newlines(text));
+    let lines: Vec<&str> = text.split('\n').collect();
+    let (title, start) = title_page(&lines);
+    let blank = |i: usize| lines.get(i).map(|l| l.trim().is_empty()).unwrap_or(true);
+    let mut elements = Vec::new();
+    let mut action: Vec<&str> = Vec::new();
+    let mut action_start = start;
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        let before = i == start || blank(i - 1);
+        let after = blank(i + 1);
+        let element = if line.is_empty() {
+            flush_action(&mut action, action_start, &mut elements);
+            i += 1;
+            continue;
+        } else if line.len() >= 3 && line.chars().all(|c| c == '=') {
+            ScreenplayElement::PageBreak
+        } else if let Some(rest) = line.strip_prefix('#') {
+            let depth = 1 + rest.chars().take_while(|&c| c == '#').count();
+            ScreenplayElement::Section { depth, text: rest.trim_start_matches('#').trim().to_string() }
+        } else if let Some(rest) = line.strip_prefix('=') {
+            ScreenplayElement::Synopsis { text: rest.trim().to_string() }
+        } else if let Some(rest) = line.strip_prefix('~') {
+            ScreenplayElement::Lyrics { text: rest.trim().to_string() }
+        } else if line.starts_with('>') && line.ends_with('<') && line.len() > 1 {
+            ScreenplayElement::Centered { text: line[1..line.len() - 1].trim().to_string() }
+        } else if let Some(rest) = line.strip_prefix('>') {
+            ScreenplayElement::Transition { text: rest.trim().to_string() }
+        } else if let Some((text, number)) = scene_heading(line).filter(|_| before) {
+            ScreenplayElement::SceneHeading { text, number }
+        } else if before && after && is_transition(line) {
+            ScreenplayElement::Transition { text: line.to_string() }
+        } else if let Some((name, extension, dual)) = character(line).filter(|_| before && !after && !line.starts_with('!')) {
+            flush_action(&mut action, action_start, &mut elements);
+            elements.push((i, ScreenplayElement::Character { name, extension, dual }));
+            i += 1;
+            let mut dialogue: Vec<&str> = Vec::new();
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                let line = lines[i].trim();
+                if line.starts_with('(') && line.ends_with(')') {
+                    if !dialogue.is_empty() {
+                        elements.push((i, ScreenplayElement::Dialogue { text: dialogue.join("\n") }));
+                        dialogue.clear();
+                    }
+                    elements.push((i, ScreenplayElement::Parenthetical { text: line.to_string() }));
+                } else {
+                    dialogue.push(line);
+                }
+                i += 1;
+            }
+            if !dialogue.is_empty() {
+                elements.push((i, ScreenplayElement::Dialogue { text: dialogue.join("\n") }));
+            }
+            continue;
+        } else {
+            if action.is_empty() {
+                action_start = i;
+            }
+            let raw = lines[i].trim_end();
+            action.push(raw.strip_prefix('!').unwrap_or(raw));
+            i += 1;
+            continue;
+        };
+        flush_action(&mut action, action_start, &mut elements);
+        elements.push((i, element));
+        i += 1;
+    }
+    flush_action(&mut action, action_start, &mut elements);
+    (title, elements)
+}
+
+/// Parse Fountain text into its title page and script elements. Notes and boneyard are dropped;
+/// emphasis markers stay in the text (see [`plain`]).
+pub fn parse(text: &str) -> Screenplay {
+    let (title_page, elements) = read(text);
+    Screenplay { title_page, elements: elements.into_iter().map(|(_, e)| e).collect() }
+}
+
+/// Where a scene heading is set: "INT. KITCHEN - NIGHT" gives "KITCHEN"
+pub fn location(heading: &str) -> String {
+    static PREFIX: OnceLock<Regex> = OnceLock::new();
+    let place = regex(&PREFIX, r"(?i)^(int\.?/ext|int|ext|est|i/e)\.?\s+").replace(heading, "");
+    let place = place.split(" - ").next().unwrap_or("");
+    plain(place).trim().to_string()
+}
+
+/// Speakers of a screenplay in order of first appearance, extensions left off and duplicates
+/// (ignoring case) dropped
+pub fn speakers(screenplay: &Screenplay) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for element in &screenplay.elements {
+        if let ScreenplayElement::Character { name, .. } = element {
+            let name = plain(name).trim().to_string();
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+fn planned(name: String, lines: &[&str]) -> Option<PlannedDoc> {
+    let start = lines.iter().position(|l| !l.trim().is_empty())?;
+    let end = lines.iter().rposition(|l| !l.trim().is_empty())? + 1;
+    let text = lines[start..end].join("\n");
+    Some(PlannedDoc { words: text.split_whitespace().count(), name, text, notes: None })
+}
+
+/// Split a Fountain file for import: each scene heading starts a doc named after it and each
+/// top-level section (`# Act One`) starts a group. The title page and anything before the first
+/// scene become a doc named `name`.
+pub fn plan(text: &str, name: &str) -> ManuscriptPlan {
+    let source = normalize_newlines(text);
+    let lines: Vec<&str> = source.split('\n').collect();
+    let (_, elements) = read(&source);
+    let mut plan = ManuscriptPlan::default();
+    let mut group: Option<PlannedGroup> = None;
+    let (mut doc_name, mut start) = (name.to_string(), 0);
+    let finish = |plan: &mut ManuscriptPlan, group: &mut Option<PlannedGroup>, doc_name: String, lines: &[&str]| {
+        if let Some(doc) = planned(doc_name, lines) {
+            match group {
+                Some(group) => group.docs.push(doc),
+                None => plan.docs.push(doc),
+            }
+        }
+    };
+    for (line, element) in elements {
+        match element {
+            ScreenplayElement::Section { depth: 1, text } => {
+                finish(&mut plan, &mut group, doc_name, &lines[start..line]);
+                plan.groups.extend(group.take());
+                group = Some(PlannedGroup { name: text.clone(), docs: Vec::new() });
+                (doc_name, start) = (text, line + 1);
+            }
+            ScreenplayElement::SceneHeading { text, .. } => {
+                finish(&mut plan, &mut group, doc_name, &lines[start..line]);
+                (doc_name, start) = (plain(&text), line);
+            }
+            _ => {}
+        }
+    }
+    finish(&mut plan, &mut group, doc_name, &lines[start..]);
+    plan.groups.extend(group);
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ScreenplayElement::*;
+
+    const SCRIPT: &str = "Title: Harbour\nCredit: Written by\nAuthor: A. Writer\nContact:\n    1 Quay Road\n    Porthleven\n\n\
+        # Act One\n\n= Mara comes home.\n\nINT. MARA'S KITCHEN - NIGHT #1#\n\nRain on the glass. [[check the weather]]\nA kettle *whistles*.\n\n\
+        MARA (V.O.)\n(quietly)\nNot again.\nNever again.\n\nINES ^\nAgain.\n\nCUT TO:\n\n.HARBOUR WALL\n\n!SILENCE.\n\n/* cut\n\nthis */\n\
+        @McCLANE\nYippee.\n\n>THE END<\n";
+
+    #[test]
+    fn parses_and_splits_fountain() {
+        let screenplay = parse(SCRIPT);
+        assert_eq!(screenplay.title_page[0], ("Title".to_string(), "Harbour".to_string()));
+        assert_eq!(screenplay.title_page[3], ("Contact".to_string(), "1 Quay Road\nPorthleven".to_string()));
+        assert_eq!(screenplay.elements, vec![
+            Section { depth: 1, text: "Act One".into() },
+            Synopsis { text: "Mara comes home.".into() },
+            SceneHeading { text: "INT. MARA'S KITCHEN - NIGHT".into(), number: Some("1".into()) },
+            Action { text: "Rain on the glass.\nA kettle *whistles*.".into() },
+            Character { name: "MARA".into(), extension: Some("V.O.".into()), dual: false },
+            Parenthetical { text: "(quietly)".into() },
+            Dialogue { text: "Not again.\nNever again.".into() },
+            Character { name: "INES".into(), extension: None, dual: true },
+            Dialogue { text: "Again.".into() },
+            Transition { text: "CUT TO:".into() },
+            SceneHeading { text: "HARBOUR WALL".into(), number: None },
+            Action { text: "SILENCE.".into() },
+            Character { name: "McCLANE".into(), extension: None, dual: false },
+            Dialogue { text: "Yippee.".into() },
+            Centered { text: "THE END".into() },
+        ]);
+        assert_eq!(location("INT. MARA'S KITCHEN - NIGHT"), "MARA'S KITCHEN");
+        assert_eq!(location("int./ext. *car* - day"), "car");
+        assert_eq!(speakers(&screenplay), vec!["MARA", "INES", "McCLANE"]);
+        // A prose doc does not turn into a title page or cues
+        assert_eq!(parse("Note: she left.\nThe END").elements, vec![Action { text: "Note: she left.\nThe END".into() }]);
+
+        let plan = plan(SCRIPT, "Harbour");
+        assert_eq!(plan.docs.len(), 1);
+        assert!(plan.docs[0].text.starts_with("Title: Harbour") && plan.docs[0].text.ends_with("Porthleven"));
+        assert_eq!(plan.groups[0].name, "Act One");
+        let docs: Vec<_> = plan.groups[0].docs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(docs, vec!["Act One", "INT. MARA'S KITCHEN - NIGHT", "HARBOUR WALL"]);
+        assert!(plan.groups[0].docs[1].text.starts_with("INT. MARA'S KITCHEN - NIGHT #1#\n\nRain"));
+        assert!(plan.groups[0].docs[1].text.ends_with("CUT TO:"));
+        assert!(plan.groups[0].docs[2].text.contains("/* cut\n\nthis */"), "boneyard kept in the doc text");
+    }
+}
@@ -0,0 +1,149 @@
+use crate::models::CompileOptions;
+use crate::services::compile::{Block, Manuscript};
+use crate::services::rich_text::Span;
+
+/// Built-in stylesheets for compiled HTML, by theme name
+pub const THEMES: &[(&str, &str)] = &[
+    ("classic", include_str!("../../themes/classic.css")),
+    ("modern", include_str!("../../themes/modern.css")),
+    ("manuscript", include_str!("../../themes/manuscript.css")),
+];
+
+/// Stylesheet of a theme; no theme means classic
+pub fn theme_css(theme: Option<&str>) -> anyhow::Result<&'static str> {
+    let name = theme.map(str::trim).filter(|t| !t.is_empty()).unwrap_or("classic");
+    THEMES
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, css)| *css)
+        .ok_or_else(|| {
+            let known: Vec<&str> = THEMES.iter().map(|(key, _)| *key).collect();
+            anyhow::anyhow!("unknown theme '{}' (expected one of: {})", name, known.join(", "))
+        })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn spans_html(spans: &[Span]) -> String {
+    spans.iter().map(|s| {
+        let text = escape(&s.text);
+        match (s.bold, s.italic) {
+            (false, false) => text,
+            (true, false) => format!("<strong>{}</strong>", text),
+            (false, true) => format!("<em>{}</em>", text),
+            (true, true) => format!("<strong><em>{}</em></strong>", text),
+        }
+    }).collect()
+}
+
+/// Nested contents list linking to the anchored (group) headings
+fn toc_html(blocks: &[Block]) -> Option<String> {
+    let mut html = String::new();
+    let mut depth = 0u8;
+    for block in blocks {
+        let Block::Heading { level, text, anchor: Some(anchor) } = block else { continue };
+        if *level > depth {
+            for _ in depth..*level {
+                html.push_str("<ol>");
+            }
+        } else {
+            html.push_str("</li>");
+            for _ in *level..depth {
+                html.push_str("</ol></li>");
+            }
+        }
+        html.push_str(&format!("\n<li><a href=\"#{}\">{}</a>", anchor, escape(text)));
+        depth = *level;
+    }
+    if depth == 0 {
+        return None;
+    }
+    for _ in 0..depth {
+        html.push_str("</li></ol>");
+    }
+    Some(format!("<nav class=\"toc\">\n<h2>Contents</h2>\n{}\n</nav>\n", html))
+}
+
+/// Write a compiled manuscript as one standalone HTML page: the theme's stylesheet inlined, an
+/// optional title page, a contents list of the groups and every group heading as a link target
+pub fn write(manuscript: &Manuscript, options: &CompileOptions) -> anyhow::Result<String> {
+    let mut css = theme_css(options.theme.as_deref())?.to_string();
+    if let Some(extra) = options.css.as_deref().filter(|c| !c.trim().is_empty()) {
+        css.push('\n');
+        css.push_str(extra);
+    }
+    let mut body = String::new();
+    if options.title_page.unwrap_or(true) {
+        body.push_str(&format!("<header class=\"title-page\">\n<h1>{}</h1>\n", escape(&manuscript.title)));
+        if let Some(subtitle) = &manuscript.subtitle {
+            body.push_str(&format!("<p class=\"subtitle\">{}</p>\n", escape(subtitle)));
+        }
+        body.push_str("</header>\n");
+    }
+    if let Some(toc) = toc_html(&manuscript.blocks) {
+        body.push_str(&toc);
+    }
+    body.push_str("<main>\n");
+    for block in &manuscript.blocks {
+        match block {
+            Block::Heading { level, text, anchor } => {
+                let id = anchor.as_ref().map(|a| format!(" id=\"{}\"", a)).unwrap_or_default();
+                body.push_str(&format!("<h{0}{1}>{2}</h{0}>\n", level, id, escape(text)));
+            }
+            Block::Paragraph(spans) => body.push_str(&format!("<p>{}</p>\n", spans_html(spans))),
+            Block::SceneBreak => body.push_str(&format!(
+                "<p class=\"scene-break\">{}</p>\n",
+                escape(manuscript.scene_separator.as_deref().unwrap_or("* * *"))
+            )),
+        }
+    }
+    body.push_str("</main>\n");
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(&manuscript.title),
+        css,
+        body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::rich_text::parse_markdown;
+
+    #[test]
+    fn html_links_contents_to_group_anchors() {
+        let heading = |level: u8, text: &str, anchor: Option<&str>| Block::Heading { level, text: text.into(), anchor: anchor.map(String::from) };
+        let manuscript = Manuscript {
+            title: "Tides & Stones".into(),
+            subtitle: None,
+            scene_separator: None,
+            blocks: vec![
+                heading(1, "Part One", Some("part-one")),
+                heading(2, "Arrival", Some("arrival")),
+                Block::Paragraph(parse_markdown("She came <late>, *soaked*.")),
+                heading(3, "Aside", None),
+                Block::SceneBreak,
+                heading(2, "Harbour", Some("harbour")),
+                heading(1, "Part Two", Some("part-two")),
+            ],
+        };
+        let html = write(&manuscript, &CompileOptions { theme: Some("modern".into()), css: Some("p { color: red; }".into()), ..Default::default() }).unwrap();
+        assert!(html.contains("<title>Tides &amp; Stones</title>"));
+        assert!(html.contains("<header class=\"title-page\">\n<h1>Tides &amp; Stones</h1>"));
+        assert!(html.contains("border-bottom: 2px solid") && html.contains("p { color: red; }"));
+        let toc = &html[html.find("<nav").unwrap()..html.find("</nav>").unwrap()];
+        assert_eq!(
+            toc.replace('\n', ""),
+            "<nav class=\"toc\"><h2>Contents</h2><ol><li><a href=\"#part-one\">Part One</a><ol><li><a href=\"#arrival\">Arrival</a></li>\
+             <li><a href=\"#harbour\">Harbour</a></li></ol></li><li><a href=\"#part-two\">Part Two</a></li></ol>"
+        );
+        assert!(html.contains("<h2 id=\"arrival\">Arrival</h2>"));
+        assert!(html.contains("<h3>Aside</h3>"));
+        assert!(html.contains("<p>She came &lt;late&gt;, <em>soaked</em>.</p>"));
+        assert!(html.contains("<p class=\"scene-break\">* * *</p>"));
+        assert!(write(&manuscript, &CompileOptions { theme: Some("neon".into()), ..Default::default() }).is_err());
+    }
+}
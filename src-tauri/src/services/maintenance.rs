@@ -0,0 +1,274 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{MaintenanceCheck, MaintenanceIssue, MaintenanceReport};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+
+/// (project_id, parent id) identifying a set of siblings
+type SiblingKey = (Option<i64>, Option<i64>);
+
+fn issue(check: MaintenanceCheck, table: &str, row_id: Option<i64>, message: String, repaired: bool) -> MaintenanceIssue {
+    MaintenanceIssue { check, table_name: table.to_string(), row_id, message, repaired }
+}
+
+/// Check the database for corruption and structural drift, optionally repairing what can be repaired,
+/// then optionally VACUUM and ANALYZE. Checks run in an order where earlier repairs feed later checks
+/// (e.g. groups moved to the root are renumbered by the sort order check).
+pub fn run(pool: &DbPool, repair: bool, optimize: bool) -> anyhow::Result<MaintenanceReport> {
+    let conn = get_conn(pool)?;
+    let mut issues = integrity(&conn)?;
+
+    let tx = conn.unchecked_transaction()?;
+    issues.extend(missing_parents(&tx, repair)?);
+    issues.extend(foreign_groups(&tx, repair)?);
+    issues.extend(missing_projects(&tx, repair)?);
+    issues.extend(foreign_keys(&tx, repair)?);
+    issues.extend(orphan_timelines(&tx, repair)?);
+    issues.extend(sort_orders(&tx, "docs", "doc_group_id", repair)?);
+    issues.extend(sort_orders(&tx, "doc_groups", "parent_id", repair)?);
+    tx.commit()?;
+
+    if optimize {
+        conn.execute_batch("VACUUM; ANALYZE;")?;
+    }
+    Ok(MaintenanceReport { issues, optimized: optimize })
+}
+
+/// `PRAGMA integrity_check`; corruption is reported but never repaired here
+fn integrity(conn: &Connection) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter(|r| r != "ok")
+        .map(|r| issue(MaintenanceCheck::Integrity, "database", None, r, false))
+        .collect())
+}
+
+/// Groups whose parent is missing or belongs to another project; repaired by moving them to the root
+fn missing_parents(conn: &Connection, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare(
+        "SELECT g.id, g.name, g.parent_id FROM doc_groups g
+         WHERE g.parent_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM doc_groups p WHERE p.id = g.parent_id AND p.project_id IS g.project_id)
+         ORDER BY g.id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut issues = Vec::new();
+    for (id, name, parent_id) in rows {
+        if repair {
+            conn.execute("UPDATE doc_groups SET parent_id = NULL WHERE id = ?1", [id])?;
+        }
+        let message = format!("group '{}' points at missing parent {}", name.unwrap_or_default(), parent_id);
+        issues.push(issue(MaintenanceCheck::MissingParent, "doc_groups", Some(id), message, repair));
+    }
+    Ok(issues)
+}
+
+/// Docs filed in a group of another project; repaired by moving them to their project's root
+fn foreign_groups(conn: &Connection, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.name, g.id, g.project_id FROM docs d
+         JOIN doc_groups g ON g.id = d.doc_group_id
+         WHERE g.project_id IS NOT d.project_id
+         ORDER BY d.id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)?, row.get::<_, Option<i64>>(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut issues = Vec::new();
+    for (id, name, group_id, group_project) in rows {
+        if repair {
+            conn.execute("UPDATE docs SET doc_group_id = NULL WHERE id = ?1", [id])?;
+        }
+        let owner = group_project.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string());
+        let message = format!("doc '{}' is in group {} of project {}", name.unwrap_or_default(), group_id, owner);
+        issues.push(issue(MaintenanceCheck::ForeignGroup, "docs", Some(id), message, repair));
+    }
+    Ok(issues)
+}
+
+/// `doc_groups.project_id` has no foreign key, so groups can outlive their project; repaired by deleting them
+fn missing_projects(conn: &Connection, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, project_id FROM doc_groups
+         WHERE project_id IS NULL OR project_id NOT IN (SELECT id FROM projects)
+         ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<i64>>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut issues = Vec::new();
+    for (id, name, project_id) in rows {
+        let project = project_id.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string());
+        let message = format!("group '{}' belongs to missing project {}", name.unwrap_or_default(), project);
+        issues.push(issue(MaintenanceCheck::MissingProject, "doc_groups", Some(id), message, repair));
+    }
+    if repair {
+        // Child groups of a deleted group cascade, so delete by id rather than one-by-one from a stale list
+        conn.execute(
+            "DELETE FROM doc_groups WHERE project_id IS NULL OR project_id NOT IN (SELECT id FROM projects)",
+            [],
+        )?;
+    }
+    Ok(issues)
+}
+
+/// `PRAGMA foreign_key_check`; each dangling row is repaired the way its foreign key would have handled
+/// the parent's deletion (SET NULL clears the column, anything else deletes the row).
+/// doc_groups is left to the missing-parent check, which keeps the subtree instead of cascading.
+fn foreign_keys(conn: &Connection, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut issues = Vec::new();
+    for (table, row_id, parent, fk_id) in rows {
+        if table == "doc_groups" {
+            continue;
+        }
+        let (column, on_delete): (String, String) = conn.query_row(
+            &format!("SELECT \"from\", on_delete FROM pragma_foreign_key_list('{}') WHERE id = ?1", table),
+            [fk_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let repaired = match row_id {
+            Some(rid) if repair => {
+                if on_delete.eq_ignore_ascii_case("SET NULL") {
+                    conn.execute(&format!("UPDATE {} SET {} = NULL WHERE rowid = ?1", table, column), [rid])?;
+                } else {
+                    conn.execute(&format!("DELETE FROM {} WHERE rowid = ?1", table), [rid])?;
+                }
+                true
+            }
+            _ => false,
+        };
+        let message = format!("{}.{} refers to a missing {} row", table, column, parent);
+        issues.push(issue(MaintenanceCheck::ForeignKey, &table, row_id, message, repaired));
+    }
+    Ok(issues)
+}
+
+/// Timeline rows whose entity no longer exists
+fn orphan_timelines(conn: &Connection, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let orphans = crate::services::timelines::find_orphans(conn)?;
+    if repair && !orphans.is_empty() {
+        crate::services::timelines::delete_orphans(conn)?;
+    }
+    Ok(orphans
+        .into_iter()
+        .map(|t| {
+            let message = format!("timeline for {} {} has no owner", t.entity_type, t.entity_id);
+            issue(MaintenanceCheck::OrphanTimeline, "timelines", Some(t.id), message, repair)
+        })
+        .collect())
+}
+
+/// Siblings (same project and parent column) should be numbered 0..n without duplicates or gaps;
+/// repaired by renumbering in the current order, ties broken by id
+fn sort_orders(conn: &Connection, table: &str, parent_column: &str, repair: bool) -> anyhow::Result<Vec<MaintenanceIssue>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, project_id, {parent}, sort_order FROM {table} ORDER BY project_id, {parent}, sort_order, id",
+        parent = parent_column,
+        table = table
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Option<i64>>(2)?, row.get::<_, i64>(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut siblings: BTreeMap<SiblingKey, Vec<(i64, i64)>> = BTreeMap::new();
+    for (id, project_id, parent_id, sort_order) in rows {
+        siblings.entry((project_id, parent_id)).or_default().push((id, sort_order));
+    }
+
+    let mut issues = Vec::new();
+    for ((project_id, parent_id), items) in siblings {
+        if items.iter().enumerate().all(|(i, (_, order))| *order == i as i64) {
+            continue;
+        }
+        let mut seen = std::collections::HashSet::new();
+        let kind = if items.iter().all(|(_, order)| seen.insert(*order)) { "gapped" } else { "duplicate" };
+        let parent = parent_id.map(|p| format!("parent {}", p)).unwrap_or_else(|| "the root".to_string());
+        let message = format!(
+            "{} sort orders among {} {} under {} of project {}",
+            kind,
+            items.len(),
+            table,
+            parent,
+            project_id.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string())
+        );
+        if repair {
+            for (i, (id, _)) in items.iter().enumerate() {
+                conn.execute(&format!("UPDATE {} SET sort_order = ?1 WHERE id = ?2", table), rusqlite::params![i as i64, id])?;
+            }
+        }
+        issues.push(issue(MaintenanceCheck::SortOrder, table, parent_id, message, repair));
+    }
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    #[test]
+    fn reports_then_repairs_structural_drift() {
+        let manager = SqliteConnectionManager::file("file:memmaintenance?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+
+        // Foreign keys off so the dangling rows can be written at all
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO projects (id, name) VALUES (1, 'A'), (2, 'B');
+             INSERT INTO doc_groups (id, project_id, name, parent_id, sort_order) VALUES
+                (1, 1, 'Part', NULL, 0), (2, 2, 'Elsewhere', NULL, 0), (3, 1, 'Stray', 99, 0), (4, 7, 'Lost', NULL, 0);
+             INSERT INTO docs (id, project_id, path, name, doc_group_id, sort_order) VALUES
+                (1, 1, '', 'One', 1, 0), (2, 1, '', 'Two', 1, 0), (3, 1, '', 'Three', 1, 5), (4, 1, '', 'Moved', 2, 0);
+             INSERT INTO timelines (entity_type, entity_id, project_id, start_date) VALUES ('doc', 42, 1, '2025');",
+        ).unwrap();
+        drop(conn);
+
+        let report = run(&pool, false, false).unwrap();
+        let checks: Vec<MaintenanceCheck> = report.issues.iter().map(|i| i.check).collect();
+        assert!(checks.contains(&MaintenanceCheck::MissingParent));
+        assert!(checks.contains(&MaintenanceCheck::ForeignGroup));
+        assert!(checks.contains(&MaintenanceCheck::MissingProject));
+        assert!(checks.contains(&MaintenanceCheck::OrphanTimeline));
+        assert!(checks.contains(&MaintenanceCheck::SortOrder));
+        assert!(report.issues.iter().all(|i| !i.repaired));
+
+        let repaired = run(&pool, true, true).unwrap();
+        assert!(repaired.optimized);
+        assert!(repaired.issues.iter().all(|i| i.repaired));
+
+        let conn = pool.get().unwrap();
+        let orders: Vec<i64> = conn
+            .prepare("SELECT sort_order FROM docs WHERE doc_group_id = 1 ORDER BY sort_order").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(orders, vec![0, 1, 2]);
+        let moved: Option<i64> = conn.query_row("SELECT doc_group_id FROM docs WHERE id = 4", [], |r| r.get(0)).unwrap();
+        assert_eq!(moved, None);
+        let lost: i64 = conn.query_row("SELECT COUNT(*) FROM doc_groups WHERE id = 4", [], |r| r.get(0)).unwrap();
+        assert_eq!(lost, 0);
+        drop(conn);
+
+        assert!(run(&pool, false, false).unwrap().issues.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::db::DbPool;
+use crate::models::{HeadingImportOptions, ManuscriptPlan, ManuscriptSplitOptions, PlannedDoc, PlannedGroup};
+use anyhow::Context;
+use regex::Regex;
+use std::path::Path;
+
+fn compile(pattern: Option<&str>, what: &str) -> anyhow::Result<Option<Regex>> {
+    pattern
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| Regex::new(p).with_context(|| format!("invalid {} pattern", what)))
+        .transpose()
+}
+
+/// Chapter name from its heading line: markdown hashes and surrounding space removed
+fn heading_name(line: &str) -> String {
+    line.trim().trim_start_matches('#').trim().to_string()
+}
+
+fn planned(name: String, lines: &[&str]) -> Option<PlannedDoc> {
+    let start = lines.iter().position(|l| !l.trim().is_empty())?;
+    let end = lines.iter().rposition(|l| !l.trim().is_empty())? + 1;
+    let text = lines[start..end].join("\n");
+    Some(PlannedDoc { words: text.split_whitespace().count(), name, text, notes: None })
+}
+
+/// Scenes collected for the chapter being read
+struct Chapter<'t> {
+    name: Option<String>,
+    scenes: Vec<Vec<&'t str>>,
+}
+
+impl Chapter<'_> {
+    fn into_docs(self) -> Vec<PlannedDoc> {
+        let scenes: Vec<_> = self.scenes.iter().filter(|s| s.iter().any(|l| !l.trim().is_empty())).collect();
+        let single = scenes.len() == 1;
+        scenes.into_iter().enumerate().filter_map(|(i, lines)| {
+            let name = match (&self.name, single) {
+                (Some(chapter), true) => chapter.clone(),
+                _ => format!("Scene {}", i + 1),
+            };
+            planned(name, lines)
+        }).collect()
+    }
+}
+
+/// Work out the groups and docs a manuscript splits into, without touching the database.
+/// Each chapter heading starts a group; scene breaks and long blank-line runs start a new doc.
+/// Text before the first heading becomes loose docs. Without any heading, all scenes go into
+/// one group named `name`.
+pub fn plan(text: &str, name: &str, options: &ManuscriptSplitOptions) -> anyhow::Result<ManuscriptPlan> {
+    let chapter_re = compile(options.chapter_pattern.as_deref(), "chapter")?;
+    let scene_re = compile(options.scene_pattern.as_deref(), "scene break")?;
+    let blank_run = options.blank_lines.filter(|&n| n > 0);
+
+    let mut done: Vec<Chapter> = Vec::new();
+    let mut current = Chapter { name: None, scenes: vec![Vec::new()] };
+    let mut blanks = 0usize;
+    for line in text.lines() {
+        if chapter_re.as_ref().is_some_and(|re| re.is_match(line)) {
+            let mut name = heading_name(line);
+            if name.is_empty() {
+                name = format!("Chapter {}", done.iter().filter(|c| c.name.is_some()).count() + 1);
+            }
+            done.push(std::mem::replace(&mut current, Chapter { name: Some(name), scenes: vec![Vec::new()] }));
+            blanks = 0;
+            continue;
+        }
+        if scene_re.as_ref().is_some_and(|re| re.is_match(line.trim())) {
+            current.scenes.push(Vec::new());
+            blanks = 0;
+            continue;
+        }
+        if line.trim().is_empty() {
+            blanks += 1;
+        } else {
+            if blank_run.is_some_and(|n| blanks >= n) {
+                current.scenes.push(Vec::new());
+            }
+            blanks = 0;
+        }
+        if let Some(scene) = current.scenes.last_mut() {
+            scene.push(line);
+        }
+    }
+    done.push(current);
+
+    let mut out = ManuscriptPlan::default();
+    let mut chapters = done.into_iter();
+    if let Some(preamble) = chapters.next() {
+        out.docs = preamble.into_docs();
+    }
+    for chapter in chapters {
+        let name = chapter.name.clone().unwrap_or_default();
+        out.groups.push(PlannedGroup { name, docs: chapter.into_docs() });
+    }
+    if out.groups.is_empty() && !out.docs.is_empty() {
+        out.groups.push(PlannedGroup { name: name.to_string(), docs: std::mem::take(&mut out.docs) });
+    }
+    Ok(out)
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+/// Whether a file is one the file and folder imports pick up (.txt, .docx or .odt)
+pub fn is_importable(path: &Path) -> bool {
+    has_extension(path, "txt") || has_extension(path, "docx") || has_extension(path, "odt")
+}
+
+/// Plan importing one file: Word and OpenDocument files are split by the heading options, any
+/// other file is read as a single text doc named after it. Also returns the format or text
+/// encoding it was read as.
+pub fn plan_file(path: &Path, headings: &HeadingImportOptions) -> anyhow::Result<(ManuscriptPlan, &'static str)> {
+    if has_extension(path, "docx") {
+        return Ok((crate::services::docx::read_docx_file(path, headings)?, "docx"));
+    }
+    if has_extension(path, "odt") {
+        return Ok((crate::services::odt::read_odt_file(path, headings)?, "odt"));
+    }
+    let decoded = crate::services::text_decode::read_text_file(path)?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported").to_string();
+    let doc = PlannedDoc { words: decoded.text.split_whitespace().count(), name, text: decoded.text, notes: None };
+    Ok((ManuscriptPlan { docs: vec![doc], groups: Vec::new() }, decoded.encoding))
+}
+
+/// Create the planned tree under `parent_group_id` (None = project root); returns the docs created
+pub fn apply(pool: &DbPool, project_id: i64, parent_group_id: Option<i64>, plan: &ManuscriptPlan) -> anyhow::Result<usize> {
+    let mut created = 0usize;
+    let mut add = |group_id: Option<i64>, doc: &PlannedDoc| -> anyhow::Result<()> {
+        let d = crate::services::docs::create_doc(pool, project_id, &doc.name, group_id)?;
+        crate::services::docs::update_doc(pool, d.id, &doc.text)?;
+        if let Some(notes) = doc.notes.as_deref() {
+            crate::services::docs::update_doc_notes(pool, d.id, notes)?;
+        }
+        created += 1;
+        Ok(())
+    };
+    for doc in &plan.docs {
+        add(parent_group_id, doc)?;
+    }
+    for group in &plan.groups {
+        let g = crate::services::doc_groups::create_doc_group(pool, project_id, &group.name, parent_group_id)?;
+        for doc in &group.docs {
+            add(Some(g.id), doc)?;
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANUSCRIPT: &str = "The Harbour\nby A. Writer\n\n# Chapter 1: Arrival\n\nMara ran.\n\n***\n\nInes hid.\n\n\n\nRain fell.\n\n# Chapter 2\n\nDawn.\n";
+
+    #[test]
+    fn chapters_become_groups_and_scenes_docs() {
+        let options = ManuscriptSplitOptions {
+            chapter_pattern: Some("^# ".into()),
+            scene_pattern: Some(r"^\*\*\*$".into()),
+            blank_lines: Some(3),
+        };
+        let plan = plan(MANUSCRIPT, "Harbour", &options).unwrap();
+        let names: Vec<_> = plan.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Chapter 1: Arrival", "Chapter 2"]);
+        let scenes: Vec<_> = plan.groups[0].docs.iter().map(|d| (d.name.as_str(), d.text.as_str())).collect();
+        assert_eq!(scenes, vec![("Scene 1", "Mara ran."), ("Scene 2", "Ines hid."), ("Scene 3", "Rain fell.")]);
+        // A chapter without scene breaks is one doc named after it
+        assert_eq!(plan.groups[1].docs[0].name, "Chapter 2");
+    }
+
+    #[test]
+    fn text_before_the_first_chapter_stays_at_the_root() {
+        let options = ManuscriptSplitOptions { chapter_pattern: Some("^# ".into()), ..Default::default() };
+        let plan = plan(MANUSCRIPT, "Harbour", &options).unwrap();
+        assert_eq!(plan.docs.len(), 1);
+        assert_eq!(plan.docs[0].text, "The Harbour\nby A. Writer");
+    }
+
+    #[test]
+    fn without_separators_the_file_is_one_doc() {
+        let whole = plan(MANUSCRIPT, "Harbour", &ManuscriptSplitOptions::default()).unwrap();
+        assert!(whole.docs.is_empty());
+        assert_eq!((whole.groups[0].name.as_str(), whole.groups[0].docs.len()), ("Harbour", 1));
+        assert_eq!(whole.groups[0].docs[0].words, 20);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let options = ManuscriptSplitOptions { chapter_pattern: Some("(".into()), ..Default::default() };
+        assert!(plan(MANUSCRIPT, "Harbour", &options).is_err());
+    }
+}
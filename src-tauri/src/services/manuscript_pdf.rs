@@ -0,0 +1,330 @@
+use crate::models::{CompileOptions, PageSize};
+use crate::services::compile::{Block, Manuscript};
+use crate::services::pdf::{self, Document, Font};
+use crate::services::rich_text::Span;
+
+/// Page width and height in points
+fn dimensions(size: PageSize) -> (f32, f32) {
+    match size {
+        PageSize::A4 => (595.28, 841.89),
+        PageSize::Letter => (612.0, 792.0),
+        PageSize::A5 => (419.53, 595.28),
+    }
+}
+
+const HEAD_SIZE: f32 = 9.0;
+
+/// What the running head of a page needs to know
+struct PageInfo {
+    chapter: Option<String>,
+    // Chapter openings and the title page carry no running head
+    opening: bool,
+}
+
+/// Flows blocks onto pages top to bottom, starting a page when the current one is full
+struct Layout {
+    doc: Document,
+    pages: Vec<PageInfo>,
+    page: usize,
+    // Baseline of the last line set; the next line goes one leading below
+    y: f32,
+    left: f32,
+    right: f32,
+    top: f32,
+    bottom: f32,
+    size: f32,
+    leading: f32,
+    chapter: Option<String>,
+    blank: bool,
+}
+
+impl Layout {
+    fn new(doc: Document) -> Self {
+        let (w, h) = (doc.width, doc.height);
+        let size = if w < 500.0 { 10.0 } else { 11.0 };
+        Layout {
+            doc,
+            pages: Vec::new(),
+            page: 0,
+            y: 0.0,
+            left: w * 0.12,
+            right: w * 0.88,
+            top: h * 0.91,
+            bottom: h * 0.09,
+            size,
+            leading: size * 1.4,
+            chapter: None,
+            blank: true,
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.page = self.doc.add_page();
+        self.pages.push(PageInfo { chapter: self.chapter.clone(), opening: false });
+        self.y = self.top;
+        self.blank = true;
+    }
+
+    /// Move down for a line of `leading`, starting a page first when it would not fit
+    fn advance(&mut self, leading: f32) {
+        if self.y - leading < self.bottom {
+            self.new_page();
+        }
+        self.y -= leading;
+        self.blank = false;
+    }
+
+    /// Start a page unless `height` points still fit on this one
+    fn keep(&mut self, height: f32) {
+        if self.y - height < self.bottom {
+            self.new_page();
+        }
+    }
+
+    fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    /// Plain text centered between the margins, wrapped as needed
+    fn centered(&mut self, text: &str, font: Font, size: f32, leading: f32) {
+        let space = pdf::text_width(font, size, " ");
+        let words = pdf::words(&[(font, text)], size);
+        for line in pdf::wrap(words, self.width(), self.width(), space) {
+            self.advance(leading);
+            let x = self.left + (self.width() - pdf::line_width(&line, space)) / 2.0;
+            self.doc.page(self.page).words(&line, size, x, self.y, space);
+        }
+    }
+
+    fn title_page(&mut self, manuscript: &Manuscript) {
+        self.new_page();
+        self.pages[self.page].opening = true;
+        self.y = self.doc.height * 0.66;
+        self.centered(&manuscript.title, Font::TimesBold, 26.0, 32.0);
+        if let Some(subtitle) = &manuscript.subtitle {
+            self.y -= 12.0;
+            self.centered(subtitle, Font::TimesItalic, 14.0, 18.0);
+        }
+    }
+
+    /// Level 1 opens a chapter on a fresh page, a fifth of the way down; level 2 is centered and
+    /// deeper levels sit on the left. Every heading keeps a few lines of text after it.
+    fn heading(&mut self, level: u8, text: &str) {
+        match level {
+            1 => {
+                if !self.blank {
+                    self.new_page();
+                }
+                self.chapter = Some(text.to_string());
+                self.pages[self.page] = PageInfo { chapter: self.chapter.clone(), opening: true };
+                self.y = self.top - (self.top - self.bottom) * 0.2;
+                self.centered(text, Font::TimesBold, self.size * 1.8, self.size * 2.2);
+                self.y -= self.leading * 2.0;
+            }
+            2 => {
+                self.keep(self.leading * 6.0);
+                if !self.blank {
+                    self.y -= self.leading * 1.5;
+                }
+                self.centered(text, Font::TimesBold, self.size * 1.35, self.size * 1.7);
+                self.y -= self.leading;
+            }
+            _ => {
+                self.keep(self.leading * 5.0);
+                if !self.blank {
+                    self.y -= self.leading;
+                }
+                let font = Font::TimesBoldItalic;
+                let space = pdf::text_width(font, self.size, " ");
+                let words = pdf::words(&[(font, text)], self.size);
+                for line in pdf::wrap(words, self.width(), self.width(), space) {
+                    self.advance(self.leading);
+                    self.doc.page(self.page).words(&line, self.size, self.left, self.y, space);
+                }
+                self.y -= self.leading * 0.5;
+            }
+        }
+    }
+
+    /// Justified body text; the first line is indented unless the paragraph follows a heading
+    /// or a scene break
+    fn paragraph(&mut self, spans: &[Span], indent: bool) {
+        let runs: Vec<(Font, &str)> = spans.iter().map(|s| (Font::times(s.bold, s.italic), s.text.as_str())).collect();
+        let words = pdf::words(&runs, self.size);
+        let space = pdf::text_width(Font::Times, self.size, " ");
+        let indent = if indent { self.size * 1.5 } else { 0.0 };
+        let lines = pdf::wrap(words, self.width() - indent, self.width(), space);
+        let count = lines.len();
+        for (i, line) in lines.into_iter().enumerate() {
+            self.advance(self.leading);
+            let x = if i == 0 { self.left + indent } else { self.left };
+            let available = self.right - x;
+            let gap = if i + 1 < count && line.len() > 1 {
+                space + (available - pdf::line_width(&line, space)) / (line.len() - 1) as f32
+            } else {
+                space
+            };
+            self.doc.page(self.page).words(&line, self.size, x, self.y, gap);
+        }
+    }
+
+    fn scene_break(&mut self, separator: &str) {
+        self.advance(self.leading);
+        self.centered(separator, Font::Times, self.size, self.leading);
+        self.y -= self.leading * 0.5;
+    }
+
+    /// Page numbers on every page after the front matter, counted from `first`; running heads
+    /// carry the book title on even pages and the chapter on odd ones
+    fn running_heads(&mut self, title: &str, first: usize) {
+        let (w, h) = (self.doc.width, self.doc.height);
+        for index in first..self.pages.len() {
+            let number = index - first + 1;
+            let label = number.to_string();
+            let x = (w - pdf::text_width(Font::Times, HEAD_SIZE, &label)) / 2.0;
+            self.doc.page(index).text(Font::Times, HEAD_SIZE, x, h * 0.05, &label);
+            let info = &self.pages[index];
+            if info.opening {
+                continue;
+            }
+            let head = match &info.chapter {
+                Some(chapter) if number % 2 == 1 => chapter.clone(),
+                _ => title.to_string(),
+            };
+            let x = (w - pdf::text_width(Font::TimesItalic, HEAD_SIZE, &head)) / 2.0;
+            self.doc.page(index).text(Font::TimesItalic, HEAD_SIZE, x, h * 0.95, &head);
+        }
+    }
+}
+
+/// Lay out a compiled manuscript as a printable PDF: an optional title page, chapters (level 1
+/// headings) opening on a new page, justified Times body text, running heads and page numbers
+pub fn write(manuscript: &Manuscript, options: &CompileOptions) -> Vec<u8> {
+    let (w, h) = dimensions(options.page_size);
+    let mut layout = Layout::new(Document::new(w, h, &manuscript.title));
+    if options.title_page.unwrap_or(true) {
+        layout.title_page(manuscript);
+    }
+    let first = layout.doc.page_count();
+    layout.new_page();
+    let mut indent = false;
+    for block in &manuscript.blocks {
+        match block {
+            Block::Heading { level, text, .. } => {
+                layout.heading(*level, text);
+                indent = false;
+            }
+            Block::Paragraph(spans) => {
+                layout.paragraph(spans, indent);
+                indent = true;
+            }
+            Block::SceneBreak => {
+                layout.scene_break(manuscript.scene_separator.as_deref().unwrap_or("*   *   *"));
+                indent = false;
+            }
+        }
+    }
+    layout.running_heads(&manuscript.title, first);
+    layout.doc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::rich_text::parse_markdown;
+    use std::io::Read;
+
+    fn page_streams(pdf: &[u8]) -> Vec<String> {
+        let mut streams = Vec::new();
+        let mut rest = pdf;
+        while let Some(start) = rest.windows(10).position(|w| w == b">>\nstream\n") {
+            let after = &rest[start + 10..];
+            let end = after.windows(10).position(|w| w == b"\nendstream").unwrap();
+            let mut text = String::new();
+            flate2::read::ZlibDecoder::new(&after[..end]).read_to_string(&mut text).unwrap();
+            streams.push(text);
+            rest = &after[end..];
+        }
+        streams
+    }
+
+    #[test]
+    fn pdf_has_title_page_chapters_and_running_heads() {
+        let long = "The tide came in over the flats while the gulls argued overhead. ".repeat(12);
+        let mut blocks = vec![Block::Heading { level: 1, text: "Part One".into(), anchor: Some("part-one".into()) }];
+        for _ in 0..40 {
+            blocks.push(Block::Paragraph(parse_markdown(&format!("{}*Still* she waited.", long))));
+        }
+        blocks.push(Block::Heading { level: 1, text: "Part Two".into(), anchor: Some("part-two".into()) });
+        blocks.push(Block::Paragraph(parse_markdown("Caf\u{e9} (closed) \u{201c}again\u{201d}.")));
+        let manuscript = Manuscript { title: "Harbour".into(), subtitle: Some("A novel".into()), blocks, scene_separator: None };
+
+        let bytes = write(&manuscript, &CompileOptions::default());
+        assert!(bytes.starts_with(b"%PDF-1.4"));
+        assert!(bytes.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("/BaseFont /Times-Roman"));
+        assert!(text.contains("/MediaBox [0 0 595.28 841.89]"));
+
+        let pages = page_streams(&bytes);
+        assert!(pages.len() > 4);
+        // Title page: no page number, title and subtitle
+        assert!(pages[0].contains("(Harbour)") && pages[0].contains("(novel)"));
+        assert!(!pages[0].contains("(1)"));
+        // Chapter opening page: number but no running head
+        assert!(pages[1].contains("(1) Tj") && pages[1].contains("(Part) Tj"));
+        assert!(!pages[1].contains("(Harbour) Tj"));
+        // Following pages alternate title and chapter heads
+        assert!(pages[2].contains("(2) Tj") && pages[2].contains("(Harbour) Tj"));
+        assert!(pages[3].contains("(3) Tj") && pages[3].contains("(Part One) Tj"));
+        assert!(pages[1].contains("/F3 11.0 Tf"), "italic span set in Times-Italic");
+        // The second chapter starts its own page; WinAnsi bytes are octal escaped
+        let last = pages.last().unwrap();
+        assert!(last.contains("(Two) Tj"));
+        assert!(last.contains("(Caf\\351) Tj") && last.contains("(\\(closed\\)) Tj") && last.contains("(\\223again\\224.) Tj"));
+    }
+
+    /// Distinct baselines of body text (Times Roman at `size`) on a page
+    fn body_lines(page: &str, size: &str) -> usize {
+        let font = format!("/F1 {} Tf", size);
+        page.lines()
+            .filter(|l| l.contains(&font))
+            .filter_map(|l| l.split_whitespace().nth(5).map(str::to_string))
+            .collect::<std::collections::BTreeSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn paragraphs_fill_each_page_before_flowing_onto_the_next() {
+        let blocks = (0..12).map(|_| Block::Paragraph(parse_markdown(&"Gulls argued over the flats. ".repeat(30)))).collect();
+        let manuscript = Manuscript { title: "Flats".into(), subtitle: None, blocks, scene_separator: None };
+        let options = CompileOptions { title_page: Some(false), page_size: PageSize::A5, ..CompileOptions::default() };
+        let pages = page_streams(&write(&manuscript, &options));
+
+        let layout = Layout::new(Document::new(419.53, 595.28, ""));
+        let lines_per_page = ((layout.top - layout.bottom) / layout.leading) as usize;
+        assert!(pages.len() >= 3);
+        for page in &pages[..pages.len() - 1] {
+            assert_eq!(body_lines(page, "10.0"), lines_per_page);
+        }
+        assert!(body_lines(pages.last().unwrap(), "10.0") <= lines_per_page);
+        assert!(pages.last().unwrap().contains(&format!("({}) Tj", pages.len())));
+    }
+
+    #[test]
+    fn subheadings_near_the_bottom_move_to_the_next_page() {
+        let layout = Layout::new(Document::new(419.53, 595.28, ""));
+        let lines_per_page = ((layout.top - layout.bottom) / layout.leading) as usize;
+        let mut blocks: Vec<Block> = (0..lines_per_page - 3).map(|_| Block::Paragraph(parse_markdown("Line."))).collect();
+        blocks.push(Block::Heading { level: 2, text: "Storm".into(), anchor: None });
+        blocks.push(Block::Paragraph(parse_markdown("Rain.")));
+        let manuscript = Manuscript { title: "Flats".into(), subtitle: None, blocks, scene_separator: None };
+        let options = CompileOptions { title_page: Some(false), page_size: PageSize::A5, ..CompileOptions::default() };
+        let pages = page_streams(&write(&manuscript, &options));
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(body_lines(&pages[0], "10.0"), lines_per_page - 3);
+        assert!(!pages[0].contains("(Storm) Tj"));
+        assert!(pages[1].contains("(Storm) Tj") && pages[1].contains("(Rain.) Tj"));
+    }
+}
@@ -0,0 +1,441 @@
+use crate::models::{HeadingImportOptions, ManuscriptPlan};
+use crate::services::compile::{Block, Manuscript, MAX_HEADING};
+use crate::services::rich_text::{self, Comment, Paragraph, Span};
+use anyhow::Context;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+const NAMESPACES: &str = r#"xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/""#;
+
+/// Bold/italic set by a style, and the style it inherits the rest from
+#[derive(Debug, Default, Clone)]
+struct StyleFormat {
+    bold: Option<bool>,
+    italic: Option<bool>,
+    parent: Option<String>,
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// Bold and italic of a style, following parent styles for what it leaves unset
+fn resolve(styles: &HashMap<String, StyleFormat>, name: &str) -> (Option<bool>, Option<bool>) {
+    let (mut bold, mut italic) = (None, None);
+    let mut next = Some(name.to_string());
+    // Bounded walk in case of a parent cycle
+    for _ in 0..16 {
+        let Some(style) = next.as_ref().and_then(|n| styles.get(n)) else { break };
+        bold = bold.or(style.bold);
+        italic = italic.or(style.italic);
+        next = style.parent.clone();
+    }
+    (bold, italic)
+}
+
+/// Named and automatic styles of styles.xml or content.xml
+fn parse_styles(xml: &str, styles: &mut HashMap<String, StyleFormat>) -> anyhow::Result<()> {
+    let mut reader = Reader::from_str(xml);
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event().context("parsing OpenDocument styles")? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"style" => {
+                    if let Some(name) = attr(&e, b"name") {
+                        let entry = styles.entry(name.clone()).or_default();
+                        entry.parent = attr(&e, b"parent-style-name");
+                        current = Some(name);
+                    }
+                }
+                b"text-properties" => {
+                    if let Some(style) = current.as_ref().and_then(|n| styles.get_mut(n)) {
+                        if let Some(weight) = attr(&e, b"font-weight") {
+                            style.bold = Some(weight == "bold" || weight.parse::<u32>().is_ok_and(|w| w >= 600));
+                        }
+                        if let Some(slant) = attr(&e, b"font-style") {
+                            style.italic = Some(slant == "italic" || slant == "oblique");
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"style" => current = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Paragraphs and headings of content.xml, with annotations collected as comments. Footnotes,
+/// frames and tracked deletions are left out.
+fn parse_content(xml: &str, styles: &HashMap<String, StyleFormat>) -> anyhow::Result<(Vec<Paragraph>, Vec<Comment>)> {
+    let mut reader = Reader::from_str(xml);
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut comments: Vec<Comment> = Vec::new();
+    let mut current: Option<Paragraph> = None;
+    // Formatting of the enclosing paragraph and spans
+    let mut formats: Vec<(bool, bool)> = Vec::new();
+    let mut in_body = false;
+    let mut skip_depth = 0usize;
+    // Annotation being read, and whether we are in its author element
+    let mut annotation: Option<Comment> = None;
+    let mut in_creator = false;
+    // Named annotations whose anchored range is still open, by index into `comments`
+    let mut open_ranges: HashMap<String, usize> = HashMap::new();
+    loop {
+        let event = reader.read_event().context("parsing content.xml")?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = e.local_name();
+                let name = name.as_ref();
+                if name == b"text" && !empty && e.name().as_ref() == b"office:text" {
+                    in_body = true;
+                    continue;
+                }
+                if !in_body {
+                    continue;
+                }
+                if skip_depth > 0 || matches!(name, b"note" | b"frame" | b"tracked-changes") {
+                    if !empty {
+                        skip_depth += 1;
+                    }
+                    continue;
+                }
+                if annotation.is_some() {
+                    if name == b"creator" && !empty {
+                        in_creator = true;
+                    }
+                    if name == b"p" {
+                        if let Some(a) = annotation.as_mut().filter(|a| !a.text.is_empty()) {
+                            a.text.push('\n');
+                        }
+                    }
+                    continue;
+                }
+                match name {
+                    b"p" | b"h" => {
+                        let level = if name == b"h" {
+                            attr(&e, b"outline-level").and_then(|l| l.parse::<u8>().ok()).unwrap_or(1)
+                        } else {
+                            0
+                        };
+                        let (bold, italic) = attr(&e, b"style-name").map(|s| resolve(styles, &s)).unwrap_or_default();
+                        let base = (bold.unwrap_or(false), italic.unwrap_or(false));
+                        let paragraph = Paragraph { level, spans: Vec::new() };
+                        if empty {
+                            paragraphs.push(paragraph);
+                        } else {
+                            current = Some(paragraph);
+                            formats = vec![base];
+                        }
+                    }
+                    b"span" if !empty => {
+                        let (outer_bold, outer_italic) = formats.last().copied().unwrap_or_default();
+                        let (bold, italic) = attr(&e, b"style-name").map(|s| resolve(styles, &s)).unwrap_or_default();
+                        formats.push((bold.unwrap_or(outer_bold), italic.unwrap_or(outer_italic)));
+                    }
+                    b"s" | b"tab" | b"line-break" => {
+                        let text = match name {
+                            b"s" => " ".repeat(attr(&e, b"c").and_then(|c| c.parse::<usize>().ok()).unwrap_or(1)),
+                            b"tab" => "\t".to_string(),
+                            _ => "\n".to_string(),
+                        };
+                        let (bold, italic) = formats.last().copied().unwrap_or_default();
+                        if let Some(p) = current.as_mut() {
+                            p.push(&text, bold, italic);
+                        }
+                    }
+                    b"annotation" if !empty => {
+                        annotation = Some(Comment { paragraph: Some(paragraphs.len()), ..Default::default() });
+                        if let Some(range) = attr(&e, b"name") {
+                            open_ranges.insert(range, comments.len());
+                        }
+                    }
+                    b"annotation-end" => {
+                        if let Some(range) = attr(&e, b"name") {
+                            open_ranges.remove(&range);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                let name = e.local_name();
+                let name = name.as_ref();
+                if !in_body {
+                    continue;
+                }
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+                match name {
+                    b"annotation" => {
+                        if let Some(a) = annotation.take() {
+                            comments.push(a);
+                        }
+                    }
+                    b"creator" => in_creator = false,
+                    _ if annotation.is_some() => {}
+                    b"p" | b"h" => {
+                        if let Some(p) = current.take() {
+                            paragraphs.push(p);
+                        }
+                        formats.clear();
+                    }
+                    b"span" => {
+                        formats.pop();
+                    }
+                    b"text" if e.name().as_ref() == b"office:text" => in_body = false,
+                    _ => {}
+                }
+            }
+            Event::Text(t) if in_body && skip_depth == 0 => {
+                let text = t.unescape().context("parsing content.xml")?;
+                if let Some(a) = annotation.as_mut() {
+                    if in_creator {
+                        a.author.push_str(&text);
+                    } else {
+                        a.text.push_str(&text);
+                    }
+                    continue;
+                }
+                let Some(p) = current.as_mut() else { continue };
+                for &index in open_ranges.values() {
+                    if let Some(c) = comments.get_mut(index) {
+                        c.anchor.push_str(&text);
+                    }
+                }
+                let (bold, italic) = formats.last().copied().unwrap_or_default();
+                p.push(&text, bold, italic);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((paragraphs, comments))
+}
+
+fn read_part(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> anyhow::Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut xml = String::new();
+    file.read_to_string(&mut xml).with_context(|| format!("reading {}", name))?;
+    Ok(Some(xml))
+}
+
+/// Work out the docs and groups an OpenDocument text becomes (see `rich_text::plan`); headings
+/// are mapped by outline level and annotations become doc notes
+pub fn plan(bytes: &[u8], name: &str, options: &HeadingImportOptions) -> anyhow::Result<ManuscriptPlan> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("not an OpenDocument (.odt) file")?;
+    let content = read_part(&mut archive, "content.xml")?.context("content.xml missing: not an OpenDocument (.odt) file")?;
+    let mut styles: HashMap<String, StyleFormat> = HashMap::new();
+    if let Some(xml) = read_part(&mut archive, "styles.xml")? {
+        parse_styles(&xml, &mut styles)?;
+    }
+    parse_styles(&content, &mut styles)?;
+    let (paragraphs, comments) = parse_content(&content, &styles)?;
+    Ok(rich_text::plan(&paragraphs, comments, name, options))
+}
+
+/// Read and plan an .odt file, docs named after the file where no heading names them
+pub fn read_odt_file(path: &Path, options: &HeadingImportOptions) -> anyhow::Result<ManuscriptPlan> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported");
+    plan(&bytes, name, options).with_context(|| format!("importing {}", path.display()))
+}
+
+/// Escape text for an ODF paragraph: tabs, line breaks and runs of spaces need elements
+fn odf_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut spaces = 0usize;
+    let flush = |out: &mut String, spaces: &mut usize| {
+        match (*spaces, out.is_empty()) {
+            (0, _) => {}
+            (1, false) => out.push(' '),
+            (n, false) => out.push_str(&format!(" <text:s text:c=\"{}\"/>", n - 1)),
+            (n, true) => out.push_str(&format!("<text:s text:c=\"{}\"/>", n)),
+        }
+        *spaces = 0;
+    };
+    for c in text.chars() {
+        match c {
+            ' ' => spaces += 1,
+            '\t' | '\n' => {
+                flush(&mut out, &mut spaces);
+                out.push_str(if c == '\t' { "<text:tab/>" } else { "<text:line-break/>" });
+            }
+            _ => {
+                flush(&mut out, &mut spaces);
+                let mut buf = [0u8; 4];
+                out.push_str(&escape(&*c.encode_utf8(&mut buf)));
+            }
+        }
+    }
+    flush(&mut out, &mut spaces);
+    out
+}
+
+fn spans_xml(spans: &[Span]) -> String {
+    spans.iter().map(|s| {
+        let text = odf_text(&s.text);
+        match (s.bold, s.italic) {
+            (false, false) => text,
+            (true, false) => format!("<text:span text:style-name=\"T_Bold\">{}</text:span>", text),
+            (false, true) => format!("<text:span text:style-name=\"T_Italic\">{}</text:span>", text),
+            (true, true) => format!("<text:span text:style-name=\"T_BoldItalic\">{}</text:span>", text),
+        }
+    }).collect()
+}
+
+fn content_xml(manuscript: &Manuscript) -> String {
+    let mut body = String::new();
+    for block in &manuscript.blocks {
+        match block {
+            Block::Heading { level, text, .. } => body.push_str(&format!(
+                "<text:h text:style-name=\"Heading_20_{0}\" text:outline-level=\"{0}\">{1}</text:h>",
+                level, odf_text(text)
+            )),
+            Block::Paragraph(spans) => {
+                body.push_str(&format!("<text:p text:style-name=\"Text_20_body\">{}</text:p>", spans_xml(spans)))
+            }
+            Block::SceneBreak => body.push_str(&format!(
+                "<text:p text:style-name=\"Scene_20_break\">{}</text:p>",
+                odf_text(manuscript.scene_separator.as_deref().unwrap_or("*"))
+            )),
+        }
+        body.push('\n');
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content {} office:version="1.2">
+<office:automatic-styles>
+<style:style style:name="T_Bold" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="T_Italic" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="T_BoldItalic" style:family="text"><style:text-properties fo:font-weight="bold" fo:font-style="italic"/></style:style>
+</office:automatic-styles>
+<office:body><office:text>
+{}</office:text></office:body>
+</office:document-content>
+"#,
+        NAMESPACES, body
+    )
+}
+
+fn styles_xml() -> String {
+    let sizes = ["20pt", "17pt", "15pt", "13pt", "12pt", "12pt"];
+    let headings: String = (1..=MAX_HEADING).map(|level| format!(
+        "<style:style style:name=\"Heading_20_{0}\" style:display-name=\"Heading {0}\" style:family=\"paragraph\" style:parent-style-name=\"Heading\" style:next-style-name=\"Text_20_body\" style:default-outline-level=\"{0}\" style:class=\"text\"><style:text-properties fo:font-size=\"{1}\"/></style:style>\n",
+        level, sizes[level as usize - 1]
+    )).collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-styles {} office:version="1.2">
+<office:styles>
+<style:default-style style:family="paragraph"><style:text-properties fo:font-family="'Liberation Serif', serif" fo:font-size="12pt"/></style:default-style>
+<style:style style:name="Standard" style:family="paragraph" style:class="text"/>
+<style:style style:name="Text_20_body" style:display-name="Text body" style:family="paragraph" style:parent-style-name="Standard" style:class="text"><style:paragraph-properties fo:margin-top="0cm" fo:margin-bottom="0.2cm" fo:line-height="150%" fo:text-indent="0.6cm"/></style:style>
+<style:style style:name="Heading" style:family="paragraph" style:parent-style-name="Standard" style:next-style-name="Text_20_body" style:class="text"><style:paragraph-properties fo:margin-top="0.8cm" fo:margin-bottom="0.4cm" fo:keep-with-next="always"/><style:text-properties fo:font-weight="bold"/></style:style>
+{}<style:style style:name="Scene_20_break" style:display-name="Scene break" style:family="paragraph" style:parent-style-name="Standard" style:class="text"><style:paragraph-properties fo:text-align="center" fo:margin-top="0.3cm" fo:margin-bottom="0.3cm"/></style:style>
+</office:styles>
+</office:document-styles>
+"#,
+        NAMESPACES, headings
+    )
+}
+
+fn meta_xml(title: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta {} office:version="1.2"><office:meta><dc:title>{}</dc:title></office:meta></office:document-meta>
+"#,
+        NAMESPACES,
+        escape(title)
+    )
+}
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.text"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+<manifest:file-entry manifest:full-path="styles.xml" manifest:media-type="text/xml"/>
+<manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+/// Write a compiled manuscript as an OpenDocument text. The mimetype entry comes first and
+/// uncompressed, as the format requires.
+pub fn write(manuscript: &Manuscript) -> anyhow::Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+    for (name, xml) in [
+        ("content.xml", content_xml(manuscript)),
+        ("styles.xml", styles_xml()),
+        ("meta.xml", meta_xml(&manuscript.title)),
+        ("META-INF/manifest.xml", MANIFEST.to_string()),
+    ] {
+        zip.start_file(name, deflated)?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ImportHeading;
+    use crate::services::test_support;
+
+    /// Part One > Arrival holding two scenes with emphasis, markup-like text and a tab
+    fn manuscript() -> Manuscript {
+        let pool = test_support::pool("odt");
+        let project = test_support::project(&pool, "Harbour & Sea");
+        let part = test_support::group(&pool, project, "Part One", None);
+        let chapter = test_support::group(&pool, project, "Arrival", Some(part));
+        test_support::doc(&pool, project, Some(chapter), "s1", "Mara *ran*  **fast**.\nShe <hid>.");
+        test_support::doc(&pool, project, Some(chapter), "s2", "Rain\tfell.");
+        crate::services::compile::build(&pool, project, &Default::default()).unwrap()
+    }
+
+    #[test]
+    fn compile_nests_group_headings_and_breaks_scenes() {
+        let manuscript = manuscript();
+        assert_eq!(manuscript.blocks[0], Block::Heading { level: 1, text: "Part One".into(), anchor: Some("part-one".into()) });
+        assert_eq!(manuscript.blocks[1], Block::Heading { level: 2, text: "Arrival".into(), anchor: Some("arrival".into()) });
+        assert_eq!(manuscript.blocks[4], Block::SceneBreak);
+    }
+
+    #[test]
+    fn written_files_start_with_the_mimetype() {
+        let bytes = write(&manuscript()).unwrap();
+        assert_eq!(&bytes[30..38], b"mimetype");
+    }
+
+    #[test]
+    fn compiled_files_import_back_to_the_same_text() {
+        let bytes = write(&manuscript()).unwrap();
+        let options = HeadingImportOptions { heading1: ImportHeading::Group, heading2: ImportHeading::Doc };
+        let plan = plan(&bytes, "Harbour", &options).unwrap();
+        assert_eq!(plan.groups[0].name, "Part One");
+        let doc = &plan.groups[0].docs[0];
+        assert_eq!(doc.name, "Arrival");
+        assert_eq!(doc.text, "Mara *ran*  **fast**.\n\nShe <hid>.\n\n*\n\nRain\tfell.");
+    }
+}
@@ -0,0 +1,407 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// The standard Type1 fonts every PDF reader has built in, so nothing needs embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Times,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+    Courier,
+    CourierBold,
+}
+
+const FONTS: [Font; 6] = [Font::Times, Font::TimesBold, Font::TimesItalic, Font::TimesBoldItalic, Font::Courier, Font::CourierBold];
+
+// Advance widths (per 1000 units of font size) of the printable ASCII range, from the Adobe AFM files
+const TIMES_ROMAN: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, 921,
+    722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, 556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611,
+    333, 278, 333, 469, 500, 333,
+    444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444,
+    480, 200, 480, 541,
+];
+const TIMES_BOLD: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 930,
+    722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778, 611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667,
+    333, 278, 333, 581, 500, 333,
+    500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444,
+    394, 220, 394, 520,
+];
+const TIMES_ITALIC: [u16; 95] = [
+    250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500, 920,
+    611, 611, 667, 722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722, 611, 722, 611, 500, 556, 722, 611, 833, 611, 556, 556,
+    389, 278, 389, 422, 500, 333,
+    500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444, 278, 722, 500, 500, 500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389,
+    400, 275, 400, 541,
+];
+const TIMES_BOLD_ITALIC: [u16; 95] = [
+    250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 832,
+    667, 667, 667, 722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722, 611, 722, 667, 556, 611, 722, 667, 889, 667, 611, 611,
+    333, 278, 333, 570, 500, 333,
+    500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500, 278, 778, 556, 500, 500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389,
+    348, 220, 348, 570,
+];
+
+// Base letters of Windows-1252 0xC0..=0xFF, used to approximate the width of accented letters
+const LATIN1_BASE: &[u8; 64] = b"AAAAAAACEEEEIIIIDNOOOOOxOUUUUYPsaaaaaaaceeeeiiiidnooooo/ouuuuypy";
+
+impl Font {
+    /// Times in the given style
+    pub fn times(bold: bool, italic: bool) -> Font {
+        match (bold, italic) {
+            (false, false) => Font::Times,
+            (true, false) => Font::TimesBold,
+            (false, true) => Font::TimesItalic,
+            (true, true) => Font::TimesBoldItalic,
+        }
+    }
+
+    fn base_name(self) -> &'static str {
+        match self {
+            Font::Times => "Times-Roman",
+            Font::TimesBold => "Times-Bold",
+            Font::TimesItalic => "Times-Italic",
+            Font::TimesBoldItalic => "Times-BoldItalic",
+            Font::Courier => "Courier",
+            Font::CourierBold => "Courier-Bold",
+        }
+    }
+
+    fn resource(self) -> usize {
+        FONTS.iter().position(|&f| f == self).unwrap_or(0) + 1
+    }
+
+    /// Width of one Windows-1252 byte, per 1000 units of font size
+    fn byte_width(self, byte: u8) -> u16 {
+        let table = match self {
+            Font::Times => &TIMES_ROMAN,
+            Font::TimesBold => &TIMES_BOLD,
+            Font::TimesItalic => &TIMES_ITALIC,
+            Font::TimesBoldItalic => &TIMES_BOLD_ITALIC,
+            Font::Courier | Font::CourierBold => return 600,
+        };
+        match byte {
+            32..=126 => table[(byte - 32) as usize],
+            // ‘ ’ ‚ ‹ ›
+            0x91 | 0x92 | 0x82 | 0x8B | 0x9B => 333,
+            // “ ” „
+            0x93 | 0x94 | 0x84 if self == Font::Times => 444,
+            0x93 | 0x94 | 0x84 => 500,
+            // – • — …
+            0x96 => 500,
+            0x95 => 350,
+            0x97 | 0x85 if self == Font::TimesItalic => 889,
+            0x97 | 0x85 => 1000,
+            0xA0 => 250,
+            0xAB | 0xBB => 500,
+            0xC0..=0xFF => self.byte_width(LATIN1_BASE[(byte - 0xC0) as usize]),
+            _ => 500,
+        }
+    }
+}
+
+/// Text in the fonts' WinAnsi encoding; thin spaces become plain spaces (or no-break spaces when
+/// they are narrow no-break ones) and characters the encoding lacks become `?`
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut buf = [0u8; 4];
+    for c in text.chars() {
+        let c = match c {
+            '\u{2009}' | '\u{200A}' | '\u{2002}' | '\u{2003}' => ' ',
+            '\u{202F}' => '\u{A0}',
+            c => c,
+        };
+        let (bytes, _, had_errors) = encoding_rs::WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+        match bytes.first() {
+            Some(&b) if !had_errors && bytes.len() == 1 => out.push(b),
+            _ => out.push(b'?'),
+        }
+    }
+    out
+}
+
+/// Width of text set in `font` at `size` points
+pub fn text_width(font: Font, size: f32, text: &str) -> f32 {
+    encode(text).iter().map(|&b| font.byte_width(b) as f32).sum::<f32>() * size / 1000.0
+}
+
+/// A PDF literal string; bytes outside printable ASCII are written as octal escapes
+fn literal(bytes: &[u8]) -> String {
+    let mut out = String::from("(");
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            32..=126 => out.push(b as char),
+            _ => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// A word of running text made of one or more differently formatted pieces
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub pieces: Vec<(Font, String)>,
+    pub width: f32,
+}
+
+/// Split formatted runs into words at whitespace; a word keeps every formatting change inside it
+pub fn words(runs: &[(Font, &str)], size: f32) -> Vec<Word> {
+    let mut out: Vec<Word> = Vec::new();
+    let mut current: Option<Word> = None;
+    for (font, text) in runs {
+        for (i, part) in text.split(|c: char| c.is_whitespace() && c != '\u{A0}' && c != '\u{202F}').enumerate() {
+            if i > 0 {
+                out.extend(current.take());
+            }
+            if part.is_empty() {
+                continue;
+            }
+            let word = current.get_or_insert_with(|| Word { pieces: Vec::new(), width: 0.0 });
+            word.width += text_width(*font, size, part);
+            word.pieces.push((*font, part.to_string()));
+        }
+    }
+    out.extend(current);
+    out
+}
+
+/// Break words into lines; the first line may be narrower (an indent). A word wider than the
+/// line gets a line to itself.
+pub fn wrap(words: Vec<Word>, first_width: f32, width: f32, space: f32) -> Vec<Vec<Word>> {
+    let mut lines: Vec<Vec<Word>> = Vec::new();
+    let mut line: Vec<Word> = Vec::new();
+    let mut used = 0.0;
+    for word in words {
+        let limit = if lines.is_empty() { first_width } else { width };
+        let needed = if line.is_empty() { word.width } else { used + space + word.width };
+        if !line.is_empty() && needed > limit {
+            lines.push(std::mem::take(&mut line));
+            used = word.width;
+        } else {
+            used = needed;
+        }
+        line.push(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Natural width of a line of words
+pub fn line_width(line: &[Word], space: f32) -> f32 {
+    line.iter().map(|w| w.width).sum::<f32>() + space * line.len().saturating_sub(1) as f32
+}
+
+/// Drawing operations of one page
+#[derive(Debug, Default)]
+pub struct Page {
+    ops: String,
+}
+
+impl Page {
+    /// Set `text` with its baseline starting at (`x`, `y`), in points from the bottom left
+    pub fn text(&mut self, font: Font, size: f32, x: f32, y: f32, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.ops.push_str(&format!(
+            "BT /F{} {:.1} Tf {:.2} {:.2} Td {} Tj ET\n",
+            font.resource(),
+            size,
+            x,
+            y,
+            literal(&encode(text))
+        ));
+    }
+
+    /// Set a line of words from `x`, spacing them `gap` apart
+    pub fn words(&mut self, line: &[Word], size: f32, x: f32, y: f32, gap: f32) {
+        let mut x = x;
+        for word in line {
+            for (font, piece) in &word.pieces {
+                self.text(*font, size, x, y, piece);
+                x += text_width(*font, size, piece);
+            }
+            x += gap;
+        }
+    }
+}
+
+/// A PDF being built page by page; every page has the same size
+#[derive(Debug)]
+pub struct Document {
+    pub width: f32,
+    pub height: f32,
+    title: String,
+    pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new(width: f32, height: f32, title: &str) -> Self {
+        Document { width, height, title: title.to_string(), pages: Vec::new() }
+    }
+
+    pub fn add_page(&mut self) -> usize {
+        self.pages.push(Page::default());
+        self.pages.len() - 1
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn page(&mut self, index: usize) -> &mut Page {
+        &mut self.pages[index]
+    }
+
+    /// Serialize the document: catalog, page tree, info, the fonts, then each page and its
+    /// compressed content stream, followed by the cross-reference table
+    pub fn finish(self) -> Vec<u8> {
+        let mut out: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets: Vec<usize> = Vec::new();
+        let mut object = |out: &mut Vec<u8>, body: &[u8]| {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        };
+        let first_page = 4 + FONTS.len();
+        let kids: Vec<String> = (0..self.pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+        let fonts: String = FONTS.iter().enumerate().map(|(i, f)| format!("/F{} {} 0 R ", f.resource(), 4 + i)).collect();
+
+        object(&mut out, b"<< /Type /Catalog /Pages 2 0 R >>");
+        object(&mut out, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.pages.len()).as_bytes());
+        let mut info = b"<< /Title ".to_vec();
+        info.extend_from_slice(literal(&encode(&self.title)).as_bytes());
+        info.extend_from_slice(b" /Producer (Crate) >>");
+        object(&mut out, &info);
+        for font in FONTS {
+            object(&mut out, format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font.base_name()).as_bytes());
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            object(&mut out, format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << {}>> >> /Contents {} 0 R >>",
+                self.width, self.height, fonts, first_page + 2 * i + 1
+            ).as_bytes());
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            // Writing into a Vec cannot fail
+            let _ = encoder.write_all(page.ops.as_bytes());
+            let data = encoder.finish().unwrap_or_default();
+            let mut stream = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", data.len()).into_bytes();
+            stream.extend_from_slice(&data);
+            stream.extend_from_slice(b"\nendstream");
+            object(&mut out, &stream);
+        }
+
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+        for offset in &offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        ).as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    #[test]
+    fn xref_offsets_point_at_their_objects() {
+        let mut doc = Document::new(612.0, 792.0, "Harbour");
+        for _ in 0..2 {
+            let page = doc.add_page();
+            doc.page(page).text(Font::Times, 11.0, 72.0, 700.0, "Tide");
+        }
+        let bytes = doc.finish();
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert_eq!(find(&bytes, b"xref\n"), Some(startxref));
+        // Everything from the table on is ASCII
+        let table = std::str::from_utf8(&bytes[startxref..]).unwrap();
+        let mut lines = table.lines().skip(1);
+        let count: usize = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+        // Catalog, pages, info, six fonts, then a page and a content stream per page
+        assert_eq!(count, 1 + 3 + FONTS.len() + 2 * 2);
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for n in 1..count {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len(), 19, "xref entries are 20 bytes with their newline");
+            assert!(entry.ends_with(" 00000 n "));
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(bytes[offset..].starts_with(format!("{} 0 obj\n", n).as_bytes()), "object {}", n);
+        }
+        assert!(table.contains(&format!("trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>", count)));
+        assert!(text.contains("/Kids [10 0 R 12 0 R] /Count 2"));
+        assert!(text.ends_with("%%EOF\n"));
+    }
+
+    #[test]
+    fn literals_escape_delimiters_and_non_ascii() {
+        assert_eq!(literal(b"a(b)c\\d"), "(a\\(b\\)c\\\\d)");
+        assert_eq!(literal(b"(("), "(\\(\\()");
+        assert_eq!(literal(b"tab\tnew\n"), "(tab\\011new\\012)");
+        assert_eq!(literal(&encode("Caf\u{e9} \u{2014} \u{2603}")), "(Caf\\351 \\227 ?)");
+
+        let mut page = Page::default();
+        page.text(Font::Courier, 12.0, 10.0, 20.0, "f(x) \\ y");
+        assert_eq!(page.ops, "BT /F5 12.0 Tf 10.00 20.00 Td (f\\(x\\) \\\\ y) Tj ET\n");
+    }
+
+    #[test]
+    fn encode_maps_spaces_and_unknown_characters() {
+        assert_eq!(encode("a\u{2009}b\u{202F}c"), b"a b\xA0c");
+        assert_eq!(encode("\u{201C}x\u{201D}"), b"\x93x\x94");
+        assert_eq!(encode("\u{3042}"), b"?");
+    }
+
+    #[test]
+    fn words_keep_formatting_changes_and_no_break_spaces() {
+        let runs = [(Font::Times, "a quiet "), (Font::TimesItalic, "har"), (Font::Times, "bour\u{A0}town")];
+        let words = words(&runs, 10.0);
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[2].pieces, vec![(Font::TimesItalic, "har".to_string()), (Font::Times, "bour\u{A0}town".to_string())]);
+        let expected = text_width(Font::TimesItalic, 10.0, "har") + text_width(Font::Times, 10.0, "bour\u{A0}town");
+        assert!((words[2].width - expected).abs() < 0.001);
+        assert_eq!(text_width(Font::Courier, 10.0, "abc"), 18.0);
+    }
+
+    #[test]
+    fn wrap_breaks_lines_at_the_width() {
+        let word = |width: f32| Word { pieces: vec![(Font::Times, "w".into())], width };
+        let widths = |lines: &[Vec<Word>]| -> Vec<usize> { lines.iter().map(|l| l.len()).collect() };
+
+        // Three 10-wide words with 2-wide spaces fit in 34 but not 33
+        assert_eq!(widths(&wrap(vec![word(10.0), word(10.0), word(10.0)], 34.0, 34.0, 2.0)), vec![3]);
+        assert_eq!(widths(&wrap(vec![word(10.0), word(10.0), word(10.0)], 33.0, 33.0, 2.0)), vec![2, 1]);
+        // A narrower first line (indent) moves its last word down
+        assert_eq!(widths(&wrap(vec![word(10.0), word(10.0), word(10.0), word(10.0)], 21.0, 34.0, 2.0)), vec![1, 3]);
+        // Oversized words get a line of their own
+        assert_eq!(widths(&wrap(vec![word(5.0), word(50.0), word(5.0)], 20.0, 20.0, 2.0)), vec![1, 1, 1]);
+        assert!(wrap(Vec::new(), 20.0, 20.0, 2.0).is_empty());
+        assert_eq!(line_width(&[word(10.0), word(10.0)], 2.0), 22.0);
+    }
+}
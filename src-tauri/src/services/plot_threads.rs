@@ -0,0 +1,278 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{
+    DocThreadLink, PlotThread, PlotThreadCreate, PlotThreadUpdate,
+    ThreadCoverage, ThreadMatrix, ThreadMatrixRow, ThreadRole,
+};
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+
+const THREAD_COLUMNS: &str = "id, project_id, name, color, desc, sort_order";
+
+fn thread_from_row(row: &rusqlite::Row) -> rusqlite::Result<PlotThread> {
+    Ok(PlotThread {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        color: row.get(3)?,
+        desc: row.get(4)?,
+        sort_order: row.get(5)?,
+    })
+}
+
+fn role_str(role: ThreadRole) -> &'static str {
+    match role {
+        ThreadRole::Introduces => "introduces",
+        ThreadRole::Advances => "advances",
+        ThreadRole::Resolves => "resolves",
+    }
+}
+
+fn role_from_str(s: &str) -> ThreadRole {
+    match s {
+        "introduces" => ThreadRole::Introduces,
+        "resolves" => ThreadRole::Resolves,
+        _ => ThreadRole::Advances,
+    }
+}
+
+fn link_from_row(row: &rusqlite::Row) -> rusqlite::Result<DocThreadLink> {
+    Ok(DocThreadLink {
+        doc_id: row.get(0)?,
+        thread_id: row.get(1)?,
+        role: role_from_str(&row.get::<_, String>(2)?),
+    })
+}
+
+fn load(conn: &Connection, id: i64) -> anyhow::Result<Option<PlotThread>> {
+    let sql = format!("SELECT {} FROM plot_threads WHERE id = ?1", THREAD_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], thread_from_row).optional()?)
+}
+
+/// Accept `#rgb` or `#rrggbb`; blank means no colour
+fn normalize_color(color: Option<String>) -> anyhow::Result<Option<String>> {
+    let Some(c) = color.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()) else { return Ok(None) };
+    let hex = c.strip_prefix('#').unwrap_or("");
+    if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid colour '{}': expected #rgb or #rrggbb", c);
+    }
+    Ok(Some(format!("#{}", hex.to_ascii_lowercase())))
+}
+
+pub fn create(pool: &DbPool, project_id: i64, payload: PlotThreadCreate) -> anyhow::Result<PlotThread> {
+    let conn = get_conn(pool)?;
+    if payload.name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+    let color = normalize_color(payload.color)?;
+    let next_order: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM plot_threads WHERE project_id = ?1",
+        rusqlite::params![project_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO plot_threads (project_id, name, color, desc, sort_order) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![project_id, payload.name.trim(), color, payload.desc, next_order],
+    ).context("inserting plot thread")?;
+    load(&conn, conn.last_insert_rowid())?.ok_or_else(|| anyhow::anyhow!("plot thread not found after creation"))
+}
+
+pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<PlotThread>> {
+    let conn = get_conn(pool)?;
+    load(&conn, id)
+}
+
+/// List the plot threads of a project in their display order
+pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<PlotThread>> {
+    let conn = get_conn(pool)?;
+    let sql = format!("SELECT {} FROM plot_threads WHERE project_id = ?1 ORDER BY sort_order, id", THREAD_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![project_id], thread_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+pub fn update(pool: &DbPool, id: i64, payload: PlotThreadUpdate) -> anyhow::Result<PlotThread> {
+    let conn = get_conn(pool)?;
+    let current = load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("plot thread not found"))?;
+
+    let new_name = payload.name.unwrap_or(current.name);
+    if new_name.trim().is_empty() {
+        return Err(anyhow::anyhow!("name cannot be empty"));
+    }
+    let new_color = match payload.color {
+        Some(c) => normalize_color(Some(c))?,
+        None => current.color,
+    };
+    let new_desc = payload.desc.or(current.desc);
+
+    conn.execute(
+        "UPDATE plot_threads SET name = ?1, color = ?2, desc = ?3 WHERE id = ?4",
+        rusqlite::params![new_name.trim(), new_color, new_desc, id],
+    ).context("updating plot thread")?;
+    load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("plot thread not found after update"))
+}
+
+/// Delete a plot thread; its doc links cascade
+pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM plot_threads WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+// Doc links
+
+/// Link a doc to a thread, or change the role of an existing link
+pub fn link_doc(pool: &DbPool, doc_id: i64, thread_id: i64, role: ThreadRole) -> anyhow::Result<DocThreadLink> {
+    let conn = get_conn(pool)?;
+    let same_project: Option<bool> = conn.query_row(
+        "SELECT d.project_id = t.project_id FROM docs d, plot_threads t WHERE d.id = ?1 AND t.id = ?2",
+        rusqlite::params![doc_id, thread_id],
+        |row| row.get(0),
+    ).optional()?;
+    match same_project {
+        None => anyhow::bail!("doc or plot thread not found"),
+        Some(false) => anyhow::bail!("doc and plot thread belong to different projects"),
+        Some(true) => {}
+    }
+    conn.execute(
+        "INSERT INTO doc_plot_threads (doc_id, thread_id, role) VALUES (?1, ?2, ?3)
+         ON CONFLICT(doc_id, thread_id) DO UPDATE SET role = excluded.role",
+        rusqlite::params![doc_id, thread_id, role_str(role)],
+    )?;
+    Ok(DocThreadLink { doc_id, thread_id, role })
+}
+
+/// Remove a doc's link to a thread (idempotent)
+pub fn unlink_doc(pool: &DbPool, doc_id: i64, thread_id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "DELETE FROM doc_plot_threads WHERE doc_id = ?1 AND thread_id = ?2",
+        rusqlite::params![doc_id, thread_id],
+    )?;
+    Ok(())
+}
+
+/// Thread links of one doc
+pub fn list_for_doc(pool: &DbPool, doc_id: i64) -> anyhow::Result<Vec<DocThreadLink>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT l.doc_id, l.thread_id, l.role FROM doc_plot_threads l
+         JOIN plot_threads t ON t.id = l.thread_id
+         WHERE l.doc_id = ?1 ORDER BY t.sort_order, t.id",
+    )?;
+    let links = stmt.query_map(rusqlite::params![doc_id], link_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+/// Docs in manuscript order against the project's threads, with a coverage summary per thread
+/// so a subplot that drops out (long gap, never resolved) stands out
+pub fn matrix(pool: &DbPool, project_id: i64) -> anyhow::Result<ThreadMatrix> {
+    let threads = list(pool, project_id)?;
+    let docs = crate::services::docs::list_in_manuscript_order(pool, project_id)?;
+
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT l.doc_id, l.thread_id, l.role FROM doc_plot_threads l
+         JOIN plot_threads t ON t.id = l.thread_id
+         WHERE t.project_id = ?1",
+    )?;
+    let links: HashMap<(i64, i64), ThreadRole> = stmt
+        .query_map(rusqlite::params![project_id], link_from_row)?
+        .map(|l| l.map(|l| ((l.doc_id, l.thread_id), l.role)))
+        .collect::<Result<_, _>>()?;
+
+    let rows: Vec<ThreadMatrixRow> = docs
+        .iter()
+        .map(|d| ThreadMatrixRow {
+            doc_id: d.id,
+            doc_name: d.name.clone(),
+            cells: threads.iter().map(|t| links.get(&(d.id, t.id)).copied()).collect(),
+        })
+        .collect();
+
+    let coverage = threads
+        .iter()
+        .enumerate()
+        .map(|(col, t)| {
+            let hits: Vec<usize> = rows.iter().enumerate().filter(|(_, r)| r.cells[col].is_some()).map(|(i, _)| i).collect();
+            ThreadCoverage {
+                thread_id: t.id,
+                appearances: hits.len(),
+                first_row: hits.first().copied(),
+                last_row: hits.last().copied(),
+                longest_gap: hits.windows(2).map(|w| w[1] - w[0] - 1).max().unwrap_or(0),
+                resolved: rows.iter().any(|r| r.cells[col] == Some(ThreadRole::Resolves)),
+            }
+        })
+        .collect();
+
+    Ok(ThreadMatrix { threads, rows, coverage })
+}
+
+// Import
+
+/// Recreate exported threads in a project; returns old thread id -> new thread id
+pub fn import_snapshot(pool: &DbPool, project_id: i64, threads: &[PlotThread]) -> anyhow::Result<HashMap<i64, i64>> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let mut id_map = HashMap::new();
+    for t in threads {
+        tx.execute(
+            "INSERT INTO plot_threads (project_id, name, color, desc, sort_order) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![project_id, t.name, t.color, t.desc, t.sort_order],
+        ).context("importing plot thread")?;
+        id_map.insert(t.id, tx.last_insert_rowid());
+    }
+    tx.commit()?;
+    Ok(id_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    #[test]
+    fn matrix_follows_manuscript_order_and_flags_gaps() {
+        let manager = SqliteConnectionManager::file("file:memplotthreads?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/015_add_plot_threads.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO projects (id, name) VALUES (1, 'P'), (2, 'Other');
+             INSERT INTO doc_groups (id, project_id, name, parent_id, sort_order) VALUES (1, 1, 'Part One', NULL, 0);
+             INSERT INTO docs (id, project_id, path, name, doc_group_id, sort_order) VALUES
+                (1, 1, '', 'Opening', 1, 0), (2, 1, '', 'Journey', 1, 1), (3, 1, '', 'Storm', 1, 2),
+                (4, 1, '', 'Landfall', 1, 3), (5, 2, '', 'Elsewhere', NULL, 0);",
+        ).unwrap();
+        drop(conn);
+
+        let romance = create(&pool, 1, PlotThreadCreate { name: "Romance".into(), color: Some("#E91E63".into()), desc: None }).unwrap();
+        let mutiny = create(&pool, 1, PlotThreadCreate { name: "Mutiny".into(), color: None, desc: None }).unwrap();
+        assert_eq!(romance.color.as_deref(), Some("#e91e63"));
+        assert!(create(&pool, 1, PlotThreadCreate { name: "Bad".into(), color: Some("pink".into()), desc: None }).is_err());
+
+        link_doc(&pool, 1, romance.id, ThreadRole::Introduces).unwrap();
+        link_doc(&pool, 4, romance.id, ThreadRole::Advances).unwrap();
+        link_doc(&pool, 2, mutiny.id, ThreadRole::Introduces).unwrap();
+        link_doc(&pool, 3, mutiny.id, ThreadRole::Advances).unwrap();
+        link_doc(&pool, 3, mutiny.id, ThreadRole::Resolves).unwrap();
+        assert!(link_doc(&pool, 5, mutiny.id, ThreadRole::Advances).is_err());
+
+        let m = matrix(&pool, 1).unwrap();
+        let order: Vec<i64> = m.rows.iter().map(|r| r.doc_id).collect();
+        assert_eq!(order, vec![1, 2, 3, 4]);
+        assert_eq!(m.rows[2].cells, vec![None, Some(ThreadRole::Resolves)]);
+
+        let romance_cov = &m.coverage[0];
+        assert_eq!((romance_cov.appearances, romance_cov.longest_gap, romance_cov.resolved), (2, 2, false));
+        let mutiny_cov = &m.coverage[1];
+        assert_eq!((mutiny_cov.first_row, mutiny_cov.last_row, mutiny_cov.resolved), (Some(1), Some(2), true));
+
+        delete(&pool, mutiny.id).unwrap();
+        assert!(list_for_doc(&pool, 3).unwrap().is_empty());
+    }
+}
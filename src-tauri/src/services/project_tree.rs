@@ -0,0 +1,111 @@
+use crate::db::DbPool;
+use crate::models::{Doc, DocGroup};
+use std::collections::HashMap;
+
+/// A group with its docs and subgroups, siblings in sort order
+#[derive(Debug)]
+pub struct TreeGroup {
+    pub group: DocGroup,
+    pub docs: Vec<Doc>,
+    pub children: Vec<TreeGroup>,
+}
+
+/// A project's manuscript tree: root-level docs, then the root groups
+#[derive(Debug, Default)]
+pub struct ProjectTree {
+    pub docs: Vec<Doc>,
+    pub groups: Vec<TreeGroup>,
+}
+
+/// Load the group/doc tree of a project, siblings ordered by sort_order
+pub fn load(pool: &DbPool, project_id: i64) -> anyhow::Result<ProjectTree> {
+    let groups = crate::services::doc_groups::list_doc_groups(pool, project_id)?;
+    let docs = crate::services::docs::list_docs(pool, project_id)?;
+
+    let mut children: HashMap<Option<i64>, Vec<DocGroup>> = HashMap::new();
+    for g in groups {
+        children.entry(g.parent_id).or_default().push(g);
+    }
+    for v in children.values_mut() {
+        v.sort_by_key(|g| g.sort_order.unwrap_or(0));
+    }
+    let mut docs_by_group: HashMap<Option<i64>, Vec<Doc>> = HashMap::new();
+    for d in docs {
+        docs_by_group.entry(d.doc_group_id).or_default().push(d);
+    }
+    for v in docs_by_group.values_mut() {
+        v.sort_by_key(|d| d.sort_order.unwrap_or(0));
+    }
+
+    fn build(
+        parent: Option<i64>,
+        children: &mut HashMap<Option<i64>, Vec<DocGroup>>,
+        docs_by_group: &mut HashMap<Option<i64>, Vec<Doc>>,
+    ) -> Vec<TreeGroup> {
+        children.remove(&parent).unwrap_or_default().into_iter().map(|group| {
+            let docs = docs_by_group.remove(&Some(group.id)).unwrap_or_default();
+            let sub = build(Some(group.id), children, docs_by_group);
+            TreeGroup { group, docs, children: sub }
+        }).collect()
+    }
+    let groups = build(None, &mut children, &mut docs_by_group);
+    Ok(ProjectTree { docs: docs_by_group.remove(&None).unwrap_or_default(), groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectCreate;
+    use crate::services::{doc_groups, docs};
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn make_pool() -> DbPool {
+        let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let db_name = format!("file:memtree{}?mode=memory&cache=shared", id);
+        Pool::new(SqliteConnectionManager::file(&db_name)).unwrap()
+    }
+
+    fn init_schema(conn: &rusqlite::Connection) {
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+    }
+
+    fn names(docs: &[Doc]) -> Vec<&str> {
+        docs.iter().map(|d| d.name.as_deref().unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn test_load_keeps_root_docs_and_nests_groups_in_order() {
+        let pool = make_pool();
+        init_schema(&pool.get().unwrap());
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        let part = doc_groups::create_doc_group(&pool, project.id, "Part One", None).unwrap();
+        let chapter = doc_groups::create_doc_group(&pool, project.id, "Landfall", Some(part.id)).unwrap();
+        doc_groups::create_doc_group(&pool, project.id, "Part Two", None).unwrap();
+        docs::create_doc(&pool, project.id, "Dedication", None).unwrap();
+        docs::create_doc(&pool, project.id, "Prologue", None).unwrap();
+        docs::create_doc(&pool, project.id, "Opening", Some(part.id)).unwrap();
+        let arrival = docs::create_doc(&pool, project.id, "Arrival", Some(chapter.id)).unwrap();
+        docs::create_doc(&pool, project.id, "Harbour", Some(chapter.id)).unwrap();
+        docs::reorder_doc(&pool, arrival.id, "down").unwrap();
+
+        let tree = load(&pool, project.id).unwrap();
+        assert_eq!(names(&tree.docs), vec!["Dedication", "Prologue"]);
+        assert_eq!(tree.groups.iter().map(|g| g.group.name.as_str()).collect::<Vec<_>>(), vec!["Part One", "Part Two"]);
+        let part_one = &tree.groups[0];
+        assert_eq!(names(&part_one.docs), vec!["Opening"]);
+        assert_eq!(part_one.children.len(), 1);
+        assert_eq!(names(&part_one.children[0].docs), vec!["Harbour", "Arrival"]);
+        assert!(tree.groups[1].docs.is_empty() && tree.groups[1].children.is_empty());
+    }
+}
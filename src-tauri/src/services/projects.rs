@@ -12,7 +12,7 @@ pub fn create(pool: &DbPool, payload: ProjectCreate) -> anyhow::Result<Project>
     .context("inserting project")?;
 
     let id = conn.last_insert_rowid();
-    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end FROM projects WHERE id = ?1")?;
+    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end, linear_chronology FROM projects WHERE id = ?1")?;
     let project = stmt
         .query_row(rusqlite::params![id], |row| {
             Ok(Project {
@@ -22,6 +22,7 @@ pub fn create(pool: &DbPool, payload: ProjectCreate) -> anyhow::Result<Project>
                 path: row.get(3)?,
                 timeline_start: row.get(4)?,
                 timeline_end: row.get(5)?,
+                linear_chronology: row.get(6)?,
             })
         })
         .context("querying created project")?;
@@ -31,7 +32,7 @@ pub fn create(pool: &DbPool, payload: ProjectCreate) -> anyhow::Result<Project>
 
 pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Project>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end FROM projects WHERE id = ?1")?;
+    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end, linear_chronology FROM projects WHERE id = ?1")?;
     let res = stmt.query_row(rusqlite::params![id], |row| {
         Ok(Project {
             id: row.get(0)?,
@@ -40,6 +41,7 @@ pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Project>> {
             path: row.get(3)?,
             timeline_start: row.get(4)?,
             timeline_end: row.get(5)?,
+            linear_chronology: row.get(6)?,
         })
     }).optional()?;
 
@@ -48,7 +50,7 @@ pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Project>> {
 
 pub fn list(pool: &DbPool) -> anyhow::Result<Vec<Project>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end FROM projects ORDER BY id")?;
+    let mut stmt = conn.prepare("SELECT id, name, desc, path, timeline_start, timeline_end, linear_chronology FROM projects ORDER BY id")?;
     let rows = stmt.query_map([], |row| {
         Ok(Project {
             id: row.get(0)?,
@@ -57,6 +59,7 @@ pub fn list(pool: &DbPool) -> anyhow::Result<Vec<Project>> {
             path: row.get(3)?,
             timeline_start: row.get(4)?,
             timeline_end: row.get(5)?,
+            linear_chronology: row.get(6)?,
         })
     })?;
 
@@ -93,6 +96,33 @@ pub fn update(pool: &DbPool, id: i64, name: Option<String>, desc: Option<String>
     get(pool, id).and_then(|opt| opt.ok_or_else(|| anyhow::anyhow!("not found after update")))
 }
 
+/// Set the project's story timeline range (ISO 8601, partial dates allowed)
+pub fn set_timeline_range(pool: &DbPool, id: i64, start: Option<String>, end: Option<String>) -> anyhow::Result<Project> {
+    let (start, end) = crate::dates::normalize_range(start, end)?;
+    let conn = get_conn(pool)?;
+    let affected = conn.execute(
+        "UPDATE projects SET timeline_start = ?1, timeline_end = ?2 WHERE id = ?3",
+        rusqlite::params![start, end, id],
+    ).context("updating project timeline")?;
+    if affected == 0 {
+        anyhow::bail!("project not found");
+    }
+    get(pool, id).and_then(|opt| opt.ok_or_else(|| anyhow::anyhow!("not found after update")))
+}
+
+/// Mark whether the manuscript order must follow story chronology
+pub fn set_linear_chronology(pool: &DbPool, id: i64, linear: bool) -> anyhow::Result<Project> {
+    let conn = get_conn(pool)?;
+    let affected = conn.execute(
+        "UPDATE projects SET linear_chronology = ?1 WHERE id = ?2",
+        rusqlite::params![linear, id],
+    ).context("updating project chronology")?;
+    if affected == 0 {
+        anyhow::bail!("project not found");
+    }
+    get(pool, id).and_then(|opt| opt.ok_or_else(|| anyhow::anyhow!("not found after update")))
+}
+
 pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<bool> {
     let mut conn = get_conn(pool)?;
     let tx = conn.transaction()?;
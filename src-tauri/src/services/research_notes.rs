@@ -0,0 +1,228 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{ResearchNote, ResearchNoteCreate, ResearchNoteUpdate};
+use anyhow::Context;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+
+const NOTE_COLUMNS: &str = "id, project_id, parent_type, parent_id, title, text, tags, created_at, updated_at";
+
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<ResearchNote> {
+    let tags: String = row.get(6)?;
+    Ok(ResearchNote {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        parent_type: row.get(2)?,
+        parent_id: row.get(3)?,
+        title: row.get(4)?,
+        text: row.get(5)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?,
+        created_at: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+        updated_at: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+    })
+}
+
+fn load(conn: &Connection, id: i64) -> anyhow::Result<Option<ResearchNote>> {
+    let sql = format!("SELECT {} FROM notes WHERE id = ?1 AND project_id IS NOT NULL", NOTE_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], note_from_row).optional()?)
+}
+
+/// Project owning the note's parent, or None if the parent does not exist
+fn owner_project(conn: &Connection, parent_type: &str, parent_id: i64) -> anyhow::Result<Option<i64>> {
+    let sql = match parent_type {
+        "project" => "SELECT id FROM projects WHERE id = ?1",
+        "folder" => "SELECT project_id FROM doc_groups WHERE id = ?1",
+        "doc" => "SELECT project_id FROM docs WHERE id = ?1",
+        "character" => "SELECT project_id FROM characters WHERE id = ?1",
+        "event" => "SELECT project_id FROM events WHERE id = ?1",
+        other => anyhow::bail!("notes cannot be attached to '{}'", other),
+    };
+    Ok(conn.query_row(sql, rusqlite::params![parent_id], |row| row.get::<_, Option<i64>>(0)).optional()?.flatten())
+}
+
+/// Trim tags, drop blanks and case-insensitive duplicates, keeping the first spelling
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for t in tags {
+        let t = t.trim();
+        if !t.is_empty() && !out.iter().any(|o| o.eq_ignore_ascii_case(t)) {
+            out.push(t.to_string());
+        }
+    }
+    out
+}
+
+pub fn create(pool: &DbPool, payload: ResearchNoteCreate) -> anyhow::Result<ResearchNote> {
+    let conn = get_conn(pool)?;
+    if payload.title.trim().is_empty() {
+        return Err(anyhow::anyhow!("title cannot be empty"));
+    }
+    let project_id = owner_project(&conn, &payload.parent_type, payload.parent_id)?
+        .ok_or_else(|| anyhow::anyhow!("{} {} not found", payload.parent_type, payload.parent_id))?;
+    let tags = normalize_tags(payload.tags);
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO notes (project_id, parent_type, parent_id, title, text, tags, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+        rusqlite::params![project_id, payload.parent_type, payload.parent_id, payload.title.trim(), payload.text, serde_json::to_string(&tags)?, now],
+    ).context("inserting note")?;
+    load(&conn, conn.last_insert_rowid())?.ok_or_else(|| anyhow::anyhow!("note not found after creation"))
+}
+
+pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<ResearchNote>> {
+    let conn = get_conn(pool)?;
+    load(&conn, id)
+}
+
+/// Notes attached to one project, folder, doc, character or event, most recently updated first
+pub fn list_for(pool: &DbPool, parent_type: &str, parent_id: i64) -> anyhow::Result<Vec<ResearchNote>> {
+    let conn = get_conn(pool)?;
+    let sql = format!(
+        "SELECT {} FROM notes WHERE parent_type = ?1 AND parent_id = ?2 AND project_id IS NOT NULL ORDER BY updated_at DESC, id DESC",
+        NOTE_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![parent_type, parent_id], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Search a project's notes: `query` matches title, text or a tag (case-insensitive substring),
+/// `tag` restricts to notes carrying that exact tag; both optional
+pub fn search(pool: &DbPool, project_id: i64, query: Option<&str>, tag: Option<&str>) -> anyhow::Result<Vec<ResearchNote>> {
+    let conn = get_conn(pool)?;
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    let tag = tag.map(str::trim).filter(|t| !t.is_empty());
+    let pattern = query.map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+    let sql = format!(
+        "SELECT {} FROM notes
+         WHERE project_id = ?1
+           AND (?2 IS NULL OR title LIKE ?2 ESCAPE '\\' OR text LIKE ?2 ESCAPE '\\'
+                OR EXISTS (SELECT 1 FROM json_each(notes.tags) WHERE value LIKE ?2 ESCAPE '\\'))
+           AND (?3 IS NULL OR EXISTS (SELECT 1 FROM json_each(notes.tags) WHERE lower(value) = lower(?3)))
+         ORDER BY updated_at DESC, id DESC",
+        NOTE_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt.query_map(rusqlite::params![project_id, pattern, tag], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Every tag used in a project's notes, alphabetically
+pub fn list_tags(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<String>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT j.value FROM notes, json_each(notes.tags) j WHERE notes.project_id = ?1 ORDER BY j.value COLLATE NOCASE",
+    )?;
+    let tags = stmt.query_map(rusqlite::params![project_id], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
+
+pub fn update(pool: &DbPool, id: i64, payload: ResearchNoteUpdate) -> anyhow::Result<ResearchNote> {
+    let conn = get_conn(pool)?;
+    let current = load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("note not found"))?;
+
+    let new_title = payload.title.unwrap_or(current.title);
+    if new_title.trim().is_empty() {
+        return Err(anyhow::anyhow!("title cannot be empty"));
+    }
+    let new_text = payload.text.or(current.text);
+    let new_tags = payload.tags.map(normalize_tags).unwrap_or(current.tags);
+
+    conn.execute(
+        "UPDATE notes SET title = ?1, text = ?2, tags = ?3, updated_at = ?4 WHERE id = ?5",
+        rusqlite::params![new_title.trim(), new_text, serde_json::to_string(&new_tags)?, Utc::now().to_rfc3339(), id],
+    ).context("updating note")?;
+    load(&conn, id)?.ok_or_else(|| anyhow::anyhow!("note not found after update"))
+}
+
+pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM notes WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+/// All notes of a project, for export
+pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<ResearchNote>> {
+    search(pool, project_id, None, None)
+}
+
+// Import
+
+/// Recreate exported notes; `map_parent` translates an old (parent_type, parent_id) to the new parent id.
+/// Notes whose parent did not come across are skipped.
+pub fn import_snapshot(
+    pool: &DbPool,
+    project_id: i64,
+    notes: &[ResearchNote],
+    map_parent: impl Fn(&str, i64) -> Option<i64>,
+) -> anyhow::Result<()> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    for n in notes {
+        let Some(parent_id) = map_parent(&n.parent_type, n.parent_id) else { continue };
+        tx.execute(
+            "INSERT INTO notes (project_id, parent_type, parent_id, title, text, tags, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![project_id, n.parent_type, parent_id, n.title, n.text, serde_json::to_string(&n.tags)?, n.created_at, n.updated_at],
+        ).context("importing note")?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    fn note(parent_type: &str, parent_id: i64, title: &str, text: &str, tags: &[&str]) -> ResearchNoteCreate {
+        ResearchNoteCreate {
+            parent_type: parent_type.into(),
+            parent_id,
+            title: title.into(),
+            text: Some(text.into()),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn notes_attach_search_and_follow_their_parent() {
+        let manager = SqliteConnectionManager::file("file:memresearchnotes?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/017_add_research_notes.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO projects (id, name) VALUES (1, 'P');
+             INSERT INTO characters (id, project_id, name) VALUES (1, 1, 'Mara');
+             INSERT INTO docs (id, project_id, path, name) VALUES (1, 1, '', 'Harbour');",
+        ).unwrap();
+        drop(conn);
+
+        let ships = create(&pool, note("project", 1, "Clipper rigging", "Square sails on all three masts", &["ships", " Ships ", ""])).unwrap();
+        assert_eq!(ships.tags, vec!["ships"]);
+        create(&pool, note("character", 1, "Accent", "Grew up near the 100% docks", &["voice"])).unwrap();
+        create(&pool, note("doc", 1, "Tide tables", "High water at 06:12", &["ships", "tides"])).unwrap();
+        assert!(create(&pool, note("character", 99, "Ghost", "", &[])).is_err());
+        assert!(create(&pool, note("chapter", 1, "Bad", "", &[])).is_err());
+
+        assert_eq!(search(&pool, 1, Some("MASTS"), None).unwrap().len(), 1);
+        assert_eq!(search(&pool, 1, Some("100%"), None).unwrap().len(), 1);
+        assert_eq!(search(&pool, 1, Some("tide"), None).unwrap().len(), 1);
+        assert_eq!(search(&pool, 1, None, Some("SHIPS")).unwrap().len(), 2);
+        assert_eq!(list_tags(&pool, 1).unwrap(), vec!["ships", "tides", "voice"]);
+
+        let renamed = update(&pool, ships.id, ResearchNoteUpdate { title: Some("Rigging".into()), text: None, tags: Some(vec![]) }).unwrap();
+        assert_eq!(renamed.text.as_deref(), Some("Square sails on all three masts"));
+        assert!(renamed.tags.is_empty());
+
+        let conn = pool.get().unwrap();
+        conn.execute("DELETE FROM characters WHERE id = 1", []).unwrap();
+        drop(conn);
+        assert!(list_for(&pool, "character", 1).unwrap().is_empty());
+        assert_eq!(list(&pool, 1).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{Doc, DocMergeOptions, DocSplitOptions, MergeTimeline, SplitShare};
+use crate::services::duplicate::{Remap, copy_rows};
+use anyhow::Context;
+use rusqlite::Connection;
+use rusqlite::types::Value;
+
+/// Link tables keyed by doc_id, carried over on split and unioned on merge
+const LINK_TABLES: &[&str] = &["doc_characters", "doc_events", "doc_codex_entities", "doc_plot_threads"];
+
+struct DocRow {
+    id: i64,
+    project_id: i64,
+    group_id: Option<i64>,
+    sort_order: i64,
+    name: Option<String>,
+    text: String,
+    notes: Option<String>,
+}
+
+fn load(conn: &Connection, id: i64) -> anyhow::Result<DocRow> {
+    conn.query_row(
+        "SELECT id, project_id, doc_group_id, sort_order, name, text, notes FROM docs WHERE id = ?1",
+        [id],
+        |row| Ok(DocRow {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            group_id: row.get(2)?,
+            sort_order: row.get(3)?,
+            name: row.get(4)?,
+            text: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            notes: row.get(6)?,
+        }),
+    ).map_err(|_| anyhow::anyhow!("doc {} not found", id))
+}
+
+/// Renumber a group's docs 0..n, keeping their order
+fn renumber(conn: &Connection, project_id: i64, group_id: Option<i64>) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare("SELECT id FROM docs WHERE project_id = ?1 AND doc_group_id IS ?2 ORDER BY sort_order, id")?;
+    let ids = stmt.query_map(rusqlite::params![project_id, group_id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+    for (i, id) in ids.into_iter().enumerate() {
+        conn.execute("UPDATE docs SET sort_order = ?1 WHERE id = ?2", rusqlite::params![i as i64, id])?;
+    }
+    Ok(())
+}
+
+/// Split a doc at the given char offsets into consecutive docs in the same group. The first part
+/// stays in the original doc together with its drafts and attachments; comments move to the part
+/// they are anchored in; every part keeps the status, label and synopsis; links, timeline and
+/// notes are shared as `options` says.
+pub fn split(pool: &DbPool, doc_id: i64, offsets: &[usize], options: DocSplitOptions) -> anyhow::Result<Vec<Doc>> {
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let doc = load(&tx, doc_id)?;
+
+    let len = doc.text.chars().count();
+    let mut cuts: Vec<usize> = offsets.iter().copied().filter(|&o| o > 0 && o < len).collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    if cuts.is_empty() {
+        anyhow::bail!("no split offset falls inside the doc's text");
+    }
+    let bounds: Vec<usize> = std::iter::once(0).chain(cuts).chain(std::iter::once(len)).collect();
+    let byte_at = |char_pos: usize| doc.text.char_indices().nth(char_pos).map(|(b, _)| b).unwrap_or(doc.text.len());
+    let pieces: Vec<&str> = bounds.windows(2).map(|w| &doc.text[byte_at(w[0])..byte_at(w[1])]).collect();
+
+    tx.execute("UPDATE docs SET text = ?1 WHERE id = ?2", rusqlite::params![pieces[0], doc_id])?;
+    tx.execute(
+        "UPDATE docs SET sort_order = sort_order + ?1 WHERE project_id = ?2 AND doc_group_id IS ?3 AND sort_order > ?4",
+        rusqlite::params![(pieces.len() - 1) as i64, doc.project_id, doc.group_id, doc.sort_order],
+    )?;
+
+    let base_name = doc.name.clone().unwrap_or_default();
+    let mut ids = vec![doc_id];
+    for (i, piece) in pieces.iter().enumerate().skip(1) {
+        let name = options.names.get(i - 1)
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("{} ({})", base_name, i + 1).trim_start().to_string());
+        let notes = if options.notes == SplitShare::All { doc.notes.clone() } else { None };
+        tx.execute(
+            "INSERT INTO docs (project_id, name, doc_group_id, sort_order, path, text, notes) VALUES (?1, ?2, ?3, ?4, '', ?5, ?6)",
+            rusqlite::params![doc.project_id, name, doc.group_id, doc.sort_order + i as i64, piece, notes],
+        ).context("inserting split doc")?;
+        let new_id = tx.last_insert_rowid();
+        ids.push(new_id);
+
+        let new_doc = [("doc_id", Remap::Set(Value::Integer(new_id)))];
+        copy_rows(&tx, "doc_metadata", "doc_id = ?1", &doc_id, &new_doc)?;
+        if options.links == SplitShare::All {
+            for table in LINK_TABLES {
+                copy_rows(&tx, table, "doc_id = ?1", &doc_id, &new_doc)?;
+            }
+        }
+        if options.timeline == SplitShare::All {
+            copy_rows(&tx, "timelines", "entity_type = 'doc' AND entity_id = ?1", &doc_id, &[("entity_id", Remap::Set(Value::Integer(new_id)))])?;
+        }
+        if options.notes == SplitShare::All {
+            copy_rows(&tx, "notes", "parent_type = 'doc' AND parent_id = ?1", &doc_id, &[("parent_id", Remap::Set(Value::Integer(new_id)))])?;
+        }
+    }
+
+    let parts: Vec<(usize, usize, i64)> = bounds.windows(2).zip(&ids).map(|(w, &id)| (w[0], w[1], id)).collect();
+    crate::services::comments::split_anchors(&tx, doc_id, &doc.text, &parts)?;
+    tx.commit()?;
+    drop(conn);
+
+    ids.into_iter()
+        .map(|id| crate::services::docs::get_doc(pool, id)?.ok_or_else(|| anyhow::anyhow!("doc not found after split")))
+        .collect()
+}
+
+/// Merge docs into the first one, in the given order, joined by the separator. Drafts, comments,
+/// research notes and attachments move to the merged doc, links are combined (the first doc's
+/// plot thread role wins), doc notes are concatenated; the other docs are deleted.
+pub fn merge(pool: &DbPool, doc_ids: &[i64], options: DocMergeOptions) -> anyhow::Result<Doc> {
+    let mut seen = std::collections::HashSet::new();
+    let doc_ids: Vec<i64> = doc_ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+    if doc_ids.len() < 2 {
+        anyhow::bail!("select at least two docs to merge");
+    }
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let docs = doc_ids.iter().map(|&id| load(&tx, id)).collect::<anyhow::Result<Vec<_>>>()?;
+    let target = &docs[0];
+    if docs.iter().any(|d| d.project_id != target.project_id) {
+        anyhow::bail!("docs from different projects cannot be merged");
+    }
+
+    if options.timeline == MergeTimeline::Span {
+        span_timelines(&tx, target.id, &doc_ids)?;
+    }
+
+    let separator = options.separator.as_deref().unwrap_or("\n\n");
+    let mut text = target.text.clone();
+    let mut notes: Vec<&str> = target.notes.as_deref().filter(|n| !n.trim().is_empty()).into_iter().collect();
+    for d in &docs[1..] {
+        text.push_str(separator);
+        crate::services::comments::move_anchors(&tx, d.id, target.id, text.chars().count())?;
+        text.push_str(&d.text);
+        notes.extend(d.notes.as_deref().filter(|n| !n.trim().is_empty()));
+
+        for table in LINK_TABLES {
+            let cols: Vec<String> = {
+                let mut stmt = tx.prepare(&format!("PRAGMA table_info({})", table))?;
+                let cols = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<Result<Vec<_>, _>>()?;
+                cols.into_iter().filter(|c| c != "doc_id").collect()
+            };
+            tx.execute(
+                &format!("INSERT OR IGNORE INTO {0} (doc_id, {1}) SELECT ?2, {1} FROM {0} WHERE doc_id = ?1", table, cols.join(", ")),
+                rusqlite::params![d.id, target.id],
+            )?;
+        }
+        tx.execute("UPDATE drafts SET doc_id = ?2 WHERE doc_id = ?1", rusqlite::params![d.id, target.id])?;
+        for table in ["notes", "attachments"] {
+            tx.execute(
+                &format!("UPDATE {} SET parent_id = ?2 WHERE parent_type = 'doc' AND parent_id = ?1", table),
+                rusqlite::params![d.id, target.id],
+            )?;
+        }
+    }
+    let notes = if notes.is_empty() { None } else { Some(notes.join("\n\n")) };
+    tx.execute("UPDATE docs SET text = ?1, notes = ?2 WHERE id = ?3", rusqlite::params![text, notes, target.id])?;
+
+    let mut groups: Vec<Option<i64>> = Vec::new();
+    for d in &docs[1..] {
+        tx.execute("DELETE FROM docs WHERE id = ?1", [d.id])?;
+        if !groups.contains(&d.group_id) {
+            groups.push(d.group_id);
+        }
+    }
+    for group_id in groups {
+        renumber(&tx, target.project_id, group_id)?;
+    }
+    tx.commit()?;
+    drop(conn);
+    crate::services::docs::get_doc(pool, target.id)?.ok_or_else(|| anyhow::anyhow!("doc not found after merge"))
+}
+
+/// Give `target` a timeline from the earliest start to the latest end among `doc_ids`
+fn span_timelines(conn: &Connection, target: i64, doc_ids: &[i64]) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT calendar_id, start_date, end_date, start_day, COALESCE(end_day, start_day) FROM timelines
+         WHERE entity_type = 'doc' AND entity_id IN (SELECT value FROM json_each(?1)) AND start_day IS NOT NULL",
+    )?;
+    let spans = stmt.query_map([serde_json::to_string(doc_ids)?], |row| {
+        Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, i64>(3)?, row.get::<_, i64>(4)?))
+    })?.collect::<Result<Vec<_>, _>>()?;
+    let (Some(first), Some(last)) = (spans.iter().min_by_key(|s| s.3), spans.iter().max_by_key(|s| s.4)) else { return Ok(()) };
+    if spans.iter().any(|s| s.0 != first.0) {
+        anyhow::bail!("cannot span timelines written in different calendars");
+    }
+    let end_date = last.2.clone().or_else(|| last.1.clone());
+    let updated = conn.execute(
+        "UPDATE timelines SET start_date = ?1, end_date = ?2, start_day = ?3, end_day = ?4, calendar_id = ?5
+         WHERE entity_type = 'doc' AND entity_id = ?6",
+        rusqlite::params![first.1, end_date, first.3, last.4, first.0, target],
+    )?;
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO timelines (entity_type, entity_id, start_date, end_date, start_day, end_day, calendar_id) VALUES ('doc', ?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![target, first.1, end_date, first.3, last.4, first.0],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DocCommentCreate;
+    use crate::services::test_support;
+
+    /// Project 1 with doc 1 "Chapter" (Mara, metadata, a dated timeline, a draft and a comment on
+    /// "Ines") followed by doc 2 "Epilogue"; returns the comment id
+    fn setup() -> (DbPool, i64) {
+        let pool = test_support::pool("restructure");
+        pool.get().unwrap().execute_batch(
+            "INSERT INTO projects (id, name) VALUES (1, 'P');
+             INSERT INTO characters (id, project_id, name) VALUES (1, 1, 'Mara'), (2, 1, 'Ines');
+             INSERT INTO docs (id, project_id, path, name, text, notes, sort_order) VALUES
+               (1, 1, '', 'Chapter', 'Mara ran. Ines hid. Rain fell.', 'pace', 0),
+               (2, 1, '', 'Epilogue', 'Fin.', NULL, 1);
+             INSERT INTO doc_characters VALUES (1, 1);
+             INSERT INTO doc_metadata (doc_id, status, label) VALUES (1, 'Draft', 'Mara');
+             INSERT INTO timelines (entity_type, entity_id, start_date, start_day, end_day) VALUES ('doc', 1, '1850-03-01', 100, 100);
+             INSERT INTO drafts (doc_id, name, content, created_at, updated_at) VALUES (1, 'v1', 'old', 't', 't');",
+        ).unwrap();
+        let ines = crate::services::comments::create(&pool, 1, DocCommentCreate { start_offset: 10, end_offset: 14, body: "Where?".into(), author: None }).unwrap();
+        (pool, ines.id)
+    }
+
+    fn split_in_three(pool: &DbPool) -> Vec<Doc> {
+        let options = DocSplitOptions { names: vec!["Hiding".into()], links: SplitShare::All, timeline: SplitShare::First, notes: SplitShare::First };
+        split(pool, 1, &[10, 20, 99], options).unwrap()
+    }
+
+    #[test]
+    fn split_cuts_at_the_offsets_and_names_the_parts() {
+        let (pool, _) = setup();
+        let parts = split_in_three(&pool);
+        let texts: Vec<_> = parts.iter().map(|d| d.text.clone().unwrap()).collect();
+        assert_eq!(texts, vec!["Mara ran. ", "Ines hid. ", "Rain fell."]);
+        assert_eq!(parts.iter().map(|d| d.sort_order.unwrap()).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!((parts[1].name.as_deref(), parts[2].name.as_deref()), (Some("Hiding"), Some("Chapter (3)")));
+        assert_eq!(crate::services::docs::get_doc(&pool, 2).unwrap().unwrap().sort_order, Some(3));
+    }
+
+    #[test]
+    fn split_shares_links_and_timeline_as_asked() {
+        let (pool, _) = setup();
+        let parts = split_in_three(&pool);
+        assert_eq!(crate::services::characters::list_for_doc(&pool, parts[2].id).unwrap(), vec![1]);
+        assert_eq!(crate::services::doc_metadata::get(&pool, parts[2].id).unwrap().status.as_deref(), Some("Draft"));
+        assert!(crate::services::timelines::get_by_entity(&pool, "doc", parts[0].id).unwrap().is_some());
+        assert!(crate::services::timelines::get_by_entity(&pool, "doc", parts[1].id).unwrap().is_none());
+    }
+
+    #[test]
+    fn split_moves_comments_into_the_part_holding_their_text() {
+        let (pool, ines) = setup();
+        let parts = split_in_three(&pool);
+        let moved = crate::services::comments::get(&pool, ines).unwrap().unwrap();
+        assert_eq!((moved.doc_id, moved.start_offset, moved.quote.as_deref()), (parts[1].id, Some(0), Some("Ines")));
+    }
+
+    #[test]
+    fn split_refuses_empty_parts() {
+        let (pool, _) = setup();
+        let options = DocSplitOptions { names: vec![], links: SplitShare::All, timeline: SplitShare::All, notes: SplitShare::First };
+        assert!(split(&pool, 2, &[0, 4], options).is_err());
+    }
+
+    #[test]
+    fn merge_joins_text_links_and_comments_of_the_parts() {
+        let (pool, ines) = setup();
+        let parts = split_in_three(&pool);
+        crate::services::characters::attach_to_doc(&pool, parts[1].id, 2).unwrap();
+        let merged = merge(&pool, &[parts[0].id, parts[1].id, parts[2].id], DocMergeOptions { separator: Some(String::new()), timeline: MergeTimeline::Span }).unwrap();
+        assert_eq!(merged.text.as_deref(), Some("Mara ran. Ines hid. Rain fell."));
+        assert_eq!(merged.notes.as_deref(), Some("pace"));
+        assert_eq!(crate::services::characters::list_for_doc(&pool, merged.id).unwrap(), vec![1, 2]);
+        let back = crate::services::comments::get(&pool, ines).unwrap().unwrap();
+        assert_eq!((back.doc_id, back.start_offset, back.end_offset), (merged.id, Some(10), Some(14)));
+        assert_eq!(crate::services::drafts::list_drafts(&pool, merged.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn merge_removes_the_merged_docs_and_closes_up_the_order() {
+        let (pool, _) = setup();
+        let parts = split_in_three(&pool);
+        merge(&pool, &[parts[0].id, parts[1].id, parts[2].id], DocMergeOptions { separator: None, timeline: MergeTimeline::Span }).unwrap();
+        assert_eq!(crate::services::docs::get_doc(&pool, 2).unwrap().unwrap().sort_order, Some(1));
+        assert_eq!(crate::services::docs::list_docs(&pool, 1).unwrap().len(), 2);
+    }
+}
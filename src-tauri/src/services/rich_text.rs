@@ -0,0 +1,308 @@
+use crate::models::{HeadingImportOptions, ImportHeading, ManuscriptPlan, PlannedDoc, PlannedGroup};
+use std::collections::HashMap;
+
+/// A run of text with one formatting, as read from a Word, OpenDocument or RTF file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Append text, extending the last span when the formatting is the same
+pub fn push(spans: &mut Vec<Span>, text: &str, bold: bool, italic: bool) {
+    match spans.last_mut() {
+        Some(last) if last.bold == bold && last.italic == italic => last.text.push_str(text),
+        _ => spans.push(Span { text: text.to_string(), bold, italic }),
+    }
+}
+
+/// Unformatted text of the spans
+pub fn plain(spans: &[Span]) -> String {
+    spans.iter().map(|s| s.text.as_str()).collect()
+}
+
+/// Text with bold as `**…**` and italics as `*…*`; whitespace stays outside the markers
+pub fn to_markdown(spans: &[Span]) -> String {
+    let mut out = String::new();
+    for span in spans {
+        let marker = match (span.bold, span.italic) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => "",
+        };
+        let inner = span.text.trim();
+        if marker.is_empty() || inner.is_empty() {
+            out.push_str(&span.text);
+            continue;
+        }
+        let lead = &span.text[..span.text.len() - span.text.trim_start().len()];
+        let trail = &span.text[span.text.trim_end().len()..];
+        out.push_str(lead);
+        out.push_str(marker);
+        out.push_str(inner);
+        out.push_str(marker);
+        out.push_str(trail);
+    }
+    out.trim_end().to_string()
+}
+
+/// Split a line of doc text into spans, reading `***…***`, `**…**` and `*…*` as emphasis. A run of
+/// asterisks only counts as a marker when it hugs text and, when opening, is closed later on.
+pub fn parse_markdown(line: &str) -> Vec<Span> {
+    let chars: Vec<char> = line.chars().collect();
+    let (mut bold, mut italic) = (false, false);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '*' {
+            let start = i;
+            while i < chars.len() && chars[i] != '*' {
+                i += 1;
+            }
+            push(&mut spans, &chars[start..i].iter().collect::<String>(), bold, italic);
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i] == '*' {
+            i += 1;
+        }
+        let run = i - start;
+        let before = start.checked_sub(1).map(|k| chars[k]);
+        let after = chars.get(i).copied();
+        let (toggles_bold, toggles_italic) = match run {
+            1 => (false, true),
+            2 => (true, false),
+            3 => (true, true),
+            _ => (false, false),
+        };
+        let open = (toggles_bold && bold) || (toggles_italic && italic);
+        let is_marker = if run > 3 {
+            false
+        } else if open {
+            before.is_some_and(|c| !c.is_whitespace())
+        } else {
+            let marker: String = "*".repeat(run);
+            after.is_some_and(|c| !c.is_whitespace())
+                && chars[i..].iter().collect::<String>().contains(&marker)
+        };
+        if is_marker {
+            bold ^= toggles_bold;
+            italic ^= toggles_italic;
+        } else {
+            push(&mut spans, &"*".repeat(run), bold, italic);
+        }
+    }
+    spans
+}
+
+/// A paragraph of an imported document: heading level (0 = body text) and its formatted runs
+#[derive(Debug, Default)]
+pub struct Paragraph {
+    pub level: u8,
+    pub spans: Vec<Span>,
+}
+
+impl Paragraph {
+    pub fn push(&mut self, text: &str, bold: bool, italic: bool) {
+        push(&mut self.spans, text, bold, italic);
+    }
+}
+
+/// A reviewer comment, the text it covers and the paragraph it is anchored to
+#[derive(Debug, Default)]
+pub struct Comment {
+    pub author: String,
+    pub text: String,
+    pub anchor: String,
+    pub paragraph: Option<usize>,
+}
+
+fn render_comment(c: &Comment) -> String {
+    let mut out = String::new();
+    let anchor = c.anchor.trim();
+    if !anchor.is_empty() {
+        out.push_str(&format!("> {}\n", anchor));
+    }
+    match c.author.trim() {
+        "" => out.push_str(c.text.trim()),
+        author => out.push_str(&format!("{}: {}", author, c.text.trim())),
+    }
+    out
+}
+
+/// Doc being filled while walking paragraphs
+struct Building {
+    name: String,
+    paragraphs: Vec<String>,
+    first: usize,
+    named: bool,
+}
+
+/// Work out the docs and groups a document's paragraphs become. Body paragraphs are separated by
+/// blank lines, with bold and italics kept as markdown markers. Depending on the options a Heading 1
+/// or 2 stays in the text as `#`/`##`, starts a group, or starts a doc named after it; groups
+/// are not nested. Comments become notes of the doc holding the text they are anchored to.
+pub fn plan(paragraphs: &[Paragraph], comments: Vec<Comment>, name: &str, options: &HeadingImportOptions) -> ManuscriptPlan {
+    let mut out = ManuscriptPlan::default();
+    // First paragraph of each finished doc; loose docs always come before grouped ones, so this
+    // follows the order of `out.docs` then each group's docs
+    let mut firsts: Vec<usize> = Vec::new();
+    let mut doc: Option<Building> = None;
+    let finish = |doc: Option<Building>, out: &mut ManuscriptPlan, firsts: &mut Vec<usize>| {
+        let Some(b) = doc else { return };
+        let text = b.paragraphs.join("\n\n").trim().to_string();
+        if !b.named && text.is_empty() {
+            return;
+        }
+        let planned = PlannedDoc { words: text.split_whitespace().count(), name: b.name, text, notes: None };
+        match out.groups.last_mut() {
+            Some(g) => g.docs.push(planned),
+            None => out.docs.push(planned),
+        }
+        firsts.push(b.first);
+    };
+
+    for (i, p) in paragraphs.iter().enumerate() {
+        let action = match p.level {
+            1 => options.heading1,
+            2 => options.heading2,
+            _ => ImportHeading::Text,
+        };
+        let heading = plain(&p.spans).trim().to_string();
+        match action {
+            ImportHeading::Group if !heading.is_empty() => {
+                finish(doc.take(), &mut out, &mut firsts);
+                out.groups.push(PlannedGroup { name: heading, docs: Vec::new() });
+            }
+            ImportHeading::Doc if !heading.is_empty() => {
+                finish(doc.take(), &mut out, &mut firsts);
+                doc = Some(Building { name: heading, paragraphs: Vec::new(), first: i, named: true });
+            }
+            _ => {
+                let text = match p.level {
+                    0 => to_markdown(&p.spans),
+                    level => format!("{} {}", "#".repeat(level as usize), heading),
+                };
+                let b = doc.get_or_insert_with(|| Building {
+                    name: out.groups.last().map(|g| g.name.clone()).unwrap_or_else(|| name.to_string()),
+                    paragraphs: Vec::new(),
+                    first: i,
+                    named: false,
+                });
+                // Empty paragraphs are only spacing; paragraphs are already blank-line separated
+                if !text.trim().is_empty() {
+                    b.paragraphs.push(text);
+                }
+            }
+        }
+    }
+    finish(doc.take(), &mut out, &mut firsts);
+
+    // Each comment goes to the last doc starting at or before its paragraph
+    let mut notes: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut ordered: Vec<&Comment> = comments.iter().filter(|c| !c.text.trim().is_empty()).collect();
+    ordered.sort_by_key(|c| c.paragraph.unwrap_or(0));
+    for c in ordered {
+        let at = c.paragraph.unwrap_or(0);
+        let target = firsts.iter().rposition(|&first| first <= at).unwrap_or(0);
+        notes.entry(target).or_default().push(render_comment(c));
+    }
+    let docs = out.docs.iter_mut().chain(out.groups.iter_mut().flat_map(|g| g.docs.iter_mut()));
+    for (i, d) in docs.enumerate() {
+        if let Some(lines) = notes.remove(&i) {
+            d.notes = Some(lines.join("\n\n"));
+        }
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, bold: bool, italic: bool) -> Span {
+        Span { text: text.to_string(), bold, italic }
+    }
+
+    #[test]
+    fn markdown_reads_each_emphasis() {
+        assert_eq!(
+            parse_markdown("a *b* **c** ***d*** e"),
+            vec![span("a ", false, false), span("b", false, true), span(" ", false, false), span("c", true, false),
+                 span(" ", false, false), span("d", true, true), span(" e", false, false)],
+        );
+    }
+
+    #[test]
+    fn markdown_nests_italics_inside_bold() {
+        assert_eq!(
+            parse_markdown("**bold *both* bold**"),
+            vec![span("bold ", true, false), span("both", true, true), span(" bold", true, false)],
+        );
+        assert_eq!(
+            parse_markdown("*it **both***"),
+            vec![span("it ", false, true), span("both", true, true)],
+        );
+    }
+
+    #[test]
+    fn unbalanced_markers_stay_literal() {
+        assert_eq!(parse_markdown("5 * 3 = 15"), vec![span("5 * 3 = 15", false, false)]);
+        assert_eq!(parse_markdown("*never closed"), vec![span("*never closed", false, false)]);
+        assert_eq!(parse_markdown("**open *inner*"), vec![span("**open ", false, false), span("inner", false, true)]);
+        assert_eq!(parse_markdown("a **** b"), vec![span("a **** b", false, false)]);
+        // A closing run must hug the text before it
+        assert_eq!(parse_markdown("*a * b*"), vec![span("a * b", false, true)]);
+    }
+
+    #[test]
+    fn spans_round_trip_through_markdown() {
+        let spans = vec![span("Plain ", false, false), span(" strong ", true, false), span("both", true, true), span(" end  ", false, false)];
+        let markdown = to_markdown(&spans);
+        assert_eq!(markdown, "Plain  **strong** ***both*** end");
+        assert_eq!(plain(&parse_markdown(&markdown)), "Plain  strong both end");
+
+        let mut merged = Vec::new();
+        push(&mut merged, "a", true, false);
+        push(&mut merged, "b", true, false);
+        push(&mut merged, "c", false, false);
+        assert_eq!(merged, vec![span("ab", true, false), span("c", false, false)]);
+    }
+
+    #[test]
+    fn headings_split_groups_and_docs_with_comments_as_notes() {
+        let para = |level: u8, text: &str| Paragraph { level, spans: vec![span(text, false, false)] };
+        let paragraphs = vec![
+            para(0, "Front matter"),
+            para(1, "Part One"),
+            para(2, "Arrival"),
+            para(0, "The boat came in."),
+            para(0, ""),
+            para(2, "Storm"),
+            para(0, "Rain."),
+        ];
+        let comments = vec![
+            Comment { author: "Ed".into(), text: "Too short".into(), anchor: "Rain".into(), paragraph: Some(6) },
+            Comment { author: String::new(), text: "  ".into(), anchor: String::new(), paragraph: Some(3) },
+        ];
+        let options = HeadingImportOptions { heading1: ImportHeading::Group, heading2: ImportHeading::Doc };
+        let split = plan(&paragraphs, comments, "Book", &options);
+
+        assert_eq!(split.docs.len(), 1);
+        assert_eq!((split.docs[0].name.as_str(), split.docs[0].text.as_str()), ("Book", "Front matter"));
+        assert_eq!(split.groups.len(), 1);
+        let docs = &split.groups[0].docs;
+        assert_eq!(docs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["Arrival", "Storm"]);
+        assert_eq!(docs[0].text, "The boat came in.");
+        assert!(docs[0].notes.is_none());
+        assert_eq!(docs[1].notes.as_deref(), Some("> Rain\nEd: Too short"));
+
+        // By default headings stay in the text
+        let kept = plan(&paragraphs, Vec::new(), "Book", &HeadingImportOptions::default());
+        assert_eq!(kept.docs.len(), 1);
+        assert!(kept.docs[0].text.starts_with("Front matter\n\n# Part One\n\n## Arrival"));
+    }
+}
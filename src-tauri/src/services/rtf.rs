@@ -0,0 +1,210 @@
+use crate::services::rich_text::{self, Span};
+use encoding_rs::WINDOWS_1252;
+
+/// Destinations whose content is not part of the text
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl", "colortbl", "expandedcolortbl", "stylesheet", "info", "pict", "object", "fldinst",
+    "header", "headerl", "headerr", "headerf", "footer", "footerl", "footerr", "footerf",
+    "footnote", "annotation", "listtable", "listoverridetable", "rsidtbl", "generator",
+    "xmlnstbl", "themedata", "colorschememapping", "latentstyles", "datastore", "NeXTGraphic",
+];
+
+/// Formatting state of an RTF group
+#[derive(Debug, Clone, Copy)]
+struct State {
+    skip: bool,
+    bold: bool,
+    italic: bool,
+    // Characters to skip after a \uN
+    uc: usize,
+}
+
+struct Converter {
+    paragraphs: Vec<Vec<Span>>,
+    current: Vec<Span>,
+    state: State,
+    // Fallback characters still to skip after a \uN
+    skipping: usize,
+    high_surrogate: Option<u16>,
+}
+
+impl Converter {
+    fn emit(&mut self, text: &str) {
+        if self.state.skip {
+            return;
+        }
+        rich_text::push(&mut self.current, text, self.state.bold, self.state.italic);
+    }
+
+    fn emit_char(&mut self, c: char) {
+        let mut buf = [0u8; 4];
+        self.emit(c.encode_utf8(&mut buf));
+    }
+
+    /// Text byte in the document code page; only Windows-1252 is decoded
+    fn emit_byte(&mut self, byte: u8) {
+        if self.skipping > 0 {
+            self.skipping -= 1;
+            return;
+        }
+        let bytes = [byte];
+        let (text, _) = WINDOWS_1252.decode_without_bom_handling(&bytes);
+        self.emit(&text);
+    }
+
+    fn paragraph(&mut self) {
+        if !self.state.skip {
+            self.paragraphs.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    fn unicode(&mut self, value: i32) {
+        let unit = if value < 0 { (value + 65536) as u16 } else { value as u16 };
+        self.skipping = self.state.uc;
+        if (0xD800..0xDC00).contains(&unit) {
+            self.high_surrogate = Some(unit);
+            return;
+        }
+        let units: Vec<u16> = self.high_surrogate.take().into_iter().chain([unit]).collect();
+        for c in char::decode_utf16(units) {
+            self.emit_char(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>, first_in_group: bool) {
+        if first_in_group && SKIPPED_DESTINATIONS.contains(&word) {
+            self.state.skip = true;
+            return;
+        }
+        let on = param != Some(0);
+        match word {
+            "par" => self.paragraph(),
+            "line" => self.emit("\n"),
+            "tab" => self.emit("\t"),
+            "emdash" => self.emit("\u{2014}"),
+            "endash" => self.emit("\u{2013}"),
+            "lquote" => self.emit("\u{2018}"),
+            "rquote" => self.emit("\u{2019}"),
+            "ldblquote" => self.emit("\u{201C}"),
+            "rdblquote" => self.emit("\u{201D}"),
+            "bullet" => self.emit("\u{2022}"),
+            "b" => self.state.bold = on,
+            "i" => self.state.italic = on,
+            "plain" => {
+                self.state.bold = false;
+                self.state.italic = false;
+            }
+            "uc" => self.state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => self.unicode(param.unwrap_or(0)),
+            _ => {}
+        }
+    }
+}
+
+/// Convert RTF to text: paragraphs separated by blank lines, bold and italics as markdown
+/// markers. Font tables, pictures, headers and other non-text destinations are dropped.
+pub fn to_text(rtf: &[u8]) -> String {
+    let mut c = Converter {
+        paragraphs: Vec::new(),
+        current: Vec::new(),
+        state: State { skip: false, bold: false, italic: false, uc: 1 },
+        skipping: 0,
+        high_surrogate: None,
+    };
+    let mut stack: Vec<State> = Vec::new();
+    // Whether nothing but `\*` has been read since the last `{`
+    let mut group_start = false;
+    let mut i = 0;
+    while i < rtf.len() {
+        let b = rtf[i];
+        i += 1;
+        match b {
+            b'{' => {
+                stack.push(c.state);
+                group_start = true;
+                continue;
+            }
+            b'}' => {
+                if let Some(state) = stack.pop() {
+                    c.state = state;
+                }
+                c.skipping = 0;
+            }
+            b'\\' => {
+                let Some(&next) = rtf.get(i) else { break };
+                if next.is_ascii_alphabetic() {
+                    let start = i;
+                    while i < rtf.len() && rtf[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = String::from_utf8_lossy(&rtf[start..i]).into_owned();
+                    let num_start = i;
+                    if rtf.get(i) == Some(&b'-') {
+                        i += 1;
+                    }
+                    while i < rtf.len() && rtf[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param = std::str::from_utf8(&rtf[num_start..i]).ok().and_then(|n| n.parse::<i32>().ok());
+                    if rtf.get(i) == Some(&b' ') {
+                        i += 1;
+                    }
+                    if c.skipping > 0 && word != "u" {
+                        c.skipping -= 1;
+                    } else {
+                        c.control_word(&word, param, group_start);
+                    }
+                } else {
+                    i += 1;
+                    match next {
+                        b'*' => {
+                            c.state.skip = true;
+                            continue;
+                        }
+                        b'\'' => {
+                            let hex = rtf.get(i..i + 2).and_then(|h| std::str::from_utf8(h).ok());
+                            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                                c.emit_byte(byte);
+                            }
+                            i += 2;
+                        }
+                        b'\\' | b'{' | b'}' => c.emit_byte(next),
+                        b'~' => c.emit("\u{00A0}"),
+                        b'_' => c.emit("-"),
+                        b'\n' | b'\r' => c.paragraph(),
+                        _ => {}
+                    }
+                }
+            }
+            b'\r' | b'\n' => {}
+            _ => c.emit_byte(b),
+        }
+        group_start = false;
+    }
+    c.paragraphs.push(c.current);
+    c.paragraphs
+        .iter()
+        .map(|p| rich_text::to_markdown(p))
+        .filter(|p| !p.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtf_converts_to_marked_up_text() {
+        let rtf = br"{\rtf1\ansi\ansicpg1252\cocoartf2639
+{\fonttbl\f0\fnil\fcharset0 Palatino-Roman;}
+{\colortbl;\red255\green255\blue255;}
+{\*\expandedcolortbl;;}
+\pard\tx560\pardirnatural\partightenfactor0
+\f0\fs26 \cf0 Mara ran \i fast\i0  and \b far\b0 .\
+\
+She said \ldblquote caf\'e9\rdblquote  \u8212\'97 then \uc0\u8230 left.\par
+}";
+        assert_eq!(to_text(rtf), "Mara ran *fast* and **far**.\n\nShe said \u{201C}café\u{201D} \u{2014} then \u{2026}left.");
+    }
+}
@@ -0,0 +1,465 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{Character, Screenplay, ScreenplayDoc, ScreenplaySync};
+use crate::services::fountain;
+use crate::services::project_tree::TreeGroup;
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Whether docs of a project are read as Fountain unless they say otherwise
+pub fn project_enabled(pool: &DbPool, project_id: i64) -> anyhow::Result<bool> {
+    let conn = get_conn(pool)?;
+    let enabled = conn.query_row(
+        "SELECT enabled FROM screenplay_projects WHERE project_id = ?1",
+        rusqlite::params![project_id],
+        |row| row.get::<_, bool>(0),
+    ).optional()?;
+    Ok(enabled.unwrap_or(false))
+}
+
+pub fn set_project(pool: &DbPool, project_id: i64, enabled: bool) -> anyhow::Result<()> {
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "INSERT INTO screenplay_projects (project_id, enabled) VALUES (?1, ?2)
+         ON CONFLICT(project_id) DO UPDATE SET enabled = excluded.enabled",
+        rusqlite::params![project_id, enabled],
+    ).context("saving project screenplay mode")?;
+    Ok(())
+}
+
+/// Screenplay mode and location of a doc
+pub fn doc(pool: &DbPool, doc_id: i64) -> anyhow::Result<ScreenplayDoc> {
+    let conn = get_conn(pool)?;
+    conn.query_row(
+        "SELECT d.id, COALESCE(s.enabled, p.enabled, 0), s.enabled, s.location FROM docs d
+         LEFT JOIN screenplay_docs s ON s.doc_id = d.id
+         LEFT JOIN screenplay_projects p ON p.project_id = d.project_id
+         WHERE d.id = ?1",
+        rusqlite::params![doc_id],
+        |row| Ok(ScreenplayDoc { doc_id: row.get(0)?, screenplay: row.get(1)?, mode: row.get(2)?, location: row.get(3)? }),
+    ).optional()?.ok_or_else(|| anyhow::anyhow!("doc {} not found", doc_id))
+}
+
+/// Set a doc's own screenplay mode; None makes it follow the project again
+pub fn set_doc(pool: &DbPool, doc_id: i64, mode: Option<bool>) -> anyhow::Result<ScreenplayDoc> {
+    {
+        let conn = get_conn(pool)?;
+        conn.execute(
+            "INSERT INTO screenplay_docs (doc_id, enabled) VALUES (?1, ?2)
+             ON CONFLICT(doc_id) DO UPDATE SET enabled = excluded.enabled",
+            rusqlite::params![doc_id, mode],
+        ).context("saving doc screenplay mode")?;
+    }
+    doc(pool, doc_id)
+}
+
+/// Own screenplay mode and location of every doc in a project that has them, by doc id
+pub fn list_for_project(pool: &DbPool, project_id: i64) -> anyhow::Result<HashMap<i64, ScreenplayDoc>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT d.id, COALESCE(s.enabled, p.enabled, 0), s.enabled, s.location FROM screenplay_docs s
+         JOIN docs d ON d.id = s.doc_id
+         LEFT JOIN screenplay_projects p ON p.project_id = d.project_id
+         WHERE d.project_id = ?1",
+    )?;
+    let rows = stmt.query_map(rusqlite::params![project_id], |row| {
+        Ok((row.get::<_, i64>(0)?, ScreenplayDoc { doc_id: row.get(0)?, screenplay: row.get(1)?, mode: row.get(2)?, location: row.get(3)? }))
+    })?;
+    Ok(rows.collect::<Result<HashMap<_, _>, _>>()?)
+}
+
+/// Restore a project's screenplay mode and its docs' own modes and locations from an export,
+/// keyed by the exported doc ids
+pub fn import_snapshot(pool: &DbPool, project_id: i64, enabled: bool, docs: &HashMap<i64, ScreenplayDoc>, doc_id_map: &HashMap<i64, i64>) -> anyhow::Result<()> {
+    if enabled {
+        set_project(pool, project_id, true)?;
+    }
+    let conn = get_conn(pool)?;
+    for (old_id, d) in docs {
+        let Some(&new_id) = doc_id_map.get(old_id) else { continue };
+        conn.execute(
+            "INSERT INTO screenplay_docs (doc_id, enabled, location) VALUES (?1, ?2, ?3)
+             ON CONFLICT(doc_id) DO UPDATE SET enabled = excluded.enabled, location = excluded.location",
+            rusqlite::params![new_id, d.mode, d.location],
+        ).context("importing doc screenplay mode")?;
+    }
+    Ok(())
+}
+
+/// Ids of the project's docs that are in screenplay mode
+fn screenplay_docs(pool: &DbPool, project_id: i64) -> anyhow::Result<HashSet<i64>> {
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT d.id FROM docs d
+         LEFT JOIN screenplay_docs s ON s.doc_id = d.id
+         LEFT JOIN screenplay_projects p ON p.project_id = d.project_id
+         WHERE d.project_id = ?1 AND COALESCE(s.enabled, p.enabled, 0) = 1",
+    )?;
+    let ids = stmt.query_map(rusqlite::params![project_id], |row| row.get(0))?.collect::<Result<HashSet<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// A doc's text read as Fountain, whatever its mode
+pub fn parse_doc(pool: &DbPool, doc_id: i64) -> anyhow::Result<Screenplay> {
+    let doc = crate::services::docs::get_doc(pool, doc_id)?.ok_or_else(|| anyhow::anyhow!("doc {} not found", doc_id))?;
+    Ok(fountain::parse(doc.text.as_deref().unwrap_or("")))
+}
+
+/// "DR. MARA QUINN" as "Dr. Mara Quinn"
+fn title_case(name: &str) -> String {
+    name.split(' ').map(|word| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+            None => String::new(),
+        }
+    }).collect::<Vec<_>>().join(" ")
+}
+
+/// The character a speaker stands for: same name, or else the only character with that first
+/// name, ignoring case. A first name shared by several characters matches none of them.
+fn find_character<'a>(characters: &'a [Character], speaker: &str) -> Option<&'a Character> {
+    let speaker = speaker.to_lowercase();
+    if let Some(c) = characters.iter().find(|c| c.name.to_lowercase() == speaker) {
+        return Some(c);
+    }
+    let mut by_first = characters.iter().filter(|c| c.name.split_whitespace().next().is_some_and(|first| first.to_lowercase() == speaker));
+    match (by_first.next(), by_first.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// `sync_doc` on one connection, so callers can run it inside their own transaction
+fn sync_with(conn: &Connection, doc_id: i64, project_id: i64, text: &str, create_characters: bool) -> anyhow::Result<ScreenplaySync> {
+    let mut result = ScreenplaySync { docs: 1, ..Default::default() };
+    let screenplay = fountain::parse(text);
+    let location = screenplay.elements.iter().find_map(|e| match e {
+        crate::models::ScreenplayElement::SceneHeading { text, .. } => Some(fountain::location(text)),
+        _ => None,
+    }).filter(|l| !l.is_empty());
+
+    let mut stmt = conn.prepare("SELECT id, project_id, name, desc FROM characters WHERE project_id = ?1 ORDER BY id")?;
+    let mut characters = stmt
+        .query_map(rusqlite::params![project_id], |row| Ok(Character { id: row.get(0)?, project_id: row.get(1)?, name: row.get(2)?, desc: row.get(3)? }))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut speaking = Vec::new();
+    for speaker in fountain::speakers(&screenplay) {
+        let id = match find_character(&characters, &speaker) {
+            Some(character) => character.id,
+            None if create_characters => {
+                let name = title_case(&speaker);
+                conn.execute("INSERT INTO characters (project_id, name) VALUES (?1, ?2)", rusqlite::params![project_id, name])
+                    .context("inserting character")?;
+                let character = Character { id: conn.last_insert_rowid(), project_id, name, desc: None };
+                characters.push(character.clone());
+                result.characters_created.push(character);
+                characters.last().map_or(0, |c| c.id)
+            }
+            None => continue,
+        };
+        if !speaking.contains(&id) {
+            speaking.push(id);
+        }
+    }
+
+    // Links from an earlier sync whose speaker is gone are dropped; links made by hand stay
+    let previous: Option<String> = conn.query_row(
+        "SELECT speakers FROM screenplay_docs WHERE doc_id = ?1",
+        rusqlite::params![doc_id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    let previous: Vec<i64> = previous.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default();
+    for id in previous.iter().filter(|id| !speaking.contains(id)) {
+        conn.execute("DELETE FROM doc_characters WHERE doc_id = ?1 AND character_id = ?2", rusqlite::params![doc_id, id])?;
+    }
+    for id in &speaking {
+        conn.execute("INSERT OR IGNORE INTO doc_characters (doc_id, character_id) VALUES (?1, ?2)", rusqlite::params![doc_id, id])?;
+    }
+    result.characters_linked = speaking.len();
+
+    conn.execute(
+        "INSERT INTO screenplay_docs (doc_id, location, speakers) VALUES (?1, ?2, ?3)
+         ON CONFLICT(doc_id) DO UPDATE SET location = excluded.location, speakers = excluded.speakers",
+        rusqlite::params![doc_id, location, serde_json::to_string(&speaking)?],
+    ).context("saving doc location")?;
+    Ok(result)
+}
+
+/// Refresh a screenplay doc from its text: the location of its first scene heading, and links
+/// from the doc to the characters who speak in it. Speakers without a character get one when
+/// `create_characters` is set and are left out otherwise; characters who no longer speak lose the
+/// link the sync made. Docs not in screenplay mode are left alone.
+pub fn sync_doc(pool: &DbPool, doc_id: i64, create_characters: bool) -> anyhow::Result<ScreenplaySync> {
+    if !doc(pool, doc_id)?.screenplay {
+        return Ok(ScreenplaySync::default());
+    }
+    let doc = crate::services::docs::get_doc(pool, doc_id)?.ok_or_else(|| anyhow::anyhow!("doc {} not found", doc_id))?;
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let result = sync_with(&tx, doc_id, doc.project_id, doc.text.as_deref().unwrap_or(""), create_characters)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+fn merge(total: &mut ScreenplaySync, part: ScreenplaySync) {
+    total.docs += part.docs;
+    total.characters_created.extend(part.characters_created);
+    total.characters_linked += part.characters_linked;
+}
+
+/// Sync every screenplay doc of a project, adding characters for new speakers
+pub fn sync_project(pool: &DbPool, project_id: i64) -> anyhow::Result<ScreenplaySync> {
+    let mut ids: Vec<i64> = screenplay_docs(pool, project_id)?.into_iter().collect();
+    ids.sort_unstable();
+    let mut total = ScreenplaySync::default();
+    for id in ids {
+        merge(&mut total, sync_doc(pool, id, true)?);
+    }
+    Ok(total)
+}
+
+/// Import a Fountain file under `parent_group_id` (None = project root), split into scenes and
+/// acts by [`fountain::plan`]. The new docs are put in screenplay mode and synced, so speakers
+/// become characters. Nothing is written if any of it fails.
+pub fn import(pool: &DbPool, project_id: i64, parent_group_id: Option<i64>, path: &Path) -> anyhow::Result<ScreenplaySync> {
+    let decoded = crate::services::text_decode::read_text_file(path)?;
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Screenplay");
+    let plan = fountain::plan(&decoded.text, name);
+
+    let mut conn = get_conn(pool)?;
+    let tx = conn.transaction()?;
+    let next_order = |table: &str, parent_col: &str, parent: Option<i64>| -> rusqlite::Result<i64> {
+        tx.query_row(
+            &format!("SELECT COALESCE(MAX(sort_order), -1) + 1 FROM {} WHERE project_id = ?1 AND {} IS ?2", table, parent_col),
+            rusqlite::params![project_id, parent],
+            |row| row.get(0),
+        )
+    };
+    let mut created = Vec::new();
+    let mut add = |group_id: Option<i64>, doc: &crate::models::PlannedDoc| -> anyhow::Result<()> {
+        tx.execute(
+            "INSERT INTO docs (project_id, name, doc_group_id, sort_order, path, text) VALUES (?1, ?2, ?3, ?4, '', ?5)",
+            rusqlite::params![project_id, doc.name, group_id, next_order("docs", "doc_group_id", group_id)?, doc.text],
+        ).context("inserting doc")?;
+        let id = tx.last_insert_rowid();
+        tx.execute("INSERT INTO screenplay_docs (doc_id, enabled) VALUES (?1, 1)", rusqlite::params![id])?;
+        created.push((id, doc.text.clone()));
+        Ok(())
+    };
+    for doc in &plan.docs {
+        add(parent_group_id, doc)?;
+    }
+    for group in &plan.groups {
+        tx.execute(
+            "INSERT INTO doc_groups (project_id, name, parent_id, sort_order) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![project_id, group.name, parent_group_id, next_order("doc_groups", "parent_id", parent_group_id)?],
+        ).context("inserting group")?;
+        let group_id = tx.last_insert_rowid();
+        for doc in &group.docs {
+            add(Some(group_id), doc)?;
+        }
+    }
+    let mut total = ScreenplaySync::default();
+    for (id, text) in created {
+        merge(&mut total, sync_with(&tx, id, project_id, &text, true)?);
+    }
+    tx.commit()?;
+    Ok(total)
+}
+
+/// Fountain text of a group's screenplay docs, headed by a section line of its depth; nothing when
+/// none of them are screenplay docs
+fn group_text(group: &TreeGroup, depth: usize, ids: &HashSet<i64>, parts: &mut Vec<String>) {
+    let mut inner = Vec::new();
+    inner.extend(group.docs.iter().filter(|d| ids.contains(&d.id)).map(|d| d.text.as_deref().unwrap_or("").trim().to_string()));
+    for child in &group.children {
+        group_text(child, depth + 1, ids, &mut inner);
+    }
+    if !inner.is_empty() {
+        parts.push(format!("{} {}", "#".repeat(depth), group.group.name));
+        parts.extend(inner);
+    }
+}
+
+/// The project's screenplay docs as one Fountain text in manuscript order, groups written as
+/// sections. A title page naming the project is added when the first doc has none.
+pub fn export_text(pool: &DbPool, project_id: i64) -> anyhow::Result<String> {
+    let project = crate::services::projects::get(pool, project_id)?.ok_or_else(|| anyhow::anyhow!("project {} not found", project_id))?;
+    let ids = screenplay_docs(pool, project_id)?;
+    if ids.is_empty() {
+        anyhow::bail!("project has no docs in screenplay mode");
+    }
+    let tree = crate::services::project_tree::load(pool, project_id)?;
+    let mut parts: Vec<String> = tree.docs.iter().filter(|d| ids.contains(&d.id)).map(|d| d.text.as_deref().unwrap_or("").trim().to_string()).collect();
+    for group in &tree.groups {
+        group_text(group, 1, &ids, &mut parts);
+    }
+    let mut text = parts.into_iter().filter(|p| !p.is_empty()).collect::<Vec<_>>().join("\n\n");
+    if fountain::parse(&text).title_page.is_empty() {
+        text = format!("Title: {}\n\n{}", project.name, text);
+    }
+    text.push('\n');
+    Ok(text)
+}
+
+/// Write the project's screenplay docs to a .fountain file
+pub fn export(pool: &DbPool, project_id: i64, dest: &Path) -> anyhow::Result<()> {
+    let text = export_text(pool, project_id)?;
+    std::fs::write(dest, text).with_context(|| format!("writing {}", dest.display()))
+}
+
+/// Compile the project's screenplay docs to an industry-format PDF
+pub fn compile(pool: &DbPool, project_id: i64, dest: &Path) -> anyhow::Result<()> {
+    let project = crate::services::projects::get(pool, project_id)?.ok_or_else(|| anyhow::anyhow!("project {} not found", project_id))?;
+    let screenplay = fountain::parse(&export_text(pool, project_id)?);
+    let bytes = crate::services::screenplay_pdf::write(&screenplay, &project.name);
+    std::fs::write(dest, bytes).with_context(|| format!("writing {}", dest.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectCreate;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    const SCRIPT: &str = "Title: Harbour\nAuthor: A. Writer\n\n# Act One\n\nEXT. QUAY - DAWN\n\nGulls.\n\nMARA\nIt's late.\n\nINES (O.S.)\nNot for me.\n\n\
+        INT. KITCHEN - NIGHT\n\nMARA\nSit.\n\nOLD TOM\nI'll stand.\n";
+
+    fn setup(name: &str) -> (DbPool, crate::models::Project) {
+        let pool: DbPool = Pool::new(SqliteConnectionManager::file(format!("file:{}?mode=memory&cache=shared", name))).unwrap();
+        let conn = pool.get().unwrap();
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/016_add_doc_comments.sql"),
+            include_str!("../../migrations/022_add_screenplay.sql"),
+            include_str!("../../migrations/023_add_screenplay_speakers.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        drop(conn);
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        (pool, project)
+    }
+
+    /// A doc in screenplay mode holding `text`
+    fn script(pool: &DbPool, project_id: i64, text: &str) -> i64 {
+        let d = crate::services::docs::create_doc(pool, project_id, "Scene", None).unwrap();
+        crate::services::docs::update_doc(pool, d.id, text).unwrap();
+        set_doc(pool, d.id, Some(true)).unwrap();
+        d.id
+    }
+
+    #[test]
+    fn sync_drops_links_of_speakers_who_stopped_speaking() {
+        let (pool, project) = setup("memscreenplaydiff");
+        let mara = crate::services::characters::create(&pool, project.id, "Mara", None).unwrap();
+        let ines = crate::services::characters::create(&pool, project.id, "Ines", None).unwrap();
+        let tom = crate::services::characters::create(&pool, project.id, "Tom", None).unwrap();
+        let scene = script(&pool, project.id, "INT. KITCHEN - NIGHT\n\nMARA\nSit.\n\nINES\nNo.\n");
+        sync_doc(&pool, scene, false).unwrap();
+        // Tom is linked by hand and never speaks
+        crate::services::characters::attach_to_doc(&pool, scene, tom.id).unwrap();
+
+        crate::services::docs::update_doc(&pool, scene, "INT. KITCHEN - NIGHT\n\nMARA\nAlone now.\n").unwrap();
+        assert_eq!(sync_doc(&pool, scene, false).unwrap().characters_linked, 1);
+        let linked = crate::services::characters::list_for_doc(&pool, scene).unwrap();
+        assert_eq!(linked, vec![mara.id, tom.id]);
+        assert!(!linked.contains(&ines.id));
+    }
+
+    #[test]
+    fn shared_first_names_match_nobody() {
+        let (pool, project) = setup("memscreenplayambiguous");
+        crate::services::characters::create(&pool, project.id, "Mara Quinn", None).unwrap();
+        crate::services::characters::create(&pool, project.id, "Mara Lopez", None).unwrap();
+        let ines = crate::services::characters::create(&pool, project.id, "Ines Ferreira", None).unwrap();
+        let scene = script(&pool, project.id, "EXT. QUAY - DAWN\n\nMARA\nLate.\n\nINES\nEarly.\n");
+        let sync = sync_doc(&pool, scene, false).unwrap();
+        assert_eq!(sync.characters_linked, 1);
+        assert_eq!(crate::services::characters::list_for_doc(&pool, scene).unwrap(), vec![ines.id]);
+    }
+
+    #[test]
+    fn prose_docs_are_not_synced() {
+        let (pool, project) = setup("memscreenplayprose");
+        crate::services::characters::create(&pool, project.id, "Mara", None).unwrap();
+        let prose = crate::services::docs::create_doc(&pool, project.id, "Notes", None).unwrap();
+        crate::services::docs::update_doc(&pool, prose.id, "EXT. QUAY - DAWN\n\nMARA\nnot a cue here").unwrap();
+        assert_eq!(sync_doc(&pool, prose.id, true).unwrap().docs, 0);
+        assert!(crate::services::characters::list_for_doc(&pool, prose.id).unwrap().is_empty());
+        assert_eq!(doc(&pool, prose.id).unwrap().location, None);
+    }
+
+    #[test]
+    fn failed_import_writes_nothing() {
+        let (pool, project) = setup("memscreenplayrollback");
+        // Fail on the last new character, after the docs and the first character are written
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER no_tom BEFORE INSERT ON characters WHEN NEW.name = 'Old Tom' BEGIN SELECT RAISE(ABORT, 'no Tom'); END;",
+        ).unwrap();
+        drop(conn);
+        let path = std::env::temp_dir().join(format!("crate-screenplay-rollback-{}.fountain", std::process::id()));
+        std::fs::write(&path, SCRIPT).unwrap();
+        let result = import(&pool, project.id, None, &path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+        assert!(crate::services::docs::list_docs(&pool, project.id).unwrap().is_empty());
+        assert!(crate::services::doc_groups::list_doc_groups(&pool, project.id).unwrap().is_empty());
+        assert!(crate::services::characters::list(&pool, project.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn imports_syncs_and_compiles_screenplays() {
+        let (pool, project) = setup("memscreenplay");
+        let ines = crate::services::characters::create(&pool, project.id, "Ines Ferreira", None).unwrap();
+        let prose = crate::services::docs::create_doc(&pool, project.id, "Notes", None).unwrap();
+        crate::services::docs::update_doc(&pool, prose.id, "MARA\nnot a cue here").unwrap();
+
+        let path = std::env::temp_dir().join(format!("crate-screenplay-{}.fountain", std::process::id()));
+        std::fs::write(&path, SCRIPT).unwrap();
+        let sync = import(&pool, project.id, None, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sync.docs, 3);
+        let created: Vec<_> = sync.characters_created.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(created, vec!["Mara", "Old Tom"], "INES matches Ines Ferreira by first name");
+        assert_eq!(sync.characters_linked, 4);
+
+        let docs = crate::services::docs::list_in_manuscript_order(&pool, project.id).unwrap();
+        let quay = docs.iter().find(|d| d.name.as_deref() == Some("EXT. QUAY - DAWN")).unwrap();
+        let kitchen = docs.iter().find(|d| d.name.as_deref() == Some("INT. KITCHEN - NIGHT")).unwrap();
+        assert_eq!(doc(&pool, quay.id).unwrap().location.as_deref(), Some("QUAY"));
+        assert!(crate::services::characters::list_for_doc(&pool, quay.id).unwrap().contains(&ines.id));
+        assert_eq!(crate::services::characters::list_for_doc(&pool, kitchen.id).unwrap().len(), 2);
+
+        // The prose doc stays out until the project or the doc switches mode
+        assert_eq!(doc(&pool, prose.id).unwrap(), ScreenplayDoc { doc_id: prose.id, screenplay: false, mode: None, location: None });
+        set_project(&pool, project.id, true).unwrap();
+        set_doc(&pool, prose.id, Some(false)).unwrap();
+        assert!(!doc(&pool, prose.id).unwrap().screenplay && doc(&pool, quay.id).unwrap().screenplay);
+
+        // A scene edited to a new place moves its location; unknown speakers wait for a full sync
+        crate::services::docs::update_doc(&pool, quay.id, "EXT. LIGHTHOUSE - DAY\n\nKEEPER\nWho's there?").unwrap();
+        let edit = sync_doc(&pool, quay.id, false).unwrap();
+        assert!(edit.characters_created.is_empty() && edit.characters_linked == 0);
+        assert_eq!(doc(&pool, quay.id).unwrap().location.as_deref(), Some("LIGHTHOUSE"));
+        assert_eq!(sync_project(&pool, project.id).unwrap().characters_created[0].name, "Keeper");
+
+        let text = export_text(&pool, project.id).unwrap();
+        assert!(text.starts_with("Title: Harbour\nAuthor: A. Writer\n\n# Act One\n\nEXT. LIGHTHOUSE - DAY"));
+        assert!(!text.contains("not a cue"));
+        let reparsed = fountain::parse(&text);
+        assert_eq!(fountain::speakers(&reparsed), vec!["KEEPER", "MARA", "OLD TOM"]);
+
+        let pdf = crate::services::screenplay_pdf::write(&reparsed, &project.name);
+        let raw = String::from_utf8_lossy(&pdf);
+        assert!(raw.starts_with("%PDF-1.4") && raw.contains("/BaseFont /Courier"));
+        assert_eq!(raw.matches("/Type /Page ").count(), 2, "title page and one script page");
+    }
+}
@@ -0,0 +1,257 @@
+use crate::models::{Screenplay, ScreenplayElement};
+use crate::services::fountain::plain;
+use crate::services::pdf::{self, Document, Font};
+
+// US Letter, 12 pt Courier at ten characters to the inch and six lines to the inch
+const WIDTH: f32 = 612.0;
+const HEIGHT: f32 = 792.0;
+const SIZE: f32 = 12.0;
+const LEADING: f32 = 12.0;
+const INCH: f32 = 72.0;
+// Lines between the one-inch top and bottom margins
+const LINES: usize = 54;
+
+/// Left edge and width in characters of an element, measured from the page's left edge
+#[derive(Clone, Copy)]
+struct Column {
+    left: f32,
+    chars: usize,
+}
+
+const ACTION: Column = Column { left: 1.5 * INCH, chars: 60 };
+const CUE: Column = Column { left: 3.7 * INCH, chars: 38 };
+const PARENTHETICAL: Column = Column { left: 3.1 * INCH, chars: 25 };
+const DIALOGUE: Column = Column { left: 2.5 * INCH, chars: 35 };
+const RIGHT: f32 = 7.5 * INCH;
+
+/// Break text into lines of at most `width` characters at spaces, keeping its own line breaks
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for source in text.split('\n') {
+        let mut line = String::new();
+        for word in source.split_whitespace() {
+            let mut word = word.to_string();
+            while word.chars().count() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let rest = word.split_off(word.char_indices().nth(width).map_or(word.len(), |(i, _)| i));
+                lines.push(std::mem::replace(&mut word, rest));
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Sets the script line by line on a fixed grid, counting lines per page
+struct Layout {
+    doc: Document,
+    page: usize,
+    line: usize,
+    // Pages of the script proper, after the title page
+    first: usize,
+}
+
+impl Layout {
+    fn new_page(&mut self) {
+        self.page = self.doc.add_page();
+        self.line = 0;
+    }
+
+    fn room(&self) -> usize {
+        LINES - self.line
+    }
+
+    /// A blank line between elements, left out at the top of a page
+    fn gap(&mut self) {
+        if self.line > 0 {
+            self.line += 1;
+        }
+    }
+
+    /// Start a page unless `lines` more fit on this one, blank line before them included
+    fn keep(&mut self, lines: usize) {
+        if self.line > 0 && self.room() < lines + 1 {
+            self.new_page();
+        }
+    }
+
+    fn put(&mut self, x: f32, text: &str) {
+        if self.line >= LINES {
+            self.new_page();
+        }
+        let y = HEIGHT - INCH - LEADING * (self.line + 1) as f32 + 3.0;
+        self.doc.page(self.page).text(Font::Courier, SIZE, x, y, text);
+        self.line += 1;
+    }
+
+    fn lines(&mut self, column: Column, lines: &[String]) {
+        for line in lines {
+            self.put(column.left, line);
+        }
+    }
+
+    fn block(&mut self, column: Column, text: &str, keep: usize) {
+        let lines = wrap(&plain(text), column.chars);
+        self.keep(keep.min(lines.len()));
+        self.gap();
+        self.lines(column, &lines);
+    }
+
+    fn right(&mut self, text: &str) {
+        self.keep(1);
+        self.gap();
+        let text = plain(text).to_uppercase();
+        self.put(RIGHT - pdf::text_width(Font::Courier, SIZE, &text), &text);
+    }
+
+    fn center(&mut self, text: &str) {
+        for line in wrap(&plain(text), ACTION.chars) {
+            let x = (ACTION.left + RIGHT - pdf::text_width(Font::Courier, SIZE, &line)) / 2.0;
+            self.put(x, &line);
+        }
+    }
+
+    fn centered(&mut self, text: &str) {
+        self.keep(1);
+        self.gap();
+        self.center(text);
+    }
+
+    /// A speech: the cue, then parentheticals and dialogue. A speech that does not fit is split
+    /// with (MORE) and continued under the cue marked (CONT'D); one that would leave fewer than
+    /// two lines of it on the page moves to the next page whole.
+    fn speech(&mut self, cue: &str, parts: &[(Column, String)]) {
+        let lines: Vec<(Column, String)> = parts
+            .iter()
+            .flat_map(|(column, text)| wrap(&plain(text), column.chars).into_iter().map(move |l| (*column, l)))
+            .collect();
+        self.keep(3.min(lines.len() + 1));
+        self.gap();
+        self.put(CUE.left, cue);
+        let mut rest = &lines[..];
+        while rest.len() > self.room() {
+            let split = self.room().saturating_sub(1);
+            if split < 2 || rest.len() - split < 2 {
+                break;
+            }
+            for (column, line) in &rest[..split] {
+                self.put(column.left, line);
+            }
+            self.put(CUE.left, "(MORE)");
+            self.new_page();
+            self.put(CUE.left, &format!("{} (CONT'D)", cue.trim_end_matches(" (CONT'D)")));
+            rest = &rest[split..];
+        }
+        for (column, line) in rest {
+            self.put(column.left, line);
+        }
+    }
+
+    /// "2." at the top right of every script page but the first
+    fn page_numbers(&mut self) {
+        for index in self.first + 1..self.doc.page_count() {
+            let label = format!("{}.", index - self.first + 1);
+            let x = RIGHT - pdf::text_width(Font::Courier, SIZE, &label);
+            self.doc.page(index).text(Font::Courier, SIZE, x, HEIGHT - 0.5 * INCH, &label);
+        }
+    }
+}
+
+fn field<'a>(screenplay: &'a Screenplay, keys: &[&str]) -> Option<&'a str> {
+    screenplay.title_page.iter().find(|(k, _)| keys.iter().any(|key| k.eq_ignore_ascii_case(key))).map(|(_, v)| v.as_str())
+}
+
+/// Title centered a third of the way down with credit and author below; contact details and
+/// draft date at the bottom left
+fn title_page(layout: &mut Layout, screenplay: &Screenplay, title: &str) {
+    layout.new_page();
+    layout.line = 18;
+    let title = field(screenplay, &["title"]).unwrap_or(title).to_uppercase();
+    layout.center(&title);
+    let credit = field(screenplay, &["credit"]);
+    let author = field(screenplay, &["author", "authors"]);
+    if let Some(author) = author {
+        layout.line += 2;
+        layout.center(credit.unwrap_or("Written by"));
+        layout.line += 1;
+        layout.center(author);
+    }
+    if let Some(source) = field(screenplay, &["source"]) {
+        layout.line += 2;
+        layout.center(source);
+    }
+    let footer: Vec<&str> = ["contact", "draft date", "copyright"]
+        .iter()
+        .filter_map(|key| field(screenplay, &[key]))
+        .flat_map(str::lines)
+        .collect();
+    layout.line = LINES.saturating_sub(footer.len());
+    for line in footer {
+        layout.put(ACTION.left, &plain(line));
+    }
+}
+
+/// Lay out a screenplay in the usual industry format: Courier 12 on US Letter, scene headings
+/// and action across the page, cues, parentheticals and dialogue indented, transitions on the
+/// right. Dual dialogue is set one speech after the other; sections, synopses and emphasis
+/// markers are not printed. `title` stands in when the title page has none.
+pub fn write(screenplay: &Screenplay, title: &str) -> Vec<u8> {
+    let title_text = field(screenplay, &["title"]).unwrap_or(title).replace('\n', " ");
+    let mut layout = Layout { doc: Document::new(WIDTH, HEIGHT, &plain(&title_text)), page: 0, line: 0, first: 0 };
+    title_page(&mut layout, screenplay, title);
+    layout.first = layout.doc.page_count();
+    layout.new_page();
+    let mut elements = screenplay.elements.iter().peekable();
+    while let Some(element) = elements.next() {
+        match element {
+            ScreenplayElement::SceneHeading { text, number } => {
+                let heading = match number {
+                    Some(n) => format!("{}  {}", n, plain(text).to_uppercase()),
+                    None => plain(text).to_uppercase(),
+                };
+                // Keep the heading with two lines of what follows
+                layout.keep(3);
+                layout.gap();
+                layout.lines(ACTION, &wrap(&heading, ACTION.chars));
+            }
+            ScreenplayElement::Action { text } | ScreenplayElement::Lyrics { text } => layout.block(ACTION, text, 2),
+            ScreenplayElement::Character { name, extension, .. } => {
+                let cue = match extension {
+                    Some(ext) => format!("{} ({})", plain(name).to_uppercase(), ext.to_uppercase()),
+                    None => plain(name).to_uppercase(),
+                };
+                let mut parts = Vec::new();
+                while let Some(next) = elements.peek() {
+                    match next {
+                        ScreenplayElement::Parenthetical { text } => parts.push((PARENTHETICAL, text.clone())),
+                        ScreenplayElement::Dialogue { text } => parts.push((DIALOGUE, text.clone())),
+                        _ => break,
+                    }
+                    elements.next();
+                }
+                layout.speech(&cue, &parts);
+            }
+            ScreenplayElement::Parenthetical { text } => layout.block(PARENTHETICAL, text, 1),
+            ScreenplayElement::Dialogue { text } => layout.block(DIALOGUE, text, 1),
+            ScreenplayElement::Transition { text } => layout.right(text),
+            ScreenplayElement::Centered { text } => layout.centered(text),
+            ScreenplayElement::PageBreak => {
+                if layout.line > 0 {
+                    layout.new_page();
+                }
+            }
+            ScreenplayElement::Section { .. } | ScreenplayElement::Synopsis { .. } => {}
+        }
+    }
+    layout.page_numbers();
+    layout.doc.finish()
+}
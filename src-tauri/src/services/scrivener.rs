@@ -0,0 +1,447 @@
+use crate::db::DbPool;
+use crate::models::{DocMetadata, Project, ProjectCreate, ResearchNoteCreate};
+use anyhow::Context;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// An item of the .scrivx binder with its metadata ids resolved to names
+#[derive(Debug, Default)]
+pub struct BinderItem {
+    // UUID (Scrivener 3) or numeric ID (Scrivener 2) naming the content files
+    pub id: String,
+    // DraftFolder, ResearchFolder, TrashFolder, Folder, Text, PDF, Image, ...
+    pub kind: String,
+    pub title: String,
+    pub label: Option<String>,
+    pub status: Option<String>,
+    pub keywords: Vec<String>,
+    pub children: Vec<BinderItem>,
+}
+
+/// The parsed binder of a .scriv bundle
+#[derive(Debug)]
+pub struct Binder {
+    pub name: String,
+    pub items: Vec<BinderItem>,
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// Parse .scrivx XML: the binder tree plus the label, status and keyword lists its ids refer to
+pub fn parse_binder(xml: &str, name: &str) -> anyhow::Result<Binder> {
+    let mut reader = Reader::from_str(xml);
+    let mut path: Vec<String> = Vec::new();
+    let mut stack: Vec<BinderItem> = Vec::new();
+    let mut roots: Vec<BinderItem> = Vec::new();
+    // (label id, status id, keyword ids) per item, resolved once the lists are known
+    let mut ids: Vec<(Option<String>, Option<String>, Vec<String>)> = Vec::new();
+    let mut labels: HashMap<String, String> = HashMap::new();
+    let mut statuses: HashMap<String, String> = HashMap::new();
+    let mut keywords: HashMap<String, String> = HashMap::new();
+    let mut pending_id: Option<String> = None;
+    loop {
+        match reader.read_event().context("parsing .scrivx")? {
+            Event::Start(e) => {
+                let tag = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match tag.as_str() {
+                    "BinderItem" => {
+                        let id = attr(&e, b"UUID").or_else(|| attr(&e, b"ID")).unwrap_or_default();
+                        let kind = attr(&e, b"Type").unwrap_or_default();
+                        stack.push(BinderItem { id, kind, ..Default::default() });
+                        ids.push((None, None, Vec::new()));
+                    }
+                    "Label" | "Status" | "Keyword" => pending_id = attr(&e, b"ID"),
+                    _ => {}
+                }
+                path.push(tag);
+            }
+            Event::End(e) => {
+                path.pop();
+                if e.local_name().as_ref() == b"BinderItem" {
+                    if let Some(mut item) = stack.pop() {
+                        let (label, status, keyword_ids) = ids.pop().unwrap_or_default();
+                        item.label = label;
+                        item.status = status;
+                        item.keywords = keyword_ids;
+                        match stack.last_mut() {
+                            Some(parent) => parent.children.push(item),
+                            None => roots.push(item),
+                        }
+                    }
+                }
+            }
+            Event::Text(t) => {
+                let text = t.unescape().context("parsing .scrivx")?.trim().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+                let tail: Vec<&str> = path.iter().rev().take(3).map(String::as_str).collect();
+                match tail.as_slice() {
+                    ["Title", "BinderItem", ..] => {
+                        if let Some(item) = stack.last_mut() {
+                            item.title = text;
+                        }
+                    }
+                    ["LabelID", "MetaData", ..] => {
+                        if let Some(entry) = ids.last_mut() {
+                            entry.0 = Some(text);
+                        }
+                    }
+                    ["StatusID", "MetaData", ..] => {
+                        if let Some(entry) = ids.last_mut() {
+                            entry.1 = Some(text);
+                        }
+                    }
+                    ["KeywordID", "Keywords", "BinderItem"] => {
+                        if let Some(entry) = ids.last_mut() {
+                            entry.2.push(text);
+                        }
+                    }
+                    ["Label", "Labels", ..] => {
+                        if let Some(id) = pending_id.clone() {
+                            labels.insert(id, text);
+                        }
+                    }
+                    ["Status", "StatusItems", ..] => {
+                        if let Some(id) = pending_id.clone() {
+                            statuses.insert(id, text);
+                        }
+                    }
+                    // Scrivener 3 nests the keyword name in <Title>, Scrivener 2 has it inline
+                    ["Title", "Keyword", ..] | ["Keyword", "Keywords", ..] => {
+                        if let Some(id) = pending_id.clone() {
+                            keywords.insert(id, text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    fn resolve(item: &mut BinderItem, labels: &HashMap<String, String>, statuses: &HashMap<String, String>, keywords: &HashMap<String, String>) {
+        item.label = item.label.take().and_then(|id| labels.get(&id).cloned());
+        item.status = item.status.take().and_then(|id| statuses.get(&id).cloned());
+        item.keywords = item.keywords.iter().filter_map(|id| keywords.get(id).cloned()).collect();
+        for child in &mut item.children {
+            resolve(child, labels, statuses, keywords);
+        }
+    }
+    // "No Label" / "No Status" have id -1 and are dropped
+    labels.remove("-1");
+    statuses.remove("-1");
+    for item in &mut roots {
+        resolve(item, &labels, &statuses, &keywords);
+    }
+    Ok(Binder { name: name.to_string(), items: roots })
+}
+
+/// Content, synopsis and notes of a binder item, from the Scrivener 3 or Scrivener 2 layout
+struct Content {
+    text: String,
+    synopsis: String,
+    notes: String,
+}
+
+fn read_content(bundle: &Path, id: &str) -> anyhow::Result<Content> {
+    if id.is_empty() {
+        return Ok(Content { text: String::new(), synopsis: String::new(), notes: String::new() });
+    }
+    let read_rtf = |path: PathBuf| -> anyhow::Result<String> {
+        if !path.is_file() {
+            return Ok(String::new());
+        }
+        let bytes = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(crate::services::rtf::to_text(&bytes))
+    };
+    let read_txt = |path: PathBuf| -> anyhow::Result<String> {
+        if !path.is_file() {
+            return Ok(String::new());
+        }
+        Ok(crate::services::text_decode::read_text_file(&path)?.text.trim().to_string())
+    };
+    let v3 = bundle.join("Files").join("Data").join(id);
+    if v3.is_dir() {
+        return Ok(Content {
+            text: read_rtf(v3.join("content.rtf"))?,
+            synopsis: read_txt(v3.join("synopsis.txt"))?,
+            notes: read_rtf(v3.join("notes.rtf"))?,
+        });
+    }
+    let v2 = bundle.join("Files").join("Docs");
+    Ok(Content {
+        text: read_rtf(v2.join(format!("{}.rtf", id)))?,
+        synopsis: read_txt(v2.join(format!("{}_synopsis.txt", id)))?,
+        notes: read_rtf(v2.join(format!("{}_notes.rtf", id)))?,
+    })
+}
+
+/// Doc notes for an item: its label, status and keywords, then synopsis and notes
+fn notes_for(item: &BinderItem, content: &Content) -> String {
+    let mut meta: Vec<String> = Vec::new();
+    if let Some(label) = &item.label {
+        meta.push(format!("Label: {}", label));
+    }
+    if let Some(status) = &item.status {
+        meta.push(format!("Status: {}", status));
+    }
+    if !item.keywords.is_empty() {
+        meta.push(format!("Keywords: {}", item.keywords.join(", ")));
+    }
+    let mut sections: Vec<String> = Vec::new();
+    if !meta.is_empty() {
+        sections.push(meta.join("\n"));
+    }
+    if !content.synopsis.is_empty() {
+        sections.push(format!("Synopsis:\n{}", content.synopsis));
+    }
+    if !content.notes.is_empty() {
+        sections.push(content.notes.clone());
+    }
+    sections.join("\n\n")
+}
+
+/// Label, status and synopsis also become the doc's metadata, for compile filters
+fn set_metadata(pool: &DbPool, doc_id: i64, item: &BinderItem, content: &Content) -> anyhow::Result<()> {
+    if item.label.is_none() && item.status.is_none() && content.synopsis.is_empty() {
+        return Ok(());
+    }
+    crate::services::doc_metadata::set(pool, doc_id, DocMetadata {
+        status: item.status.clone(),
+        label: item.label.clone(),
+        synopsis: Some(content.synopsis.clone()),
+    })?;
+    Ok(())
+}
+
+fn title_of(item: &BinderItem) -> &str {
+    match item.title.trim() {
+        "" => "Untitled",
+        title => title,
+    }
+}
+
+fn is_folder(item: &BinderItem) -> bool {
+    matches!(item.kind.as_str(), "Folder" | "DraftFolder" | "ResearchFolder" | "TrashFolder")
+}
+
+/// Import manuscript items under `parent_id`: folders and texts with children become groups (a
+/// text's own content becomes the group's first doc), texts become docs; other items are skipped
+fn import_items(pool: &DbPool, bundle: &Path, project_id: i64, parent_id: Option<i64>, items: &[BinderItem]) -> anyhow::Result<()> {
+    for item in items {
+        if !is_folder(item) && item.kind != "Text" {
+            continue;
+        }
+        let content = read_content(bundle, &item.id)?;
+        let notes = notes_for(item, &content);
+        if item.children.is_empty() && item.kind == "Text" {
+            let doc = crate::services::docs::create_doc(pool, project_id, title_of(item), parent_id)?;
+            crate::services::docs::update_doc(pool, doc.id, &content.text)?;
+            if !notes.is_empty() {
+                crate::services::docs::update_doc_notes(pool, doc.id, &notes)?;
+            }
+            set_metadata(pool, doc.id, item, &content)?;
+            continue;
+        }
+        let group = crate::services::doc_groups::create_doc_group(pool, project_id, title_of(item), parent_id)?;
+        if !content.text.is_empty() {
+            let doc = crate::services::docs::create_doc(pool, project_id, title_of(item), Some(group.id))?;
+            crate::services::docs::update_doc(pool, doc.id, &content.text)?;
+            if !notes.is_empty() {
+                crate::services::docs::update_doc_notes(pool, doc.id, &notes)?;
+            }
+            set_metadata(pool, doc.id, item, &content)?;
+        } else if !notes.is_empty() {
+            crate::services::research_notes::create(pool, ResearchNoteCreate {
+                parent_type: "folder".into(),
+                parent_id: group.id,
+                title: title_of(item).to_string(),
+                text: Some(notes),
+                tags: item.keywords.clone(),
+            })?;
+        }
+        import_items(pool, bundle, project_id, Some(group.id), &item.children)?;
+    }
+    Ok(())
+}
+
+/// Import research items as project notes, tagged with their keywords and enclosing folders.
+/// Non-text items (PDFs, images, web pages) get a note with their title only.
+fn import_research(pool: &DbPool, bundle: &Path, project_id: i64, folders: &[String], items: &[BinderItem]) -> anyhow::Result<()> {
+    for item in items {
+        let content = read_content(bundle, &item.id)?;
+        let mut text = content.text.clone();
+        let notes = notes_for(item, &content);
+        if !notes.is_empty() {
+            text = if text.is_empty() { notes } else { format!("{}\n\n{}", text, notes) };
+        }
+        let mut tags = item.keywords.clone();
+        tags.extend(folders.iter().cloned());
+        if !is_folder(item) || !text.is_empty() {
+            crate::services::research_notes::create(pool, ResearchNoteCreate {
+                parent_type: "project".into(),
+                parent_id: project_id,
+                title: title_of(item).to_string(),
+                text: (!text.is_empty()).then_some(text),
+                tags,
+            })?;
+        }
+        if !item.children.is_empty() {
+            let mut inner = folders.to_vec();
+            inner.push(title_of(item).to_string());
+            import_research(pool, bundle, project_id, &inner, &item.children)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the binder of a .scriv bundle
+pub fn read_binder(bundle: &Path) -> anyhow::Result<Binder> {
+    let scrivx = std::fs::read_dir(bundle)
+        .with_context(|| format!("reading {}", bundle.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("scrivx")))
+        .ok_or_else(|| anyhow::anyhow!("no .scrivx file in {}: not a Scrivener project", bundle.display()))?;
+    let xml = std::fs::read_to_string(&scrivx).with_context(|| format!("reading {}", scrivx.display()))?;
+    let name = bundle.file_stem().or_else(|| scrivx.file_stem()).and_then(|s| s.to_str()).unwrap_or("Imported Project");
+    parse_binder(&xml, name)
+}
+
+/// Import a .scriv bundle as a new project. The Draft folder's contents become the root of the
+/// manuscript, other top-level folders follow as groups, Research becomes project notes and the
+/// Trash is left out. If anything fails the half-built project is removed again.
+pub fn import(pool: &DbPool, bundle: &Path) -> anyhow::Result<Project> {
+    let binder = read_binder(bundle)?;
+    let project = crate::services::projects::create(pool, ProjectCreate {
+        name: binder.name.clone(),
+        desc: Some("Imported from Scrivener".into()),
+        path: None,
+    })?;
+    let result = (|| -> anyhow::Result<()> {
+        let (draft, rest): (Vec<&BinderItem>, Vec<&BinderItem>) = binder.items.iter().partition(|i| i.kind == "DraftFolder");
+        for item in draft {
+            import_items(pool, bundle, project.id, None, &item.children)?;
+        }
+        for item in rest {
+            match item.kind.as_str() {
+                "TrashFolder" => {}
+                "ResearchFolder" => import_research(pool, bundle, project.id, &[], &item.children)?,
+                _ => import_items(pool, bundle, project.id, None, std::slice::from_ref(item))?,
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        crate::services::projects::delete(pool, project.id)?;
+        return Err(e.context(format!("importing {}", bundle.display())));
+    }
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support;
+
+    const SCRIVX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ScrivenerProject Version="2.0">
+  <Binder>
+    <BinderItem UUID="D" Type="DraftFolder"><Title>Manuscript</Title><Children>
+      <BinderItem UUID="C1" Type="Folder"><Title>Chapter One</Title>
+        <MetaData><LabelID>1</LabelID></MetaData>
+        <Children>
+          <BinderItem UUID="S1" Type="Text"><Title>Arrival</Title>
+            <MetaData><LabelID>1</LabelID><StatusID>2</StatusID></MetaData>
+            <Keywords><KeywordID>7</KeywordID></Keywords>
+          </BinderItem>
+        </Children>
+      </BinderItem>
+    </Children></BinderItem>
+    <BinderItem UUID="R" Type="ResearchFolder"><Title>Research</Title><Children>
+      <BinderItem UUID="R1" Type="Text"><Title>Harbour history</Title></BinderItem>
+      <BinderItem UUID="R2" Type="PDF"><Title>Tide tables</Title></BinderItem>
+    </Children></BinderItem>
+    <BinderItem UUID="T" Type="TrashFolder"><Title>Trash</Title><Children>
+      <BinderItem UUID="X" Type="Text"><Title>Cut scene</Title></BinderItem>
+    </Children></BinderItem>
+  </Binder>
+  <LabelSettings><Labels><Label ID="-1">No Label</Label><Label ID="1" Color="0 0 1">Mara POV</Label></Labels></LabelSettings>
+  <StatusSettings><StatusItems><Status ID="-1">No Status</Status><Status ID="2">First Draft</Status></StatusItems></StatusSettings>
+  <Keywords><Keyword ID="7" Color="1 0 0"><Title>Storm</Title></Keyword></Keywords>
+</ScrivenerProject>"#;
+
+    /// Write a bundle around SCRIVX to a temporary folder and import it
+    fn import_bundle(name: &str) -> (DbPool, i64) {
+        let dir = std::env::temp_dir().join(format!("cora-scriv-{}-{}", name, std::process::id()));
+        let bundle = dir.join("Harbour.scriv");
+        let file = |rel: &str, body: &str| {
+            let path = bundle.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, body).unwrap();
+        };
+        file("Harbour.scrivx", SCRIVX);
+        file("Files/Data/S1/content.rtf", r"{\rtf1\ansi{\fonttbl\f0 Times;}\f0 Mara \i ran\i0 .\par Rain.}");
+        file("Files/Data/S1/synopsis.txt", "Mara lands.\n");
+        file("Files/Data/S1/notes.rtf", r"{\rtf1 Check tides.}");
+        file("Files/Data/R1/content.rtf", r"{\rtf1 Built 1820.}");
+        file("Files/Data/X/content.rtf", r"{\rtf1 Gone.}");
+
+        let pool = test_support::pool("scrivener");
+        let project = import(&pool, &bundle).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(project.name, "Harbour");
+        (pool, project.id)
+    }
+
+    #[test]
+    fn binder_folders_and_texts_become_groups_and_docs() {
+        let (pool, project_id) = import_bundle("binder");
+        let groups = crate::services::doc_groups::list_doc_groups(&pool, project_id).unwrap();
+        assert_eq!(groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>(), vec!["Chapter One"]);
+        // The trashed scene is left out
+        let docs = crate::services::docs::list_docs(&pool, project_id).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].doc_group_id, Some(groups[0].id));
+        assert_eq!(docs[0].text.as_deref(), Some("Mara *ran*.\n\nRain."));
+    }
+
+    #[test]
+    fn labels_status_keywords_and_synopsis_are_kept() {
+        let (pool, project_id) = import_bundle("metadata");
+        let docs = crate::services::docs::list_docs(&pool, project_id).unwrap();
+        assert_eq!(
+            docs[0].notes.as_deref(),
+            Some("Label: Mara POV\nStatus: First Draft\nKeywords: Storm\n\nSynopsis:\nMara lands.\n\nCheck tides.")
+        );
+        let meta = crate::services::doc_metadata::get(&pool, docs[0].id).unwrap();
+        assert_eq!((meta.label.as_deref(), meta.status.as_deref()), (Some("Mara POV"), Some("First Draft")));
+        assert_eq!(meta.synopsis.as_deref(), Some("Mara lands."));
+    }
+
+    #[test]
+    fn folder_labels_become_group_notes() {
+        let (pool, project_id) = import_bundle("folders");
+        let groups = crate::services::doc_groups::list_doc_groups(&pool, project_id).unwrap();
+        let folder_notes = crate::services::research_notes::list_for(&pool, "folder", groups[0].id).unwrap();
+        assert_eq!(folder_notes[0].text.as_deref(), Some("Label: Mara POV"));
+    }
+
+    #[test]
+    fn research_items_become_project_notes() {
+        let (pool, project_id) = import_bundle("research");
+        let research = crate::services::research_notes::list_for(&pool, "project", project_id).unwrap();
+        let mut titles: Vec<_> = research.iter().map(|n| (n.title.as_str(), n.text.as_deref())).collect();
+        titles.sort();
+        assert_eq!(titles, vec![("Harbour history", Some("Built 1820.")), ("Tide tables", None)]);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{
+    CodexField, CodexTypeCreate, CodexTypeUpdate, PlotThreadCreate, Project, ProjectCreate, ProjectTemplate,
+    TemplateDoc, TemplateEntry, TemplateGroup, TemplateStructure,
+};
+use anyhow::Context;
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+use std::collections::HashMap;
+
+const BUILTIN: &[(&str, &str, &str, &str)] = &[
+    ("three_act", "Three-Act Structure", "Setup, confrontation and resolution with the key plot points", include_str!("../../templates/three_act.json")),
+    ("save_the_cat", "Save the Cat", "Blake Snyder's fifteen beats across three acts", include_str!("../../templates/save_the_cat.json")),
+    ("heros_journey", "The Hero's Journey", "Departure, initiation and return in twelve stages", include_str!("../../templates/heros_journey.json")),
+    ("short_story_collection", "Short Story Collection", "One folder per story plus front matter and a submissions tracker", include_str!("../../templates/short_story_collection.json")),
+];
+
+const SAVED_PREFIX: &str = "saved:";
+
+fn builtin(key: &str, name: &str, desc: &str, json: &str) -> anyhow::Result<ProjectTemplate> {
+    Ok(ProjectTemplate {
+        key: key.to_string(),
+        name: name.to_string(),
+        desc: Some(desc.to_string()),
+        builtin: true,
+        structure: serde_json::from_str(json).with_context(|| format!("parsing built-in template '{}'", key))?,
+    })
+}
+
+fn saved_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProjectTemplate> {
+    let id: i64 = row.get(0)?;
+    let structure: String = row.get(3)?;
+    Ok(ProjectTemplate {
+        key: format!("{}{}", SAVED_PREFIX, id),
+        name: row.get(1)?,
+        desc: row.get(2)?,
+        builtin: false,
+        structure: serde_json::from_str(&structure)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?,
+    })
+}
+
+/// Built-in templates first, then the user's saved templates by name
+pub fn list(pool: &DbPool) -> anyhow::Result<Vec<ProjectTemplate>> {
+    let mut out = BUILTIN.iter()
+        .map(|(key, name, desc, json)| builtin(key, name, desc, json))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare("SELECT id, name, desc, structure FROM project_templates ORDER BY name COLLATE NOCASE")?;
+    let saved = stmt.query_map([], saved_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    out.extend(saved);
+    Ok(out)
+}
+
+pub fn get(pool: &DbPool, key: &str) -> anyhow::Result<Option<ProjectTemplate>> {
+    if let Some(id) = key.strip_prefix(SAVED_PREFIX) {
+        let Ok(id) = id.parse::<i64>() else { return Ok(None) };
+        let conn = get_conn(pool)?;
+        return Ok(conn.query_row(
+            "SELECT id, name, desc, structure FROM project_templates WHERE id = ?1",
+            rusqlite::params![id],
+            saved_from_row,
+        ).optional()?);
+    }
+    BUILTIN.iter()
+        .find(|(k, ..)| *k == key)
+        .map(|(key, name, desc, json)| builtin(key, name, desc, json))
+        .transpose()
+}
+
+/// Create a project and scaffold it from a template. Doc prompts go into the docs' notes so the
+/// manuscript itself starts empty, unless the template was saved with its text. If scaffolding fails the half-built project is removed again.
+pub fn create_project(pool: &DbPool, payload: ProjectCreate, key: &str) -> anyhow::Result<Project> {
+    let template = get(pool, key)?.ok_or_else(|| anyhow::anyhow!("template '{}' not found", key))?;
+    let project = crate::services::projects::create(pool, payload)?;
+    if let Err(e) = scaffold(pool, project.id, &template.structure) {
+        crate::services::projects::delete(pool, project.id)?;
+        return Err(e.context(format!("applying template '{}'", template.name)));
+    }
+    Ok(project)
+}
+
+fn scaffold(pool: &DbPool, project_id: i64, structure: &TemplateStructure) -> anyhow::Result<()> {
+    fn add_group(pool: &DbPool, project_id: i64, group: &TemplateGroup, parent_id: Option<i64>) -> anyhow::Result<()> {
+        let created = crate::services::doc_groups::create_doc_group(pool, project_id, &group.name, parent_id)?;
+        add_docs(pool, project_id, &group.docs, Some(created.id))?;
+        for child in &group.groups {
+            add_group(pool, project_id, child, Some(created.id))?;
+        }
+        Ok(())
+    }
+    fn add_docs(pool: &DbPool, project_id: i64, docs: &[TemplateDoc], group_id: Option<i64>) -> anyhow::Result<()> {
+        for d in docs {
+            let doc = crate::services::docs::create_doc(pool, project_id, &d.name, group_id)?;
+            if let Some(prompt) = d.prompt.as_deref().filter(|p| !p.is_empty()) {
+                crate::services::docs::update_doc_notes(pool, doc.id, prompt)?;
+            }
+            if let Some(text) = d.text.as_deref().filter(|t| !t.is_empty()) {
+                crate::services::docs::update_doc(pool, doc.id, text)?;
+            }
+        }
+        Ok(())
+    }
+
+    for group in &structure.groups {
+        add_group(pool, project_id, group, None)?;
+    }
+    add_docs(pool, project_id, &structure.docs, None)?;
+    for c in &structure.characters {
+        crate::services::characters::create(pool, project_id, &c.name, c.desc.clone())?;
+    }
+    for e in &structure.events {
+        crate::services::events::create(pool, project_id, &e.name, e.desc.clone(), None, None, None, None)?;
+    }
+
+    // Types are created without references first so a field may point at a type listed after it
+    let mut type_ids = Vec::with_capacity(structure.codex_types.len());
+    for t in &structure.codex_types {
+        let fields = t.fields.iter().cloned().map(|f| CodexField { ref_type_id: None, ..f }).collect();
+        let created = crate::services::codex::create_type(pool, project_id, CodexTypeCreate { name: t.name.clone(), desc: t.desc.clone(), fields })?;
+        type_ids.push(created.id);
+    }
+    for (t, &id) in structure.codex_types.iter().zip(&type_ids) {
+        if t.fields.iter().all(|f| f.ref_type_id.is_none()) {
+            continue;
+        }
+        let fields = t.fields.iter().cloned().map(|f| {
+            let ref_type_id = f.ref_type_id.and_then(|i| usize::try_from(i).ok()).and_then(|i| type_ids.get(i).copied());
+            CodexField { ref_type_id, ..f }
+        }).collect();
+        crate::services::codex::update_type(pool, id, CodexTypeUpdate { name: None, desc: None, fields: Some(fields) })?;
+    }
+
+    for t in &structure.plot_threads {
+        crate::services::plot_threads::create(pool, project_id, t.clone())?;
+    }
+    Ok(())
+}
+
+/// Capture a project's outline as a template: folders and doc names (doc notes become the prompts,
+/// and the doc text is kept too when `include_text` is set), characters, events, codex types and
+/// plot threads
+pub fn save_project(pool: &DbPool, project_id: i64, name: &str, desc: Option<String>, include_text: bool) -> anyhow::Result<ProjectTemplate> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("template name cannot be empty");
+    }
+    crate::services::projects::get(pool, project_id)?.ok_or_else(|| anyhow::anyhow!("project not found"))?;
+
+    let mut groups = crate::services::doc_groups::list_doc_groups(pool, project_id)?;
+    groups.sort_by_key(|g| (g.sort_order.unwrap_or(0), g.id));
+    let mut docs = crate::services::docs::list_docs(pool, project_id)?;
+    docs.sort_by_key(|d| (d.sort_order.unwrap_or(0), d.id));
+
+    let mut docs_by_group: HashMap<Option<i64>, Vec<TemplateDoc>> = HashMap::new();
+    for d in docs {
+        let text = if include_text { d.text } else { None };
+        docs_by_group.entry(d.doc_group_id).or_default().push(TemplateDoc {
+            name: d.name.unwrap_or_default(),
+            prompt: d.notes.filter(|p| !p.trim().is_empty()),
+            text: text.filter(|t| !t.trim().is_empty()),
+        });
+    }
+    fn build(parent: Option<i64>, groups: &[crate::models::DocGroup], docs: &mut HashMap<Option<i64>, Vec<TemplateDoc>>) -> Vec<TemplateGroup> {
+        groups.iter()
+            .filter(|g| g.parent_id == parent)
+            .map(|g| TemplateGroup {
+                name: g.name.clone(),
+                docs: docs.remove(&Some(g.id)).unwrap_or_default(),
+                groups: build(Some(g.id), groups, docs),
+            })
+            .collect()
+    }
+    let tree = build(None, &groups, &mut docs_by_group);
+
+    let codex_types = crate::services::codex::list_types(pool, project_id)?;
+    let type_index: HashMap<i64, i64> = codex_types.iter().enumerate().map(|(i, t)| (t.id, i as i64)).collect();
+    let structure = TemplateStructure {
+        groups: tree,
+        docs: docs_by_group.remove(&None).unwrap_or_default(),
+        characters: crate::services::characters::list(pool, project_id)?
+            .into_iter().map(|c| TemplateEntry { name: c.name, desc: c.desc }).collect(),
+        events: crate::services::events::list(pool, project_id)?
+            .into_iter().map(|e| TemplateEntry { name: e.name, desc: e.desc }).collect(),
+        codex_types: codex_types.into_iter().map(|t| CodexTypeCreate {
+            name: t.name,
+            desc: t.desc,
+            fields: t.fields.into_iter().map(|f| CodexField {
+                ref_type_id: f.ref_type_id.and_then(|id| type_index.get(&id).copied()),
+                ..f
+            }).collect(),
+        }).collect(),
+        plot_threads: crate::services::plot_threads::list(pool, project_id)?
+            .into_iter().map(|t| PlotThreadCreate { name: t.name, color: t.color, desc: t.desc }).collect(),
+    };
+
+    let conn = get_conn(pool)?;
+    conn.execute(
+        "INSERT INTO project_templates (name, desc, structure, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![name, desc, serde_json::to_string(&structure)?, Utc::now().to_rfc3339()],
+    ).map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            anyhow::anyhow!("a template named '{}' already exists", name)
+        }
+        other => anyhow::Error::new(other).context("saving template"),
+    })?;
+    Ok(ProjectTemplate {
+        key: format!("{}{}", SAVED_PREFIX, conn.last_insert_rowid()),
+        name: name.to_string(),
+        desc,
+        builtin: false,
+        structure,
+    })
+}
+
+/// Delete a saved template; built-in templates cannot be deleted
+pub fn delete(pool: &DbPool, key: &str) -> anyhow::Result<()> {
+    let id = key.strip_prefix(SAVED_PREFIX)
+        .and_then(|id| id.parse::<i64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("only saved templates can be deleted"))?;
+    let conn = get_conn(pool)?;
+    conn.execute("DELETE FROM project_templates WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    #[test]
+    fn templates_scaffold_and_round_trip() {
+        let manager = SqliteConnectionManager::file("file:memtemplates?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/005_add_event_start_end.sql"),
+            include_str!("../../migrations/006_add_timelines.sql"),
+            include_str!("../../migrations/008_add_codex.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/012_add_calendars.sql"),
+            include_str!("../../migrations/013_add_timeline_day_indexes.sql"),
+            include_str!("../../migrations/015_add_plot_threads.sql"),
+            include_str!("../../migrations/016_add_doc_comments.sql"),
+            include_str!("../../migrations/019_add_project_templates.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        drop(conn);
+
+        // Every built-in template parses
+        assert_eq!(list(&pool).unwrap().len(), BUILTIN.len());
+
+        let novel = |name: &str| ProjectCreate { name: name.into(), desc: None, path: None };
+        let cat = create_project(&pool, novel("Harbour"), "save_the_cat").unwrap();
+        let groups = crate::services::doc_groups::list_doc_groups(&pool, cat.id).unwrap();
+        assert_eq!(groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>(), vec!["Act One", "Act Two", "Act Three"]);
+        let docs = crate::services::docs::list_in_manuscript_order(&pool, cat.id).unwrap();
+        assert_eq!(docs.len(), 15);
+        assert_eq!(docs[3].name.as_deref(), Some("Catalyst"));
+        assert!(docs[3].notes.as_deref().unwrap().contains("life-changing"));
+        assert_eq!(crate::services::plot_threads::list(&pool, cat.id).unwrap().len(), 2);
+        assert!(create_project(&pool, novel("Nope"), "snowflake").is_err());
+
+        // A saved template reproduces nested folders and codex references
+        let collection = create_project(&pool, novel("Tales"), "short_story_collection").unwrap();
+        let story = crate::services::doc_groups::list_doc_groups(&pool, collection.id).unwrap()[0].id;
+        crate::services::doc_groups::create_doc_group(&pool, collection.id, "Outtakes", Some(story)).unwrap();
+        let sub = crate::services::codex::list_types(&pool, collection.id).unwrap()[0].id;
+        crate::services::codex::create_type(&pool, collection.id, CodexTypeCreate {
+            name: "Market".into(),
+            desc: None,
+            fields: vec![CodexField { key: "last".into(), label: None, kind: crate::models::CodexFieldKind::Reference, ref_type_id: Some(sub), item_kind: None }],
+        }).unwrap();
+        let saved = save_project(&pool, collection.id, "My collection", None, false).unwrap();
+        assert!(save_project(&pool, collection.id, "My collection", None, false).is_err());
+        assert_eq!(saved.structure.groups[0].groups[0].name, "Outtakes");
+        assert_eq!(saved.structure.docs.len(), 2);
+
+        let copy = create_project(&pool, novel("More tales"), &saved.key).unwrap();
+        let types = crate::services::codex::list_types(&pool, copy.id).unwrap();
+        assert_eq!(types[1].fields[0].ref_type_id, Some(types[0].id));
+        assert_eq!(crate::services::docs::list_docs(&pool, copy.id).unwrap().len(), 5);
+
+        // Text saved with a template comes back as text, next to the notes
+        let doc = crate::services::docs::list_docs(&pool, collection.id).unwrap().remove(0);
+        crate::services::docs::update_doc(&pool, doc.id, "Once upon a tide.").unwrap();
+        crate::services::docs::update_doc_notes(&pool, doc.id, "Start at sea").unwrap();
+        let with_text = save_project(&pool, collection.id, "With text", None, true).unwrap();
+        let restored = create_project(&pool, novel("Tales again"), &with_text.key).unwrap();
+        let copied = crate::services::docs::list_docs(&pool, restored.id).unwrap();
+        let copied = copied.iter().find(|d| d.name == doc.name).unwrap();
+        assert_eq!((copied.text.as_deref(), copied.notes.as_deref()), (Some("Once upon a tide."), Some("Start at sea")));
+
+        assert!(delete(&pool, "three_act").is_err());
+        delete(&pool, &saved.key).unwrap();
+        assert!(get(&pool, &saved.key).unwrap().is_none());
+    }
+}
@@ -0,0 +1,63 @@
+//! Fixtures for service tests: a private in-memory database with the full schema, plus helpers
+//! that add the project, group and doc rows most tests start from
+use crate::db::DbPool;
+use crate::models::ProjectCreate;
+use r2d2_sqlite::SqliteConnectionManager;
+use r2d2::Pool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Every migration, in the order a fresh database gets them
+const MIGRATIONS: [&str; 23] = [
+    include_str!("../../migrations/001_create_schema.sql"),
+    include_str!("../../migrations/002_add_tree_order.sql"),
+    include_str!("../../migrations/003_add_doc_notes.sql"),
+    include_str!("../../migrations/004_add_doc_drafts.sql"),
+    include_str!("../../migrations/005_add_event_start_end.sql"),
+    include_str!("../../migrations/006_add_timelines.sql"),
+    include_str!("../../migrations/007_add_project_folder_drafts.sql"),
+    include_str!("../../migrations/008_add_codex.sql"),
+    include_str!("../../migrations/009_add_event_links.sql"),
+    include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+    include_str!("../../migrations/011_add_date_issues.sql"),
+    include_str!("../../migrations/012_add_calendars.sql"),
+    include_str!("../../migrations/013_add_timeline_day_indexes.sql"),
+    include_str!("../../migrations/014_scope_timelines_to_projects.sql"),
+    include_str!("../../migrations/015_add_plot_threads.sql"),
+    include_str!("../../migrations/016_add_doc_comments.sql"),
+    include_str!("../../migrations/017_add_research_notes.sql"),
+    include_str!("../../migrations/018_add_attachments.sql"),
+    include_str!("../../migrations/019_add_project_templates.sql"),
+    include_str!("../../migrations/020_add_compile_presets.sql"),
+    include_str!("../../migrations/021_add_typography_runs.sql"),
+    include_str!("../../migrations/022_add_screenplay.sql"),
+    include_str!("../../migrations/023_add_screenplay_speakers.sql"),
+];
+
+/// A shared-cache in-memory database of its own, migrated; `name` only makes failures easier to place
+pub fn pool(name: &str) -> DbPool {
+    let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let db_name = format!("file:mem{}{}?mode=memory&cache=shared", name, id);
+    let pool = Pool::new(SqliteConnectionManager::file(&db_name)).unwrap();
+    let conn = pool.get().unwrap();
+    for sql in MIGRATIONS {
+        conn.execute_batch(sql).unwrap();
+    }
+    pool
+}
+
+pub fn project(pool: &DbPool, name: &str) -> i64 {
+    crate::services::projects::create(pool, ProjectCreate { name: name.into(), desc: None, path: None }).unwrap().id
+}
+
+pub fn group(pool: &DbPool, project_id: i64, name: &str, parent_id: Option<i64>) -> i64 {
+    crate::services::doc_groups::create_doc_group(pool, project_id, name, parent_id).unwrap().id
+}
+
+/// A doc at the end of its group (or the root) holding `text`
+pub fn doc(pool: &DbPool, project_id: i64, group_id: Option<i64>, name: &str, text: &str) -> i64 {
+    let id = crate::services::docs::create_doc(pool, project_id, name, group_id).unwrap().id;
+    crate::services::docs::update_doc(pool, id, text).unwrap();
+    id
+}
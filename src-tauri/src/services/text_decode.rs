@@ -0,0 +1,101 @@
+use anyhow::Context;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::path::Path;
+
+/// How many leading bytes the UTF-16 heuristic looks at
+const SNIFF_LEN: usize = 4096;
+
+/// Text decoded to UTF-8 with `\n` line endings, and the encoding it was read as
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static str,
+}
+
+/// Turn CRLF and lone CR line endings into LF
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Guess UTF-16 without a BOM: mostly-ASCII text has a zero in every other byte
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN) & !1];
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+fn decode_as(encoding: &'static Encoding, bytes: &[u8]) -> anyhow::Result<String> {
+    let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+    if had_errors {
+        anyhow::bail!("not valid {}", encoding.name());
+    }
+    Ok(text.into_owned())
+}
+
+/// Decode file contents: a BOM decides if present, then BOM-less UTF-16, then valid UTF-8,
+/// and anything else is read as Windows-1252 (which also covers Latin-1). The BOM is dropped
+/// and line endings are normalised. Binary data is rejected.
+pub fn decode(bytes: &[u8]) -> anyhow::Result<Decoded> {
+    let (encoding, text) = if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        (encoding, decode_as(encoding, &bytes[bom_len..])?)
+    } else if let Some(encoding) = sniff_utf16(bytes) {
+        (encoding, decode_as(encoding, bytes)?)
+    } else if let Ok(text) = std::str::from_utf8(bytes) {
+        (encoding_rs::UTF_8, text.to_string())
+    } else {
+        if bytes.contains(&0) {
+            anyhow::bail!("looks like a binary file");
+        }
+        (WINDOWS_1252, decode_as(WINDOWS_1252, bytes)?)
+    };
+    if text.contains('\0') {
+        anyhow::bail!("looks like a binary file");
+    }
+    Ok(Decoded { text: normalize_newlines(&text), encoding: encoding.name() })
+}
+
+/// Read and decode a text file for import
+pub fn read_text_file(path: &Path) -> anyhow::Result<Decoded> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    decode(&bytes).with_context(|| format!("decoding {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_common_manuscript_encodings() {
+        let utf8 = decode("Café\r\nNaïve\rEnd".as_bytes()).unwrap();
+        assert_eq!((utf8.text.as_str(), utf8.encoding), ("Café\nNaïve\nEnd", "UTF-8"));
+
+        let bom = decode(b"\xEF\xBB\xBFHello").unwrap();
+        assert_eq!((bom.text.as_str(), bom.encoding), ("Hello", "UTF-8"));
+
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter()
+            .chain("Mara’s\r\n".encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect();
+        let utf16 = decode(&utf16).unwrap();
+        assert_eq!((utf16.text.as_str(), utf16.encoding), ("Mara’s\n", "UTF-16LE"));
+
+        let bare: Vec<u8> = "Rain fell on the quay.".encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(decode(&bare).unwrap().encoding, "UTF-16BE");
+
+        // “Café” — in Windows-1252 smart quotes and é are single bytes
+        let cp1252 = decode(b"\x93Caf\xE9\x94").unwrap();
+        assert_eq!((cp1252.text.as_str(), cp1252.encoding), ("\u{201C}Café\u{201D}", "windows-1252"));
+
+        assert!(decode(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\xff").is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{TimelineNode, TimelineSource, TimelineSpan};
+use crate::services::calendars;
+use std::collections::HashMap;
+
+type Row = (Option<String>, Option<String>, Option<i64>, Option<i64>);
+
+/// Stored dates as a span; rows whose dates could not be resolved to days are undated
+fn span_of((start_date, end_date, start_day, end_day): Row) -> Option<TimelineSpan> {
+    let (start_day, end_day) = match (start_day, end_day) {
+        (Some(s), Some(e)) => (s, e),
+        (Some(d), None) | (None, Some(d)) => (d, d),
+        (None, None) => return None,
+    };
+    Some(TimelineSpan { start_date, end_date, start_day, end_day })
+}
+
+/// Earliest start and latest end of the children's effective spans
+fn roll_up(children: &[TimelineNode]) -> Option<TimelineSpan> {
+    let spans: Vec<&TimelineSpan> = children.iter().filter_map(effective).collect();
+    let first = spans.iter().min_by_key(|s| s.start_day)?;
+    let last = spans.iter().max_by_key(|s| s.end_day)?;
+    Some(TimelineSpan {
+        start_date: first.start_date.clone().or_else(|| first.end_date.clone()),
+        end_date: last.end_date.clone().or_else(|| last.start_date.clone()),
+        start_day: first.start_day,
+        end_day: last.end_day,
+    })
+}
+
+/// A project's Gregorian timeline_start/timeline_end columns as a span; a single bound stands for both
+fn column_span(start: Option<String>, end: Option<String>) -> Option<TimelineSpan> {
+    let (start_day, end_day) = calendars::gregorian_days(start.as_deref(), end.as_deref())?;
+    Some(TimelineSpan { start_date: start, end_date: end, start_day, end_day })
+}
+
+/// An explicit range wins over the derived one
+pub fn effective(node: &TimelineNode) -> Option<&TimelineSpan> {
+    node.explicit.as_ref().or(node.derived.as_ref())
+}
+
+fn node(entity_type: &str, entity_id: i64, name: String, explicit: Option<TimelineSpan>, children: Vec<TimelineNode>) -> TimelineNode {
+    let derived = roll_up(&children);
+    let source = match (&explicit, &derived) {
+        (Some(_), _) => TimelineSource::Explicit,
+        (None, Some(_)) => TimelineSource::Derived,
+        (None, None) => TimelineSource::Undated,
+    };
+    TimelineNode { entity_type: entity_type.to_string(), entity_id, name, explicit, derived, source, children }
+}
+
+/// The whole timeline hierarchy of a project in a fixed number of queries.
+/// Folders and the project derive their range from descendant docs and events; docs from their events.
+/// Children follow manuscript order: a folder's docs, then its subfolders.
+pub fn tree(pool: &DbPool, project_id: i64) -> anyhow::Result<TimelineNode> {
+    let conn = get_conn(pool)?;
+    let (project_name, project_start, project_end): (String, Option<String>, Option<String>) = conn.query_row(
+        "SELECT COALESCE(name, ''), timeline_start, timeline_end FROM projects WHERE id = ?1",
+        rusqlite::params![project_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).map_err(|_| anyhow::anyhow!("project not found"))?;
+
+    // Explicit timeline rows, keyed by (entity_type, entity_id)
+    let mut stmt = conn.prepare(
+        "SELECT t.entity_type, t.entity_id, t.start_date, t.end_date, t.start_day, t.end_day FROM timelines t
+         WHERE (t.entity_type = 'project' AND t.entity_id = ?1)
+            OR (t.entity_type = 'folder' AND t.entity_id IN (SELECT id FROM doc_groups WHERE project_id = ?1))
+            OR (t.entity_type = 'doc' AND t.entity_id IN (SELECT id FROM docs WHERE project_id = ?1))
+            OR (t.entity_type = 'event' AND t.entity_id IN (SELECT id FROM events WHERE project_id = ?1))"
+    )?;
+    let mut explicit: HashMap<(String, i64), TimelineSpan> = stmt
+        .query_map(rusqlite::params![project_id], |row| {
+            Ok(((row.get::<_, String>(0)?, row.get::<_, i64>(1)?), (row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)))
+        })?
+        .collect::<Result<Vec<(_, Row)>, _>>()?
+        .into_iter()
+        .filter_map(|(key, row)| span_of(row).map(|s| (key, s)))
+        .collect();
+
+    // Events carry their own dates; a timeline row for the event overrides them
+    let mut stmt = conn.prepare(
+        "SELECT id, COALESCE(name, ''), COALESCE(start_date, date), end_date, start_day, end_day FROM events WHERE project_id = ?1"
+    )?;
+    let events: HashMap<i64, (String, Option<TimelineSpan>)> = stmt
+        .query_map(rusqlite::params![project_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, (row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)))
+        })?
+        .collect::<Result<Vec<(i64, String, Row)>, _>>()?
+        .into_iter()
+        .map(|(id, name, row)| {
+            let span = explicit.remove(&("event".to_string(), id)).or_else(|| span_of(row));
+            (id, (name, span))
+        })
+        .collect();
+    let event_node = |id: i64| -> Option<TimelineNode> {
+        let (name, span) = events.get(&id)?;
+        Some(node("event", id, name.clone(), span.clone(), Vec::new()))
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT de.doc_id, de.event_id FROM doc_events de JOIN docs d ON d.id = de.doc_id
+         WHERE d.project_id = ?1 ORDER BY de.doc_id, de.event_id"
+    )?;
+    let mut events_by_doc: HashMap<i64, Vec<i64>> = HashMap::new();
+    for pair in stmt.query_map(rusqlite::params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))? {
+        let (doc_id, event_id) = pair?;
+        events_by_doc.entry(doc_id).or_default().push(event_id);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, COALESCE(name, ''), parent_id FROM doc_groups WHERE project_id = ?1 ORDER BY sort_order, id"
+    )?;
+    let mut groups_by_parent: HashMap<Option<i64>, Vec<(i64, String)>> = HashMap::new();
+    for g in stmt.query_map(rusqlite::params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?)))? {
+        let (id, name, parent_id) = g?;
+        groups_by_parent.entry(parent_id).or_default().push((id, name));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, COALESCE(name, 'Untitled'), doc_group_id FROM docs WHERE project_id = ?1 ORDER BY sort_order, id"
+    )?;
+    let mut docs_by_group: HashMap<Option<i64>, Vec<(i64, String)>> = HashMap::new();
+    for d in stmt.query_map(rusqlite::params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?)))? {
+        let (id, name, group_id) = d?;
+        docs_by_group.entry(group_id).or_default().push((id, name));
+    }
+
+    fn children_of(
+        group_id: Option<i64>,
+        groups_by_parent: &HashMap<Option<i64>, Vec<(i64, String)>>,
+        docs_by_group: &HashMap<Option<i64>, Vec<(i64, String)>>,
+        events_by_doc: &HashMap<i64, Vec<i64>>,
+        explicit: &mut HashMap<(String, i64), TimelineSpan>,
+        event_node: &dyn Fn(i64) -> Option<TimelineNode>,
+    ) -> Vec<TimelineNode> {
+        let mut out = Vec::new();
+        for (doc_id, name) in docs_by_group.get(&group_id).into_iter().flatten() {
+            let events = events_by_doc.get(doc_id).into_iter().flatten().filter_map(|id| event_node(*id)).collect();
+            let span = explicit.remove(&("doc".to_string(), *doc_id));
+            out.push(node("doc", *doc_id, name.clone(), span, events));
+        }
+        for (child_id, name) in groups_by_parent.get(&group_id).into_iter().flatten() {
+            let children = children_of(Some(*child_id), groups_by_parent, docs_by_group, events_by_doc, explicit, event_node);
+            let span = explicit.remove(&("folder".to_string(), *child_id));
+            out.push(node("folder", *child_id, name.clone(), span, children));
+        }
+        out
+    }
+
+    let mut children = children_of(None, &groups_by_parent, &docs_by_group, &events_by_doc, &mut explicit, &event_node);
+    // Events not attached to any doc still belong to the project's range
+    let attached: std::collections::HashSet<i64> = events_by_doc.values().flatten().copied().collect();
+    let mut loose: Vec<i64> = events.keys().filter(|id| !attached.contains(id)).copied().collect();
+    loose.sort();
+    children.extend(loose.into_iter().filter_map(event_node));
+
+    // The project's own start/end columns win over its timeline row, as in continuity checks
+    let row = explicit.remove(&("project".to_string(), project_id));
+    let span = column_span(project_start, project_end).or(row);
+    Ok(node("project", project_id, project_name, span, children))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+
+    #[test]
+    fn folders_and_project_roll_up_from_docs_and_events() {
+        let manager = SqliteConnectionManager::file("file:memrollup?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name) VALUES ('P')", []).unwrap();
+        let project_id = conn.last_insert_rowid();
+        let group = |name: &str, parent: Option<i64>| -> i64 {
+            conn.execute(
+                "INSERT INTO doc_groups (project_id, name, parent_id, sort_order) VALUES (?1, ?2, ?3, 0)",
+                rusqlite::params![project_id, name, parent],
+            ).unwrap();
+            conn.last_insert_rowid()
+        };
+        let part = group("Part One", None);
+        let chapter = group("Chapter 1", Some(part));
+        let fixed = group("Fixed", None);
+
+        let doc = |name: &str, group_id: i64, order: i64| -> i64 {
+            conn.execute(
+                "INSERT INTO docs (project_id, path, name, doc_group_id, sort_order) VALUES (?1, '', ?2, ?3, ?4)",
+                rusqlite::params![project_id, name, group_id, order],
+            ).unwrap();
+            conn.last_insert_rowid()
+        };
+        let timeline = |entity_type: &str, id: i64, start: &str, end: &str| {
+            crate::services::timelines::create(&pool, crate::models::TimelineCreate {
+                entity_type: entity_type.to_string(),
+                entity_id: id,
+                start_date: Some(start.to_string()),
+                end_date: Some(end.to_string()),
+                calendar_id: None,
+            }).unwrap();
+        };
+        let opening = doc("Opening", chapter, 0);
+        let battle = doc("Battle", chapter, 1);
+        doc("Undated", part, 0);
+        let epilogue = doc("Epilogue", fixed, 0);
+        timeline("doc", opening, "2025-03-01", "2025-03-02");
+        timeline("folder", fixed, "2026-01-01", "2026-12-31");
+        timeline("doc", epilogue, "2026-06-01", "2026-06-01");
+
+        let siege = crate::services::events::create(&pool, project_id, "Siege", None, Some("2025-04".into()), None, None, None).unwrap();
+        conn.execute("INSERT INTO doc_events (doc_id, event_id) VALUES (?1, ?2)", rusqlite::params![battle, siege.id]).unwrap();
+        crate::services::events::create(&pool, project_id, "Prophecy", None, Some("2024-12-24".into()), None, None, None).unwrap();
+
+        let root = tree(&pool, project_id).unwrap();
+        assert_eq!(root.source, TimelineSource::Derived);
+        let derived = root.derived.as_ref().unwrap();
+        assert_eq!(derived.start_date.as_deref(), Some("2024-12-24"));
+        assert_eq!(derived.end_date.as_deref(), Some("2026-12-31"));
+
+        let part_node = &root.children[0];
+        assert_eq!(part_node.name, "Part One");
+        assert_eq!(part_node.children[0].name, "Undated");
+        assert_eq!(part_node.children[0].source, TimelineSource::Undated);
+        let chapter_node = &part_node.children[1];
+        let battle_node = &chapter_node.children[1];
+        assert_eq!(battle_node.source, TimelineSource::Derived);
+        assert_eq!(battle_node.children[0].entity_type, "event");
+        let chapter_span = chapter_node.derived.as_ref().unwrap();
+        assert_eq!(chapter_span.start_date.as_deref(), Some("2025-03-01"));
+        assert_eq!(chapter_span.end_date.as_deref(), Some("2025-04"));
+        assert_eq!(part_node.derived, chapter_node.derived);
+
+        let fixed_node = &root.children[1];
+        assert_eq!(fixed_node.source, TimelineSource::Explicit);
+        assert_eq!(fixed_node.derived.as_ref().unwrap().start_date.as_deref(), Some("2026-06-01"));
+        assert_eq!(root.children[2].name, "Prophecy");
+    }
+
+    #[test]
+    fn project_columns_and_unnamed_folders() {
+        let manager = SqliteConnectionManager::file("file:memrollupcolumns?mode=memory&cache=shared");
+        let pool: DbPool = Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO projects (id, name, timeline_start, timeline_end) VALUES (1, 'P', '2025-01', '2025-06-30');
+             INSERT INTO doc_groups (id, project_id, name, parent_id, sort_order) VALUES (1, 1, NULL, NULL, 0);
+             INSERT INTO timelines (entity_type, entity_id, project_id, start_date, start_day, end_day) VALUES ('project', 1, 1, '1999-01-01', 0, 0);",
+        ).unwrap();
+        drop(conn);
+
+        let root = tree(&pool, 1).unwrap();
+        assert_eq!(root.children[0].name, "");
+        let span = root.explicit.as_ref().unwrap();
+        assert_eq!((span.start_date.as_deref(), span.end_date.as_deref()), (Some("2025-01"), Some("2025-06-30")));
+        assert_eq!(span.end_day - span.start_day, 180);
+    }
+}
@@ -1,10 +1,42 @@
 use crate::db::{DbPool, get_conn};
-use crate::models::{Timeline, TimelineCreate, TimelineUpdate};
+use crate::models::{Timeline, TimelineCreate, TimelineHit, TimelineUpdate};
+use crate::services::calendars;
 use rusqlite::OptionalExtension;
 use anyhow::Context;
 
+const TIMELINE_COLUMNS: &str = "id, entity_type, entity_id, project_id, start_date, end_date, calendar_id, start_day, end_day";
+
+fn timeline_from_row(row: &rusqlite::Row) -> rusqlite::Result<Timeline> {
+    Ok(Timeline {
+        id: row.get(0)?,
+        entity_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        project_id: row.get(3)?,
+        start_date: row.get(4)?,
+        end_date: row.get(5)?,
+        calendar_id: row.get(6)?,
+        start_day: row.get(7)?,
+        end_day: row.get(8)?,
+    })
+}
+
+/// Project owning a timeline's entity, or None if the entity does not exist
+fn owner_project(conn: &rusqlite::Connection, entity_type: &str, entity_id: i64) -> anyhow::Result<Option<i64>> {
+    let sql = match entity_type {
+        "project" => "SELECT id FROM projects WHERE id = ?1",
+        "doc" => "SELECT project_id FROM docs WHERE id = ?1",
+        "folder" => "SELECT project_id FROM doc_groups WHERE id = ?1",
+        "event" => "SELECT project_id FROM events WHERE id = ?1",
+        other => anyhow::bail!("unknown timeline entity type '{}'", other),
+    };
+    Ok(conn.query_row(sql, rusqlite::params![entity_id], |row| row.get::<_, Option<i64>>(0)).optional()?.flatten())
+}
+
 pub fn create(pool: &DbPool, payload: TimelineCreate) -> anyhow::Result<Timeline> {
     let conn = get_conn(pool)?;
+    let project_id = owner_project(&conn, &payload.entity_type, payload.entity_id)?
+        .ok_or_else(|| anyhow::anyhow!("{} {} not found", payload.entity_type, payload.entity_id))?;
+    let range = calendars::resolve_range(&conn, project_id, payload.calendar_id, payload.start_date, payload.end_date)?;
     
     // First, check if a timeline already exists for this entity
     let existing: Option<i64> = conn
@@ -18,8 +50,8 @@ pub fn create(pool: &DbPool, payload: TimelineCreate) -> anyhow::Result<Timeline
     if let Some(existing_id) = existing {
         // Update existing timeline
         conn.execute(
-            "UPDATE timelines SET start_date = ?1, end_date = ?2 WHERE id = ?3",
-            rusqlite::params![payload.start_date, payload.end_date, existing_id],
+            "UPDATE timelines SET start_date = ?1, end_date = ?2, calendar_id = ?3, start_day = ?4, end_day = ?5, project_id = ?6 WHERE id = ?7",
+            rusqlite::params![range.start_date, range.end_date, payload.calendar_id, range.start_day, range.end_day, project_id, existing_id],
         )
         .context("updating existing timeline")?;
         
@@ -29,8 +61,8 @@ pub fn create(pool: &DbPool, payload: TimelineCreate) -> anyhow::Result<Timeline
     
     // Create new timeline
     conn.execute(
-        "INSERT INTO timelines (entity_type, entity_id, start_date, end_date) VALUES (?1, ?2, ?3, ?4)",
-        rusqlite::params![payload.entity_type, payload.entity_id, payload.start_date, payload.end_date],
+        "INSERT INTO timelines (entity_type, entity_id, project_id, start_date, end_date, calendar_id, start_day, end_day) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![payload.entity_type, payload.entity_id, project_id, range.start_date, range.end_date, payload.calendar_id, range.start_day, range.end_day],
     )
     .context("inserting timeline")?;
 
@@ -41,50 +73,26 @@ pub fn create(pool: &DbPool, payload: TimelineCreate) -> anyhow::Result<Timeline
 
 pub fn get(pool: &DbPool, id: i64) -> anyhow::Result<Option<Timeline>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare("SELECT id, entity_type, entity_id, start_date, end_date FROM timelines WHERE id = ?1")?;
-    let res = stmt.query_row(rusqlite::params![id], |row| {
-        Ok(Timeline {
-            id: row.get(0)?,
-            entity_type: row.get(1)?,
-            entity_id: row.get(2)?,
-            start_date: row.get(3)?,
-            end_date: row.get(4)?,
-        })
-    }).optional()?;
+    let sql = format!("SELECT {} FROM timelines WHERE id = ?1", TIMELINE_COLUMNS);
+    let res = conn.query_row(&sql, rusqlite::params![id], timeline_from_row).optional()?;
 
     Ok(res)
 }
 
 pub fn get_by_entity(pool: &DbPool, entity_type: &str, entity_id: i64) -> anyhow::Result<Option<Timeline>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare(
-        "SELECT id, entity_type, entity_id, start_date, end_date FROM timelines WHERE entity_type = ?1 AND entity_id = ?2"
-    )?;
-    let res = stmt.query_row(rusqlite::params![entity_type, entity_id], |row| {
-        Ok(Timeline {
-            id: row.get(0)?,
-            entity_type: row.get(1)?,
-            entity_id: row.get(2)?,
-            start_date: row.get(3)?,
-            end_date: row.get(4)?,
-        })
-    }).optional()?;
+    let sql = format!("SELECT {} FROM timelines WHERE entity_type = ?1 AND entity_id = ?2", TIMELINE_COLUMNS);
+    let res = conn.query_row(&sql, rusqlite::params![entity_type, entity_id], timeline_from_row).optional()?;
 
     Ok(res)
 }
 
-pub fn list(pool: &DbPool) -> anyhow::Result<Vec<Timeline>> {
+/// List the timeline rows of one project
+pub fn list(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<Timeline>> {
     let conn = get_conn(pool)?;
-    let mut stmt = conn.prepare("SELECT id, entity_type, entity_id, start_date, end_date FROM timelines ORDER BY id")?;
-    let rows = stmt.query_map([], |row| {
-        Ok(Timeline {
-            id: row.get(0)?,
-            entity_type: row.get(1)?,
-            entity_id: row.get(2)?,
-            start_date: row.get(3)?,
-            end_date: row.get(4)?,
-        })
-    })?;
+    let sql = format!("SELECT {} FROM timelines WHERE project_id = ?1 ORDER BY id", TIMELINE_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params![project_id], timeline_from_row)?;
 
     let mut out = Vec::new();
     for r in rows {
@@ -100,13 +108,24 @@ pub fn update(pool: &DbPool, id: i64, payload: TimelineUpdate) -> anyhow::Result
     let existing = get(pool, id)?
         .ok_or_else(|| anyhow::anyhow!("timeline not found"))?;
     
-    conn.execute(
-        "UPDATE timelines SET start_date = ?1, end_date = ?2 WHERE id = ?3",
-        rusqlite::params![
+    let project_id = owner_project(&conn, &existing.entity_type, existing.entity_id)?
+        .ok_or_else(|| anyhow::anyhow!("{} {} not found", existing.entity_type, existing.entity_id))?;
+    let calendar_id = payload.calendar_id.unwrap_or(existing.calendar_id);
+    let range = if calendar_id != existing.calendar_id && payload.start_date.is_none() && payload.end_date.is_none() {
+        // Same days, rendered in the new calendar
+        calendars::convert_range(&conn, project_id, calendar_id, existing.start_day, existing.end_day)?
+    } else {
+        calendars::resolve_range(
+            &conn,
+            project_id,
+            calendar_id,
             payload.start_date.or(existing.start_date),
             payload.end_date.or(existing.end_date),
-            id
-        ],
+        )?
+    };
+    conn.execute(
+        "UPDATE timelines SET start_date = ?1, end_date = ?2, calendar_id = ?3, start_day = ?4, end_day = ?5 WHERE id = ?6",
+        rusqlite::params![range.start_date, range.end_date, calendar_id, range.start_day, range.end_day, id],
     )
     .context("updating timeline")?;
     
@@ -114,6 +133,81 @@ pub fn update(pool: &DbPool, id: i64, payload: TimelineUpdate) -> anyhow::Result
         .ok_or_else(|| anyhow::anyhow!("timeline not found after update"))
 }
 
+/// Delete timeline rows whose entity no longer exists, after re-attaching rows that lost their owner.
+/// Returns the number of rows removed.
+pub fn purge_orphans(pool: &DbPool) -> anyhow::Result<usize> {
+    let conn = get_conn(pool)?;
+    let tx = conn.unchecked_transaction()?;
+    let removed = delete_orphans(&tx)?;
+    tx.commit()?;
+    Ok(removed)
+}
+
+/// Project owning a row's entity; NULL when the entity is gone or has no project
+const OWNER_EXPR: &str = "CASE entity_type
+    WHEN 'project' THEN (SELECT id FROM projects WHERE id = timelines.entity_id)
+    WHEN 'doc' THEN (SELECT project_id FROM docs WHERE id = timelines.entity_id)
+    WHEN 'folder' THEN (SELECT project_id FROM doc_groups WHERE id = timelines.entity_id)
+    WHEN 'event' THEN (SELECT project_id FROM events WHERE id = timelines.entity_id)
+END";
+
+/// Connection-level half of `purge_orphans`, for callers already inside a transaction
+pub fn delete_orphans(conn: &rusqlite::Connection) -> anyhow::Result<usize> {
+    conn.execute(&format!("UPDATE timelines SET project_id = {} WHERE project_id IS NULL", OWNER_EXPR), [])?;
+    Ok(conn.execute(&format!("DELETE FROM timelines WHERE ({}) IS NULL", OWNER_EXPR), [])?)
+}
+
+/// Timeline rows `delete_orphans` would remove, without changing anything
+pub fn find_orphans(conn: &rusqlite::Connection) -> anyhow::Result<Vec<Timeline>> {
+    let sql = format!("SELECT {} FROM timelines WHERE ({}) IS NULL ORDER BY id", TIMELINE_COLUMNS, OWNER_EXPR);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], timeline_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Docs and events of a project whose dates overlap `from`..=`to` (either bound may be open),
+/// earliest first. Bounds are read in the given calendar (None = Gregorian) and compared by whole days.
+pub fn list_in_range(pool: &DbPool, project_id: i64, from: Option<&str>, to: Option<&str>, calendar_id: Option<i64>) -> anyhow::Result<Vec<TimelineHit>> {
+    let from_day = match from.filter(|s| !s.trim().is_empty()) {
+        Some(f) => calendars::parse_date(pool, calendar_id, f)?.0,
+        None => i64::MIN,
+    };
+    let to_day = match to.filter(|s| !s.trim().is_empty()) {
+        Some(t) => calendars::parse_date(pool, calendar_id, t)?.1,
+        None => i64::MAX,
+    };
+    if to_day < from_day {
+        anyhow::bail!("invalid date range: end is before start");
+    }
+
+    let conn = get_conn(pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT 'doc', d.id, COALESCE(d.name, 'Untitled'), t.start_date, t.end_date, t.calendar_id, t.start_day, COALESCE(t.end_day, t.start_day)
+         FROM timelines t JOIN docs d ON d.id = t.entity_id
+         WHERE t.entity_type = 'doc' AND d.project_id = ?1
+           AND t.start_day <= ?3 AND COALESCE(t.end_day, t.start_day) >= ?2
+         UNION ALL
+         SELECT 'event', e.id, COALESCE(e.name, ''), COALESCE(e.start_date, e.date), e.end_date, e.calendar_id, e.start_day, COALESCE(e.end_day, e.start_day)
+         FROM events e
+         WHERE e.project_id = ?1
+           AND e.start_day <= ?3 AND COALESCE(e.end_day, e.start_day) >= ?2
+         ORDER BY 7, 8, 1, 2"
+    )?;
+    let hits = stmt.query_map(rusqlite::params![project_id, from_day, to_day], |row| {
+        Ok(TimelineHit {
+            entity_type: row.get(0)?,
+            entity_id: row.get(1)?,
+            name: row.get(2)?,
+            start_date: row.get(3)?,
+            end_date: row.get(4)?,
+            calendar_id: row.get(5)?,
+            start_day: row.get(6)?,
+            end_day: row.get(7)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(hits)
+}
+
 pub fn delete(pool: &DbPool, id: i64) -> anyhow::Result<()> {
     let conn = get_conn(pool)?;
     let rows = conn.execute("DELETE FROM timelines WHERE id = ?1", rusqlite::params![id])?;
@@ -151,22 +245,20 @@ mod tests {
             .build(manager)?;
         
         let conn = pool.get()?;
-        
-        // Enable foreign keys and create table
-        conn.execute_batch(
-            "PRAGMA foreign_keys = ON;
-            
-            CREATE TABLE timelines (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                entity_type TEXT NOT NULL CHECK(entity_type IN ('project', 'doc', 'folder', 'event')),
-                entity_id INTEGER NOT NULL,
-                start_date TEXT,
-                end_date TEXT,
-                UNIQUE(entity_type, entity_id)
-            );
-            
-            CREATE INDEX idx_timelines_entity ON timelines(entity_type, entity_id);"
-        )?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/013_add_timeline_day_indexes.sql"))?;
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql"))?;
+
+        // Project 1 with docs 1-9 and event 10 for the tests to attach timelines to
+        conn.execute("INSERT INTO projects (id, name) VALUES (1, 'P')", [])?;
+        for id in 1..=9 {
+            conn.execute("INSERT INTO docs (id, project_id, path, name) VALUES (?1, 1, '', 'Doc')", rusqlite::params![id])?;
+        }
+        conn.execute("INSERT INTO events (id, project_id, name) VALUES (10, 1, 'Event')", [])?;
         
         Ok(pool)
     }
@@ -190,6 +282,7 @@ mod tests {
             entity_id: 1,
             start_date: Some("2025-01-01".to_string()),
             end_date: Some("2025-12-31".to_string()),
+            calendar_id: None,
         };
         
         let result = create(&pool, payload).unwrap();
@@ -208,6 +301,7 @@ mod tests {
             entity_id: 1,
             start_date: Some("2025-01-01".to_string()),
             end_date: Some("2025-06-30".to_string()),
+            calendar_id: None,
         };
         let timeline1 = create(&pool, payload1).unwrap();
         
@@ -216,6 +310,7 @@ mod tests {
             entity_id: 1,
             start_date: Some("2025-02-01".to_string()),
             end_date: Some("2025-12-31".to_string()),
+            calendar_id: None,
         };
         let timeline2 = create(&pool, payload2).unwrap();
         
@@ -223,6 +318,146 @@ mod tests {
         assert_eq!(timeline2.start_date, Some("2025-02-01".to_string()));
     }
 
+    #[test]
+    fn test_dates_are_validated() {
+        let pool = setup_test_pool().unwrap();
+
+        let malformed = TimelineCreate {
+            entity_type: "doc".to_string(),
+            entity_id: 3,
+            start_date: Some("03/01/2025".to_string()),
+            end_date: None,
+            calendar_id: None,
+        };
+        assert!(create(&pool, malformed).is_err());
+
+        let payload = TimelineCreate {
+            entity_type: "doc".to_string(),
+            entity_id: 3,
+            start_date: Some("2025-03".to_string()),
+            end_date: Some("2025-03-20T18:00".to_string()),
+            calendar_id: None,
+        };
+        let created = create(&pool, payload).unwrap();
+        assert_eq!(created.end_date, Some("2025-03-20T18:00:00".to_string()));
+
+        let inverted = TimelineUpdate { start_date: Some("2025-04-01".to_string()), end_date: None, calendar_id: None };
+        assert!(update(&pool, created.id, inverted).is_err());
+    }
+
+    #[test]
+    fn test_calendar_dates_convert() {
+        use crate::models::{CalendarCreate, CalendarKind};
+        let pool = setup_test_pool().unwrap();
+        let relative = calendars::create(&pool, 1, CalendarCreate {
+            name: "Voyage".to_string(),
+            kind: CalendarKind::Relative,
+            months: vec![],
+            weekdays: vec![],
+            eras: vec![],
+            epoch_offset: crate::dates::day_number(chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()),
+            day_label: None,
+        }).unwrap();
+
+        let payload = TimelineCreate {
+            entity_type: "doc".to_string(),
+            entity_id: 4,
+            start_date: Some("Day 1".to_string()),
+            end_date: Some("day 14".to_string()),
+            calendar_id: Some(relative.id),
+        };
+        let created = create(&pool, payload).unwrap();
+        assert_eq!(created.end_date.as_deref(), Some("Day 14"));
+        assert_eq!(created.end_day.unwrap() - created.start_day.unwrap(), 13);
+
+        let moved = update(&pool, created.id, TimelineUpdate { start_date: Some("Day 3".to_string()), end_date: None, calendar_id: None }).unwrap();
+        assert_eq!(moved.start_day.unwrap(), created.start_day.unwrap() + 2);
+
+        // Switching to Gregorian keeps the days
+        let converted = update(&pool, created.id, TimelineUpdate { start_date: None, end_date: None, calendar_id: Some(None) }).unwrap();
+        assert_eq!(converted.start_date.as_deref(), Some("2025-03-03"));
+        assert_eq!(converted.end_date.as_deref(), Some("2025-03-14"));
+        assert_eq!(converted.end_day, moved.end_day);
+    }
+
+    #[test]
+    fn test_list_in_range() {
+        let manager = SqliteConnectionManager::file("file:test_timelines_range?mode=memory&cache=shared");
+        let pool: DbPool = r2d2::Pool::new(manager).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(include_str!("../../migrations/001_create_schema.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/005_add_event_start_end.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/006_add_timelines.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/012_add_calendars.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/013_add_timeline_day_indexes.sql")).unwrap();
+        conn.execute_batch(include_str!("../../migrations/014_scope_timelines_to_projects.sql")).unwrap();
+
+        conn.execute("INSERT INTO projects (name) VALUES ('P')", []).unwrap();
+        let project_id = conn.last_insert_rowid();
+        let mut doc_ids = Vec::new();
+        for (name, start, end) in [("Spring", "2025-03-01", "2025-03-05"), ("Summer", "2025-07", ""), ("Winter", "2025-12-24", "")] {
+            conn.execute("INSERT INTO docs (project_id, path, name) VALUES (?1, '', ?2)", rusqlite::params![project_id, name]).unwrap();
+            let doc_id = conn.last_insert_rowid();
+            create(&pool, TimelineCreate {
+                entity_type: "doc".to_string(),
+                entity_id: doc_id,
+                start_date: Some(start.to_string()),
+                end_date: Some(end.to_string()),
+                calendar_id: None,
+            }).unwrap();
+            doc_ids.push(doc_id);
+        }
+        let festival = crate::services::events::create(&pool, project_id, "Festival", None, Some("2025-07-15".into()), None, None, None).unwrap();
+
+        let hits = list_in_range(&pool, project_id, Some("2025-03-04"), Some("2025-07-20"), None).unwrap();
+        let found: Vec<(&str, i64)> = hits.iter().map(|h| (h.entity_type.as_str(), h.entity_id)).collect();
+        assert_eq!(found, vec![("doc", doc_ids[0]), ("doc", doc_ids[1]), ("event", festival.id)]);
+
+        let open_ended = list_in_range(&pool, project_id, Some("2025-08"), None, None).unwrap();
+        assert_eq!(open_ended.len(), 1);
+        assert_eq!(open_ended[0].name, "Winter");
+        assert!(list_in_range(&pool, project_id, Some("2025-08"), Some("2025-07"), None).is_err());
+    }
+
+    #[test]
+    fn test_timelines_follow_their_owner() {
+        let pool = setup_test_pool().unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute("INSERT INTO projects (id, name) VALUES (2, 'Other')", []).unwrap();
+        conn.execute("INSERT INTO doc_groups (id, project_id, name) VALUES (20, 2, 'Part')", []).unwrap();
+        drop(conn);
+
+        let span = |entity_type: &str, entity_id: i64| TimelineCreate {
+            entity_type: entity_type.to_string(),
+            entity_id,
+            start_date: Some("2025-05-01".to_string()),
+            end_date: None,
+            calendar_id: None,
+        };
+        let doc = create(&pool, span("doc", 2)).unwrap();
+        assert_eq!(doc.project_id, Some(1));
+        create(&pool, span("event", 10)).unwrap();
+        create(&pool, span("folder", 20)).unwrap();
+        assert!(create(&pool, span("doc", 99)).is_err());
+        assert_eq!(list(&pool, 1).unwrap().len(), 2);
+        assert_eq!(list(&pool, 2).unwrap().len(), 1);
+
+        let conn = pool.get().unwrap();
+        conn.execute("DELETE FROM docs WHERE id = 2", []).unwrap();
+        conn.execute("DELETE FROM projects WHERE id = 2", []).unwrap();
+        // Rows written behind the service's back, pointing at nothing
+        conn.execute("INSERT INTO timelines (entity_type, entity_id, start_date) VALUES ('doc', 404, '2025')", []).unwrap();
+        conn.execute("INSERT INTO timelines (entity_type, entity_id, project_id, start_date) VALUES ('event', 405, 1, '2025')", []).unwrap();
+        drop(conn);
+
+        assert!(get(&pool, doc.id).unwrap().is_none());
+        assert_eq!(list(&pool, 2).unwrap().len(), 0);
+        assert_eq!(purge_orphans(&pool).unwrap(), 2);
+        let remaining = list(&pool, 1).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].entity_type, "event");
+    }
+
     #[test]
     fn test_get_timeline() {
         let pool = setup_test_pool().unwrap();
@@ -232,6 +467,7 @@ mod tests {
             entity_id: 5,
             start_date: Some("2025-03-01".to_string()),
             end_date: Some("2025-03-31".to_string()),
+            calendar_id: None,
         };
         let created = create(&pool, payload).unwrap();
         
@@ -250,6 +486,7 @@ mod tests {
             entity_id: 10,
             start_date: Some("2025-04-01".to_string()),
             end_date: Some("2025-04-15".to_string()),
+            calendar_id: None,
         };
         create(&pool, payload).unwrap();
         
@@ -268,6 +505,7 @@ mod tests {
             entity_id: 8,
             start_date: Some("2025-06-01".to_string()),
             end_date: Some("2025-06-30".to_string()),
+            calendar_id: None,
         };
         let created = create(&pool, payload).unwrap();
         
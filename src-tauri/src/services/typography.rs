@@ -0,0 +1,592 @@
+use crate::db::{DbPool, get_conn};
+use crate::models::{
+    Doc, TypographyEdit, TypographyLocale, TypographyOptions, TypographyPreview, TypographyRun, TypographyUndo,
+};
+use anyhow::Context;
+use chrono::Utc;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use similar::{DiffTag, TextDiff};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const NBSP: char = '\u{A0}';
+// Narrow no-break space, the thin space of French punctuation
+const NNBSP: char = '\u{202F}';
+// Characters of surrounding text shown around each change in a preview
+const PREVIEW_CONTEXT: usize = 12;
+const PREVIEW_EDITS: usize = 50;
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("built-in typography pattern"))
+}
+
+/// Whether a quote after `prev` opens rather than closes
+fn opens(prev: Option<char>) -> bool {
+    match prev {
+        None => true,
+        Some(c) => c.is_whitespace() || matches!(c, '(' | '[' | '{' | '/' | '\u{2014}' | '\u{2013}' | '-'),
+    }
+}
+
+/// The locale's quotation marks for straight quotes. `prev` is the character before the text (from a
+/// preceding span), so a quote at the start still gets the right direction; emphasis asterisks
+/// do not count. A single quote after a letter is an apostrophe unless it closes an open quote
+/// and no letter follows; a quote between spaces closes an open quote. French marks get thin no-break spaces inside, replacing typed spaces.
+pub fn smart_quotes(text: &str, prev: Option<char>, locale: TypographyLocale) -> String {
+    let [double_open, double_close, single_open, single_close] = match locale {
+        TypographyLocale::En => ["\u{201C}", "\u{201D}", "\u{2018}", "\u{2019}"],
+        TypographyLocale::De => ["\u{201E}", "\u{201C}", "\u{201A}", "\u{2018}"],
+        TypographyLocale::Fr => ["\u{AB}\u{202F}", "\u{202F}\u{BB}", "\u{2039}\u{202F}", "\u{202F}\u{203A}"],
+    };
+    let spaced = locale == TypographyLocale::Fr;
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut prev = prev;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if c == '*' {
+            out.push(c);
+            continue;
+        }
+        if c != '"' && c != '\'' {
+            out.push(c);
+            prev = Some(c);
+            continue;
+        }
+        let after_word = prev.is_some_and(char::is_alphanumeric);
+        let word_next = chars.get(i).is_some_and(|n| n.is_alphanumeric());
+        let closes_single = in_single && !word_next;
+        let (mark, opening) = if c == '"' {
+            // A spaced-out quote ("Il dit " bonjour "") closes an open one
+            in_double = opens(prev) && (word_next || !in_double);
+            if in_double { (double_open, true) } else { (double_close, false) }
+        } else if after_word && !closes_single {
+            out.push('\u{2019}');
+            prev = Some('\u{2019}');
+            continue;
+        } else {
+            in_single = opens(prev);
+            if in_single { (single_open, true) } else { (single_close, false) }
+        };
+        if spaced && !opening {
+            while out.ends_with([' ', NBSP, NNBSP]) {
+                out.pop();
+            }
+        }
+        out.push_str(mark);
+        if spaced && opening {
+            while i < chars.len() && matches!(chars[i], ' ' | NBSP | NNBSP) {
+                i += 1;
+            }
+        }
+        prev = Some(if opening { '(' } else { ')' });
+    }
+    out
+}
+
+/// `---` as an em dash; `--` as an en dash between digits (ranges) and otherwise as the locale's
+/// dash: em dash in English, en dash in German and French. Longer runs of hyphens stay.
+pub fn dashes(text: &str, locale: TypographyLocale) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '-' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let run = chars[i..].iter().take_while(|&&c| c == '-').count();
+        match run {
+            3 => out.push('\u{2014}'),
+            2 => {
+                let range = i > 0 && chars[i - 1].is_ascii_digit() && chars.get(i + 2).is_some_and(|c| c.is_ascii_digit());
+                out.push(if range || locale != TypographyLocale::En { '\u{2013}' } else { '\u{2014}' });
+            }
+            _ => out.extend(&chars[i..i + run]),
+        }
+        i += run;
+    }
+    out
+}
+
+/// `...` and `. . .` as an ellipsis character
+pub fn ellipses(text: &str) -> String {
+    text.replace(". . .", "\u{2026}").replace("...", "\u{2026}")
+}
+
+/// Runs of spaces inside a line shrink to one; indentation at the start of a line stays
+pub fn collapse_spaces(text: &str) -> String {
+    text.split('\n').map(|line| {
+        let body = line.trim_start_matches(' ');
+        let mut out = line[..line.len() - body.len()].to_string();
+        let mut last_space = false;
+        for c in body.chars() {
+            if c == ' ' && last_space {
+                continue;
+            }
+            last_space = c == ' ';
+            out.push(c);
+        }
+        out
+    }).collect::<Vec<_>>().join("\n")
+}
+
+/// French spacing: a thin no-break space before ; : ! ? % and closing guillemets and after opening
+/// ones, replacing a typed space. A colon only gets one when a space or the end follows, which
+/// leaves times and URLs alone.
+fn french_spacing(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut space_after_open = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if std::mem::take(&mut space_after_open) && matches!(c, ' ' | NBSP) {
+            out.push(NNBSP);
+            continue;
+        }
+        let before = i.checked_sub(1).map(|k| chars[k]);
+        let after = chars.get(i + 1).copied();
+        match c {
+            ';' | ':' | '!' | '?' | '%' | '\u{BB}' | '\u{203A}' => {
+                if c == ':' && after.is_some_and(|a| !a.is_whitespace()) {
+                    out.push(c);
+                    continue;
+                }
+                if matches!(before, Some(' ') | Some(NBSP)) {
+                    out.pop();
+                    out.push(NNBSP);
+                } else if before.is_some_and(|b| b.is_alphanumeric() || matches!(b, ')' | '\u{2019}' | '\u{2026}' | '\u{BB}')) {
+                    out.push(NNBSP);
+                }
+                out.push(c);
+            }
+            '\u{AB}' | '\u{2039}' => {
+                out.push(c);
+                match after {
+                    Some(' ') | Some(NBSP) => space_after_open = true,
+                    Some(a) if a.is_alphanumeric() => out.push(NNBSP),
+                    _ => {}
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// German abbreviations written with a space inside
+const GERMAN_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("z.", "B."), ("d.", "h."), ("u.", "a."), ("u.", "U."), ("v.", "a."), ("s.", "o."), ("s.", "u."),
+    ("o.", "\u{C4}."), ("m.", "E."), ("z.", "T."), ("u.", "\u{C4}."),
+];
+
+/// No-break spaces that keep words together: after English titles ("Dr. Watson"), inside German
+/// abbreviations ("z. B.") and before a number's percent sign or after "Nr."/"S.", and the thin
+/// spaces of French punctuation
+pub fn no_break_spaces(text: &str, locale: TypographyLocale) -> String {
+    static TITLES: OnceLock<Regex> = OnceLock::new();
+    static GERMAN_NUMBERS: OnceLock<Regex> = OnceLock::new();
+    static GERMAN_PERCENT: OnceLock<Regex> = OnceLock::new();
+    match locale {
+        TypographyLocale::En => regex(&TITLES, r"\b(Mr|Mrs|Ms|Dr|St|Prof)\. (\p{Lu})")
+            .replace_all(text, "$1.\u{A0}$2")
+            .into_owned(),
+        TypographyLocale::De => {
+            let mut text = text.to_string();
+            for (first, second) in GERMAN_ABBREVIATIONS {
+                let joined = format!("{}{}{}", first, NBSP, second);
+                text = text.replace(&format!("{} {}", first, second), &joined).replace(&format!("{}{}", first, second), &joined);
+            }
+            let text = regex(&GERMAN_NUMBERS, r"\b(Nr|S|Abs|Kap|Bd)\. (\d)").replace_all(&text, "$1.\u{A0}$2");
+            regex(&GERMAN_PERCENT, r"(\d) ?(%|\x{2030})").replace_all(&text, "$1\u{A0}$2").into_owned()
+        }
+        TypographyLocale::Fr => french_spacing(text),
+    }
+}
+
+/// Run the chosen rules over `text`, whose preceding character (if it continues a paragraph) is
+/// `prev`: spaces first, then ellipses, dashes, quotes and finally no-break spaces
+pub fn transform_after(text: &str, prev: Option<char>, options: &TypographyOptions) -> String {
+    let mut text = text.to_string();
+    if options.double_spaces {
+        text = collapse_spaces(&text);
+    }
+    if options.ellipses {
+        text = ellipses(&text);
+    }
+    if options.dashes {
+        text = dashes(&text, options.locale);
+    }
+    if options.smart_quotes {
+        text = smart_quotes(&text, prev, options.locale);
+    }
+    if options.nbsp {
+        text = no_break_spaces(&text, options.locale);
+    }
+    text
+}
+
+/// Run the chosen rules over a whole text
+pub fn transform(text: &str, options: &TypographyOptions) -> String {
+    transform_after(text, None, options)
+}
+
+/// Number of changed spots between two texts and the first of them with a little context
+fn edits(before: &str, after: &str) -> (usize, Vec<TypographyEdit>) {
+    let diff = TextDiff::configure().timeout(Duration::from_secs(1)).diff_chars(before, after);
+    let changes = diff.ops().iter().filter(|op| op.tag() != DiffTag::Equal).count();
+    let old: Vec<char> = before.chars().collect();
+    let new: Vec<char> = after.chars().collect();
+    let edits = diff.grouped_ops(PREVIEW_CONTEXT).iter().take(PREVIEW_EDITS).filter_map(|group| {
+        let (first, last) = (group.first()?, group.last()?);
+        Some(TypographyEdit {
+            before: old[first.old_range().start..last.old_range().end].iter().collect(),
+            after: new[first.new_range().start..last.new_range().end].iter().collect(),
+        })
+    }).collect();
+    (changes, edits)
+}
+
+/// The docs a pass covers: one doc of the project, or all of them in manuscript order
+fn scope(pool: &DbPool, project_id: i64, doc_id: Option<i64>) -> anyhow::Result<Vec<Doc>> {
+    match doc_id {
+        Some(id) => {
+            let doc = crate::services::docs::get_doc(pool, id)?
+                .filter(|d| d.project_id == project_id)
+                .ok_or_else(|| anyhow::anyhow!("doc {} not found in project {}", id, project_id))?;
+            Ok(vec![doc])
+        }
+        None => crate::services::docs::list_in_manuscript_order(pool, project_id),
+    }
+}
+
+/// Docs whose text the options would change, with their new text
+fn changed(docs: Vec<Doc>, options: &TypographyOptions) -> Vec<(Doc, String, String)> {
+    docs.into_iter().filter_map(|doc| {
+        let before = doc.text.clone().unwrap_or_default();
+        let after = transform(&before, options);
+        (after != before).then_some((doc, before, after))
+    }).collect()
+}
+
+/// What a pass would change, per doc with changes, without touching anything
+pub fn preview(pool: &DbPool, project_id: i64, doc_id: Option<i64>, options: &TypographyOptions) -> anyhow::Result<Vec<TypographyPreview>> {
+    let docs = scope(pool, project_id, doc_id)?;
+    Ok(changed(docs, options).into_iter().map(|(doc, before, after)| {
+        let (changes, edits) = edits(&before, &after);
+        TypographyPreview { doc_id: doc.id, doc_name: doc.name, changes, edits }
+    }).collect())
+}
+
+/// Replace a doc's text inside a transaction, keeping its comments anchored
+fn write_text(conn: &Connection, doc_id: i64, old: &str, new: &str) -> anyhow::Result<()> {
+    conn.execute("UPDATE docs SET text = ?1 WHERE id = ?2", rusqlite::params![new, doc_id])
+        .context("updating doc text")?;
+    crate::services::comments::remap_anchors(conn, doc_id, old, new)
+}
+
+const RUN_COLUMNS: &str = "r.id, r.project_id, r.doc_id, r.options, r.created_at, r.undone_at, \
+     (SELECT COUNT(*) FROM typography_run_docs d WHERE d.run_id = r.id)";
+
+fn run_from_row(row: &rusqlite::Row) -> rusqlite::Result<TypographyRun> {
+    let options: String = row.get(3)?;
+    Ok(TypographyRun {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        doc_id: row.get(2)?,
+        options: serde_json::from_str(&options)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?,
+        created_at: row.get(4)?,
+        undone_at: row.get(5)?,
+        docs_changed: row.get::<_, i64>(6)? as usize,
+    })
+}
+
+fn load_run(conn: &Connection, id: i64) -> anyhow::Result<Option<TypographyRun>> {
+    let sql = format!("SELECT {} FROM typography_runs r WHERE r.id = ?1", RUN_COLUMNS);
+    Ok(conn.query_row(&sql, rusqlite::params![id], run_from_row).optional()?)
+}
+
+/// Apply a pass to docs.text for good, recording each changed doc's text so the pass can be undone
+pub fn apply(pool: &DbPool, project_id: i64, doc_id: Option<i64>, options: &TypographyOptions) -> anyhow::Result<TypographyRun> {
+    let changes = changed(scope(pool, project_id, doc_id)?, options);
+    let conn = get_conn(pool)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO typography_runs (project_id, doc_id, options, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![project_id, doc_id, serde_json::to_string(options)?, Utc::now().to_rfc3339()],
+    ).context("recording typography pass")?;
+    let run_id = tx.last_insert_rowid();
+    for (doc, before, after) in &changes {
+        tx.execute(
+            "INSERT INTO typography_run_docs (run_id, doc_id, before_text, after_text) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![run_id, doc.id, before, after],
+        )?;
+        write_text(&tx, doc.id, before, after)?;
+    }
+    tx.commit()?;
+    load_run(&conn, run_id)?.ok_or_else(|| anyhow::anyhow!("typography pass not found after applying"))
+}
+
+/// Passes applied to a project, newest first
+pub fn list_runs(pool: &DbPool, project_id: i64) -> anyhow::Result<Vec<TypographyRun>> {
+    let conn = get_conn(pool)?;
+    let sql = format!("SELECT {} FROM typography_runs r WHERE r.project_id = ?1 ORDER BY r.id DESC", RUN_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let runs = stmt.query_map(rusqlite::params![project_id], run_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(runs)
+}
+
+/// Put back the text a pass replaced. Docs edited since the pass keep their current text and are
+/// reported as skipped.
+pub fn undo(pool: &DbPool, run_id: i64) -> anyhow::Result<TypographyUndo> {
+    let conn = get_conn(pool)?;
+    let run = load_run(&conn, run_id)?.ok_or_else(|| anyhow::anyhow!("typography pass {} not found", run_id))?;
+    if run.undone_at.is_some() {
+        anyhow::bail!("typography pass {} was already undone", run_id);
+    }
+    let tx = conn.unchecked_transaction()?;
+    let entries: Vec<(i64, String, String, Option<String>)> = {
+        let mut stmt = tx.prepare(
+            "SELECT r.doc_id, r.before_text, r.after_text, d.text FROM typography_run_docs r
+             JOIN docs d ON d.id = r.doc_id WHERE r.run_id = ?1 ORDER BY r.doc_id",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![run_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    let mut result = TypographyUndo::default();
+    for (doc_id, before, after, current) in entries {
+        if current.as_deref().unwrap_or("") == after {
+            write_text(&tx, doc_id, &after, &before)?;
+            result.restored.push(doc_id);
+        } else {
+            result.skipped.push(doc_id);
+        }
+    }
+    tx.execute(
+        "UPDATE typography_runs SET undone_at = ?1 WHERE id = ?2",
+        rusqlite::params![Utc::now().to_rfc3339(), run_id],
+    )?;
+    tx.commit()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectCreate;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use r2d2::Pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn make_pool() -> DbPool {
+        let id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let db_name = format!("file:memtypography{}?mode=memory&cache=shared", id);
+        let manager = SqliteConnectionManager::file(&db_name);
+        Pool::new(manager).unwrap()
+    }
+
+    fn init_schema(conn: &rusqlite::Connection) {
+        for sql in [
+            include_str!("../../migrations/001_create_schema.sql"),
+            include_str!("../../migrations/002_add_tree_order.sql"),
+            include_str!("../../migrations/003_add_doc_notes.sql"),
+            include_str!("../../migrations/010_add_project_linear_chronology.sql"),
+            include_str!("../../migrations/016_add_doc_comments.sql"),
+            include_str!("../../migrations/021_add_typography_runs.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+    }
+
+    /// A project with one doc per text, in order
+    fn setup(texts: &[&str]) -> (DbPool, i64, Vec<i64>) {
+        let pool = make_pool();
+        init_schema(&pool.get().unwrap());
+        let project = crate::services::projects::create(&pool, ProjectCreate { name: "Harbour".into(), desc: None, path: None }).unwrap();
+        let ids = texts.iter().enumerate().map(|(i, text)| {
+            let d = crate::services::docs::create_doc(&pool, project.id, &format!("doc {}", i), None).unwrap();
+            crate::services::docs::update_doc(&pool, d.id, text).unwrap();
+            d.id
+        }).collect();
+        (pool, project.id, ids)
+    }
+
+    fn text(pool: &DbPool, id: i64) -> String {
+        crate::services::docs::get_doc(pool, id).unwrap().unwrap().text.unwrap()
+    }
+
+    fn all(locale: TypographyLocale) -> TypographyOptions {
+        TypographyOptions { locale, smart_quotes: true, dashes: true, ellipses: true, double_spaces: true, nbsp: true }
+    }
+
+    #[test]
+    fn test_english_quotes_and_apostrophes() {
+        assert_eq!(smart_quotes("\"It's 'fine'\"", None, TypographyLocale::En), "\u{201C}It\u{2019}s \u{2018}fine\u{2019}\u{201D}");
+        assert_eq!(smart_quotes("the '90s", None, TypographyLocale::En), "the \u{2018}90s");
+        // Emphasis markers do not hide the start of a quote
+        assert_eq!(smart_quotes("*\"Hi,\"* she said.", None, TypographyLocale::En), "*\u{201C}Hi,\u{201D}* she said.");
+        // A quote continuing a span takes its direction from the text before
+        assert_eq!(smart_quotes("\" she said", Some('o'), TypographyLocale::En), "\u{201D} she said");
+    }
+
+    #[test]
+    fn test_german_quotes() {
+        assert_eq!(
+            smart_quotes("Er sagte \"Hallo\" und 'Meer'.", None, TypographyLocale::De),
+            "Er sagte \u{201E}Hallo\u{201C} und \u{201A}Meer\u{2018}."
+        );
+    }
+
+    #[test]
+    fn test_french_quotes_get_thin_spaces() {
+        assert_eq!(
+            smart_quotes("Il dit \" bonjour \" puis \"salut\".", None, TypographyLocale::Fr),
+            "Il dit \u{AB}\u{202F}bonjour\u{202F}\u{BB} puis \u{AB}\u{202F}salut\u{202F}\u{BB}."
+        );
+    }
+
+    #[test]
+    fn test_dashes_by_locale() {
+        assert_eq!(dashes("1990--95, then -- gone --- for good", TypographyLocale::En), "1990\u{2013}95, then \u{2014} gone \u{2014} for good");
+        assert_eq!(dashes("1990--95, dann -- weg", TypographyLocale::De), "1990\u{2013}95, dann \u{2013} weg");
+        assert_eq!(dashes("puis -- rien", TypographyLocale::Fr), "puis \u{2013} rien");
+        assert_eq!(dashes("a ---- b", TypographyLocale::En), "a ---- b");
+    }
+
+    #[test]
+    fn test_ellipses() {
+        assert_eq!(ellipses("Wait... no. . . yes"), "Wait\u{2026} no\u{2026} yes");
+    }
+
+    #[test]
+    fn test_double_spaces_keep_indentation() {
+        assert_eq!(collapse_spaces("  a  b   c\n    d  e"), "  a b c\n    d e");
+    }
+
+    #[test]
+    fn test_english_no_break_spaces() {
+        assert_eq!(no_break_spaces("said Dr. Watson to Mr. Holmes", TypographyLocale::En), "said Dr.\u{A0}Watson to Mr.\u{A0}Holmes");
+        assert_eq!(no_break_spaces("the dr. said", TypographyLocale::En), "the dr. said");
+    }
+
+    #[test]
+    fn test_german_no_break_spaces() {
+        assert_eq!(
+            no_break_spaces("z. B. um 10 % auf S. 4, d.h. genug", TypographyLocale::De),
+            "z.\u{A0}B. um 10\u{A0}% auf S.\u{A0}4, d.\u{A0}h. genug"
+        );
+    }
+
+    #[test]
+    fn test_french_punctuation_spacing() {
+        assert_eq!(
+            no_break_spaces("Quoi ? Oui! Rendez-vous 10:30 : http://x.fr", TypographyLocale::Fr),
+            "Quoi\u{202F}? Oui\u{202F}! Rendez-vous 10:30\u{202F}: http://x.fr"
+        );
+    }
+
+    #[test]
+    fn test_transform_runs_every_rule_for_a_locale() {
+        assert_eq!(
+            transform("\"It's 1990--95,\"  said Dr. Watson -- 'fine'...", &all(TypographyLocale::En)),
+            "\u{201C}It\u{2019}s 1990\u{2013}95,\u{201D} said Dr.\u{A0}Watson \u{2014} \u{2018}fine\u{2019}\u{2026}"
+        );
+        assert_eq!(
+            transform("Er sagte \"Hallo\" -- z. B. 'Meer' um 10 %.", &all(TypographyLocale::De)),
+            "Er sagte \u{201E}Hallo\u{201C} \u{2013} z.\u{A0}B. \u{201A}Meer\u{2018} um 10\u{A0}%."
+        );
+        assert_eq!(
+            transform("Il dit \" bonjour \" : quoi? Rendez-vous 10:30.", &all(TypographyLocale::Fr)),
+            "Il dit \u{AB}\u{202F}bonjour\u{202F}\u{BB}\u{202F}: quoi\u{202F}? Rendez-vous 10:30."
+        );
+    }
+
+    #[test]
+    fn test_rules_that_are_off_do_nothing() {
+        let only_spaces = TypographyOptions { double_spaces: true, ..Default::default() };
+        assert_eq!(transform("  \"a\"  b... -- c", &only_spaces), "  \"a\" b... -- c");
+        assert_eq!(transform("\"a\" -- b...", &TypographyOptions::default()), "\"a\" -- b...");
+    }
+
+    #[test]
+    fn test_old_presets_keep_dashes_on() {
+        // Presets saved before the locale-aware pass still turn dashes on
+        let old: TypographyOptions = serde_json::from_str(r#"{"smart_quotes": true, "em_dashes": true}"#).unwrap();
+        assert!(old.dashes && old.smart_quotes && old.locale == TypographyLocale::En);
+    }
+
+    #[test]
+    fn test_preview_leaves_text_alone() {
+        let (pool, project_id, ids) = setup(&["\"Wait,\" she said.", "Rain -- then sun.", "Nothing to fix."]);
+        let en = all(TypographyLocale::En);
+        let preview = preview(&pool, project_id, None, &en).unwrap();
+        assert_eq!(preview.iter().map(|p| p.doc_id).collect::<Vec<_>>(), vec![ids[0], ids[1]]);
+        assert_eq!(preview[0].changes, 2);
+        assert_eq!(preview[1].edits[0].after, "Rain \u{2014} then sun.");
+        assert_eq!(text(&pool, ids[0]), "\"Wait,\" she said.");
+    }
+
+    #[test]
+    fn test_apply_to_one_doc_or_the_project() {
+        let (pool, project_id, ids) = setup(&["\"Wait,\" she said.", "Rain -- then sun.", "Nothing to fix."]);
+        let en = all(TypographyLocale::En);
+        let single = apply(&pool, project_id, Some(ids[1]), &en).unwrap();
+        assert_eq!((single.doc_id, single.docs_changed), (Some(ids[1]), 1));
+        assert_eq!(text(&pool, ids[0]), "\"Wait,\" she said.");
+        assert_eq!(text(&pool, ids[1]), "Rain \u{2014} then sun.");
+
+        let run = apply(&pool, project_id, None, &en).unwrap();
+        assert_eq!(run.docs_changed, 1);
+        assert_eq!(text(&pool, ids[0]), "\u{201C}Wait,\u{201D} she said.");
+        assert_eq!(text(&pool, ids[2]), "Nothing to fix.");
+        assert_eq!(list_runs(&pool, project_id).unwrap().iter().map(|r| r.id).collect::<Vec<_>>(), vec![run.id, single.id]);
+    }
+
+    #[test]
+    fn test_undo_restores_the_text_before_the_pass() {
+        let (pool, project_id, ids) = setup(&["\"Wait,\" she said.", "Rain -- then sun."]);
+        let run = apply(&pool, project_id, None, &all(TypographyLocale::En)).unwrap();
+        let undone = undo(&pool, run.id).unwrap();
+        assert_eq!((undone.restored, undone.skipped), (ids.clone(), vec![]));
+        assert_eq!(text(&pool, ids[0]), "\"Wait,\" she said.");
+        assert_eq!(text(&pool, ids[1]), "Rain -- then sun.");
+        assert!(undo(&pool, run.id).is_err(), "a pass is undone once");
+    }
+
+    #[test]
+    fn test_undo_after_an_intervening_edit() {
+        let (pool, project_id, ids) = setup(&["\"Wait,\" she said.", "Rain -- then sun.", "Nothing to fix."]);
+        let run = apply(&pool, project_id, None, &all(TypographyLocale::En)).unwrap();
+
+        // Edits to a doc the pass did not touch, and to one it did, land between pass and undo
+        crate::services::docs::update_doc(&pool, ids[2], "Still nothing.").unwrap();
+        crate::services::docs::update_doc(&pool, ids[1], "Rain \u{2014} then more rain.").unwrap();
+        let undone = undo(&pool, run.id).unwrap();
+        assert_eq!((undone.restored, undone.skipped), (vec![ids[0]], vec![ids[1]]));
+        assert_eq!(text(&pool, ids[0]), "\"Wait,\" she said.");
+        assert_eq!(text(&pool, ids[1]), "Rain \u{2014} then more rain.");
+        assert_eq!(text(&pool, ids[2]), "Still nothing.");
+    }
+
+    #[test]
+    fn test_undo_after_a_later_pass() {
+        let (pool, project_id, ids) = setup(&["\"Wait...\" she said."]);
+        let quotes = apply(&pool, project_id, None, &TypographyOptions { smart_quotes: true, ..Default::default() }).unwrap();
+        let dots = apply(&pool, project_id, None, &TypographyOptions { ellipses: true, ..Default::default() }).unwrap();
+        assert_eq!(text(&pool, ids[0]), "\u{201C}Wait\u{2026}\u{201D} she said.");
+
+        // Undone newest first, each pass finds the text it left and restores the one before
+        assert_eq!(undo(&pool, dots.id).unwrap().restored, vec![ids[0]]);
+        assert_eq!(text(&pool, ids[0]), "\u{201C}Wait...\u{201D} she said.");
+        assert_eq!(undo(&pool, quotes.id).unwrap().restored, vec![ids[0]]);
+        assert_eq!(text(&pool, ids[0]), "\"Wait...\" she said.");
+    }
+}
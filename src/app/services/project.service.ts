@@ -5,7 +5,11 @@ import type {
   Doc, Character, Event,
   Draft, DraftCreate,
   ProjectDraft, ProjectDraftCreate, ProjectDraftUpdate,
-  FolderDraft, FolderDraftCreate, FolderDraftUpdate
+  FolderDraft, FolderDraftCreate, FolderDraftUpdate,
+  ImportReport, ImportedFile, HeadingImportOptions, CompileFormat, CompileSettings,
+  CompilePreset, CompilePresetInput, DocMetadata,
+  TypographyOptions, TypographyPreview, TypographyRun, TypographyUndo,
+  Screenplay, ScreenplayDoc, ScreenplaySync
 } from "../shared/models";
 
 @Injectable({ providedIn: "root" })
@@ -32,6 +36,10 @@ export class ProjectService {
     return invoke<Project>("project_update", { id, changes });
   }
 
+  async setLinearChronology(id: number, linear: boolean): Promise<Project> {
+    return invoke<Project>("project_set_linear_chronology", { id, linear });
+  }
+
   // Doc Groups
   async listDocGroups(projectId: number): Promise<any[]> {
     return invoke<any[]>("doc_group_list", { projectId });
@@ -246,18 +254,109 @@ export class ProjectService {
     return invoke<void>("folder_draft_delete_all", { docGroupId });
   }
 
-  // Import .txt files into a target folder
-  async importTxtFiles(projectId: number, docGroupId: number, files: string[]): Promise<number> {
-    return invoke<number>("import_txt_files", { projectId, docGroupId, files });
+  // Import .txt/.docx/.odt files into a target folder
+  async importTxtFiles(projectId: number, docGroupId: number, files: string[], headings?: HeadingImportOptions): Promise<ImportReport> {
+    return invoke<ImportReport>("import_txt_files", { projectId, docGroupId, files, headings: headings ?? null });
   }
 
   // Import a whole project from a folder
-  async importProject(folderPath: string): Promise<Project> {
-    return invoke<Project>("import_project", { folderPath });
+  // A folder without metadata.json comes back with the files that could not be read
+  async importProject(folderPath: string): Promise<Project & { skipped?: ImportedFile[] }> {
+    return invoke<Project & { skipped?: ImportedFile[] }>("import_project", { folderPath });
+  }
+
+  // Import a Scrivener project (.scriv bundle) as a new project
+  async importScrivener(bundlePath: string): Promise<Project> {
+    return invoke<Project>("import_scrivener", { bundlePath });
   }
 
   // Export a whole project to a folder
-  async exportProject(projectId: number, destPath: string): Promise<void> {
-    return invoke<void>("export_project", { projectId, destPath });
+  async exportProject(projectId: number, destPath: string, presetId?: number): Promise<void> {
+    return invoke<void>("export_project", { projectId, destPath, presetId: presetId ?? null });
+  }
+
+  // Compile the manuscript into a single file
+  async compileProject(projectId: number, format: CompileFormat, destPath: string, settings?: CompileSettings): Promise<void> {
+    return invoke<void>("compile_project", { projectId, format, destPath, settings: settings ?? null });
+  }
+
+  async compileWithPreset(presetId: number, destPath: string): Promise<void> {
+    return invoke<void>("compile_with_preset", { presetId, destPath });
+  }
+
+  async listCompilePresets(projectId: number): Promise<CompilePreset[]> {
+    return invoke<CompilePreset[]>("compile_preset_list", { projectId });
+  }
+
+  async createCompilePreset(projectId: number, payload: CompilePresetInput): Promise<CompilePreset> {
+    return invoke<CompilePreset>("compile_preset_create", { projectId, payload });
+  }
+
+  async updateCompilePreset(id: number, payload: CompilePresetInput): Promise<CompilePreset> {
+    return invoke<CompilePreset>("compile_preset_update", { id, payload });
+  }
+
+  async deleteCompilePreset(id: number): Promise<void> {
+    return invoke<void>("compile_preset_delete", { id });
+  }
+
+  async getDocMetadata(docId: number): Promise<DocMetadata> {
+    return invoke<DocMetadata>("doc_metadata_get", { docId });
+  }
+
+  async setDocMetadata(docId: number, metadata: DocMetadata): Promise<DocMetadata> {
+    return invoke<DocMetadata>("doc_metadata_set", { docId, metadata });
+  }
+
+  async previewTypography(projectId: number, docId: number | null, options: TypographyOptions): Promise<TypographyPreview[]> {
+    return invoke<TypographyPreview[]>("typography_preview", { projectId, docId, options });
+  }
+
+  async applyTypography(projectId: number, docId: number | null, options: TypographyOptions): Promise<TypographyRun> {
+    return invoke<TypographyRun>("typography_apply", { projectId, docId, options });
+  }
+
+  async listTypographyRuns(projectId: number): Promise<TypographyRun[]> {
+    return invoke<TypographyRun[]>("typography_run_list", { projectId });
+  }
+
+  async undoTypography(runId: number): Promise<TypographyUndo> {
+    return invoke<TypographyUndo>("typography_undo", { runId });
+  }
+
+  async getScreenplayMode(projectId: number): Promise<boolean> {
+    return invoke<boolean>("screenplay_project_get", { projectId });
+  }
+
+  async setScreenplayMode(projectId: number, enabled: boolean): Promise<void> {
+    return invoke<void>("screenplay_project_set", { projectId, enabled });
+  }
+
+  async getScreenplayDoc(docId: number): Promise<ScreenplayDoc> {
+    return invoke<ScreenplayDoc>("screenplay_doc_get", { docId });
+  }
+
+  async setScreenplayDocMode(docId: number, mode: boolean | null): Promise<ScreenplayDoc> {
+    return invoke<ScreenplayDoc>("screenplay_doc_set", { docId, mode });
+  }
+
+  async parseScreenplay(docId: number): Promise<Screenplay> {
+    return invoke<Screenplay>("screenplay_parse", { docId });
+  }
+
+  async syncScreenplay(projectId: number): Promise<ScreenplaySync> {
+    return invoke<ScreenplaySync>("screenplay_sync", { projectId });
+  }
+
+  async importFountain(projectId: number, docGroupId: number | null, file: string): Promise<ScreenplaySync> {
+    return invoke<ScreenplaySync>("import_fountain", { projectId, docGroupId, file });
+  }
+
+  async exportFountain(projectId: number, destPath: string): Promise<void> {
+    return invoke<void>("export_fountain", { projectId, destPath });
+  }
+
+  async compileScreenplay(projectId: number, destPath: string): Promise<void> {
+    return invoke<void>("compile_screenplay", { projectId, destPath });
   }
 }
@@ -98,7 +98,7 @@ describe('TimelineService', () => {
   });
 
   describe('listTimelines', () => {
-    it('should list all timelines', async () => {
+    it('should list the timelines of a project', async () => {
       const mockTimelines = [
         {
           id: 1,
@@ -117,9 +117,9 @@ describe('TimelineService', () => {
       ];
       mockInvoke.mockResolvedValue(mockTimelines);
 
-      const result = await service.listTimelines();
+      const result = await service.listTimelines(7);
 
-      expect(mockInvoke).toHaveBeenCalledWith('timeline_list', {});
+      expect(mockInvoke).toHaveBeenCalledWith('timeline_list', { projectId: 7 });
       expect(result).toEqual(mockTimelines);
     });
   });
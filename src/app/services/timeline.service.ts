@@ -16,8 +16,8 @@ export class TimelineService {
     return invoke<Timeline | null>("timeline_get_by_entity", { entityType, entityId });
   }
 
-  async listTimelines(): Promise<Timeline[]> {
-    return invoke<Timeline[]>("timeline_list", {});
+  async listTimelines(projectId: number): Promise<Timeline[]> {
+    return invoke<Timeline[]>("timeline_list", { projectId });
   }
 
   async updateTimeline(id: number, payload: TimelineUpdate): Promise<Timeline> {
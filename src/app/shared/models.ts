@@ -11,6 +11,8 @@ export interface Project {
   path?: string | null;
   timeline_start?: string | null;
   timeline_end?: string | null;
+  // Manuscript order must follow story chronology (see continuity checks)
+  linear_chronology: boolean;
 }
 
 export interface Character {
@@ -141,3 +143,141 @@ export interface TimelineUpdate {
   start_date?: string | null;
   end_date?: string | null;
 }
+
+export interface ImportedFile {
+  path: string;
+  encoding?: string | null;
+  warning?: string | null;
+}
+
+export interface ImportReport {
+  imported: number;
+  files: ImportedFile[];
+}
+
+// What a Heading 1/2 in an imported Word or OpenDocument file becomes
+export type ImportHeading = 'text' | 'group' | 'doc';
+
+export interface HeadingImportOptions {
+  heading1?: ImportHeading;
+  heading2?: ImportHeading;
+}
+
+// Single-file formats a project can be compiled to
+export type CompileFormat = 'odt' | 'html' | 'pdf';
+
+export type PageSize = 'a4' | 'letter' | 'a5';
+
+export interface CompileOptions {
+  theme?: 'classic' | 'modern' | 'manuscript';
+  css?: string;
+  page_size?: PageSize;
+  title_page?: boolean;
+}
+
+export interface DocMetadata {
+  status?: string | null;
+  label?: string | null;
+  synopsis?: string | null;
+}
+
+export type TypographyLocale = 'en' | 'de' | 'fr';
+
+export interface TypographyOptions {
+  locale?: TypographyLocale;
+  smart_quotes?: boolean;
+  dashes?: boolean;
+  ellipses?: boolean;
+  double_spaces?: boolean;
+  nbsp?: boolean;
+}
+
+export interface TypographyEdit {
+  before: string;
+  after: string;
+}
+
+export interface TypographyPreview {
+  doc_id: number;
+  doc_name?: string | null;
+  changes: number;
+  edits: TypographyEdit[];
+}
+
+export interface TypographyRun {
+  id: number;
+  project_id: number;
+  doc_id?: number | null;
+  options: TypographyOptions;
+  docs_changed: number;
+  created_at: string;
+  undone_at?: string | null;
+}
+
+export interface TypographyUndo {
+  restored: number[];
+  skipped: number[];
+}
+
+export type ScreenplayElement =
+  | { type: 'scene_heading'; text: string; number?: string | null }
+  | { type: 'action'; text: string }
+  | { type: 'character'; name: string; extension?: string | null; dual: boolean }
+  | { type: 'parenthetical'; text: string }
+  | { type: 'dialogue'; text: string }
+  | { type: 'transition'; text: string }
+  | { type: 'centered'; text: string }
+  | { type: 'lyrics'; text: string }
+  | { type: 'section'; depth: number; text: string }
+  | { type: 'synopsis'; text: string }
+  | { type: 'page_break' };
+
+export interface Screenplay {
+  title_page: [string, string][];
+  elements: ScreenplayElement[];
+}
+
+export interface ScreenplayDoc {
+  doc_id: number;
+  screenplay: boolean;
+  mode?: boolean | null;
+  location?: string | null;
+}
+
+export interface ScreenplaySync {
+  docs: number;
+  characters_created: Character[];
+  characters_linked: number;
+}
+
+export interface CompileSettings extends CompileOptions, TypographyOptions {
+  exclude_groups?: number[];
+  exclude_docs?: number[];
+  include_statuses?: string[];
+  exclude_statuses?: string[];
+  include_labels?: string[];
+  exclude_labels?: string[];
+  heading_templates?: string[];
+  scene_separator?: string | null;
+  include_synopses?: boolean;
+  include_notes?: boolean;
+  front_matter?: number[];
+  back_matter?: number[];
+  file_names?: string | null;
+}
+
+export interface CompilePreset {
+  id: number;
+  project_id: number;
+  name: string;
+  format: CompileFormat;
+  settings: CompileSettings;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CompilePresetInput {
+  name: string;
+  format: CompileFormat;
+  settings?: CompileSettings;
+}
@@ -16,7 +16,7 @@ describe("ProjectService", () => {
   });
 
   it("createProject calls invoke and returns project", async () => {
-    const fake: Project = { id: 1, name: "P1", desc: null, path: null };
+    const fake: Project = { id: 1, name: "P1", desc: null, path: null, linear_chronology: false };
     (invoke as jest.Mock).mockResolvedValueOnce(fake);
 
     const res = await svc.createProject({ name: "P1" });
@@ -25,13 +25,21 @@ describe("ProjectService", () => {
   });
 
   it("getProject calls invoke with id", async () => {
-    const fake: Project = { id: 2, name: "P2", desc: null, path: null };
+    const fake: Project = { id: 2, name: "P2", desc: null, path: null, linear_chronology: false };
     (invoke as jest.Mock).mockResolvedValueOnce(fake);
     const res = await svc.getProject(2);
     expect(invoke).toHaveBeenCalledWith("project_get", { id: 2 });
     expect(res).toEqual(fake);
   });
 
+  it("setLinearChronology calls invoke with id and flag", async () => {
+    const fake: Project = { id: 2, name: "P2", desc: null, path: null, linear_chronology: true };
+    (invoke as jest.Mock).mockResolvedValueOnce(fake);
+    const res = await svc.setLinearChronology(2, true);
+    expect(invoke).toHaveBeenCalledWith("project_set_linear_chronology", { id: 2, linear: true });
+    expect(res).toEqual(fake);
+  });
+
   describe("Draft Operations", () => {
     const mockDraft = {
       id: 1,
@@ -130,6 +130,9 @@ export class ProjectDashboardComponent {
       if (!selected || Array.isArray(selected)) return;
       const imported = await this.svc.importProject(selected as string);
       await this.reload();
+      if (imported.skipped?.length) {
+        alert('Some files could not be imported:\n' + imported.skipped.map(f => `${f.path}: ${f.warning}`).join('\n'));
+      }
       // Navigate to the newly imported project
       this.openProject(imported);
     } catch (err) {
@@ -10,7 +10,7 @@ import { DocumentEditorComponent } from '../../components/document-editor/docume
 import { GroupViewComponent } from '../../components/group-view/group-view.component';
 import { RightSidebarComponent } from '../../components/right-sidebar/right-sidebar.component';
 import { ProjectTimelineComponent } from '../../components/project-timeline/project-timeline.component';
-import type { Timeline, FolderDraft } from '../../shared/models';
+import type { Timeline, FolderDraft, ImportReport } from '../../shared/models';
 
 interface DocGroup {
   id: number;
@@ -328,8 +328,8 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
       const fileSelection = await open({
         multiple: true,
         directory: false,
-        filters: [{ name: 'Text Files', extensions: ['txt'] }],
-        title: 'Select .txt files to also import (optional)'
+        filters: [{ name: 'Documents', extensions: ['txt', 'docx', 'odt'] }],
+        title: 'Select .txt, .docx or .odt files to also import (optional)'
       });
       const files = fileSelection ? (Array.isArray(fileSelection) ? fileSelection : [fileSelection]) : [];
 
@@ -337,8 +337,9 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
 
       if (files.length === 0) {
         // Only folders -> import immediately to root (backend ignores group id for folders)
-        await this.projectService.importTxtFiles(this.projectId, -1, folders as string[]);
+        const report = await this.projectService.importTxtFiles(this.projectId, -1, folders as string[]);
         await this.loadProject(true);
+        this.showImportReport(report);
         setTimeout(() => this.focusTree(), 0);
         return;
       }
@@ -371,8 +372,9 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
       const folderSelection = await open({ multiple: true, directory: true, title: 'Select folder(s) to import' });
       const folders = folderSelection ? (Array.isArray(folderSelection) ? folderSelection : [folderSelection]) : [];
       if (folders.length === 0) return;
-      await this.projectService.importTxtFiles(this.projectId, -1, folders as string[]);
+      const report = await this.projectService.importTxtFiles(this.projectId, -1, folders as string[]);
       await this.loadProject(true);
+      this.showImportReport(report);
       setTimeout(() => this.focusTree(), 0);
     } catch (err) {
       console.error('Failed to import folders:', err);
@@ -383,7 +385,7 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
   // Import Files: ask for destination folder
   async onImportFilesRequested() {
     try {
-      const fileSelection = await open({ multiple: true, directory: false, filters: [{ name: 'Text Files', extensions: ['txt'] }], title: 'Select .txt files to import' });
+      const fileSelection = await open({ multiple: true, directory: false, filters: [{ name: 'Documents', extensions: ['txt', 'docx', 'odt'] }], title: 'Select .txt, .docx or .odt files to import' });
       const files = fileSelection ? (Array.isArray(fileSelection) ? fileSelection : [fileSelection]) : [];
       if (files.length === 0) return;
 
@@ -423,10 +425,11 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
 
   private async performImport(files: string[], groupId: number) {
     try {
-      await this.projectService.importTxtFiles(this.projectId, groupId, files);
+      const report = await this.projectService.importTxtFiles(this.projectId, groupId, files);
       await this.loadProject(true);
       const group = this.findGroupById(this.docGroups, groupId);
       if (group) this.selectGroup(group);
+      this.showImportReport(report);
       setTimeout(() => this.focusTree(), 0);
     } catch (error) {
       console.error('Import failed:', error);
@@ -434,6 +437,20 @@ export class ProjectViewComponent implements OnInit, OnDestroy {
     }
   }
 
+  // List files the import skipped, and text files that were not valid UTF-8 and fell back to Windows-1252
+  private showImportReport(report: ImportReport) {
+    const skipped = report.files.filter(f => f.warning);
+    const fallbacks = report.files.filter(f => !f.warning && f.encoding === 'windows-1252');
+    const sections: string[] = [];
+    if (skipped.length) {
+      sections.push('Some files could not be imported:\n' + skipped.map(f => `${f.path}: ${f.warning}`).join('\n'));
+    }
+    if (fallbacks.length) {
+      sections.push('Some files were not UTF-8 and were read as Windows-1252; check their accents and quotes:\n' + fallbacks.map(f => f.path).join('\n'));
+    }
+    if (sections.length) alert(sections.join('\n\n'));
+  }
+
   private flattenGroupsForSelect(groups: DocGroup[], depth = 0): Array<{ id: number; label: string }> {
     const items: Array<{ id: number; label: string }> = [];
     for (const g of groups) {